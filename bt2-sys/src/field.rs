@@ -7,7 +7,7 @@ use thiserror::Error;
 
 use crate::raw_bindings::{
     bt_field, bt_field_array_borrow_element_field_by_index_const, bt_field_array_get_length,
-    bt_field_bool_get_value, bt_field_borrow_class_const, bt_field_class,
+    bt_field_bool_get_value, bt_field_borrow_class_const, bt_field_class, bt_field_class_get_type,
     bt_field_class_integer_get_field_value_range,
     bt_field_class_integer_get_preferred_display_base,
     bt_field_class_integer_preferred_display_base,
@@ -991,6 +991,21 @@ impl BtFieldClassConst {
     const fn as_ptr(&self) -> *const bt_field_class {
         self.0.as_ptr()
     }
+
+    #[must_use]
+    pub fn get_type(&self) -> Option<BtFieldClassType> {
+        let class = unsafe { bt_field_class_get_type(self.as_ptr()) };
+        BtFieldClassType::from_field_class_type(class)
+    }
+
+    /// Cast the field class into a structure field class.
+    ///
+    /// Returns `None` if the field class is not a structure.
+    #[must_use]
+    pub fn into_struct(self) -> Option<BtFieldStructClassConst> {
+        matches!(self.get_type(), Some(BtFieldClassType::Structure))
+            .then_some(BtFieldStructClassConst(self))
+    }
 }
 
 #[repr(transparent)]
@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::ffi::{c_void, CStr, CString};
 use std::ptr::NonNull;
 use std::rc::Rc;
+use std::time::Duration;
 
 use thiserror::Error;
 
@@ -24,12 +25,37 @@ pub(crate) enum MessageIteratorState {
     Ended,
 }
 
+/// Source of the trace messages.
+#[derive(Debug, Clone, Copy)]
+pub enum TraceSource<'a> {
+    /// CTF traces in the directories read by the `ctf.fs` component.
+    Files(&'a [&'a CStr]),
+    /// Tracing session of an LTTng relay daemon read by the `ctf.lttng-live` component.
+    ///
+    /// The URL has the form `net://HOST/host/TARGET_HOSTNAME/SESSION_NAME`.
+    Live(&'a CStr),
+}
+
+/// Time window of the messages passed by the `utils.trimmer` component.
+///
+/// The bounds are in a format accepted by the component, e.g., `2024-05-01 12:30:00.5`
+/// in local time or `1714566600.5` seconds from the clock origin. Both bounds are inclusive.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeWindow<'a> {
+    pub begin: Option<&'a CStr>,
+    pub end: Option<&'a CStr>,
+}
+
+/// Time to wait before asking a live source for new messages again.
+const LIVE_RETRY_DURATION: Duration = Duration::from_millis(100);
+
 #[derive(Default)]
 struct BatchMessageIteratorInner(RefCell<Option<BtMessageArrayConst>>);
 
 pub(crate) struct BatchMessageIterator {
     graph: BtGraph,
     internal: Rc<BatchMessageIteratorInner>,
+    live: bool,
 }
 
 impl BatchMessageIterator {
@@ -41,6 +67,8 @@ impl BatchMessageIterator {
             bt_graph_simple_sink_component_consume_func_status::BT_GRAPH_SIMPLE_SINK_COMPONENT_CONSUME_FUNC_STATUS_OK;
         const STATUS_END: bt_graph_simple_sink_component_consume_func_status =
             bt_graph_simple_sink_component_consume_func_status::BT_GRAPH_SIMPLE_SINK_COMPONENT_CONSUME_FUNC_STATUS_END;
+        const STATUS_AGAIN: bt_graph_simple_sink_component_consume_func_status =
+            bt_graph_simple_sink_component_consume_func_status::BT_GRAPH_SIMPLE_SINK_COMPONENT_CONSUME_FUNC_STATUS_AGAIN;
         const STATUS_ERROR: bt_graph_simple_sink_component_consume_func_status =
             bt_graph_simple_sink_component_consume_func_status::BT_GRAPH_SIMPLE_SINK_COMPONENT_CONSUME_FUNC_STATUS_ERROR;
 
@@ -61,24 +89,18 @@ impl BatchMessageIterator {
             return STATUS_ERROR;
         }
 
-        loop {
-            let result = iterator.next();
-            match result {
-                Ok(messages) => {
-                    *internal = Some(messages);
+        match iterator.next() {
+            Ok(messages) => {
+                *internal = Some(messages);
 
-                    return STATUS_OK;
-                }
-                Err(BtError::Again(_)) => {
-                    continue;
-                }
-                Err(BtError::End) => {
-                    return STATUS_END;
-                }
-                Err(e) => {
-                    rethrow!(e, "Failed to get next batch of messages");
-                    return STATUS_ERROR;
-                }
+                STATUS_OK
+            }
+            // Let the graph retry, a live source has no new messages yet.
+            Err(BtError::Again(_)) => STATUS_AGAIN,
+            Err(BtError::End) => STATUS_END,
+            Err(e) => {
+                rethrow!(e, "Failed to get next batch of messages");
+                STATUS_ERROR
             }
         }
     }
@@ -115,7 +137,7 @@ pub enum IteratorConstructionError {
 }
 
 impl BatchMessageIterator {
-    pub fn new(trace_paths: &[&CStr], log_level: LogLevel) -> Self {
+    pub fn new(source: TraceSource, window: Option<TimeWindow>, log_level: LogLevel) -> Self {
         let shared = Rc::new(BatchMessageIteratorInner::default());
         let shared_ptr = Rc::into_raw(shared.clone());
         let sink = SimpleSink {
@@ -125,12 +147,13 @@ impl BatchMessageIterator {
             user_data: shared_ptr as *mut c_void,
         };
 
-        let graph_result = Self::construct_graph(trace_paths, sink, log_level);
+        let graph_result = Self::construct_graph(source, window, sink, log_level);
 
         match graph_result {
             Ok(graph) => Self {
                 graph,
                 internal: shared,
+                live: matches!(source, TraceSource::Live(_)),
             },
             Err(e) => {
                 let _ = unsafe { Rc::from_raw(shared_ptr) };
@@ -140,7 +163,8 @@ impl BatchMessageIterator {
     }
 
     fn construct_graph(
-        trace_paths: &[&CStr],
+        source: TraceSource,
+        window: Option<TimeWindow>,
         sink: SimpleSink,
         log_level: LogLevel,
     ) -> Result<BtGraph, IteratorConstructionError> {
@@ -149,17 +173,30 @@ impl BatchMessageIterator {
         let ctf_plugin = BtPlugin::find_anywhere(c"ctf")?;
         let utils_plugin = BtPlugin::find_anywhere(c"utils")?;
 
-        let source_class = ctf_plugin
-            .borrow_source_component_class_by_name(c"fs")
-            .ok_or(IteratorConstructionError::ComponentLoadError("fs"))?;
+        let (source_class, inputs) = match &source {
+            TraceSource::Files(trace_paths) => (
+                ctf_plugin
+                    .borrow_source_component_class_by_name(c"fs")
+                    .ok_or(IteratorConstructionError::ComponentLoadError("fs"))?,
+                *trace_paths,
+            ),
+            TraceSource::Live(url) => (
+                ctf_plugin
+                    .borrow_source_component_class_by_name(c"lttng-live")
+                    .ok_or(IteratorConstructionError::ComponentLoadError("lttng-live"))?,
+                std::slice::from_ref(url),
+            ),
+        };
         let filter_class = utils_plugin
             .borrow_filter_component_class_by_name(c"muxer")
             .ok_or(IteratorConstructionError::ComponentLoadError("muxer"))?;
 
-        let mut source_components = Vec::with_capacity(trace_paths.len());
-        for (i, trace_path) in trace_paths.iter().enumerate() {
+        // Each `ctf.fs` component reads one trace. The `ctf.lttng-live` component reads
+        // all traces of the session.
+        let mut source_components = Vec::with_capacity(inputs.len());
+        for (i, input) in inputs.iter().enumerate() {
             let mut path = BtValueArray::new()?;
-            path.push(&BtValueString::new_cstr(trace_path)?.into())?;
+            path.push(&BtValueString::new_cstr(input)?.into())?;
             let mut params = BtValueMap::new()?;
             params.insert_with_cstr_key(c"inputs", &path.into())?;
 
@@ -203,7 +240,29 @@ impl BatchMessageIterator {
             )
         }?;
 
-        let out_port = muxer.get_output_port(0);
+        let mut out_port = muxer.get_output_port(0);
+        if let Some(window) = window {
+            let trimmer_class = utils_plugin
+                .borrow_filter_component_class_by_name(c"trimmer")
+                .ok_or(IteratorConstructionError::ComponentLoadError("trimmer"))?;
+            let mut params = BtValueMap::new()?;
+            for (key, bound) in [(c"begin", window.begin), (c"end", window.end)] {
+                if let Some(bound) = bound {
+                    params.insert_with_cstr_key(key, &BtValueString::new_cstr(bound)?.into())?;
+                }
+            }
+            let trimmer = unsafe {
+                graph.add_filter_component_unchecked(
+                    trimmer_class,
+                    c"trimmer",
+                    Some(params),
+                    log_level,
+                )
+            }?;
+            unsafe { graph.connect_ports_unchecked(out_port, trimmer.get_input_port(0)) }?;
+            out_port = trimmer.get_output_port(0);
+        }
+
         let in_port = sink.get_input_port(0);
         unsafe { graph.connect_ports_unchecked(out_port, in_port) }?;
 
@@ -216,9 +275,13 @@ impl BatchMessageIterator {
         drop(taken);
         drop(internal);
 
-        let mut status = bt_graph_run_once_status::BT_GRAPH_RUN_ONCE_STATUS_AGAIN;
+        let mut status = unsafe { self.graph.run_once() };
 
         while status == bt_graph_run_once_status::BT_GRAPH_RUN_ONCE_STATUS_AGAIN {
+            if self.live {
+                // Wait for the relay daemon to receive new events.
+                std::thread::sleep(LIVE_RETRY_DURATION);
+            }
             unsafe {
                 status = self.graph.run_once();
             }
@@ -262,8 +325,18 @@ pub struct MessageIterator {
 impl MessageIterator {
     #[must_use]
     pub fn new(trace_paths: &[&CStr], log_level: LogLevel) -> Self {
+        Self::from_source(TraceSource::Files(trace_paths), None, log_level)
+    }
+
+    /// Creates an iterator over the messages of the `source` in the time `window`.
+    #[must_use]
+    pub fn from_source(
+        source: TraceSource,
+        window: Option<TimeWindow>,
+        log_level: LogLevel,
+    ) -> Self {
         Self {
-            batch_iterator: BatchMessageIterator::new(trace_paths, log_level),
+            batch_iterator: BatchMessageIterator::new(source, window, log_level),
             current_batch: None,
             current_index: 0,
         }
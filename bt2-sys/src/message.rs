@@ -7,12 +7,19 @@ use derive_more::derive::{Deref, From};
 use crate::clock_snapshot::{BtClockClassConst, BtClockSnapshotConst};
 use crate::event::BtEventConst;
 use crate::raw_bindings::{
-    bt_message, bt_message_event_borrow_default_clock_snapshot_const,
-    bt_message_event_borrow_event_const,
+    bt_message, bt_message_discarded_events_borrow_beginning_default_clock_snapshot_const,
+    bt_message_discarded_events_borrow_end_default_clock_snapshot_const,
+    bt_message_discarded_events_borrow_stream_const, bt_message_discarded_events_get_count,
+    bt_message_discarded_packets_borrow_beginning_default_clock_snapshot_const,
+    bt_message_discarded_packets_borrow_end_default_clock_snapshot_const,
+    bt_message_discarded_packets_borrow_stream_const, bt_message_discarded_packets_get_count,
+    bt_message_event_borrow_default_clock_snapshot_const, bt_message_event_borrow_event_const,
     bt_message_event_borrow_stream_class_default_clock_class_const, bt_message_get_ref,
-    bt_message_get_type, bt_message_put_ref, bt_message_type,
+    bt_message_get_type, bt_message_put_ref, bt_message_stream_beginning_borrow_stream_const,
+    bt_message_type,
 };
-use crate::utils::ConstNonNull;
+use crate::stream::BtStreamConst;
+use crate::utils::{BtProperyAvailabilty, ConstNonNull};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BtMessageType {
@@ -138,6 +145,18 @@ impl BtMessageConst {
     }
 }
 
+impl BtStreamBeginningMessageConst {
+    /// Get the stream which begins.
+    #[must_use]
+    pub fn get_stream(&self) -> BtStreamConst {
+        unsafe {
+            BtStreamConst::new_unchecked(bt_message_stream_beginning_borrow_stream_const(
+                self.as_ptr(),
+            ))
+        }
+    }
+}
+
 impl BtEventMessageConst {
     /// Get the event contained in this message.
     #[must_use]
@@ -177,6 +196,118 @@ impl BtEventMessageConst {
     }
 }
 
+impl BtDiscardedEventsMessageConst {
+    /// Get the number of discarded events if it is known.
+    #[must_use]
+    pub fn get_count(&self) -> Option<u64> {
+        let mut count = 0;
+        let availability =
+            unsafe { bt_message_discarded_events_get_count(self.as_ptr(), &mut count) };
+        match availability.into() {
+            BtProperyAvailabilty::Available => Some(count),
+            BtProperyAvailabilty::NotAvailable => None,
+        }
+    }
+
+    /// Get the stream in which the events were discarded.
+    #[must_use]
+    pub fn get_stream(&self) -> BtStreamConst {
+        unsafe {
+            BtStreamConst::new_unchecked(bt_message_discarded_events_borrow_stream_const(
+                self.as_ptr(),
+            ))
+        }
+    }
+
+    /// Get snapshots of the default clock at the beginning and the end of the time range
+    /// in which the events were discarded.
+    ///
+    /// Returns `None` if the stream class does not provide them.
+    #[must_use]
+    pub fn get_default_clock_snapshots(
+        &self,
+    ) -> Option<(BtClockSnapshotConst, BtClockSnapshotConst)> {
+        if !self
+            .get_stream()
+            .get_class()
+            .discarded_events_have_default_clock_snapshots()
+        {
+            return None;
+        }
+
+        unsafe {
+            Some((
+                BtClockSnapshotConst::new_unchecked(
+                    bt_message_discarded_events_borrow_beginning_default_clock_snapshot_const(
+                        self.as_ptr(),
+                    ),
+                ),
+                BtClockSnapshotConst::new_unchecked(
+                    bt_message_discarded_events_borrow_end_default_clock_snapshot_const(
+                        self.as_ptr(),
+                    ),
+                ),
+            ))
+        }
+    }
+}
+
+impl BtDiscardedPacketsMessageConst {
+    /// Get the number of discarded packets if it is known.
+    #[must_use]
+    pub fn get_count(&self) -> Option<u64> {
+        let mut count = 0;
+        let availability =
+            unsafe { bt_message_discarded_packets_get_count(self.as_ptr(), &mut count) };
+        match availability.into() {
+            BtProperyAvailabilty::Available => Some(count),
+            BtProperyAvailabilty::NotAvailable => None,
+        }
+    }
+
+    /// Get the stream in which the packets were discarded.
+    #[must_use]
+    pub fn get_stream(&self) -> BtStreamConst {
+        unsafe {
+            BtStreamConst::new_unchecked(bt_message_discarded_packets_borrow_stream_const(
+                self.as_ptr(),
+            ))
+        }
+    }
+
+    /// Get snapshots of the default clock at the beginning and the end of the time range
+    /// in which the packets were discarded.
+    ///
+    /// Returns `None` if the stream class does not provide them.
+    #[must_use]
+    pub fn get_default_clock_snapshots(
+        &self,
+    ) -> Option<(BtClockSnapshotConst, BtClockSnapshotConst)> {
+        if !self
+            .get_stream()
+            .get_class()
+            .discarded_packets_have_default_clock_snapshots()
+        {
+            return None;
+        }
+
+        unsafe {
+            Some((
+                BtClockSnapshotConst::new_unchecked(
+                    bt_message_discarded_packets_borrow_beginning_default_clock_snapshot_const(
+                        self.as_ptr(),
+                    ),
+                ),
+                BtClockSnapshotConst::new_unchecked(
+                    bt_message_discarded_packets_borrow_end_default_clock_snapshot_const(
+                        self.as_ptr(),
+                    ),
+                ),
+            ))
+        }
+    }
+}
+
 impl Clone for BtMessageConst {
     fn clone(&self) -> Self {
         unsafe {
@@ -1,8 +1,12 @@
-use crate::field::BtFieldConst;
+use crate::field::{BtFieldClassConst, BtFieldConst};
 use crate::raw_bindings::{
     bt_packet, bt_packet_borrow_context_field_const, bt_packet_borrow_stream_const, bt_stream,
     bt_stream_borrow_class_const, bt_stream_borrow_trace_const, bt_stream_class,
-    bt_stream_class_supports_packets,
+    bt_stream_class_borrow_event_common_context_field_class_const,
+    bt_stream_class_borrow_packet_context_field_class_const,
+    bt_stream_class_discarded_events_have_default_clock_snapshots,
+    bt_stream_class_discarded_packets_have_default_clock_snapshots,
+    bt_stream_class_supports_packets, bt_stream_get_id, bt_stream_get_name,
 };
 use crate::trace::BtTraceConst;
 use crate::utils::ConstNonNull;
@@ -33,6 +37,33 @@ impl BtStreamConst {
     pub fn get_trace(&self) -> BtTraceConst {
         unsafe { BtTraceConst::new_unchecked(bt_stream_borrow_trace_const(self.get_ptr())) }
     }
+
+    #[must_use]
+    pub fn get_id(&self) -> u64 {
+        unsafe { bt_stream_get_id(self.get_ptr()) }
+    }
+
+    /// Get the name of the stream.
+    ///
+    /// The `source.ctf.fs` component names the streams by the path of their data file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if name is not valid UTF-8.
+    #[must_use]
+    pub fn get_name(&self) -> Option<&str> {
+        Some(
+            unsafe {
+                let name = bt_stream_get_name(self.get_ptr());
+                if name.is_null() {
+                    return None;
+                }
+                std::ffi::CStr::from_ptr(name)
+            }
+            .to_str()
+            .expect("Failed to convert CStr to str"),
+        )
+    }
 }
 
 #[repr(transparent)]
@@ -52,6 +83,47 @@ impl BtStreamClassConst {
     pub fn supports_packets(&self) -> bool {
         0 != unsafe { bt_stream_class_supports_packets(self.get_ptr()) }
     }
+
+    /// Get the class of the packet context fields, e.g., containing the `cpu_id`.
+    #[must_use]
+    pub fn get_packet_context_field_class(&self) -> Option<BtFieldClassConst> {
+        unsafe {
+            let field_class =
+                bt_stream_class_borrow_packet_context_field_class_const(self.get_ptr());
+            if field_class.is_null() {
+                return None;
+            }
+            Some(BtFieldClassConst::new_unchecked(field_class))
+        }
+    }
+
+    /// Get the class of the context fields common to all events of the stream class,
+    /// e.g., containing the `vpid` and `vtid` added by `lttng add-context`.
+    #[must_use]
+    pub fn get_event_common_context_field_class(&self) -> Option<BtFieldClassConst> {
+        unsafe {
+            let field_class =
+                bt_stream_class_borrow_event_common_context_field_class_const(self.get_ptr());
+            if field_class.is_null() {
+                return None;
+            }
+            Some(BtFieldClassConst::new_unchecked(field_class))
+        }
+    }
+
+    #[must_use]
+    pub fn discarded_events_have_default_clock_snapshots(&self) -> bool {
+        0 != unsafe {
+            bt_stream_class_discarded_events_have_default_clock_snapshots(self.get_ptr())
+        }
+    }
+
+    #[must_use]
+    pub fn discarded_packets_have_default_clock_snapshots(&self) -> bool {
+        0 != unsafe {
+            bt_stream_class_discarded_packets_have_default_clock_snapshots(self.get_ptr())
+        }
+    }
 }
 
 #[repr(transparent)]
@@ -3,6 +3,7 @@ use std::sync::{Arc, Mutex};
 
 use graph::Graph;
 
+use crate::data_quality::DiscardedWindow;
 use crate::model::{Callback, CallbackCaller, CallbackType};
 use crate::processed_events::{Event, FullEvent, ros2};
 use crate::utils::Known;
@@ -230,7 +231,13 @@ impl EventAnalysis for CallbackDependency {
         }
     }
 
+    fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+        self.publication_in_callback
+            .process_discarded_events(window);
+    }
+
     fn finalize(&mut self) {
+        self.publication_in_callback.finalize();
         self.construct_callback_graph();
     }
 }
@@ -1,16 +1,19 @@
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use std::vec::Vec;
 
 use crate::argsv2::Args;
+use crate::data_quality::{DiscardedWindow, DiscardedWindows};
 use crate::events_common::Context;
+use crate::model::arena::ObjectsView;
 use crate::model::display::{DisplayCallbackSummary, get_node_name_from_weak};
-use crate::model::{Callback, CallbackInstance, Time};
+use crate::model::{Callback, CallbackId, CallbackInstance, Time};
 use crate::processed_events::{Event, FullEvent, ros2};
-use crate::statistics::{Quantile, Sorted};
+use crate::statistics::{Quantile, Samples, SamplesExport, Sorted, StatisticsBackend};
 use crate::utils::{DurationDisplayImprecise, WeakKnown};
 
+use super::sim_time::SimTimeMapping;
 use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
 
 #[derive(Debug, Serialize)]
@@ -18,8 +21,11 @@ pub struct ExecutionData {
     #[serde(serialize_with = "serialize_time")]
     pub start_time: Time,
     pub duration: i64,
-    pub tid: u32,
-    pub cpuid: u32,
+    /// Time spent on a CPU, known only if the kernel scheduler events were traced.
+    pub on_cpu_time: Option<i64>,
+    /// Thread and CPU of the execution, `null` if the trace did not record them.
+    pub tid: Option<u32>,
+    pub cpuid: Option<u32>,
 }
 
 fn serialize_time<S>(time: &Time, s: S) -> Result<S::Ok, S::Error>
@@ -29,28 +35,102 @@ where
     s.serialize_i64(time.timestamp_nanos())
 }
 
+/// Durations and inter-arrival times of the executions of a callback.
+#[derive(Debug)]
+struct ExecutionSamples {
+    durations: Samples,
+    inter_arrival_times: Samples,
+    first_start_time: Option<Time>,
+    last_start_time: Option<Time>,
+    /// Executions by thread id. The executions on unknown threads are left out.
+    threads: HashMap<u32, ThreadExecutions>,
+}
+
+/// Executions of a callback on one thread.
+#[derive(Debug, Clone)]
+pub struct ThreadExecutions {
+    pub count: usize,
+    /// Sum of the durations in nanoseconds.
+    pub total_duration: i64,
+    pub durations: Samples,
+}
+
+impl ThreadExecutions {
+    fn new(durations: Samples) -> Self {
+        Self {
+            count: 0,
+            total_duration: 0,
+            durations,
+        }
+    }
+
+    fn push(&mut self, duration: i64) {
+        self.count += 1;
+        self.total_duration += duration;
+        self.durations.push(duration);
+    }
+}
+
+/// Executions of a callback needed by the utilization analyses.
+#[derive(Debug)]
+pub struct ExecutionSummary {
+    pub count: usize,
+    /// Mean of the wall time between the starts of the executions in nanoseconds.
+    pub mean_inter_arrival_time: Option<i64>,
+    /// Executions by thread id.
+    pub threads: HashMap<u32, ThreadExecutions>,
+}
+
+/// Returns the mean time between `count` executions started from `first` to `last`.
+fn mean_inter_arrival_time(count: usize, first: Time, last: Time) -> Option<i64> {
+    let intervals = i64::try_from(count.checked_sub(1)?).ok()?;
+    (intervals > 0).then(|| (last.timestamp_nanos() - first.timestamp_nanos()) / intervals)
+}
+
 #[derive(Debug, Default)]
 pub struct CallbackDuration {
-    execution_data: HashMap<ArcMutWrapper<Callback>, Vec<ExecutionData>>,
+    /// Executions of each callback, kept only with the exact statistics.
+    execution_data: HashMap<CallbackId, Vec<ExecutionData>>,
+    /// Sketched samples of the executions, used instead of the execution data unless
+    /// the backend is exact.
+    execution_samples: HashMap<CallbackId, ExecutionSamples>,
+    /// Callbacks read by their identifiers when the outputs are written.
+    objects: ObjectsView,
+    statistics: StatisticsBackend,
     // durations: HashMap<ArcMutWrapper<Callback>, Vec<i64>>,
     // execution_start_time: HashMap<ArcMutWrapper<Callback>, Vec<Time>>,
     started_callbacks: HashSet<ArcMutWrapper<CallbackInstance>>,
     not_ended_callbacks: Vec<ArcMutWrapper<CallbackInstance>>,
+    discarded_windows: DiscardedWindows,
+    /// Mapping to express the inter-arrival times in simulation time.
+    sim_time: Option<Arc<SimTimeMapping>>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
-    node: String,
-    caller_type: String,
+    pub(crate) node: String,
+    pub(crate) caller_type: String,
     /// The caller parameter is the main parameter of the caller.
     ///
     /// - For subscriptions, it is the topic name.
     /// - For timers, it is the timer period.
     /// - For services, it is the service name.
-    caller_param: String,
+    pub(crate) caller_param: String,
+    /// Identifier of the callback in the analyzed trace.
+    #[serde(default)]
+    pub(crate) callback_id: Option<CallbackId>,
 
-    durations: Vec<i64>,
-    inter_arrival_times: Vec<i64>,
+    pub(crate) durations: SamplesExport,
+    /// On-CPU times of the executions in the same order as `durations`.
+    ///
+    /// The values are `null` if the kernel scheduler events were not traced.
+    /// They are not exported with the sketched durations.
+    #[serde(default)]
+    pub(crate) on_cpu_times: Vec<Option<i64>>,
+    pub(crate) inter_arrival_times: SamplesExport,
+    /// `true` if the inter-arrival times are in simulation time.
+    #[serde(default)]
+    pub(crate) sim_time: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -63,13 +143,39 @@ impl CallbackDuration {
     pub fn new() -> Self {
         Self {
             execution_data: HashMap::new(),
+            execution_samples: HashMap::new(),
+            objects: ObjectsView::default(),
+            statistics: StatisticsBackend::default(),
             // durations: HashMap::new(),
             // execution_start_time: HashMap::new(),
             started_callbacks: HashSet::new(),
             not_ended_callbacks: Vec::new(),
+            discarded_windows: DiscardedWindows::default(),
+            sim_time: None,
         }
     }
 
+    /// Expresses the inter-arrival times in simulation time using the `mapping`.
+    ///
+    /// Executions outside of the simulation are left out of the inter-arrival times.
+    pub fn set_sim_time(&mut self, mapping: Arc<SimTimeMapping>) {
+        self.sim_time = Some(mapping);
+    }
+
+    /// Reads the callbacks of the executions from the `objects` of the processor.
+    pub fn set_objects(&mut self, objects: ObjectsView) {
+        self.objects = objects;
+    }
+
+    /// Keeps the durations and inter-arrival times by the `statistics` backend.
+    ///
+    /// With a sketch, only the sketched samples of each callback and thread are kept, and
+    /// the inter-arrival times are expressed in simulation time when they are added, so
+    /// the mapping must be set before the events are processed.
+    pub fn set_statistics(&mut self, statistics: StatisticsBackend) {
+        self.statistics = statistics;
+    }
+
     fn calculate_duration(callback: &CallbackInstance) -> Option<i64> {
         let start_time = callback.get_start_time();
         let end_time = callback.get_end_time()?;
@@ -84,13 +190,29 @@ impl CallbackDuration {
         self.started_callbacks.insert(callback.into());
     }
 
-    fn end_callback(&mut self, callback: Arc<Mutex<CallbackInstance>>, context: &Context) {
+    fn end_callback(
+        &mut self,
+        callback: Arc<Mutex<CallbackInstance>>,
+        callback_id: CallbackId,
+        context: &Context,
+    ) {
         let callback = callback.into();
         if self.started_callbacks.remove(&callback) {
             let callback_instance = callback.0.lock().unwrap();
             let duration = Self::calculate_duration(&callback_instance)
                 .expect("Duration should be known in callback_end");
 
+            let start_time = callback_instance.get_start_time();
+            let end_time = callback_instance.get_end_time().unwrap();
+            if self
+                .discarded_windows
+                .exclude(&[context.hostname()], start_time, end_time)
+            {
+                // Events during the execution could have been discarded, e.g., the end of
+                // an earlier execution of the callback.
+                return;
+            }
+
             // self.durations
             //     .entry(callback_instance.get_callback().into())
             //     .or_default()
@@ -100,12 +222,47 @@ impl CallbackDuration {
             //     .or_default()
             //     .push(callback_instance.get_start_time());
 
+            if !self.statistics.is_exact() {
+                let samples = self
+                    .execution_samples
+                    .entry(callback_id)
+                    .or_insert_with(|| ExecutionSamples {
+                        durations: self.statistics.samples(),
+                        inter_arrival_times: self.statistics.samples(),
+                        first_start_time: None,
+                        last_start_time: None,
+                        threads: HashMap::new(),
+                    });
+                samples.durations.push(duration);
+                if let Some(vtid) = context.vtid() {
+                    samples
+                        .threads
+                        .entry(vtid)
+                        .or_insert_with(|| ThreadExecutions::new(self.statistics.samples()))
+                        .push(duration);
+                }
+                samples.first_start_time.get_or_insert(start_time);
+                if let Some(last_start_time) = samples.last_start_time.replace(start_time) {
+                    let inter_arrival_time = match &self.sim_time {
+                        Some(mapping) => mapping.sim_duration(last_start_time, start_time),
+                        None => {
+                            Some(start_time.timestamp_nanos() - last_start_time.timestamp_nanos())
+                        }
+                    };
+                    if let Some(inter_arrival_time) = inter_arrival_time {
+                        samples.inter_arrival_times.push(inter_arrival_time);
+                    }
+                }
+                return;
+            }
+
             self.execution_data
-                .entry(callback_instance.get_callback().into())
+                .entry(callback_id)
                 .or_default()
                 .push(ExecutionData {
-                    start_time: callback_instance.get_start_time(),
+                    start_time,
                     duration,
+                    on_cpu_time: callback_instance.get_on_cpu_time(),
                     tid: context.vtid(),
                     cpuid: context.cpu_id(),
                 });
@@ -134,11 +291,19 @@ impl CallbackDuration {
             .collect();
     }
 
-    fn calculate_duration_summary(
-        &self,
-        callback: &ArcMutWrapper<Callback>,
-    ) -> Option<RecordSummary> {
-        let exec_data = self.execution_data.get(callback)?;
+    fn calculate_duration_summary(&self, callback: CallbackId) -> Option<RecordSummary> {
+        let quantiles = Args::get_analyses_args().quantiles();
+        if let Some(samples) = self.execution_samples.get(&callback) {
+            return Some(RecordSummary {
+                call_count: samples.durations.len(),
+                quantiles: samples
+                    .durations
+                    .quantiles(quantiles)
+                    .expect("Should not be empty"),
+            });
+        }
+
+        let exec_data = self.execution_data.get(&callback)?;
         debug_assert!(
             !exec_data.is_empty(),
             "Callback should have at least one execution data. Otherwise, it should not be present in the map."
@@ -149,8 +314,7 @@ impl CallbackDuration {
         durations.sort_unstable();
         let durations_sorted = Sorted::from_sorted(durations).unwrap();
 
-        let duration_quantiles = Args::get_analyses_args()
-            .quantiles()
+        let duration_quantiles = quantiles
             .iter()
             .map(|q| {
                 let duration = *durations_sorted.quantile(*q).expect("Should not be empty");
@@ -164,25 +328,77 @@ impl CallbackDuration {
         })
     }
 
+    fn record_id(&self, callback: CallbackId) -> (String, String, String) {
+        self.objects
+            .read_callback(callback, Self::callback_record_id)
+            .expect("Executed callbacks should be created by the processor")
+    }
+
+    fn callback_record_id(callback: &Callback) -> (String, String, String) {
+        let node_name = callback.get_node().map_or(WeakKnown::Unknown, |node_weak| {
+            get_node_name_from_weak(&node_weak.get_weak())
+        });
+        let callback_type = callback.get_type();
+        let callback_caller = callback.get_caller().unwrap().get_caller_as_string();
+
+        (
+            node_name.to_string(),
+            callback_type.to_string(),
+            callback_caller.to_string(),
+        )
+    }
+
+    /// Returns the identifiers of the executed callbacks in the order of their creation.
+    fn callback_ids(&self) -> Vec<CallbackId> {
+        let mut callbacks: Vec<_> = if self.statistics.is_exact() {
+            self.execution_data.keys().copied().collect()
+        } else {
+            self.execution_samples.keys().copied().collect()
+        };
+        callbacks.sort_unstable();
+        callbacks
+    }
+
     pub fn get_records(&self) -> Vec<Record> {
-        self.execution_data
-            .iter()
-            .map(|(callback_arc, data)| {
-                let callback = callback_arc.0.lock().unwrap();
-                let node_name = callback.get_node().map_or(WeakKnown::Unknown, |node_weak| {
-                    get_node_name_from_weak(&node_weak.get_weak())
-                });
-                let callback_type = callback.get_type();
-                let callback_caller = callback.get_caller().unwrap().get_caller_as_string();
-                drop(callback);
+        if !self.statistics.is_exact() {
+            let quantiles = Args::get_analyses_args().quantiles();
+            return self
+                .callback_ids()
+                .into_iter()
+                .map(|callback| {
+                    let samples = &self.execution_samples[&callback];
+                    let (node, caller_type, caller_param) = self.record_id(callback);
+                    Record {
+                        node,
+                        caller_type,
+                        caller_param,
+                        callback_id: Some(callback),
+                        durations: samples.durations.export(quantiles),
+                        on_cpu_times: Vec::new(),
+                        inter_arrival_times: samples.inter_arrival_times.export(quantiles),
+                        sim_time: self.sim_time.is_some(),
+                    }
+                })
+                .collect();
+        }
 
+        self.callback_ids()
+            .into_iter()
+            .map(|callback| {
+                let data = &self.execution_data[&callback];
+                let (node, caller_type, caller_param) = self.record_id(callback);
                 Record {
-                    node: node_name.to_string(),
-                    caller_type: callback_type.to_string(),
-                    caller_param: callback_caller.to_string(),
-                    durations: data.iter().map(|data| data.duration).collect(),
-                    inter_arrival_times: Self::get_inter_arrival_time_inner(data)
-                        .unwrap_or_default(),
+                    node,
+                    caller_type,
+                    caller_param,
+                    callback_id: Some(callback),
+                    durations: SamplesExport::Raw(data.iter().map(|data| data.duration).collect()),
+                    on_cpu_times: data.iter().map(|data| data.on_cpu_time).collect(),
+                    inter_arrival_times: SamplesExport::Raw(match &self.sim_time {
+                        Some(mapping) => Self::get_sim_inter_arrival_time(data, mapping),
+                        None => Self::get_inter_arrival_time_inner(data).unwrap_or_default(),
+                    }),
+                    sim_time: self.sim_time.is_some(),
                 }
             })
             .collect()
@@ -190,13 +406,16 @@ impl CallbackDuration {
 
     pub(crate) fn print_stats(&self) {
         println!("Callback duration statistics:");
-        for (i, callback_arc) in self.execution_data.keys().enumerate() {
-            let callback = callback_arc.0.lock().unwrap();
+        for (i, callback) in self.callback_ids().into_iter().enumerate() {
             let summary = self
-                .calculate_duration_summary(callback_arc)
+                .calculate_duration_summary(callback)
                 .expect("Callback key should exist.");
 
-            println!("- [{i:4}] Callback {}:", DisplayCallbackSummary(&callback));
+            self.objects
+                .read_callback(callback, |callback| {
+                    println!("- [{i:4}] Callback {}:", DisplayCallbackSummary(callback));
+                })
+                .expect("Executed callbacks should be created by the processor");
             println!("    Call count: {}", summary.call_count);
             if summary.call_count > 0 {
                 println!("    Duration quantiles:");
@@ -210,19 +429,16 @@ impl CallbackDuration {
         }
     }
 
-    pub fn get_durations_for_callback(
-        &self,
-        callback: &ArcMutWrapper<Callback>,
-    ) -> Option<Vec<i64>> {
+    pub fn get_durations_for_callback(&self, callback: CallbackId) -> Option<Vec<i64>> {
         self.execution_data
-            .get(callback)
+            .get(&callback)
             .map(|data| data.iter().map(|data| data.duration).collect::<Vec<_>>())
     }
 
-    pub fn get_all_durations(&self) -> HashMap<ArcMutWrapper<Callback>, Vec<i64>> {
+    pub fn get_all_durations(&self) -> HashMap<CallbackId, Vec<i64>> {
         self.execution_data
             .iter()
-            .map(|(k, v)| (k.clone(), v.iter().map(|data| data.duration).collect()))
+            .map(|(&k, v)| (k, v.iter().map(|data| data.duration).collect()))
             .collect()
     }
 
@@ -243,21 +459,76 @@ impl CallbackDuration {
         Some(inter_callback_time)
     }
 
-    pub fn get_inter_arrival_time(&self, callback: &ArcMutWrapper<Callback>) -> Option<Vec<i64>> {
-        let start_times = self.execution_data.get(callback)?;
-        Self::get_inter_arrival_time_inner(start_times)
+    fn get_sim_inter_arrival_time(data: &[ExecutionData], mapping: &SimTimeMapping) -> Vec<i64> {
+        data.windows(2)
+            .filter_map(|w| mapping.sim_duration(w[0].start_time, w[1].start_time))
+            .collect()
+    }
+
+    /// Returns the executions of each callback needed by the utilization analyses.
+    pub(super) fn get_execution_summaries(
+        &self,
+    ) -> HashMap<ArcMutWrapper<Callback>, ExecutionSummary> {
+        if !self.statistics.is_exact() {
+            return self
+                .execution_samples
+                .iter()
+                .map(|(callback, samples)| {
+                    let count = samples.durations.len();
+                    let summary = ExecutionSummary {
+                        count,
+                        mean_inter_arrival_time: samples
+                            .first_start_time
+                            .zip(samples.last_start_time)
+                            .and_then(|(first, last)| mean_inter_arrival_time(count, first, last)),
+                        threads: samples.threads.clone(),
+                    };
+                    (self.callback_arc(*callback), summary)
+                })
+                .collect();
+        }
+
+        self.execution_data
+            .iter()
+            .map(|(callback, data)| {
+                let mut threads = HashMap::new();
+                for execution in data {
+                    if let Some(tid) = execution.tid {
+                        threads
+                            .entry(tid)
+                            .or_insert_with(|| ThreadExecutions::new(self.statistics.samples()))
+                            .push(execution.duration);
+                    }
+                }
+                let summary = ExecutionSummary {
+                    count: data.len(),
+                    mean_inter_arrival_time: data.first().zip(data.last()).and_then(
+                        |(first, last)| {
+                            mean_inter_arrival_time(data.len(), first.start_time, last.start_time)
+                        },
+                    ),
+                    threads,
+                };
+                (self.callback_arc(*callback), summary)
+            })
+            .collect()
     }
 
-    pub fn get_execution_data(&self) -> &HashMap<ArcMutWrapper<Callback>, Vec<ExecutionData>> {
-        &self.execution_data
+    fn callback_arc(&self, callback: CallbackId) -> ArcMutWrapper<Callback> {
+        self.objects
+            .callback_arc(callback)
+            .expect("Executed callbacks should be created by the processor")
+            .into()
     }
 }
 
 impl EventAnalysis for CallbackDuration {
     fn initialize(&mut self) {
         self.execution_data.clear();
+        self.execution_samples.clear();
         self.started_callbacks.clear();
         self.not_ended_callbacks.clear();
+        self.discarded_windows.clear();
     }
 
     fn process_event(&mut self, full_event: &FullEvent) {
@@ -266,17 +537,38 @@ impl EventAnalysis for CallbackDuration {
                 self.start_callback(event.callback.clone());
             }
             Event::Ros2(ros2::Event::CallbackEnd(event)) => {
-                self.end_callback(event.callback.clone(), &full_event.context);
+                self.end_callback(
+                    event.callback.clone(),
+                    event.callback_id,
+                    &full_event.context,
+                );
             }
 
             _ => {}
         }
     }
 
+    fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+        self.discarded_windows.add(window);
+    }
+
     fn finalize(&mut self) {
         // Make sure all started callbacks are ended. The remaining callbacks are
         // missing the CallbackEnd event.
         self.end_remaining_callbacks();
+
+        let excluded = self.discarded_windows.get_excluded_count();
+        if excluded > 0 {
+            log::warn!(
+                "Callback duration: excluded {excluded} executions overlapping discarded events"
+            );
+        }
+        let uncertain = self.discarded_windows.get_uncertain_count();
+        if uncertain > 0 {
+            log::warn!(
+                "Callback duration: {uncertain} executions may be inaccurate, events were discarded at unknown times"
+            );
+        }
     }
 }
 
@@ -286,3 +578,41 @@ impl AnalysisOutput for CallbackDuration {
         serde_json::to_writer(file, &records)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::super::analyze_events;
+    use super::*;
+
+    #[test]
+    fn export_of_timer_executions() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .timer(0, 1, 10, 20)
+            .execution(1_000, 1_100, VPID, 20)
+            .execution(2_000, 2_300, VPID, 20)
+            .execution(3_000, 3_200, VPID, 20);
+
+        let mut analysis = CallbackDuration::new();
+        let model = analyze_events(trace.build(), &mut analysis);
+        analysis.set_objects(model.objects());
+        let export = serde_json::to_value(analysis.get_records()).unwrap();
+
+        assert_eq!(
+            export,
+            serde_json::json!([{
+                "callback_id": 0,
+                "node": "/node",
+                "caller_type": "Timer",
+                "caller_param": "1 μs",
+                "durations": [100, 300, 200],
+                "on_cpu_times": [null, null, null],
+                "inter_arrival_times": [1_000, 1_000],
+                "sim_time": false,
+            }])
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::argsv2::Args;
+use crate::model::{Callback, Thread, Time};
+use crate::processed_events::{Event, FullEvent, kernel, ros2};
+use crate::statistics::{Quantile, Samples, SamplesExport, StatisticsBackend};
+
+use super::utils::CallbackRecordId;
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
+
+/// Number of wakers reported for each callback.
+const TOP_WAKERS: usize = 10;
+
+/// Breaks the callback durations into the time spent running, waiting for a CPU and blocked
+/// based on the kernel scheduler events.
+#[derive(Debug, Default)]
+pub struct CallbackOffCpu {
+    /// Threads observed by the kernel scheduler events by hostname and thread id.
+    kernel_threads: HashSet<(String, u32)>,
+
+    running_callbacks: HashMap<(String, u32), RunningCallback>,
+    callbacks: HashMap<ArcMutWrapper<Callback>, CallbackData>,
+
+    statistics: StatisticsBackend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ThreadState {
+    Running,
+    /// The thread is runnable but waits for a CPU, e.g., because it was preempted.
+    Runnable,
+    /// The thread sleeps until it is woken up, e.g., on a futex or I/O.
+    Blocked,
+}
+
+#[derive(Debug)]
+struct RunningCallback {
+    callback: ArcMutWrapper<Callback>,
+    breakdown: ExecutionBreakdown,
+}
+
+/// State of the thread executing a callback and the time spent in each state so far.
+#[derive(Debug)]
+struct ExecutionBreakdown {
+    state: ThreadState,
+    state_since: Time,
+    execution: ExecutionRecord,
+}
+
+#[derive(Debug)]
+struct CallbackData {
+    /// Breakdown of each execution, kept only with the exact statistics.
+    executions: Vec<ExecutionRecord>,
+    /// Sketches of the breakdowns, kept instead of the executions.
+    summary: Option<ExecutionsSummary>,
+    wakers: HashMap<String, WakerData>,
+}
+
+#[derive(Debug)]
+struct ExecutionsSummary {
+    duration: Samples,
+    running_time: Samples,
+    runnable_time: Samples,
+    blocked_time: Samples,
+}
+
+impl CallbackData {
+    fn new(statistics: StatisticsBackend) -> Self {
+        Self {
+            executions: Vec::new(),
+            summary: (!statistics.is_exact()).then(|| ExecutionsSummary {
+                duration: statistics.samples(),
+                running_time: statistics.samples(),
+                runnable_time: statistics.samples(),
+                blocked_time: statistics.samples(),
+            }),
+            wakers: HashMap::new(),
+        }
+    }
+
+    fn add_execution(&mut self, execution: ExecutionRecord) {
+        let Some(summary) = &mut self.summary else {
+            self.executions.push(execution);
+            return;
+        };
+        summary.duration.push(execution.duration);
+        summary.running_time.push(execution.running_time);
+        summary.runnable_time.push(execution.runnable_time);
+        summary.blocked_time.push(execution.blocked_time);
+    }
+}
+
+#[derive(Debug, Default)]
+struct WakerData {
+    wakeups: usize,
+    blocked_time: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ExecutionRecord {
+    start_time: i64,
+    duration: i64,
+    running_time: i64,
+    runnable_time: i64,
+    blocked_time: i64,
+}
+
+impl ExecutionBreakdown {
+    /// Creates a breakdown of a callback started at `time`.
+    ///
+    /// The thread runs when the callback starts.
+    fn new(time: Time) -> Self {
+        Self {
+            state: ThreadState::Running,
+            state_since: time,
+            execution: ExecutionRecord {
+                start_time: time.timestamp_nanos(),
+                duration: 0,
+                running_time: 0,
+                runnable_time: 0,
+                blocked_time: 0,
+            },
+        }
+    }
+
+    /// Moves the thread to the `state` and returns the time spent in the previous state.
+    fn enter_state(&mut self, state: ThreadState, time: Time) -> i64 {
+        let duration = time.timestamp_nanos() - self.state_since.timestamp_nanos();
+        let total = match self.state {
+            ThreadState::Running => &mut self.execution.running_time,
+            ThreadState::Runnable => &mut self.execution.runnable_time,
+            ThreadState::Blocked => &mut self.execution.blocked_time,
+        };
+        *total += duration;
+
+        self.state = state;
+        self.state_since = time;
+        duration
+    }
+
+    fn end(mut self, time: Time) -> ExecutionRecord {
+        self.enter_state(ThreadState::Running, time);
+        self.execution.duration = time.timestamp_nanos() - self.execution.start_time;
+        self.execution
+    }
+}
+
+fn thread_key(thread: &Arc<Mutex<Thread>>) -> (String, u32) {
+    let thread = thread.lock().unwrap();
+    (thread.get_hostname().to_owned(), thread.get_tid())
+}
+
+impl CallbackOffCpu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// With a sketch backend, only the summaries of the breakdowns are kept instead of every
+    /// execution.
+    pub fn set_statistics(&mut self, statistics: StatisticsBackend) {
+        self.statistics = statistics;
+    }
+
+    fn process_sched_switch(&mut self, event: &kernel::SchedSwitch, time: Time) {
+        if let Some(prev) = &event.prev {
+            let key = thread_key(prev);
+            if let Some(running) = self.running_callbacks.get_mut(&key) {
+                let state = if event.prev_preempted {
+                    ThreadState::Runnable
+                } else {
+                    ThreadState::Blocked
+                };
+                running.breakdown.enter_state(state, time);
+            }
+            self.kernel_threads.insert(key);
+        }
+
+        if let Some(next) = &event.next {
+            let key = thread_key(next);
+            if let Some(running) = self.running_callbacks.get_mut(&key) {
+                running.breakdown.enter_state(ThreadState::Running, time);
+            }
+            self.kernel_threads.insert(key);
+        }
+    }
+
+    fn process_sched_wakeup(&mut self, event: &kernel::SchedWakeup, time: Time) {
+        let key = thread_key(&event.thread);
+        if let Some(running) = self.running_callbacks.get_mut(&key)
+            && running.breakdown.state == ThreadState::Blocked
+        {
+            let blocked_time = running.breakdown.enter_state(ThreadState::Runnable, time);
+
+            let waker = event
+                .waker
+                .as_ref()
+                .zip(event.waker_name.as_ref())
+                .map_or_else(
+                    || "<idle or unknown>".to_owned(),
+                    |(waker, name)| format!("{name} ({})", waker.lock().unwrap().get_tid()),
+                );
+            let waker_data = self
+                .callbacks
+                .entry(running.callback.clone())
+                .or_insert_with(|| CallbackData::new(self.statistics))
+                .wakers
+                .entry(waker)
+                .or_default();
+            waker_data.wakeups += 1;
+            waker_data.blocked_time += blocked_time;
+        }
+        self.kernel_threads.insert(key);
+    }
+
+    fn process_callback_start(&mut self, event: &ros2::CallbackStart, full_event: &FullEvent) {
+        let Some(vtid) = full_event.context.vtid() else {
+            return;
+        };
+        let key = (full_event.context.hostname().to_owned(), vtid);
+        // The callback can be broken down only if the kernel scheduler events were traced.
+        if !self.kernel_threads.contains(&key) {
+            return;
+        }
+
+        let callback = event.callback.lock().unwrap().get_callback();
+        self.running_callbacks.insert(
+            key,
+            RunningCallback {
+                callback: callback.into(),
+                breakdown: ExecutionBreakdown::new(full_event.time),
+            },
+        );
+    }
+
+    fn process_callback_end(&mut self, full_event: &FullEvent) {
+        let Some(vtid) = full_event.context.vtid() else {
+            return;
+        };
+        let key = (full_event.context.hostname().to_owned(), vtid);
+        let Some(running) = self.running_callbacks.remove(&key) else {
+            return;
+        };
+
+        let execution = running.breakdown.end(full_event.time);
+        self.callbacks
+            .entry(running.callback)
+            .or_insert_with(|| CallbackData::new(self.statistics))
+            .add_execution(execution);
+    }
+}
+
+impl EventAnalysis for CallbackOffCpu {
+    fn initialize(&mut self) {
+        self.kernel_threads.clear();
+        self.running_callbacks.clear();
+        self.callbacks.clear();
+    }
+
+    fn process_event(&mut self, full_event: &FullEvent) {
+        match &full_event.event {
+            Event::Kernel(kernel::Event::SchedSwitch(event)) => {
+                self.process_sched_switch(event, full_event.time);
+            }
+            Event::Kernel(kernel::Event::SchedWakeup(event)) => {
+                self.process_sched_wakeup(event, full_event.time);
+            }
+            Event::Ros2(ros2::Event::CallbackStart(event)) => {
+                self.process_callback_start(event, full_event);
+            }
+            Event::Ros2(ros2::Event::CallbackEnd(_)) => {
+                self.process_callback_end(full_event);
+            }
+            _ => {}
+        }
+    }
+
+    fn finalize(&mut self) {
+        // Callbacks without the end event have unknown duration.
+        self.running_callbacks.clear();
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CallbackOffCpuRecord {
+    #[serde(flatten)]
+    callback: CallbackRecordId,
+    executions: Vec<ExecutionRecord>,
+    /// Summary of the executions if they were kept by a sketch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<ExecutionsSummaryRecord>,
+    /// Threads which woke up the blocked callback, sorted by the blocked time.
+    top_wakers: Vec<WakerRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExecutionsSummaryRecord {
+    duration: SamplesExport,
+    running_time: SamplesExport,
+    runnable_time: SamplesExport,
+    blocked_time: SamplesExport,
+}
+
+impl ExecutionsSummaryRecord {
+    fn new(summary: &ExecutionsSummary, quantiles: &[Quantile]) -> Self {
+        Self {
+            duration: summary.duration.export(quantiles),
+            running_time: summary.running_time.export(quantiles),
+            runnable_time: summary.runnable_time.export(quantiles),
+            blocked_time: summary.blocked_time.export(quantiles),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WakerRecord {
+    /// Thread name and thread id of the waker.
+    waker: String,
+    wakeups: usize,
+    /// Total time the callback was blocked before being woken up by the waker.
+    blocked_time: i64,
+}
+
+impl AnalysisOutput for CallbackOffCpu {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let quantiles = Args::get_analyses_args().quantiles();
+        let mut records: Vec<_> = self
+            .callbacks
+            .iter()
+            .map(|(callback, data)| {
+                let mut top_wakers: Vec<_> = data
+                    .wakers
+                    .iter()
+                    .map(|(waker, waker_data)| WakerRecord {
+                        waker: waker.clone(),
+                        wakeups: waker_data.wakeups,
+                        blocked_time: waker_data.blocked_time,
+                    })
+                    .collect();
+                top_wakers
+                    .sort_by(|a, b| (b.blocked_time, &a.waker).cmp(&(a.blocked_time, &b.waker)));
+                top_wakers.truncate(TOP_WAKERS);
+
+                CallbackOffCpuRecord {
+                    callback: (&*callback.0.lock().unwrap()).into(),
+                    executions: data.executions.clone(),
+                    summary: data
+                        .summary
+                        .as_ref()
+                        .map(|summary| ExecutionsSummaryRecord::new(summary, quantiles)),
+                    top_wakers,
+                }
+            })
+            .collect();
+        records.sort_by(|a, b| a.callback.cmp(&b.callback));
+
+        serde_json::to_writer(file, &records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execution_breakdown() {
+        let mut running = ExecutionBreakdown::new(Time::from_nanos(100));
+
+        running.enter_state(ThreadState::Runnable, Time::from_nanos(110));
+        running.enter_state(ThreadState::Running, Time::from_nanos(130));
+        running.enter_state(ThreadState::Blocked, Time::from_nanos(140));
+        assert_eq!(
+            running.enter_state(ThreadState::Runnable, Time::from_nanos(190)),
+            50
+        );
+        running.enter_state(ThreadState::Running, Time::from_nanos(195));
+        let execution = running.end(Time::from_nanos(200));
+
+        assert_eq!(execution.duration, 100);
+        assert_eq!(execution.running_time, 25);
+        assert_eq!(execution.runnable_time, 25);
+        assert_eq!(execution.blocked_time, 50);
+    }
+}
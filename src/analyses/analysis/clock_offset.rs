@@ -0,0 +1,447 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::model::{Publisher, Time};
+use crate::processed_events::{Event, FullEvent, ros2};
+
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
+
+/// Length of the windows in which the minimal latencies are taken.
+const WINDOW_NS: i64 = 1_000_000_000;
+
+/// Offset between the clocks of two hosts.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct OffsetEstimate {
+    /// Time on the clock of the first host at which the offset was estimated.
+    #[serde(serialize_with = "serialize_time")]
+    reference_time: Time,
+    /// Clock of the second host minus the clock of the first host at `reference_time`.
+    offset: i64,
+    /// Change of the offset in nanoseconds per second.
+    drift: f64,
+    /// `true` if messages were sent in both directions.
+    ///
+    /// Otherwise, the offset is only an upper bound including the minimal latency.
+    bidirectional: bool,
+}
+
+fn serialize_time<S>(time: &Time, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    s.serialize_i64(time.timestamp_nanos())
+}
+
+impl OffsetEstimate {
+    fn offset_at(&self, time: Time) -> i64 {
+        let elapsed = (time.timestamp_nanos() - self.reference_time.timestamp_nanos()) as f64;
+        self.offset + (self.drift * elapsed / 1e9) as i64
+    }
+}
+
+/// Estimated offsets between the clocks of the traced hosts.
+#[derive(Debug, Default)]
+pub struct ClockOffsets {
+    /// Offsets by the pair of hostnames, the first one is lower.
+    offsets: BTreeMap<(String, String), OffsetEstimate>,
+    /// Correct the latencies also by the offsets estimated from messages in one direction.
+    one_directional: bool,
+}
+
+impl ClockOffsets {
+    /// Returns `true` if none of the offsets can correct the latencies.
+    pub fn is_empty(&self) -> bool {
+        !self
+            .offsets
+            .values()
+            .any(|estimate| self.corrects(estimate))
+    }
+
+    /// Returns `true` if the `estimate` is used to correct the latencies.
+    ///
+    /// The offsets estimated from messages in one direction are biased by the minimum
+    /// network latency, so they are used only if allowed explicitly.
+    fn corrects(&self, estimate: &OffsetEstimate) -> bool {
+        estimate.bidirectional || self.one_directional
+    }
+
+    /// Returns the clock of `to_host` minus the clock of `from_host` at the `time`.
+    fn offset(&self, from_host: &str, to_host: &str, time: Time) -> Option<i64> {
+        if from_host == to_host {
+            return Some(0);
+        }
+        if let Some(estimate) = self
+            .offsets
+            .get(&(from_host.to_owned(), to_host.to_owned()))
+        {
+            return self.corrects(estimate).then(|| estimate.offset_at(time));
+        }
+        self.offsets
+            .get(&(to_host.to_owned(), from_host.to_owned()))
+            .filter(|estimate| self.corrects(estimate))
+            .map(|estimate| -estimate.offset_at(time))
+    }
+
+    /// Returns the `latency` of a message sent from `send_host` corrected by the offset
+    /// of the clocks, or `None` if the offset of the hosts is unknown or not used.
+    pub fn correct_latency(
+        &self,
+        send_host: &str,
+        receive_host: &str,
+        receive_time: Time,
+        latency: i64,
+    ) -> Option<i64> {
+        Some(latency - self.offset(send_host, receive_host, receive_time)?)
+    }
+}
+
+/// Offsets between the clocks of the hosts estimated from the messages sent between them.
+///
+/// The latency measured across hosts is the real latency plus the offset of their clocks.
+/// The minimal latency in each direction is assumed to be the same, so the offset is half
+/// of the difference of the minimal latencies measured in both directions. The minimums
+/// are taken in windows of one second and a line is fitted through them to get the drift.
+#[derive(Debug, Default)]
+pub struct ClockOffset {
+    offsets: Arc<ClockOffsets>,
+    /// Correct the latencies also by the offsets estimated from messages in one direction.
+    one_directional: bool,
+    publisher_hosts: HashMap<ArcMutWrapper<Publisher>, String>,
+    /// Receive time and latency of the messages by the sending and receiving host.
+    samples: HashMap<(String, String), Vec<(Time, i64)>>,
+}
+
+impl ClockOffset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Corrects the latencies also by the offsets estimated from messages sent in one
+    /// direction only. These offsets include the minimum network latency.
+    pub fn use_one_directional(&mut self) {
+        self.one_directional = true;
+    }
+
+    /// Returns the offsets to correct the latencies of other analyses.
+    pub fn get_offsets(&self) -> Arc<ClockOffsets> {
+        self.offsets.clone()
+    }
+
+    fn process_rmw_take(&mut self, event: &ros2::RmwTake, time: Time, hostname: &str) {
+        if !event.taken {
+            return;
+        }
+        let message = event.message.lock().unwrap();
+        let Some(publication) = message.get_publication_message() else {
+            return;
+        };
+        let publication = publication.lock().unwrap();
+        let (Some(publisher), Some(send_time)) = (
+            publication.get_publisher(),
+            publication.get_rmw_publication_time(),
+        ) else {
+            return;
+        };
+        let Some(publisher_host) = self.publisher_hosts.get(&publisher.into()) else {
+            return;
+        };
+        if publisher_host == hostname {
+            return;
+        }
+
+        self.samples
+            .entry((publisher_host.clone(), hostname.to_owned()))
+            .or_default()
+            .push((time, time.timestamp_nanos() - send_time.timestamp_nanos()));
+    }
+}
+
+/// Returns the minimal latency of each window by the window index.
+///
+/// The samples are given by the time on the clock of the first host and the latency.
+fn window_minimums(samples: impl Iterator<Item = (Time, i64)>, start: Time) -> BTreeMap<i64, i64> {
+    let mut minimums = BTreeMap::new();
+    for (time, latency) in samples {
+        let window = (time.timestamp_nanos() - start.timestamp_nanos()).div_euclid(WINDOW_NS);
+        minimums
+            .entry(window)
+            .and_modify(|minimum: &mut i64| *minimum = (*minimum).min(latency))
+            .or_insert(latency);
+    }
+    minimums
+}
+
+/// Fits a line through the offsets of the windows.
+///
+/// Returns the offset at the start of the first window and the drift in nanoseconds per second.
+fn fit_offset(points: &[(i64, i64)]) -> (i64, f64) {
+    let n = points.len() as f64;
+    let mean_window = points.iter().map(|&(window, _)| window as f64).sum::<f64>() / n;
+    let mean_offset = points.iter().map(|&(_, offset)| offset as f64).sum::<f64>() / n;
+    let (covariance, variance) =
+        points
+            .iter()
+            .fold((0.0, 0.0), |(covariance, variance), &(window, offset)| {
+                let window = window as f64 - mean_window;
+                (
+                    covariance + window * (offset as f64 - mean_offset),
+                    variance + window * window,
+                )
+            });
+    let drift = if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance
+    };
+    // Windows are one second long and their offsets are measured in the middle.
+    let offset = mean_offset - drift * (mean_window + 0.5);
+    (offset as i64, drift)
+}
+
+/// Estimates the offset of the clock of the receiving host of the `forward` messages.
+///
+/// The `backward` messages are sent in the other direction.
+fn estimate_offset(forward: &[(Time, i64)], backward: &[(Time, i64)]) -> Option<OffsetEstimate> {
+    // The forward messages are sent and the backward messages received on the clock
+    // of the first host.
+    let forward = forward
+        .iter()
+        .map(|&(time, latency)| (Time::from_nanos(time.timestamp_nanos() - latency), latency));
+    let reference_time = forward
+        .clone()
+        .map(|(time, _)| time)
+        .chain(backward.iter().map(|&(time, _)| time))
+        .min()?;
+    let forward_minimums = window_minimums(forward, reference_time);
+    let backward_minimums = window_minimums(backward.iter().copied(), reference_time);
+
+    let bidirectional = !forward_minimums.is_empty() && !backward_minimums.is_empty();
+    let points: Vec<(i64, i64)> = if bidirectional {
+        forward_minimums
+            .iter()
+            .filter_map(|(window, forward)| {
+                let backward = backward_minimums.get(window)?;
+                Some((*window, (forward - backward) / 2))
+            })
+            .collect()
+    } else {
+        forward_minimums
+            .iter()
+            .map(|(&window, &forward)| (window, forward))
+            .chain(
+                backward_minimums
+                    .iter()
+                    .map(|(&window, &backward)| (window, -backward)),
+            )
+            .collect()
+    };
+    if points.is_empty() {
+        // Messages in each direction were sent at different times.
+        return None;
+    }
+
+    let (offset, drift) = fit_offset(&points);
+    Some(OffsetEstimate {
+        reference_time,
+        offset,
+        drift,
+        bidirectional,
+    })
+}
+
+impl EventAnalysis for ClockOffset {
+    fn initialize(&mut self) {
+        *self = Self {
+            one_directional: self.one_directional,
+            ..Self::default()
+        };
+    }
+
+    fn process_event(&mut self, full_event: &FullEvent) {
+        match &full_event.event {
+            Event::Ros2(ros2::Event::RmwPublish(event)) => {
+                if let Some(publisher) = event.message.lock().unwrap().get_publisher() {
+                    self.publisher_hosts
+                        .entry(publisher.into())
+                        .or_insert_with(|| full_event.context.hostname().to_owned());
+                }
+            }
+            Event::Ros2(ros2::Event::RmwTake(event)) => {
+                self.process_rmw_take(event, full_event.time, full_event.context.hostname());
+            }
+            _ => {}
+        }
+    }
+
+    fn finalize(&mut self) {
+        let mut offsets = ClockOffsets {
+            one_directional: self.one_directional,
+            ..ClockOffsets::default()
+        };
+        for (first_host, second_host) in self.samples.keys() {
+            if first_host > second_host
+                && self
+                    .samples
+                    .contains_key(&(second_host.clone(), first_host.clone()))
+            {
+                // Estimated together with the opposite direction.
+                continue;
+            }
+            let (first_host, second_host) = if first_host < second_host {
+                (first_host, second_host)
+            } else {
+                (second_host, first_host)
+            };
+            let key = (first_host.clone(), second_host.clone());
+            let forward = self.samples.get(&key).map_or(&[][..], Vec::as_slice);
+            let backward = self
+                .samples
+                .get(&(second_host.clone(), first_host.clone()))
+                .map_or(&[][..], Vec::as_slice);
+
+            let Some(estimate) = estimate_offset(forward, backward) else {
+                log::warn!(
+                    "Clock offset of {second_host} to {first_host} cannot be estimated. \
+                    The messages in each direction were sent at different times."
+                );
+                continue;
+            };
+            log::info!(
+                "Clock offset of {second_host} to {first_host}: {} ns, drift {:.3} ppm",
+                estimate.offset,
+                estimate.drift / 1e3
+            );
+            if !estimate.bidirectional {
+                log::warn!(
+                    "Clock offset of {second_host} to {first_host} is estimated from messages \
+                    in one direction only. It is biased by the minimum network latency{}.",
+                    if self.one_directional {
+                        ""
+                    } else {
+                        " and does not correct the latencies"
+                    }
+                );
+            }
+            offsets.offsets.insert(key, estimate);
+        }
+        self.offsets = Arc::new(offsets);
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ClockOffsetRecord<'a> {
+    first_host: &'a str,
+    second_host: &'a str,
+    #[serde(flatten)]
+    estimate: OffsetEstimate,
+    /// Number of messages sent from the first host to the second one.
+    forward_messages: usize,
+    /// Number of messages sent from the second host to the first one.
+    backward_messages: usize,
+    /// Bias of the offset estimated from messages in one direction only.
+    bias: Option<&'static str>,
+    /// `true` if the offset corrects the message latencies with `--correct-clock-offsets`.
+    corrects_latencies: bool,
+}
+
+impl AnalysisOutput for ClockOffset {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let message_count = |from: &str, to: &str| {
+            self.samples
+                .get(&(from.to_owned(), to.to_owned()))
+                .map_or(0, Vec::len)
+        };
+        let records: Vec<_> = self
+            .offsets
+            .offsets
+            .iter()
+            .map(|((first_host, second_host), estimate)| ClockOffsetRecord {
+                first_host,
+                second_host,
+                estimate: *estimate,
+                forward_messages: message_count(first_host, second_host),
+                backward_messages: message_count(second_host, first_host),
+                bias: (!estimate.bidirectional).then_some("minimum network latency"),
+                corrects_latencies: self.offsets.corrects(estimate),
+            })
+            .collect();
+
+        serde_json::to_writer(file, &records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_from_bidirectional_messages() {
+        // The second host is 5 ms ahead and drifts by 10 us per second.
+        // The minimal latency is 100 us in both directions.
+        let offset_at = |time: i64| 5_000_000 + time / 100_000;
+        let mut forward = Vec::new();
+        let mut backward = Vec::new();
+        for i in 0..20 {
+            let send = i * 500_000_000;
+            let latency = 100_000 + (i % 3) * 50_000;
+            forward.push((
+                Time::from_nanos(send + latency + offset_at(send)),
+                latency + offset_at(send),
+            ));
+            backward.push((Time::from_nanos(send + latency), latency - offset_at(send)));
+        }
+
+        let estimate = estimate_offset(&forward, &backward).unwrap();
+        assert!(estimate.bidirectional);
+        assert!((estimate.offset - 5_000_000).abs() < 10_000, "{estimate:?}");
+        assert!((estimate.drift - 10_000.0).abs() < 1_000.0, "{estimate:?}");
+
+        let offsets = ClockOffsets {
+            offsets: BTreeMap::from([(("a".to_owned(), "b".to_owned()), estimate)]),
+            one_directional: false,
+        };
+        let corrected = offsets
+            .correct_latency("b", "a", Time::from_nanos(0), -4_900_000)
+            .unwrap();
+        assert!((corrected - 100_000).abs() < 10_000, "{corrected}");
+        assert_eq!(
+            offsets.correct_latency("a", "c", Time::from_nanos(0), 10),
+            None
+        );
+    }
+
+    #[test]
+    fn one_directional_offset_corrects_only_if_allowed() {
+        // The second host is 5 ms ahead and the minimal latency is 100 us.
+        let forward: Vec<_> = (0..4)
+            .map(|i| {
+                let send = i * 500_000_000;
+                (Time::from_nanos(send + 5_100_000), 5_100_000)
+            })
+            .collect();
+
+        let estimate = estimate_offset(&forward, &[]).unwrap();
+        assert!(!estimate.bidirectional);
+        // The offset includes the minimal latency.
+        assert_eq!(estimate.offset, 5_100_000);
+
+        let mut offsets = ClockOffsets {
+            offsets: BTreeMap::from([(("a".to_owned(), "b".to_owned()), estimate)]),
+            one_directional: false,
+        };
+        assert!(offsets.is_empty());
+        assert_eq!(
+            offsets.correct_latency("a", "b", Time::from_nanos(0), 5_100_000),
+            None
+        );
+
+        offsets.one_directional = true;
+        assert!(!offsets.is_empty());
+        assert_eq!(
+            offsets.correct_latency("a", "b", Time::from_nanos(0), 5_100_000),
+            Some(0)
+        );
+    }
+}
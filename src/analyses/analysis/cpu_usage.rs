@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::model::{Thread, Time};
+use crate::processed_events::{Event, FullEvent, kernel, ros2};
+
+use super::EventAnalysis;
+
+/// CPU usage of threads and CPUs based on the kernel scheduler events.
+#[derive(Debug, Default)]
+pub struct CpuUsage {
+    /// Threads which executed a callback by hostname and thread id.
+    callback_threads: HashSet<(String, u32)>,
+
+    threads: HashMap<(String, u32), ThreadData>,
+    cpus: HashMap<(String, u32), CpuData>,
+
+    /// Time of the first and the last kernel event on each host.
+    time_spans: HashMap<String, (Time, Time)>,
+}
+
+#[derive(Debug)]
+struct ThreadData {
+    thread: Arc<Mutex<Thread>>,
+
+    /// Time since when the thread waits for a CPU after being preempted.
+    preempted_since: Option<Time>,
+    /// Total time spent preempted while runnable.
+    preempted_time: i64,
+}
+
+#[derive(Debug, Default)]
+struct CpuData {
+    /// Thread id running on the CPU and the time since when it runs. Thread id 0 is idle.
+    running: Option<(u32, Time)>,
+    /// Time the CPU spent running each thread, except the idle task.
+    busy_time_by_thread: HashMap<u32, i64>,
+}
+
+impl ThreadData {
+    fn new(thread: Arc<Mutex<Thread>>) -> Self {
+        Self {
+            thread,
+            preempted_since: None,
+            preempted_time: 0,
+        }
+    }
+
+    fn end_preemption(&mut self, time: Time) {
+        if let Some(since) = self.preempted_since.take() {
+            self.preempted_time += time.timestamp_nanos() - since.timestamp_nanos();
+        }
+    }
+}
+
+impl CpuData {
+    fn switch_to(&mut self, tid: u32, time: Time) {
+        self.end_interval(time);
+        self.running = Some((tid, time));
+    }
+
+    fn end_interval(&mut self, time: Time) {
+        if let Some((tid, since)) = self.running.take()
+            && tid != 0
+        {
+            *self.busy_time_by_thread.entry(tid).or_default() +=
+                time.timestamp_nanos() - since.timestamp_nanos();
+        }
+    }
+}
+
+impl CpuUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn thread_data(&mut self, thread: &Arc<Mutex<Thread>>) -> &mut ThreadData {
+        let key = {
+            let thread = thread.lock().unwrap();
+            (thread.get_hostname().to_owned(), thread.get_tid())
+        };
+        self.threads
+            .entry(key)
+            .or_insert_with(|| ThreadData::new(thread.clone()))
+    }
+
+    fn process_sched_switch(&mut self, event: &kernel::SchedSwitch, full_event: &FullEvent) {
+        let time = full_event.time;
+        if let Some(prev) = &event.prev {
+            let prev = self.thread_data(prev);
+            if event.prev_preempted {
+                prev.preempted_since = Some(time);
+            }
+        }
+
+        let next_tid = match &event.next {
+            Some(next) => {
+                let next = self.thread_data(next);
+                next.end_preemption(time);
+                next.thread.lock().unwrap().get_tid()
+            }
+            None => 0,
+        };
+
+        self.cpus
+            .entry((full_event.context.hostname().to_owned(), event.cpu))
+            .or_default()
+            .switch_to(next_tid, time);
+    }
+
+    /// Returns `true` if no kernel scheduler events were processed.
+    pub fn is_empty(&self) -> bool {
+        self.time_spans.is_empty()
+    }
+
+    fn time_span(&self, hostname: &str) -> i64 {
+        self.time_spans.get(hostname).map_or(0, |(start, end)| {
+            end.timestamp_nanos() - start.timestamp_nanos()
+        })
+    }
+
+    /// Returns the usage of threads which executed a callback.
+    pub(crate) fn get_thread_records(&self) -> Vec<ThreadUsageRecord> {
+        let mut records: Vec<_> = self
+            .callback_threads
+            .iter()
+            .filter_map(|key| {
+                let data = self.threads.get(key)?;
+                let (_, end_time) = self.time_spans[&key.0];
+                let time_span = self.time_span(&key.0);
+
+                let thread = data.thread.lock().unwrap();
+                let on_cpu_time = thread.get_on_cpu_time(end_time);
+                Some(ThreadUsageRecord {
+                    hostname: key.0.clone(),
+                    tid: key.1,
+                    name: thread.get_name().to_owned(),
+                    on_cpu_time,
+                    preempted_time: data.preempted_time,
+                    utilization: (time_span != 0).then(|| on_cpu_time as f64 / time_span as f64),
+                })
+            })
+            .collect();
+        records.sort_by(|a, b| (&a.hostname, a.tid).cmp(&(&b.hostname, b.tid)));
+        records
+    }
+
+    /// Returns the usage of each CPU split between the threads executing callbacks
+    /// and the other processes.
+    pub(crate) fn get_cpu_records(&self) -> Vec<CpuUsageRecord> {
+        let mut records: Vec<_> = self
+            .cpus
+            .iter()
+            .map(|((hostname, cpu), data)| {
+                let (callback_threads_time, other_time) = data.busy_time_by_thread.iter().fold(
+                    (0, 0),
+                    |(callback_threads_time, other_time), (tid, busy_time)| {
+                        if self.callback_threads.contains(&(hostname.clone(), *tid)) {
+                            (callback_threads_time + busy_time, other_time)
+                        } else {
+                            (callback_threads_time, other_time + busy_time)
+                        }
+                    },
+                );
+
+                let time_span = self.time_span(hostname);
+                let ratio = |time: i64| (time_span != 0).then(|| time as f64 / time_span as f64);
+                CpuUsageRecord {
+                    hostname: hostname.clone(),
+                    cpu: *cpu,
+                    utilization: ratio(callback_threads_time + other_time),
+                    callback_threads_utilization: ratio(callback_threads_time),
+                    other_utilization: ratio(other_time),
+                }
+            })
+            .collect();
+        records.sort_by(|a, b| (&a.hostname, a.cpu).cmp(&(&b.hostname, b.cpu)));
+        records
+    }
+}
+
+impl EventAnalysis for CpuUsage {
+    fn initialize(&mut self) {
+        *self = Self::default();
+    }
+
+    fn process_event(&mut self, full_event: &FullEvent) {
+        match &full_event.event {
+            Event::Kernel(event) => {
+                let time = full_event.time;
+                self.time_spans
+                    .entry(full_event.context.hostname().to_owned())
+                    .and_modify(|(_, end)| *end = time)
+                    .or_insert((time, time));
+
+                if let kernel::Event::SchedSwitch(event) = event {
+                    self.process_sched_switch(event, full_event);
+                }
+            }
+            Event::Ros2(ros2::Event::CallbackStart(_)) => {
+                if let Some(vtid) = full_event.context.vtid() {
+                    self.callback_threads
+                        .insert((full_event.context.hostname().to_owned(), vtid));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finalize(&mut self) {
+        for ((hostname, _), data) in &mut self.threads {
+            let (_, end_time) = self.time_spans[hostname];
+            data.end_preemption(end_time);
+        }
+        for ((hostname, _), data) in &mut self.cpus {
+            let (_, end_time) = self.time_spans[hostname];
+            data.end_interval(end_time);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ThreadUsageRecord {
+    pub(crate) hostname: String,
+    pub(crate) tid: u32,
+    pub(crate) name: String,
+    /// Total time the thread spent on a CPU in nanoseconds.
+    pub(crate) on_cpu_time: i64,
+    /// Total time the thread was preempted while runnable in nanoseconds.
+    pub(crate) preempted_time: i64,
+    /// Ratio of the on-CPU time to the duration of the kernel trace.
+    pub(crate) utilization: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CpuUsageRecord {
+    pub(crate) hostname: String,
+    pub(crate) cpu: u32,
+    /// Ratio of the time the CPU was not idle to the duration of the kernel trace.
+    pub(crate) utilization: Option<f64>,
+    /// Part of the utilization caused by the threads executing callbacks.
+    pub(crate) callback_threads_utilization: Option<f64>,
+    /// Part of the utilization caused by other threads and processes.
+    pub(crate) other_utilization: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raw_events::testing::TraceBuilder;
+
+    use super::super::analyze_events;
+    use super::*;
+
+    /// `prev_state` of a preempted task since Linux 4.14, shown as `R+`.
+    const TASK_REPORT_MAX: i64 = 0x100;
+    const TASK_INTERRUPTIBLE: i64 = 0x1;
+
+    #[test]
+    fn cpu_busy_time() {
+        let mut cpu = CpuData::default();
+        cpu.switch_to(10, Time::from_nanos(0));
+        cpu.switch_to(0, Time::from_nanos(30));
+        cpu.switch_to(20, Time::from_nanos(50));
+        cpu.switch_to(10, Time::from_nanos(60));
+        cpu.end_interval(Time::from_nanos(100));
+
+        assert_eq!(cpu.busy_time_by_thread, HashMap::from([(10, 70), (20, 10)]));
+    }
+
+    #[test]
+    fn preempted_time_of_runnable_threads() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .sched_switch(0, 0, 0, 0, 10)
+            // Thread 10 is preempted while runnable by thread 20, which then sleeps.
+            .sched_switch(100, 0, 10, TASK_REPORT_MAX, 20)
+            .sched_switch(150, 0, 20, TASK_INTERRUPTIBLE, 10)
+            .sched_switch(200, 0, 10, 0, 20)
+            .sched_switch(230, 0, 20, TASK_INTERRUPTIBLE, 10)
+            .sched_switch(300, 0, 10, TASK_INTERRUPTIBLE, 0);
+
+        let mut analysis = CpuUsage::new();
+        let _model = analyze_events(trace.build(), &mut analysis);
+
+        let preempted_time = |tid| analysis.threads[&("host".to_owned(), tid)].preempted_time;
+        assert_eq!(preempted_time(10), 80);
+        assert_eq!(preempted_time(20), 0);
+    }
+}
@@ -1,20 +1,28 @@
 use std::collections::{HashMap, HashSet};
-use std::ops::Not;
 use std::sync::{Arc, Mutex};
 
+use serde::{Deserialize, Serialize};
+
 use crate::analysis::utils::DisplayDurationStats;
+use crate::argsv2::Args;
+use crate::argsv2::chart_args::ChartedValue;
+use crate::chart_links::{
+    callback_interface, chart_link, publisher_interface, service_interface, subscriber_interface,
+};
+use crate::data_quality::{DiscardedWindow, DiscardedWindows};
 use crate::events_common::Context;
+use crate::model::display::get_node_name_from_weak;
 use crate::model::{
     self, Callback, CallbackCaller, CallbackInstance, CallbackTrigger, Publisher, Service,
     Subscriber, Time, Timer,
 };
 use crate::processed_events::{Event, FullEvent, r2r, ros2};
-use crate::statistics::Sorted;
-use crate::utils::{DisplayDuration, Known};
+use crate::statistics::{Quantile, Samples, SamplesExport, StatisticsBackend};
+use crate::utils::{ArcWeak, DisplayDuration, Known, WeakKnown};
 use crate::visualization::COLOR_GRADIENT;
 use crate::visualization::graphviz_export::{self, NodeShape};
 
-use super::{ArcMutWrapper, EventAnalysis};
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
 
 const LATENCY_INVALID: i64 = i64::MAX;
 
@@ -24,12 +32,13 @@ struct ThreadId {
     hostname: String,
 }
 
-impl From<&Context> for ThreadId {
-    fn from(context: &Context) -> Self {
-        Self {
-            vtid: context.vtid(),
+impl ThreadId {
+    /// Returns the thread of the event or `None` if the trace did not record it.
+    fn of(context: &Context) -> Option<Self> {
+        Some(Self {
+            vtid: context.vtid()?,
             hostname: context.hostname().to_string(),
-        }
+        })
     }
 }
 
@@ -46,6 +55,9 @@ pub struct DependencyGraph {
 
     last_spin_wake_up_time_for_node: HashMap<ArcMutWrapper<model::Node>, Time>,
     running_callbacks: HashMap<ThreadId, Arc<Mutex<CallbackInstance>>>,
+    discarded_windows: DiscardedWindows,
+
+    statistics: StatisticsBackend,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -57,45 +69,125 @@ pub enum Node {
     Callback(ArcMutWrapper<Callback>),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+impl Node {
+    /// Returns the full name of the ROS node containing this graph node.
+    fn ros_node_name(&self) -> WeakKnown<String> {
+        let ros_node: Option<ArcWeak<Mutex<model::Node>>> = match self {
+            Node::Publisher(publisher) => publisher.0.lock().unwrap().get_node().into(),
+            Node::Subscriber(subscriber) => subscriber.0.lock().unwrap().get_node().into(),
+            Node::Service(service) => service.0.lock().unwrap().get_node().into(),
+            Node::Timer(timer) => timer.0.lock().unwrap().get_node().into(),
+            Node::Callback(callback) => callback.0.lock().unwrap().get_node(),
+        };
+
+        ros_node.map_or(WeakKnown::Unknown, |ros_node| {
+            get_node_name_from_weak(&ros_node.get_weak())
+        })
+    }
+
+    /// Returns the name of the interface within its ROS node, e.g. `Publisher(/topic)`.
+    ///
+    /// The names match the interfaces accepted by the `chart` command.
+    fn interface_name(&self) -> String {
+        match self {
+            Node::Publisher(publisher) => {
+                publisher_interface(publisher.0.lock().unwrap().get_topic())
+            }
+            Node::Subscriber(subscriber) => {
+                subscriber_interface(subscriber.0.lock().unwrap().get_topic())
+            }
+            Node::Service(service) => service_interface(service.0.lock().unwrap().get_name()),
+            Node::Timer(timer) => {
+                let period = timer.0.lock().unwrap().get_period();
+                callback_interface("Timer", period.map(DisplayDuration))
+            }
+            Node::Callback(callback) => {
+                let callback = callback.0.lock().unwrap();
+                let caller = callback
+                    .get_caller()
+                    .map_or(WeakKnown::Unknown, CallbackCaller::get_caller_as_string);
+                callback_interface(callback.get_type(), caller)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct PublisherNode {
     /// Time between two consecutive publications
-    publication_delay: Vec<i64>,
+    publication_delay: Samples,
 
     /// Time of the last publication
     last_publication: Option<Time>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+impl PublisherNode {
+    fn new(statistics: StatisticsBackend) -> Self {
+        Self {
+            publication_delay: statistics.samples(),
+            last_publication: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SubscriberNode {
     /// Time between two consecutive take events
-    take_delay: Vec<i64>,
+    take_delay: Samples,
 
     /// Time of the last take event
     last_take: Option<Time>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+impl SubscriberNode {
+    fn new(statistics: StatisticsBackend) -> Self {
+        Self {
+            take_delay: statistics.samples(),
+            last_take: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TimerNode {
     /// Time between two consecutive timer activations
-    activation_delay: Vec<i64>,
+    activation_delay: Samples,
 
     /// Last activation time
     last_activation: Option<Time>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+impl TimerNode {
+    fn new(statistics: StatisticsBackend) -> Self {
+        Self {
+            activation_delay: statistics.samples(),
+            last_activation: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct CallbackNode {
     /// Time between two consecutive callback activations. (c1.start) -> (c2.start)
-    activation_delay: Vec<i64>,
+    activation_delay: Samples,
 
     /// Duration of the callback execution. (c1.start) -> (c1.end)
-    durations: Vec<i64>,
+    durations: Samples,
 
     /// Last activation time
     last_activation: Option<Time>,
 }
 
+impl CallbackNode {
+    fn new(statistics: StatisticsBackend) -> Self {
+        Self {
+            activation_delay: statistics.samples(),
+            durations: statistics.samples(),
+            last_activation: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum Edge {
     PublicationInCallback(ArcMutWrapper<Publisher>, ArcMutWrapper<Callback>),
@@ -105,8 +197,9 @@ enum Edge {
     PublisherSubscriberCommunication(ArcMutWrapper<Publisher>, ArcMutWrapper<Subscriber>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum EdgeType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EdgeType {
     PublisherSubscriberCommunication,
     SubscriberCallbackInvocation,
     TimerCallbackInvocation,
@@ -156,19 +249,57 @@ impl Edge {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Clone)]
 pub struct EdgeData {
-    activation_delay: Vec<i64>,
-    latencies: Vec<i64>,
+    activation_delay: Samples,
+    /// Latencies of the activations, [`LATENCY_INVALID`] if unknown.
+    ///
+    /// The sketch keeps only the known latencies.
+    latencies: Samples,
+    /// Number of activations with an unknown latency.
+    unknown_latencies: usize,
     last_activation: Option<Time>,
 }
 
+impl EdgeData {
+    fn new(statistics: StatisticsBackend) -> Self {
+        Self {
+            activation_delay: statistics.samples(),
+            latencies: statistics.samples(),
+            unknown_latencies: 0,
+            last_activation: None,
+        }
+    }
+
+    fn push_latency(&mut self, latency: i64) {
+        if latency == LATENCY_INVALID {
+            self.unknown_latencies += 1;
+            if let Samples::Sketch(_) = self.latencies {
+                return;
+            }
+        }
+        self.latencies.push(latency);
+    }
+
+    fn activation_count(&self) -> usize {
+        match self.latencies {
+            Samples::Exact(_) => self.latencies.len(),
+            Samples::Sketch(_) => self.latencies.len() + self.unknown_latencies,
+        }
+    }
+}
+
 // Public API
 impl DependencyGraph {
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Keeps the timing series by the `statistics` backend.
+    pub fn set_statistics(&mut self, statistics: StatisticsBackend) {
+        self.statistics = statistics;
+    }
+
     pub fn display_as_dot(
         &self,
         color: bool,
@@ -186,7 +317,10 @@ impl DependencyGraph {
     }
 
     fn process_timer_invocation(&mut self, timer: &Arc<Mutex<Timer>>, event_time: Time) {
-        let timer_node = self.timer_nodes.entry(timer.clone().into()).or_default();
+        let timer_node = self
+            .timer_nodes
+            .entry(timer.clone().into())
+            .or_insert_with(|| TimerNode::new(self.statistics));
         if let Some(previous_activation) = timer_node.last_activation.replace(event_time) {
             let activation_delay =
                 event_time.timestamp_nanos() - previous_activation.timestamp_nanos();
@@ -207,19 +341,22 @@ impl DependencyGraph {
                 let message = msg.lock().unwrap();
                 let subscriber = message.get_subscriber().unwrap();
                 let edge = Edge::SubscriberCallbackInvocation(subscriber.into(), callback_arc);
-                let edge_data = self.edges.entry(edge).or_default();
+                let edge_data = self
+                    .edges
+                    .entry(edge)
+                    .or_insert_with(|| EdgeData::new(self.statistics));
 
                 if let Some(previous_activation) = edge_data.last_activation.replace(event_time) {
                     debug_assert_eq!(
                         edge_data.activation_delay.len() + 1,
-                        edge_data.latencies.len()
+                        edge_data.activation_count()
                     );
 
                     let activation_delay =
                         event_time.timestamp_nanos() - previous_activation.timestamp_nanos();
                     edge_data.activation_delay.push(activation_delay);
                 } else {
-                    debug_assert!(edge_data.latencies.is_empty());
+                    debug_assert_eq!(edge_data.activation_count(), 0);
                     debug_assert!(edge_data.activation_delay.is_empty());
                 }
 
@@ -227,7 +364,7 @@ impl DependencyGraph {
                     .get_rmw_receive_time()
                     .expect("RMW receive time should be known");
                 let latency = event_time.timestamp_nanos() - receive_time.timestamp_nanos();
-                edge_data.latencies.push(latency);
+                edge_data.push_latency(latency);
             }
             CallbackTrigger::Timer(timer) => {
                 // Timer and Timer callback have the same data because the timer invocation is the
@@ -235,19 +372,22 @@ impl DependencyGraph {
                 self.process_timer_invocation(timer, event_time);
 
                 let edge = Edge::TimerCallbackInvocation(timer.clone().into(), callback_arc);
-                let edge_data = self.edges.entry(edge).or_default();
+                let edge_data = self
+                    .edges
+                    .entry(edge)
+                    .or_insert_with(|| EdgeData::new(self.statistics));
 
                 if let Some(previous_activation) = edge_data.last_activation.replace(event_time) {
                     debug_assert_eq!(
                         edge_data.activation_delay.len() + 1,
-                        edge_data.latencies.len()
+                        edge_data.activation_count()
                     );
 
                     let activation_delay =
                         event_time.timestamp_nanos() - previous_activation.timestamp_nanos();
                     edge_data.activation_delay.push(activation_delay);
                 } else {
-                    debug_assert!(edge_data.latencies.is_empty());
+                    debug_assert_eq!(edge_data.activation_count(), 0);
                     debug_assert!(edge_data.activation_delay.is_empty());
                 }
 
@@ -266,24 +406,27 @@ impl DependencyGraph {
                         event_time.timestamp_nanos() - wake_up_time.timestamp_nanos()
                     });
 
-                edge_data.latencies.push(latency);
+                edge_data.push_latency(latency);
             }
             CallbackTrigger::Service(service_arc) => {
                 let edge =
                     Edge::ServiceCallbackInvocation(service_arc.clone().into(), callback_arc);
-                let edge_data = self.edges.entry(edge).or_default();
+                let edge_data = self
+                    .edges
+                    .entry(edge)
+                    .or_insert_with(|| EdgeData::new(self.statistics));
 
                 if let Some(previous_activation) = edge_data.last_activation.replace(event_time) {
                     debug_assert_eq!(
                         edge_data.activation_delay.len() + 1,
-                        edge_data.latencies.len()
+                        edge_data.activation_count()
                     );
 
                     let activation_delay =
                         event_time.timestamp_nanos() - previous_activation.timestamp_nanos();
                     edge_data.activation_delay.push(activation_delay);
                 } else {
-                    debug_assert!(edge_data.latencies.is_empty());
+                    debug_assert_eq!(edge_data.activation_count(), 0);
                     debug_assert!(edge_data.activation_delay.is_empty());
                 }
 
@@ -300,7 +443,7 @@ impl DependencyGraph {
                         event_time.timestamp_nanos() - wake_up_time.timestamp_nanos()
                     });
 
-                edge_data.latencies.push(latency);
+                edge_data.push_latency(latency);
             }
         }
     }
@@ -311,15 +454,14 @@ impl DependencyGraph {
         event_time: Time,
         context: &Context,
     ) {
-        self.running_callbacks
-            .insert(context.into(), event.callback.clone())
-            .inspect(|old| {
-                panic!(
-                    "Callback {old:?} is already running on vtid {} on host {}",
-                    context.vtid(),
-                    context.hostname()
-                );
-            });
+        // Without the thread, the publications cannot be attributed to the callback.
+        if let Some(thread) = ThreadId::of(context)
+            && let Some(stale) = self
+                .running_callbacks
+                .insert(thread.clone(), event.callback.clone())
+        {
+            self.drop_stale_callback(&stale, &thread, event_time);
+        }
 
         let callback_instance = event.callback.lock().unwrap();
         let callback = callback_instance.get_callback();
@@ -327,7 +469,7 @@ impl DependencyGraph {
         let callback_node = self
             .callback_nodes
             .entry(callback.clone().into())
-            .or_default();
+            .or_insert_with(|| CallbackNode::new(self.statistics));
         let previous_activation = callback_node.last_activation.replace(event_time);
         if let Some(previous_activation) = previous_activation {
             debug_assert_eq!(event_time, callback_instance.get_start_time());
@@ -345,21 +487,16 @@ impl DependencyGraph {
         event_time: Time,
         context: &Context,
     ) {
-        self.running_callbacks
-            .remove(&context.into())
-            .and_then(|callback| {
-                Arc::ptr_eq(&callback, &event.callback)
-                    .not()
-                    .then_some(callback)
-            })
-            .inspect(|old| {
-                panic!(
-                    "Callback {old:?} is running on vtid {} on host {} instead of expected {:?}",
-                    context.vtid(),
-                    context.hostname(),
-                    event.callback
-                );
-            });
+        if let Some(thread) = ThreadId::of(context)
+            && let Some(stale) = self
+                .running_callbacks
+                .remove(&thread)
+                .filter(|callback| !Arc::ptr_eq(callback, &event.callback))
+        {
+            // The start of this execution is not known, so its duration is not either.
+            self.drop_stale_callback(&stale, &thread, event_time);
+            return;
+        }
 
         let callback_instance = event.callback.lock().unwrap();
         let callback = callback_instance.get_callback();
@@ -384,6 +521,24 @@ impl DependencyGraph {
         callback_node.durations.push(duration);
     }
 
+    /// Drops the `stale` execution still running on the `thread` at `time` and counts it
+    /// against the discarded windows. Its end was likely discarded by the tracer.
+    fn drop_stale_callback(
+        &mut self,
+        stale: &Arc<Mutex<CallbackInstance>>,
+        thread: &ThreadId,
+        time: Time,
+    ) {
+        let stale = stale.lock().unwrap();
+        self.discarded_windows
+            .exclude(&[&thread.hostname], stale.get_start_time(), time);
+        log::warn!(
+            "Dependency graph: callback {stale} on vtid {} on host {} did not end. Missing callback_end event?",
+            thread.vtid,
+            thread.hostname,
+        );
+    }
+
     fn process_rmw_take(&mut self, event: &ros2::RmwTake, event_time: Time) {
         if !event.taken {
             // Only process taken messages
@@ -394,7 +549,7 @@ impl DependencyGraph {
         let subscriber_node = self
             .subscriber_nodes
             .entry(subscriber_arc.clone().into())
-            .or_default();
+            .or_insert_with(|| SubscriberNode::new(self.statistics));
         if let Some(previous_take) = subscriber_node.last_take.replace(event_time) {
             let take_delay = event_time.timestamp_nanos() - previous_take.timestamp_nanos();
             subscriber_node.take_delay.push(take_delay);
@@ -413,19 +568,22 @@ impl DependencyGraph {
             .expect("Publisher should be known.");
         let edge =
             Edge::PublisherSubscriberCommunication(publisher_arc.into(), subscriber_arc.into());
-        let edge_data = self.edges.entry(edge).or_default();
+        let edge_data = self
+            .edges
+            .entry(edge)
+            .or_insert_with(|| EdgeData::new(self.statistics));
 
         if let Some(previous_activation) = edge_data.last_activation.replace(event_time) {
             debug_assert_eq!(
                 edge_data.activation_delay.len() + 1,
-                edge_data.latencies.len()
+                edge_data.activation_count()
             );
 
             let activation_delay =
                 event_time.timestamp_nanos() - previous_activation.timestamp_nanos();
             edge_data.activation_delay.push(activation_delay);
         } else {
-            debug_assert!(edge_data.latencies.is_empty());
+            debug_assert_eq!(edge_data.activation_count(), 0);
             debug_assert!(edge_data.activation_delay.is_empty());
         }
 
@@ -436,7 +594,7 @@ impl DependencyGraph {
                 .expect("Publication time should be known on published messages")
                 .timestamp_nanos();
 
-        edge_data.latencies.push(latency);
+        edge_data.push_latency(latency);
     }
 
     fn process_publication(
@@ -451,7 +609,7 @@ impl DependencyGraph {
         let publisher_node = self
             .publisher_nodes
             .entry(publisher_arc.clone().into())
-            .or_default();
+            .or_insert_with(|| PublisherNode::new(self.statistics));
 
         if let Some(previous_publication) = publisher_node.last_publication.replace(event_time) {
             let publication_delay =
@@ -461,36 +619,44 @@ impl DependencyGraph {
             debug_assert!(publisher_node.publication_delay.is_empty());
         }
 
-        if let Some(callback_instance_arc) = self.running_callbacks.get(&context.into()) {
+        if let Some(callback_instance_arc) =
+            ThreadId::of(context).and_then(|thread| self.running_callbacks.get(&thread))
+        {
             let callback_instance = callback_instance_arc.lock().unwrap();
             let callback_arc = callback_instance.get_callback();
             let edge = Edge::PublicationInCallback(publisher_arc.into(), callback_arc.into());
-            let edge_data = self.edges.entry(edge).or_default();
+            let edge_data = self
+                .edges
+                .entry(edge)
+                .or_insert_with(|| EdgeData::new(self.statistics));
 
             if let Some(previous_activation) = edge_data.last_activation.replace(event_time) {
                 debug_assert_eq!(
                     edge_data.activation_delay.len() + 1,
-                    edge_data.latencies.len()
+                    edge_data.activation_count()
                 );
 
                 let activation_delay =
                     event_time.timestamp_nanos() - previous_activation.timestamp_nanos();
                 edge_data.activation_delay.push(activation_delay);
             } else {
-                debug_assert!(edge_data.latencies.is_empty());
+                debug_assert_eq!(edge_data.activation_count(), 0);
                 debug_assert!(edge_data.activation_delay.is_empty());
             }
 
             let latency =
                 event_time.timestamp_nanos() - callback_instance.get_start_time().timestamp_nanos();
-            edge_data.latencies.push(latency);
+            edge_data.push_latency(latency);
         }
     }
 }
 
 impl EventAnalysis for DependencyGraph {
     fn initialize(&mut self) {
-        *self = Self::default();
+        *self = Self {
+            statistics: self.statistics,
+            ..Self::default()
+        };
     }
 
     fn process_event(&mut self, full_event: &FullEvent) {
@@ -531,11 +697,185 @@ impl EventAnalysis for DependencyGraph {
         }
     }
 
+    fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+        self.discarded_windows.add(window);
+    }
+
     fn finalize(&mut self) {
         self.running_callbacks.clear();
+
+        let excluded = self.discarded_windows.get_excluded_count();
+        if excluded > 0 {
+            log::warn!(
+                "Dependency graph: dropped {excluded} callback executions overlapping discarded events"
+            );
+        }
+        let uncertain = self.discarded_windows.get_uncertain_count();
+        if uncertain > 0 {
+            log::warn!(
+                "Dependency graph: dropped {uncertain} callback executions, events were discarded at unknown times"
+            );
+        }
     }
 }
 
+impl AnalysisOutput for DependencyGraph {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        serde_json::to_writer(
+            file,
+            &self.get_export(Args::get_analyses_args().quantiles()),
+        )
+    }
+}
+
+impl DependencyGraph {
+    /// Collects the series summarized in the tooltips of the DOT output.
+    ///
+    /// The sketched series are summarized by the `quantiles`.
+    pub(crate) fn get_export(&self, quantiles: &[Quantile]) -> DependencyGraphExport {
+        let publishers = self.publisher_nodes.iter().map(|(publisher, data)| {
+            NodeRecord::new(
+                &Node::Publisher(publisher.clone()),
+                NodeRecordData::Publisher {
+                    publication_delay: data.publication_delay.export(quantiles),
+                },
+            )
+        });
+        let subscribers = self.subscriber_nodes.iter().map(|(subscriber, data)| {
+            NodeRecord::new(
+                &Node::Subscriber(subscriber.clone()),
+                NodeRecordData::Subscriber {
+                    take_delay: data.take_delay.export(quantiles),
+                },
+            )
+        });
+        let timers = self.timer_nodes.iter().map(|(timer, data)| {
+            NodeRecord::new(
+                &Node::Timer(timer.clone()),
+                NodeRecordData::Timer {
+                    activation_delay: data.activation_delay.export(quantiles),
+                },
+            )
+        });
+        let callbacks = self.callback_nodes.iter().map(|(callback, data)| {
+            NodeRecord::new(
+                &Node::Callback(callback.clone()),
+                NodeRecordData::Callback {
+                    activation_delay: data.activation_delay.export(quantiles),
+                    durations: data.durations.export(quantiles),
+                },
+            )
+        });
+
+        let mut nodes: Vec<_> = publishers
+            .chain(subscribers)
+            .chain(timers)
+            .chain(callbacks)
+            .collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut edges: Vec<_> = self
+            .edges
+            .iter()
+            .map(|(edge, data)| EdgeRecord {
+                edge_type: edge.as_type(),
+                source: (&edge.source()).into(),
+                target: (&edge.target()).into(),
+                activation_delay: data.activation_delay.export(quantiles),
+                latencies: match data.latencies.export(quantiles) {
+                    SamplesExport::Raw(latencies) => SamplesExport::Raw(
+                        latencies
+                            .into_iter()
+                            .map(|latency| (latency != LATENCY_INVALID).then_some(latency))
+                            .collect(),
+                    ),
+                    SamplesExport::Summary(summary) => SamplesExport::Summary(summary),
+                },
+                unknown_latencies: data.unknown_latencies,
+            })
+            .collect();
+        edges.sort_by(|a, b| (&a.source, &a.target).cmp(&(&b.source, &b.target)));
+
+        DependencyGraphExport { nodes, edges }
+    }
+}
+
+/// Timing series of the dependency graph in nanoseconds.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct DependencyGraphExport {
+    pub(crate) nodes: Vec<NodeRecord>,
+    pub(crate) edges: Vec<EdgeRecord>,
+}
+
+/// Identification of a dependency graph node by its ROS node and interface.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct InterfaceRecord {
+    pub(crate) node: String,
+    pub(crate) interface: String,
+}
+
+impl From<&Node> for InterfaceRecord {
+    fn from(node: &Node) -> Self {
+        Self {
+            node: node.ros_node_name().to_string(),
+            interface: node.interface_name(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct NodeRecord {
+    #[serde(flatten)]
+    pub(crate) id: InterfaceRecord,
+
+    #[serde(flatten)]
+    pub(crate) data: NodeRecordData,
+}
+
+impl NodeRecord {
+    fn new(node: &Node, data: NodeRecordData) -> Self {
+        Self {
+            id: node.into(),
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum NodeRecordData {
+    Publisher {
+        publication_delay: SamplesExport,
+    },
+    Subscriber {
+        take_delay: SamplesExport,
+    },
+    Timer {
+        activation_delay: SamplesExport,
+    },
+    Callback {
+        activation_delay: SamplesExport,
+        durations: SamplesExport,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct EdgeRecord {
+    #[serde(rename = "type")]
+    pub(crate) edge_type: EdgeType,
+    pub(crate) source: InterfaceRecord,
+    pub(crate) target: InterfaceRecord,
+    pub(crate) activation_delay: SamplesExport,
+
+    /// `None` if the latency of the activation is unknown.
+    ///
+    /// The unknown latencies are not included in the summary of sketched latencies.
+    pub(crate) latencies: SamplesExport<Option<i64>>,
+    /// Number of activations with an unknown latency.
+    #[serde(default)]
+    pub(crate) unknown_latencies: usize,
+}
+
 struct EdgeWeightStats {
     subscriber_to_callback: (i64, i64),
     service_to_callback: (i64, i64),
@@ -596,12 +936,14 @@ impl Default for EdgeWeightStats {
     }
 }
 
-struct DisplayAsDotEdge {
+struct DisplayAsDotEdge<'a> {
     source: usize,
     target: usize,
-    latencies: Sorted<i64>,
+    latencies: &'a Samples,
+    median_latency: i64,
     node_index: Option<usize>,
     edge_type: EdgeType,
+    chart_link: Option<String>,
 }
 
 pub struct DisplayAsDot<'a> {
@@ -611,7 +953,7 @@ pub struct DisplayAsDot<'a> {
     ros_node_to_id: HashMap<ArcMutWrapper<model::Node>, usize>,
     ros_nodes_min_max_latency_stats: HashMap<ArcMutWrapper<model::Node>, EdgeWeightStats>,
 
-    edges: Vec<DisplayAsDotEdge>,
+    edges: Vec<DisplayAsDotEdge<'a>>,
     pub_sub_latency_range: Option<(i64, i64)>,
 
     analysis: &'a DependencyGraph,
@@ -735,13 +1077,13 @@ impl<'a> DisplayAsDot<'a> {
     }
 }
 
-fn process_edges(
-    graph_edges: &HashMap<Edge, EdgeData>,
+fn process_edges<'a>(
+    graph_edges: &'a HashMap<Edge, EdgeData>,
     graph_node_to_ros_node: &HashMap<Node, ArcMutWrapper<model::Node>>,
     ros_node_to_id: &HashMap<ArcMutWrapper<model::Node>, usize>,
     node_to_id: &HashMap<Node, usize>,
 ) -> (
-    Vec<DisplayAsDotEdge>,
+    Vec<DisplayAsDotEdge<'a>>,
     HashMap<ArcMutWrapper<model::Node>, EdgeWeightStats>,
     Option<(i64, i64)>,
 ) {
@@ -751,8 +1093,7 @@ fn process_edges(
         HashMap::new();
 
     for (edge, edge_data) in graph_edges {
-        let latencies = Sorted::from_unsorted(&edge_data.latencies);
-        let Some(median) = latencies.median().copied() else {
+        let Some(median) = edge_data.latencies.median() else {
             log::warn!("Skipping edge without latency samples: {edge:?}");
             continue;
         };
@@ -819,12 +1160,27 @@ fn process_edges(
             _ => None,
         };
 
+        let chart_link = match target_ros_node.0.lock().unwrap().get_full_name() {
+            Known::Known(ros_node_name)
+                if edge_type == EdgeType::PublisherSubscriberCommunication =>
+            {
+                Some(chart_link(
+                    ros_node_name,
+                    &target.interface_name(),
+                    &[ChartedValue::MessagesLatency],
+                ))
+            }
+            _ => None,
+        };
+
         edges.push(DisplayAsDotEdge {
             source: source_id,
             target: target_id,
-            latencies,
+            latencies: &edge_data.latencies,
+            median_latency: median,
             node_index: node_id,
             edge_type,
+            chart_link,
         });
     }
     let pub_sub_latency_range =
@@ -909,6 +1265,25 @@ fn get_node_name_and_tooltip(
     }
 }
 
+/// Returns the viewer link to the charts of the graph node, if any are available.
+fn get_node_chart_link(node: &Node, ros_node_name: &str) -> Option<String> {
+    let values: &[ChartedValue] = match node {
+        Node::Publisher(_) => &[ChartedValue::PublicationsDelay],
+        Node::Subscriber(_) => &[ChartedValue::MessagesDelay],
+        Node::Timer(_) => &[ChartedValue::ActivationsDelay],
+        Node::Callback(callback_arc) => {
+            callback_arc.0.lock().unwrap().get_caller()?;
+            &[
+                ChartedValue::CallbackDuration,
+                ChartedValue::ActivationsDelay,
+            ]
+        }
+        Node::Service(_) => return None,
+    };
+
+    Some(chart_link(ros_node_name, &node.interface_name(), values))
+}
+
 impl std::fmt::Display for DisplayAsDot<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let cluster_names = self
@@ -951,9 +1326,17 @@ impl std::fmt::Display for DisplayAsDot<'_> {
             let (node_name, tooltip) =
                 get_node_name_and_tooltip(node, self.analysis, ros_node_name.as_deref());
 
+            let chart_link = match &ros_node_name {
+                Known::Known(ros_node_name) => get_node_chart_link(node, ros_node_name),
+                Known::Unknown => None,
+            };
+
             let graph_node = graph.add_node(&node_name, *id);
             graph_node.set_shape(NodeShape::Ellipse);
             graph_node.set_attribute("tooltip", &tooltip);
+            if let Some(chart_link) = chart_link {
+                graph_node.set_attribute("URL", &chart_link);
+            }
         }
 
         for edge in &self.edges {
@@ -962,9 +1345,12 @@ impl std::fmt::Display for DisplayAsDot<'_> {
                 "tooltip",
                 &format!(
                     "Latency:\n{}",
-                    DisplayDurationStats::with_newline(&edge.latencies),
+                    DisplayDurationStats::with_newline(edge.latencies),
                 ),
             );
+            if let Some(chart_link) = &edge.chart_link {
+                graph_edge.set_attribute("URL", chart_link);
+            }
 
             if let Some((min_latency, max_latency)) = match edge.edge_type {
                 EdgeType::PublisherSubscriberCommunication => self.pub_sub_latency_range,
@@ -981,7 +1367,7 @@ impl std::fmt::Display for DisplayAsDot<'_> {
             } {
                 let max_latency =
                     max_latency.max((min_latency as f64 * self.min_multiplier) as i64);
-                let weight = *edge.latencies.median().unwrap();
+                let weight = edge.median_latency;
 
                 if self.color {
                     graph_edge.set_attribute(
@@ -1016,3 +1402,147 @@ impl std::fmt::Display for DisplayAsDot<'_> {
         write!(f, "{graph}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::analyses::event_iterator::{EventSink, ProcessedEventsIter};
+    use crate::data_quality::DiscardedItems;
+    use crate::raw_events::ros2::CallbackStart;
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+    use crate::statistics::RelativeError;
+
+    use super::super::analyze_events;
+    use super::*;
+
+    /// Returns the latencies and the unknown latency count of the timer callback edge
+    /// of three timer executions without a spin wake-up.
+    fn timer_edge_latencies(statistics: StatisticsBackend) -> (SamplesExport<Option<i64>>, usize) {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .timer(0, 1, 10, 20)
+            .execution(1_000, 1_100, VPID, 20)
+            .execution(2_000, 2_300, VPID, 20)
+            .execution(3_000, 3_200, VPID, 20);
+
+        let mut analysis = DependencyGraph::new();
+        analysis.set_statistics(statistics);
+        let _model = analyze_events(trace.build(), &mut analysis);
+        let export = analysis.get_export(&[Quantile::new(0.5).unwrap()]);
+
+        let edge = export
+            .edges
+            .into_iter()
+            .find(|edge| edge.edge_type == EdgeType::TimerCallbackInvocation)
+            .unwrap();
+        (edge.latencies, edge.unknown_latencies)
+    }
+
+    #[test]
+    fn unknown_latencies_exported_as_null() {
+        let (latencies, unknown_latencies) = timer_edge_latencies(StatisticsBackend::Exact);
+
+        assert_eq!(latencies, SamplesExport::Raw(vec![None, None, None]));
+        assert_eq!(unknown_latencies, 3);
+    }
+
+    #[test]
+    fn unknown_latencies_not_sketched() {
+        let statistics = StatisticsBackend::Sketch(RelativeError::new(0.01).unwrap());
+        let (latencies, unknown_latencies) = timer_edge_latencies(statistics);
+
+        let SamplesExport::Summary(summary) = latencies else {
+            panic!("Sketched latencies should be summarized: {latencies:?}");
+        };
+        assert_eq!(summary.count, 0);
+        assert_eq!(summary.mean, None);
+        assert_eq!(unknown_latencies, 3);
+    }
+
+    #[test]
+    fn execution_with_discarded_end_dropped() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .timer(0, 1, 10, 20)
+            // The end of this execution was discarded.
+            .event(
+                1_000,
+                VPID,
+                CallbackStart {
+                    callback: 20,
+                    is_intra_process: false,
+                },
+            )
+            .execution(2_000, 2_300, VPID, 20);
+
+        let mut analysis = DependencyGraph::new();
+        analysis.set_statistics(StatisticsBackend::Exact);
+        analysis.initialize();
+        analysis.process_discarded_events(&DiscardedWindow {
+            items: DiscardedItems::Events,
+            hostname: "host".to_owned(),
+            stream: None,
+            cpu: None,
+            count: Some(1),
+            start_time: Some(Time::from_nanos(1_100)),
+            end_time: Some(Time::from_nanos(1_200)),
+        });
+        let mut iter = ProcessedEventsIter::from_events(trace.build(), None);
+        let mut sink = EventSink::Analyses(&mut [&mut analysis]);
+        while let Some(event) = iter.next_event(&mut sink) {
+            event.unwrap();
+        }
+
+        assert_eq!(analysis.discarded_windows.get_excluded_count(), 1);
+        let callback_node = analysis.callback_nodes.values().next().unwrap();
+        assert_eq!(
+            callback_node.durations.export(&[]).into_raw(),
+            Some(vec![300])
+        );
+    }
+
+    #[test]
+    fn export_deserialization() {
+        let json = r#"{
+            "nodes": [
+                {"node": "/talker", "interface": "Publisher(/chatter)", "type": "publisher", "publication_delay": [100, 101]},
+                {"node": "/listener", "interface": "Subscription(/chatter)", "type": "callback", "activation_delay": [99], "durations": [5, 6]}
+            ],
+            "edges": [
+                {
+                    "type": "publisher_subscriber_communication",
+                    "source": {"node": "/talker", "interface": "Publisher(/chatter)"},
+                    "target": {"node": "/listener", "interface": "Subscriber(/chatter)"},
+                    "activation_delay": [100],
+                    "latencies": [20, null]
+                }
+            ]
+        }"#;
+
+        let export: DependencyGraphExport = serde_json::from_str(json).unwrap();
+        assert_eq!(export.nodes.len(), 2);
+        assert_eq!(export.nodes[0].id.interface, "Publisher(/chatter)");
+        assert!(matches!(
+            &export.nodes[0].data,
+            NodeRecordData::Publisher { publication_delay } if publication_delay == &SamplesExport::Raw(vec![100, 101])
+        ));
+        assert!(matches!(
+            &export.nodes[1].data,
+            NodeRecordData::Callback { durations, .. } if durations == &SamplesExport::Raw(vec![5, 6])
+        ));
+
+        let edge = &export.edges[0];
+        assert_eq!(edge.edge_type, EdgeType::PublisherSubscriberCommunication);
+        assert_eq!(edge.target.node, "/listener");
+        assert_eq!(edge.latencies, SamplesExport::Raw(vec![Some(20), None]));
+        assert_eq!(edge.unknown_latencies, 0);
+
+        let serialized = serde_json::to_value(&export).unwrap();
+        assert_eq!(serialized["nodes"][0]["type"], "publisher");
+        assert_eq!(
+            serialized["edges"][0]["latencies"][1],
+            serde_json::Value::Null
+        );
+    }
+}
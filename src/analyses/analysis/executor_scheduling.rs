@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::argsv2::Args;
+use crate::model::{Callback, Executor, ExecutorActivity, ExecutorState, Time};
+use crate::processed_events::{Event, FullEvent, ros2};
+use crate::statistics::{Quantile, Samples, SamplesExport, StatisticsBackend};
+
+use super::utils::CallbackRecordId;
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
+
+#[derive(Debug, Default)]
+pub struct ExecutorScheduling {
+    executors: HashMap<ArcMutWrapper<Executor>, ExecutorData>,
+
+    /// Executed handles waiting for their callback to start, by hostname and thread id.
+    pending_executions: HashMap<(String, u32), (ArcMutWrapper<Executor>, Time)>,
+
+    statistics: StatisticsBackend,
+}
+
+#[derive(Debug)]
+struct ExecutorData {
+    wait_for_work_durations: Samples,
+    get_next_ready_durations: Samples,
+    execute_durations: Samples,
+
+    /// Total time spent in each state in nanoseconds.
+    idle_time: i64,
+    busy_time: i64,
+    get_next_ready_time: i64,
+
+    /// Delays between the executor waking up with work and the start of each callback.
+    ready_to_callback_delays: HashMap<ArcMutWrapper<Callback>, Samples>,
+}
+
+impl ExecutorData {
+    fn new(statistics: StatisticsBackend) -> Self {
+        Self {
+            wait_for_work_durations: statistics.samples(),
+            get_next_ready_durations: statistics.samples(),
+            execute_durations: statistics.samples(),
+            idle_time: 0,
+            busy_time: 0,
+            get_next_ready_time: 0,
+            ready_to_callback_delays: HashMap::new(),
+        }
+    }
+
+    fn add_activity(&mut self, activity: &ExecutorActivity) {
+        let (durations, total) = match activity.state {
+            ExecutorState::GetNextReady => (
+                &mut self.get_next_ready_durations,
+                &mut self.get_next_ready_time,
+            ),
+            ExecutorState::WaitForWork => (&mut self.wait_for_work_durations, &mut self.idle_time),
+            ExecutorState::Execute => (&mut self.execute_durations, &mut self.busy_time),
+        };
+        durations.push(activity.duration());
+        *total += activity.duration();
+    }
+}
+
+impl ExecutorScheduling {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the durations and delays by the `statistics` backend.
+    pub fn set_statistics(&mut self, statistics: StatisticsBackend) {
+        self.statistics = statistics;
+    }
+
+    fn process_activity(
+        &mut self,
+        executor: &ArcMutWrapper<Executor>,
+        previous: Option<&ExecutorActivity>,
+    ) {
+        let data = self
+            .executors
+            .entry(executor.clone())
+            .or_insert_with(|| ExecutorData::new(self.statistics));
+        if let Some(previous) = previous {
+            data.add_activity(previous);
+        }
+    }
+
+    fn process_callback_start(&mut self, event: &ros2::CallbackStart, full_event: &FullEvent) {
+        let Some(vtid) = full_event.context.vtid() else {
+            return;
+        };
+        let thread = (full_event.context.hostname().to_owned(), vtid);
+        let Some((executor, ready_since)) = self.pending_executions.remove(&thread) else {
+            return;
+        };
+
+        let callback = event.callback.lock().unwrap().get_callback();
+        let delay = full_event.time.timestamp_nanos() - ready_since.timestamp_nanos();
+
+        self.executors
+            .entry(executor)
+            .or_insert_with(|| ExecutorData::new(self.statistics))
+            .ready_to_callback_delays
+            .entry(callback.into())
+            .or_insert_with(|| self.statistics.samples())
+            .push(delay);
+    }
+}
+
+impl EventAnalysis for ExecutorScheduling {
+    fn initialize(&mut self) {
+        self.executors.clear();
+        self.pending_executions.clear();
+    }
+
+    fn process_event(&mut self, full_event: &FullEvent) {
+        match &full_event.event {
+            Event::Ros2(ros2::Event::RclcppExecutorGetNextReady(event)) => {
+                self.process_activity(&event.executor.clone().into(), event.previous.as_ref());
+            }
+            Event::Ros2(ros2::Event::RclcppExecutorWaitForWork(event)) => {
+                self.process_activity(&event.executor.clone().into(), event.previous.as_ref());
+            }
+            Event::Ros2(ros2::Event::RclcppExecutorExecute(event)) => {
+                let executor = event.executor.clone().into();
+                self.process_activity(&executor, event.previous.as_ref());
+
+                // The executor events are processed only if they have a thread.
+                let thread = (
+                    full_event.context.hostname().to_owned(),
+                    full_event.context.vtid().unwrap(),
+                );
+                if let Some(ready_since) = event.ready_since {
+                    self.pending_executions
+                        .insert(thread, (executor, ready_since));
+                } else {
+                    self.pending_executions.remove(&thread);
+                }
+            }
+            Event::Ros2(ros2::Event::CallbackStart(event)) => {
+                self.process_callback_start(event, full_event);
+            }
+            _ => {}
+        }
+    }
+
+    fn finalize(&mut self) {
+        self.pending_executions.clear();
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ExecutorRecord {
+    hostname: String,
+    pid: u32,
+    tid: u32,
+
+    /// Total time spent waiting for work in nanoseconds.
+    idle_time: i64,
+    /// Total time spent executing handles in nanoseconds.
+    busy_time: i64,
+    /// Total time spent searching for ready handles in nanoseconds.
+    get_next_ready_time: i64,
+    /// Ratio of the idle time to the time spent in all executor states.
+    idle_ratio: Option<f64>,
+    /// Ratio of the busy time to the time spent in all executor states.
+    busy_ratio: Option<f64>,
+
+    wait_for_work_durations: SamplesExport,
+    get_next_ready_durations: SamplesExport,
+    execute_durations: SamplesExport,
+    ready_to_callback_delays: Vec<ReadyToCallbackRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReadyToCallbackRecord {
+    #[serde(flatten)]
+    callback: CallbackRecordId,
+    delays: SamplesExport,
+}
+
+impl ExecutorRecord {
+    fn new(executor: &Executor, data: &ExecutorData, quantiles: &[Quantile]) -> Self {
+        let idle_time = data.idle_time;
+        let busy_time = data.busy_time;
+        let get_next_ready_time = data.get_next_ready_time;
+
+        let total = idle_time + busy_time + get_next_ready_time;
+        let ratio = |time: i64| (total != 0).then(|| time as f64 / total as f64);
+
+        let mut ready_to_callback_delays: Vec<_> = data
+            .ready_to_callback_delays
+            .iter()
+            .map(|(callback, delays)| ReadyToCallbackRecord {
+                callback: (&*callback.0.lock().unwrap()).into(),
+                delays: delays.export(quantiles),
+            })
+            .collect();
+        ready_to_callback_delays.sort_by(|a, b| a.callback.cmp(&b.callback));
+
+        Self {
+            hostname: executor.get_hostname().to_owned(),
+            pid: executor.get_pid(),
+            tid: executor.get_tid(),
+            idle_time,
+            busy_time,
+            get_next_ready_time,
+            idle_ratio: ratio(idle_time),
+            busy_ratio: ratio(busy_time),
+            wait_for_work_durations: data.wait_for_work_durations.export(quantiles),
+            get_next_ready_durations: data.get_next_ready_durations.export(quantiles),
+            execute_durations: data.execute_durations.export(quantiles),
+            ready_to_callback_delays,
+        }
+    }
+}
+
+impl AnalysisOutput for ExecutorScheduling {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let quantiles = Args::get_analyses_args().quantiles();
+        let mut records: Vec<_> = self
+            .executors
+            .iter()
+            .map(|(executor, data)| {
+                ExecutorRecord::new(&executor.0.lock().unwrap(), data, quantiles)
+            })
+            .collect();
+        records.sort_by(|a, b| (&a.hostname, a.pid, a.tid).cmp(&(&b.hostname, b.pid, b.tid)));
+
+        serde_json::to_writer(file, &records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raw_events::ros2::{
+        CallbackEnd, CallbackStart, RclCppExecutorExecute, RclCppExecutorGetNextReady,
+        RclCppExecutorWaitForWork,
+    };
+    use crate::raw_events::testing::TraceBuilder;
+
+    use super::super::analyze_events;
+    use super::*;
+
+    fn get_next_ready(trace: &mut TraceBuilder, time: i64, vtid: u32) {
+        trace.event(time, vtid, RclCppExecutorGetNextReady);
+    }
+
+    fn wait_for_work(trace: &mut TraceBuilder, time: i64, vtid: u32) {
+        trace.event(time, vtid, RclCppExecutorWaitForWork { timeout: -1 });
+    }
+
+    fn execute(trace: &mut TraceBuilder, time: i64, vtid: u32) {
+        trace.event(time, vtid, RclCppExecutorExecute { handle: 1 });
+    }
+
+    fn start(trace: &mut TraceBuilder, time: i64, vtid: u32, callback: u64) {
+        trace.event(
+            time,
+            vtid,
+            CallbackStart {
+                callback,
+                is_intra_process: false,
+            },
+        );
+    }
+
+    fn end(trace: &mut TraceBuilder, time: i64, vtid: u32, callback: u64) {
+        trace.event(time, vtid, CallbackEnd { callback });
+    }
+
+    #[test]
+    fn executors_on_separate_threads() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .timer(0, 1, 10, 20)
+            .timer(0, 1, 30, 40)
+            .timer(0, 1, 50, 60);
+
+        // The executor of thread 103 never waited for work, so the time since which its
+        // handle was ready is unknown.
+        get_next_ready(&mut trace, 0, 103);
+        execute(&mut trace, 5, 103);
+        trace.execution(6, 8, 103, 60);
+
+        // After waking up, the executor searches for the ready handle before executing it.
+        get_next_ready(&mut trace, 0, 101);
+        wait_for_work(&mut trace, 10, 101);
+        wait_for_work(&mut trace, 100, 102);
+        get_next_ready(&mut trace, 110, 101);
+        execute(&mut trace, 112, 101);
+        start(&mut trace, 115, 101, 20);
+        get_next_ready(&mut trace, 120, 102);
+        execute(&mut trace, 121, 102);
+        trace.execution(122, 130, 102, 40);
+        get_next_ready(&mut trace, 131, 102);
+        wait_for_work(&mut trace, 132, 102);
+        end(&mut trace, 140, 101, 20);
+        // The second handle found after waking up was ready since the same time.
+        get_next_ready(&mut trace, 150, 101);
+        execute(&mut trace, 155, 101);
+        trace.execution(165, 170, 101, 20);
+        get_next_ready(&mut trace, 175, 101);
+        wait_for_work(&mut trace, 180, 101);
+        // The callback was not started by the executor.
+        trace.execution(200, 210, 102, 40);
+
+        let mut analysis = ExecutorScheduling::new();
+        analysis.set_statistics(StatisticsBackend::Exact);
+        analyze_events(trace.build(), &mut analysis);
+
+        let executors: HashMap<_, _> = analysis
+            .executors
+            .iter()
+            .map(|(executor, data)| (executor.0.lock().unwrap().get_tid(), data))
+            .collect();
+        assert_eq!(executors.len(), 3);
+
+        let raw = |samples: &Samples| samples.export(&[]).into_raw().unwrap();
+        let delays = |data: &ExecutorData| -> Vec<_> {
+            data.ready_to_callback_delays.values().map(raw).collect()
+        };
+
+        let data = executors[&101];
+        assert_eq!(raw(&data.get_next_ready_durations), [10, 2, 5, 5]);
+        assert_eq!(raw(&data.wait_for_work_durations), [100]);
+        assert_eq!(raw(&data.execute_durations), [38, 20]);
+        assert_eq!((data.idle_time, data.busy_time), (100, 58));
+        assert_eq!(delays(data), [vec![5, 55]]);
+
+        let data = executors[&102];
+        assert_eq!(raw(&data.wait_for_work_durations), [20]);
+        assert_eq!(raw(&data.execute_durations), [10]);
+        assert_eq!(raw(&data.get_next_ready_durations), [1, 1]);
+        assert_eq!(delays(data), [vec![2]]);
+
+        let data = executors[&103];
+        assert_eq!(raw(&data.get_next_ready_durations), [5]);
+        assert!(delays(data).is_empty());
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::model::display::get_node_name_from_weak;
+use crate::model::{RingBuffer, Time};
+use crate::processed_events::{Event, FullEvent, ros2};
+
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
+
+#[derive(Debug, Default)]
+pub struct IntraProcessBuffers {
+    buffers: HashMap<ArcMutWrapper<RingBuffer>, BufferData>,
+}
+
+#[derive(Debug, Default)]
+struct BufferData {
+    enqueued: usize,
+    dequeued: usize,
+    cleared: usize,
+    occupancy: Vec<OccupancyRecord>,
+    overwritten: Vec<OverwrittenRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OccupancyRecord {
+    time: i64,
+    size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OverwrittenRecord {
+    /// Time when the message was overwritten.
+    time: i64,
+    /// Publication time of the overwritten message if it is known.
+    publication_time: Option<i64>,
+}
+
+impl IntraProcessBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the `size` of the buffer after an event at `time`.
+    fn buffer_data(
+        &mut self,
+        ring_buffer: &Arc<Mutex<RingBuffer>>,
+        time: Time,
+        size: u64,
+    ) -> &mut BufferData {
+        let data = self.buffers.entry(ring_buffer.clone().into()).or_default();
+        data.occupancy.push(OccupancyRecord {
+            time: time.timestamp_nanos(),
+            size,
+        });
+        data
+    }
+}
+
+impl EventAnalysis for IntraProcessBuffers {
+    fn initialize(&mut self) {
+        self.buffers.clear();
+    }
+
+    fn process_event(&mut self, full_event: &FullEvent) {
+        let time = full_event.time;
+        match &full_event.event {
+            // The buffers are empty when they are constructed and after they are cleared.
+            Event::Ros2(ros2::Event::RclcppConstructRingBuffer(event)) => {
+                self.buffer_data(&event.ring_buffer, time, 0);
+            }
+            Event::Ros2(ros2::Event::RclcppRingBufferEnqueue(event)) => {
+                let data = self.buffer_data(&event.ring_buffer, time, event.size);
+                data.enqueued += 1;
+                if event.overwritten {
+                    let publication_time = event.overwritten_message.as_ref().and_then(|message| {
+                        message
+                            .lock()
+                            .unwrap()
+                            .get_publication_time()
+                            .map(Time::timestamp_nanos)
+                    });
+                    data.overwritten.push(OverwrittenRecord {
+                        time: time.timestamp_nanos(),
+                        publication_time,
+                    });
+                }
+            }
+            Event::Ros2(ros2::Event::RclcppRingBufferDequeue(event)) => {
+                self.buffer_data(&event.ring_buffer, time, event.size)
+                    .dequeued += 1;
+            }
+            Event::Ros2(ros2::Event::RclcppRingBufferClear(event)) => {
+                self.buffer_data(&event.ring_buffer, time, 0).cleared += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn finalize(&mut self) {}
+}
+
+#[derive(Debug, Serialize)]
+struct IntraProcessBufferRecord {
+    topic: String,
+    node: String,
+    buffer: u64,
+    capacity: u64,
+    enqueued: usize,
+    dequeued: usize,
+    /// Number of times the buffer was cleared.
+    cleared: usize,
+    occupancy: Vec<OccupancyRecord>,
+    overwritten: Vec<OverwrittenRecord>,
+}
+
+impl AnalysisOutput for IntraProcessBuffers {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let mut records: Vec<_> = self
+            .buffers
+            .iter()
+            .map(|(ring_buffer, data)| {
+                let ring_buffer = ring_buffer.0.lock().unwrap();
+                let (topic, node) = ring_buffer.get_subscriber().map_or_else(
+                    || ("Unknown".to_owned(), "Unknown".to_owned()),
+                    |subscriber| {
+                        let subscriber = subscriber.lock().unwrap();
+                        let node = subscriber
+                            .get_node()
+                            .map(|node| get_node_name_from_weak(&node.get_weak()).to_string())
+                            .unwrap_or("Unknown".to_owned());
+                        (subscriber.get_topic().to_string(), node)
+                    },
+                );
+
+                IntraProcessBufferRecord {
+                    topic,
+                    node,
+                    buffer: ring_buffer.get_handle(),
+                    capacity: ring_buffer.get_capacity(),
+                    enqueued: data.enqueued,
+                    dequeued: data.dequeued,
+                    cleared: data.cleared,
+                    occupancy: data.occupancy.clone(),
+                    overwritten: data.overwritten.clone(),
+                }
+            })
+            .collect();
+        records.sort_by(|a, b| (&a.topic, &a.node, a.buffer).cmp(&(&b.topic, &b.node, b.buffer)));
+
+        serde_json::to_writer(file, &records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyses::event_iterator::{EventSink, ProcessedEventsIter};
+    use crate::analyses::pipeline::ParallelAnalyses;
+    use crate::raw_events::FullEvent;
+    use crate::raw_events::ros2::{
+        RclCppConstructRingBuffer, RclCppRingBufferClear, RclCppRingBufferDequeue,
+        RclCppRingBufferEnqueue, RclcppIntraPublish,
+    };
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::super::analyze_events;
+    use super::*;
+
+    fn publish(trace: &mut TraceBuilder, time: i64, message: u64) {
+        trace.event(
+            time,
+            VPID,
+            RclcppIntraPublish {
+                publisher_handle: 20,
+                message,
+            },
+        );
+    }
+
+    fn enqueue(trace: &mut TraceBuilder, time: i64, vtid: u32, index: u64, size: u64) {
+        trace.event(
+            time,
+            vtid,
+            RclCppRingBufferEnqueue {
+                buffer: 30,
+                index,
+                size,
+                overwritten: size > 2,
+            },
+        );
+    }
+
+    /// Ring buffer with the capacity of 2 which overflows and is cleared at the end.
+    fn ring_buffer_trace() -> Vec<FullEvent> {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .publisher(0, 1, 20, "/topic", 10)
+            .event(
+                0,
+                VPID,
+                RclCppConstructRingBuffer {
+                    buffer: 30,
+                    capacity: 2,
+                },
+            );
+        publish(&mut trace, 100, 1000);
+        enqueue(&mut trace, 101, VPID, 0, 1);
+        publish(&mut trace, 200, 1001);
+        enqueue(&mut trace, 201, VPID, 1, 2);
+        // The buffer is full, so the message published at 100 is overwritten.
+        publish(&mut trace, 300, 1002);
+        enqueue(&mut trace, 301, VPID, 0, 3);
+        trace.event(
+            350,
+            VPID,
+            RclCppRingBufferDequeue {
+                buffer: 30,
+                index: 1,
+                size: 1,
+            },
+        );
+        // Messages enqueued by a thread which did not publish them are unknown.
+        enqueue(&mut trace, 400, 101, 1, 2);
+        enqueue(&mut trace, 450, 101, 0, 3);
+        enqueue(&mut trace, 500, 101, 1, 3);
+        trace.event(600, VPID, RclCppRingBufferClear { buffer: 30 });
+        trace.build()
+    }
+
+    /// Returns the occupancy and the overwritten messages of the only buffer.
+    #[expect(clippy::type_complexity)]
+    fn buffer_records(
+        analysis: &IntraProcessBuffers,
+    ) -> (Vec<(i64, u64)>, Vec<(i64, Option<i64>)>) {
+        assert_eq!(analysis.buffers.len(), 1);
+        let data = analysis.buffers.values().next().unwrap();
+        let occupancy = data
+            .occupancy
+            .iter()
+            .map(|record| (record.time, record.size))
+            .collect();
+        let overwritten = data
+            .overwritten
+            .iter()
+            .map(|record| (record.time, record.publication_time))
+            .collect();
+        (occupancy, overwritten)
+    }
+
+    #[test]
+    fn overwritten_messages() {
+        let mut analysis = IntraProcessBuffers::new();
+        analyze_events(ring_buffer_trace(), &mut analysis);
+
+        let data = analysis.buffers.values().next().unwrap();
+        assert_eq!((data.enqueued, data.dequeued, data.cleared), (6, 1, 1));
+
+        // The occupancy does not exceed the capacity when a message is overwritten.
+        let (occupancy, overwritten) = buffer_records(&analysis);
+        assert_eq!(
+            occupancy,
+            [
+                (0, 0),
+                (101, 1),
+                (201, 2),
+                (301, 2),
+                (350, 1),
+                (400, 2),
+                (450, 2),
+                (500, 2),
+                (600, 0)
+            ]
+        );
+
+        assert_eq!(
+            overwritten,
+            [(301, Some(100)), (450, Some(300)), (500, None)]
+        );
+    }
+
+    #[test]
+    fn parallel_analysis_sees_the_sizes_at_the_events() {
+        let mut sequential = IntraProcessBuffers::new();
+        analyze_events(ring_buffer_trace(), &mut sequential);
+
+        // The events fit in one batch, so the analysis thread gets them only after all of them
+        // were processed and the buffer was cleared.
+        let mut parallel = IntraProcessBuffers::new();
+        parallel.initialize();
+        let mut iter = ProcessedEventsIter::from_events(ring_buffer_trace(), None);
+        std::thread::scope(|scope| {
+            let mut analyses = ParallelAnalyses::spawn(scope, vec![&mut parallel]);
+            let mut sink = EventSink::Parallel(&mut analyses);
+            while let Some(event) = iter.next_event(&mut sink) {
+                event.unwrap();
+            }
+        });
+
+        assert_eq!(buffer_records(&parallel), buffer_records(&sequential));
+    }
+}
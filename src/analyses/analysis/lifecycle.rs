@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::model::display::get_node_name_from_weak;
+use crate::model::{Callback, LifecycleStateMachine, Time};
+use crate::processed_events::{Event, FullEvent, ros2};
+
+use super::utils::CallbackRecordId;
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
+
+#[derive(Debug, Default)]
+pub struct Lifecycle {
+    state_machines: HashMap<ArcMutWrapper<LifecycleStateMachine>, StateMachineData>,
+
+    /// Time of the last processed event used to close the last state interval.
+    last_event_time: Option<Time>,
+}
+
+#[derive(Debug)]
+struct StateMachineData {
+    /// State the node is in since `state_since`.
+    state: String,
+    state_since: Time,
+
+    /// Primary state and time in which the running transition started.
+    transition_start: Option<(String, Time)>,
+
+    transitions: Vec<TransitionRecord>,
+    transition_durations: Vec<TransitionDurationRecord>,
+
+    /// Total time spent in each state in nanoseconds.
+    time_in_state: BTreeMap<String, i64>,
+
+    /// Number of callback executions per callback and lifecycle state other than active.
+    callbacks_outside_active: HashMap<(ArcMutWrapper<Callback>, String), usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TransitionRecord {
+    time: i64,
+    start: String,
+    goal: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TransitionDurationRecord {
+    /// Label of the transition state, e.g. `configuring`.
+    transition: String,
+    from: String,
+    to: String,
+    start_time: i64,
+    duration: i64,
+}
+
+impl StateMachineData {
+    fn new(init_time: Time) -> Self {
+        Self {
+            state: LifecycleStateMachine::INITIAL_STATE.to_owned(),
+            state_since: init_time,
+            transition_start: None,
+            transitions: Vec::new(),
+            transition_durations: Vec::new(),
+            time_in_state: BTreeMap::new(),
+            callbacks_outside_active: HashMap::new(),
+        }
+    }
+
+    fn close_state_interval(&mut self, time: Time) {
+        let duration = time.timestamp_nanos() - self.state_since.timestamp_nanos();
+        *self.time_in_state.entry(self.state.clone()).or_default() += duration;
+        self.state_since = time;
+    }
+
+    fn transition(&mut self, start_label: &str, goal_label: &str, time: Time) {
+        self.close_state_interval(time);
+
+        self.transitions.push(TransitionRecord {
+            time: time.timestamp_nanos(),
+            start: start_label.to_owned(),
+            goal: goal_label.to_owned(),
+        });
+
+        if LifecycleStateMachine::is_primary_state(start_label) {
+            self.transition_start = Some((start_label.to_owned(), time));
+        } else if let Some((from, start_time)) = self.transition_start.take() {
+            self.transition_durations.push(TransitionDurationRecord {
+                transition: start_label.to_owned(),
+                from,
+                to: goal_label.to_owned(),
+                start_time: start_time.timestamp_nanos(),
+                duration: time.timestamp_nanos() - start_time.timestamp_nanos(),
+            });
+        }
+
+        goal_label.clone_into(&mut self.state);
+    }
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn process_callback_start(&mut self, event: &ros2::CallbackStart) {
+        let callback = event.callback.lock().unwrap().get_callback();
+
+        let Some(node) = callback
+            .lock()
+            .unwrap()
+            .get_node()
+            .and_then(|node| node.get_arc())
+        else {
+            return;
+        };
+        let Some(state_machine) = node.lock().unwrap().get_lifecycle_state_machine().cloned()
+        else {
+            return;
+        };
+
+        let Some(data) = self.state_machines.get_mut(&state_machine.clone().into()) else {
+            return;
+        };
+
+        if data.state != LifecycleStateMachine::ACTIVE_STATE {
+            *data
+                .callbacks_outside_active
+                .entry((callback.into(), data.state.clone()))
+                .or_default() += 1;
+        }
+    }
+}
+
+impl EventAnalysis for Lifecycle {
+    fn initialize(&mut self) {
+        *self = Self::default();
+    }
+
+    fn process_event(&mut self, full_event: &FullEvent) {
+        self.last_event_time = Some(full_event.time);
+
+        match &full_event.event {
+            Event::Ros2(ros2::Event::RclLifecycleStateMachineInit(event)) => {
+                self.state_machines.insert(
+                    event.state_machine.clone().into(),
+                    StateMachineData::new(full_event.time),
+                );
+            }
+            Event::Ros2(ros2::Event::RclLifecycleTransition(event)) => {
+                let Some(data) = self
+                    .state_machines
+                    .get_mut(&event.state_machine.clone().into())
+                else {
+                    return;
+                };
+                data.transition(&event.start_label, &event.goal_label, full_event.time);
+            }
+            Event::Ros2(ros2::Event::CallbackStart(event)) => {
+                self.process_callback_start(event);
+            }
+            _ => {}
+        }
+    }
+
+    fn finalize(&mut self) {
+        let Some(end_time) = self.last_event_time else {
+            return;
+        };
+
+        for data in self.state_machines.values_mut() {
+            data.close_state_interval(end_time);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct LifecycleRecord {
+    node: String,
+    transitions: Vec<TransitionRecord>,
+    time_in_state: BTreeMap<String, i64>,
+    transition_durations: Vec<TransitionDurationRecord>,
+    callbacks_outside_active: Vec<CallbackOutsideActiveRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct CallbackOutsideActiveRecord {
+    #[serde(flatten)]
+    callback: CallbackRecordId,
+    state: String,
+    count: usize,
+}
+
+impl AnalysisOutput for Lifecycle {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let mut records: Vec<_> = self
+            .state_machines
+            .iter()
+            .map(|(state_machine, data)| {
+                let node =
+                    get_node_name_from_weak(&state_machine.0.lock().unwrap().get_node().get_weak());
+
+                let mut callbacks_outside_active: Vec<_> = data
+                    .callbacks_outside_active
+                    .iter()
+                    .map(|((callback, state), count)| CallbackOutsideActiveRecord {
+                        callback: (&*callback.0.lock().unwrap()).into(),
+                        state: state.clone(),
+                        count: *count,
+                    })
+                    .collect();
+                callbacks_outside_active
+                    .sort_by(|a, b| (&a.callback, &a.state).cmp(&(&b.callback, &b.state)));
+
+                LifecycleRecord {
+                    node: node.to_string(),
+                    transitions: data.transitions.clone(),
+                    time_in_state: data.time_in_state.clone(),
+                    transition_durations: data.transition_durations.clone(),
+                    callbacks_outside_active,
+                }
+            })
+            .collect();
+        records.sort_by(|a, b| a.node.cmp(&b.node));
+
+        serde_json::to_writer(file, &records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raw_events::ros2::{RclLifecycleStateMachineInit, RclLifecycleTransition};
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::super::analyze_events;
+    use super::*;
+
+    fn transition(trace: &mut TraceBuilder, time: i64, start: &str, goal: &str) {
+        trace.event(
+            time,
+            VPID,
+            RclLifecycleTransition {
+                state_machine: 50,
+                start_label: start.to_owned(),
+                goal_label: goal.to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn callbacks_while_not_active() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "managed")
+            .event(
+                0,
+                VPID,
+                RclLifecycleStateMachineInit {
+                    node_handle: 1,
+                    state_machine: 50,
+                },
+            )
+            .timer(0, 1, 10, 20)
+            .node(0, 3, "unmanaged")
+            .timer(0, 3, 30, 40)
+            .execution(10, 15, VPID, 20);
+        transition(&mut trace, 100, "unconfigured", "configuring");
+        transition(&mut trace, 130, "configuring", "inactive");
+        trace
+            .execution(150, 155, VPID, 20)
+            .execution(160, 165, VPID, 20);
+        transition(&mut trace, 200, "inactive", "activating");
+        transition(&mut trace, 210, "activating", "active");
+        // Neither the execution while active nor the one of the node without a lifecycle
+        // is reported.
+        trace
+            .execution(300, 305, VPID, 20)
+            .execution(320, 325, VPID, 40);
+        transition(&mut trace, 400, "active", "deactivating");
+        transition(&mut trace, 420, "deactivating", "inactive");
+        trace.execution(500, 505, VPID, 20);
+
+        let mut analysis = Lifecycle::new();
+        analyze_events(trace.build(), &mut analysis);
+
+        assert_eq!(analysis.state_machines.len(), 1);
+        let data = analysis.state_machines.values().next().unwrap();
+        assert_eq!(data.state, "inactive");
+        assert_eq!(data.transitions.len(), 6);
+
+        let mut outside_active: Vec<_> = data
+            .callbacks_outside_active
+            .iter()
+            .map(|((_, state), count)| (state.as_str(), *count))
+            .collect();
+        outside_active.sort_unstable();
+        assert_eq!(outside_active, [("inactive", 3), ("unconfigured", 1)]);
+
+        // The last state lasts until the last event.
+        assert_eq!(
+            data.time_in_state,
+            BTreeMap::from([
+                ("activating".to_owned(), 10),
+                ("active".to_owned(), 190),
+                ("configuring".to_owned(), 30),
+                ("deactivating".to_owned(), 20),
+                ("inactive".to_owned(), 155),
+                ("unconfigured".to_owned(), 100),
+            ])
+        );
+
+        let durations: Vec<_> = data
+            .transition_durations
+            .iter()
+            .map(|record| {
+                (
+                    record.from.as_str(),
+                    record.transition.as_str(),
+                    record.to.as_str(),
+                    record.duration,
+                )
+            })
+            .collect();
+        assert_eq!(
+            durations,
+            [
+                ("unconfigured", "configuring", "inactive", 30),
+                ("inactive", "activating", "active", 10),
+                ("active", "deactivating", "inactive", 20),
+            ]
+        );
+    }
+}
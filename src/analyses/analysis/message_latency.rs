@@ -1,21 +1,59 @@
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::analysis::utils::DisplayDurationStats;
+use crate::argsv2::Args;
+use crate::data_quality::{DiscardedWindow, DiscardedWindows};
+use crate::events_common::Context;
 use crate::model::display::get_node_name_from_weak;
-use crate::model::{Publisher, Subscriber, SubscriptionMessage};
+use crate::model::{Publisher, PublisherId, Subscriber, SubscriberId, SubscriptionMessage, Time};
 use crate::processed_events::{Event, FullEvent, ros2};
+use crate::statistics::{Quantile, Samples, SamplesExport, StatisticsBackend};
 use crate::utils::{DurationDisplayImprecise, Known};
 
+use super::clock_offset::ClockOffsets;
+use super::sim_time::SimTimeMapping;
 use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
 
-type SubPubKey = (ArcMutWrapper<Subscriber>, Option<ArcMutWrapper<Publisher>>);
+/// Subscriber, publisher and whether the messages were sent by intra-process communication.
+type SubPubKey = (
+    ArcMutWrapper<Subscriber>,
+    Option<ArcMutWrapper<Publisher>>,
+    bool,
+);
+
+/// Latencies of the messages of one subscriber and publisher.
+#[derive(Debug)]
+struct LatencySeries {
+    /// Receive time of each message, kept only with the exact latencies.
+    receive_times: Vec<Time>,
+    latencies: Samples,
+    /// `true` if the sketched latencies were corrected by the clock offsets when added.
+    clock_corrected: bool,
+}
+
 pub struct MessageLatency {
-    messages: HashSet<ArcMutWrapper<SubscriptionMessage>>,
-    latencies: HashMap<SubPubKey, Vec<i64>>,
+    /// Last message taken by each subscriber and not taken by rclcpp yet.
+    ///
+    /// The message is complete when rclcpp takes it or when the subscriber takes the next
+    /// one, e.g., in rclpy or r2r nodes without the `rclcpp_take` event.
+    last_taken: HashMap<ArcMutWrapper<Subscriber>, Arc<Mutex<SubscriptionMessage>>>,
+    latencies: HashMap<SubPubKey, LatencySeries>,
+    statistics: StatisticsBackend,
+    discarded_windows: DiscardedWindows,
+    /// Mapping to express the latencies in simulation time.
+    sim_time: Option<Arc<SimTimeMapping>>,
+    /// Offsets to correct the latencies of messages sent between hosts.
+    clock_offsets: Option<Arc<ClockOffsets>>,
+    publisher_hosts: HashMap<ArcMutWrapper<Publisher>, String>,
+    subscriber_hosts: HashMap<ArcMutWrapper<Subscriber>, String>,
+    /// Number of messages received before they were published.
+    negative_latencies: usize,
+    /// `false` if the intra-process messages are left out.
+    intra_process: bool,
 }
 
 #[derive(Debug)]
@@ -23,7 +61,10 @@ pub struct MessageLatencyStats {
     topic: String,
     subscriber: Arc<Mutex<Subscriber>>,
     publisher: Option<Arc<Mutex<Publisher>>>,
-    latencies: Vec<i64>,
+    intra_process: bool,
+    latencies: Samples,
+    sim_time: bool,
+    clock_corrected: bool,
 }
 
 impl PartialEq for MessageLatencyStats {
@@ -82,13 +123,89 @@ impl PartialOrd for MessageLatencyStats {
 impl MessageLatency {
     pub fn new() -> Self {
         Self {
-            messages: HashSet::new(),
+            last_taken: HashMap::new(),
             latencies: HashMap::new(),
+            statistics: StatisticsBackend::default(),
+            discarded_windows: DiscardedWindows::default(),
+            sim_time: None,
+            clock_offsets: None,
+            publisher_hosts: HashMap::new(),
+            subscriber_hosts: HashMap::new(),
+            negative_latencies: 0,
+            intra_process: true,
         }
     }
 
+    /// Leaves out the intra-process messages, e.g., if they cannot be matched to their
+    /// publications without the threads of the events.
+    pub fn skip_intra_process(&mut self) {
+        self.intra_process = false;
+    }
+
+    /// Expresses the latencies in simulation time using the `mapping`.
+    ///
+    /// Messages received outside of the simulation are left out.
+    pub fn set_sim_time(&mut self, mapping: Arc<SimTimeMapping>) {
+        self.sim_time = Some(mapping);
+    }
+
+    /// Keeps the latencies by the `statistics` backend.
+    ///
+    /// The sketched latencies are corrected when they are added, so the simulation time
+    /// mapping and the clock offsets must be set before the events are processed.
+    pub fn set_statistics(&mut self, statistics: StatisticsBackend) {
+        self.statistics = statistics;
+    }
+
+    /// Corrects the latencies of messages sent between hosts by the offsets of their clocks.
+    pub fn set_clock_offsets(&mut self, offsets: Arc<ClockOffsets>) {
+        self.clock_offsets = Some(offsets);
+    }
+
+    /// Returns the `latency` of a message sent between hosts corrected by the `offsets`
+    /// of their clocks.
+    ///
+    /// Returns `None` for messages within a host or if the offset is unknown.
+    fn correct_latency(
+        &self,
+        offsets: &ClockOffsets,
+        subscriber: &ArcMutWrapper<Subscriber>,
+        publisher: Option<&ArcMutWrapper<Publisher>>,
+        receive_time: Time,
+        latency: i64,
+    ) -> Option<i64> {
+        let send_host = self.publisher_hosts.get(publisher?)?;
+        let receive_host = self.subscriber_hosts.get(subscriber)?;
+        if send_host == receive_host {
+            return None;
+        }
+        offsets.correct_latency(send_host, receive_host, receive_time, latency)
+    }
+
+    /// Records the `message` as the last message taken by its subscriber and completes
+    /// the previous one.
     fn add_message(&mut self, message: Arc<Mutex<SubscriptionMessage>>) {
-        self.messages.insert(message.into());
+        let subscriber = message.lock().unwrap().get_subscriber();
+        let Some(subscriber) = subscriber else {
+            // The latency series of the message cannot be identified.
+            return;
+        };
+        if let Some(previous) = self.last_taken.insert(subscriber.into(), message) {
+            self.add_latency(&previous.lock().unwrap());
+        }
+    }
+
+    /// Records the host of the subscriber of the `message` taken by an event of the `context`.
+    fn add_subscriber_host(
+        &mut self,
+        message: &Arc<Mutex<SubscriptionMessage>>,
+        context: &Context,
+    ) {
+        if let Some(subscriber) = message.lock().unwrap().get_subscriber() {
+            self.subscriber_hosts
+                .entry(subscriber.into())
+                .or_insert_with(|| context.hostname().to_owned());
+        }
     }
 
     fn calculate_latency_and_get_publisher(
@@ -114,68 +231,206 @@ impl MessageLatency {
                 (None, None)
             };
 
-        send_time.inspect(|send_time| {
-            assert!(*send_time <= receive_time);
-        });
-
         let latency =
             send_time.map(|send_time| receive_time.timestamp_nanos() - send_time.timestamp_nanos());
 
         (latency, publisher)
     }
 
-    fn remove_message(&mut self, message: Arc<Mutex<SubscriptionMessage>>) {
-        let message = message.into();
-        if self.messages.remove(&message) {
-            let message = message.0.lock().unwrap();
-            let (latency_ns, publisher) = Self::calculate_latency_and_get_publisher(&message);
-
-            if message.get_subscriber().is_none() {
-                // The message is missing the subscriber. The latency series cannot be identified.
-                return;
-            }
-
-            self.latencies
-                .entry((message.get_subscriber().unwrap().into(), publisher))
-                .or_default()
-                .push(latency_ns.unwrap());
+    /// Completes the `message` unless it was completed already, i.e., unless its subscriber
+    /// took another message since.
+    fn remove_message(&mut self, message: &Arc<Mutex<SubscriptionMessage>>) {
+        let subscriber = message.lock().unwrap().get_subscriber();
+        let Some(subscriber) = subscriber.map(ArcMutWrapper::from) else {
+            return;
+        };
+        if self
+            .last_taken
+            .get(&subscriber)
+            .is_some_and(|last| Arc::ptr_eq(last, message))
+        {
+            self.last_taken.remove(&subscriber);
+            self.add_latency(&message.lock().unwrap());
         }
     }
 
     fn remove_remaining_messages(&mut self) {
-        for message in self.messages.drain() {
-            let message = message.0.lock().unwrap();
-            let (latency_ns, publisher) = Self::calculate_latency_and_get_publisher(&message);
+        for (_, message) in std::mem::take(&mut self.last_taken) {
+            self.add_latency(&message.lock().unwrap());
+        }
+    }
 
-            if message.get_subscriber().is_none() {
-                // The message is missing the subscriber. The latency series cannot be identified.
-                continue;
-            }
+    fn add_latency(&mut self, message: &SubscriptionMessage) {
+        let (latency_ns, publisher) = Self::calculate_latency_and_get_publisher(message);
+
+        if message.get_subscriber().is_none() {
+            // The message is missing the subscriber. The latency series cannot be identified.
+            return;
+        }
 
-            self.latencies
-                .entry((message.get_subscriber().unwrap().into(), publisher))
-                .or_default()
-                .push(latency_ns.unwrap());
+        let latency_ns = latency_ns.unwrap();
+        if latency_ns < 0 {
+            // The clocks of the publisher and subscriber hosts are not synchronized.
+            self.negative_latencies += 1;
+        }
+        let receive_time = message.get_receive_time().unwrap();
+        let send_time = Time::from_nanos(receive_time.timestamp_nanos() - latency_ns);
+        let subscriber_host = self
+            .subscriber_hosts
+            .get(&message.get_subscriber().unwrap().into());
+        let publisher_host = publisher
+            .as_ref()
+            .and_then(|publisher| self.publisher_hosts.get(publisher));
+        let hosts: Vec<&str> = subscriber_host
+            .into_iter()
+            .chain(publisher_host)
+            .map(String::as_str)
+            .collect();
+        if self
+            .discarded_windows
+            .exclude(&hosts, send_time, receive_time)
+        {
+            // Events of the message could have been discarded, e.g., an earlier take.
+            return;
+        }
+
+        let subscriber = message.get_subscriber().unwrap().into();
+        let corrected_latency = if self.statistics.is_exact() {
+            None
+        } else {
+            self.clock_offsets.as_ref().and_then(|offsets| {
+                self.correct_latency(
+                    offsets,
+                    &subscriber,
+                    publisher.as_ref(),
+                    receive_time,
+                    latency_ns,
+                )
+            })
+        };
+
+        let series = self
+            .latencies
+            .entry((subscriber, publisher, message.is_intra_process()))
+            .or_insert_with(|| LatencySeries {
+                receive_times: Vec::new(),
+                latencies: self.statistics.samples(),
+                clock_corrected: corrected_latency.is_some(),
+            });
+        if let Samples::Exact(_) = series.latencies {
+            series.receive_times.push(receive_time);
+            series.latencies.push(latency_ns);
+            return;
         }
+
+        // The sketch keeps only the corrected latencies.
+        let latency_ns = match corrected_latency {
+            Some(corrected_latency) if series.clock_corrected => corrected_latency,
+            _ => latency_ns,
+        };
+        let latency_ns = match &self.sim_time {
+            Some(mapping) => {
+                let send_time = Time::from_nanos(receive_time.timestamp_nanos() - latency_ns);
+                let Some(sim_latency) = mapping.sim_duration(send_time, receive_time) else {
+                    return;
+                };
+                sim_latency
+            }
+            None => latency_ns,
+        };
+        series.latencies.push(latency_ns);
+    }
+
+    /// Returns the exact `latencies` received at the `receive_times` corrected by the clock
+    /// offsets and expressed in simulation time, and whether they were corrected.
+    fn correct_latencies(
+        &self,
+        subscriber_arc: &ArcMutWrapper<Subscriber>,
+        publisher_arc: Option<&ArcMutWrapper<Publisher>>,
+        receive_times: &[Time],
+        latencies: &[i64],
+    ) -> (Vec<i64>, bool) {
+        let correct = |receive_time, latency| {
+            self.clock_offsets.as_ref().and_then(|offsets| {
+                self.correct_latency(
+                    offsets,
+                    subscriber_arc,
+                    publisher_arc,
+                    receive_time,
+                    latency,
+                )
+            })
+        };
+        let clock_corrected = receive_times
+            .first()
+            .zip(latencies.first())
+            .is_some_and(|(&receive_time, &latency)| correct(receive_time, latency).is_some());
+
+        let latencies = receive_times
+            .iter()
+            .zip(latencies)
+            .map(|(&receive_time, &latency)| {
+                let latency = if clock_corrected {
+                    correct(receive_time, latency).unwrap()
+                } else {
+                    latency
+                };
+                (receive_time, latency)
+            })
+            .filter_map(|(receive_time, latency)| match &self.sim_time {
+                Some(mapping) => mapping.sim_duration(
+                    Time::from_nanos(receive_time.timestamp_nanos() - latency),
+                    receive_time,
+                ),
+                None => Some(latency),
+            })
+            .collect();
+
+        (latencies, clock_corrected)
     }
 
     pub fn calculate_stats(&self) -> Vec<MessageLatencyStats> {
         self.latencies
             .iter()
-            .map(|((subscriber_arc, publisher_arc), latencies)| {
+            .map(|((subscriber_arc, publisher_arc, intra_process), series)| {
                 let subscriber = subscriber_arc.0.lock().unwrap();
                 let topic = subscriber.get_topic();
+                let (latencies, clock_corrected) = match &series.latencies {
+                    Samples::Exact(latencies) => {
+                        let (latencies, clock_corrected) = self.correct_latencies(
+                            subscriber_arc,
+                            publisher_arc.as_ref(),
+                            &series.receive_times,
+                            latencies,
+                        );
+                        (Samples::Exact(latencies.into()), clock_corrected)
+                    }
+                    // The sketched latencies were corrected when added, see `set_statistics`.
+                    Samples::Sketch(_) => (series.latencies.clone(), series.clock_corrected),
+                };
 
                 MessageLatencyStats {
                     topic: topic.to_string(),
                     subscriber: subscriber_arc.0.clone(),
                     publisher: publisher_arc.as_ref().map(|p| p.0.clone()),
-                    latencies: latencies.clone(),
+                    intra_process: *intra_process,
+                    latencies,
+                    sim_time: self.sim_time.is_some(),
+                    clock_corrected,
                 }
             })
             .collect()
     }
 
+    /// Returns the latencies of each subscriber and publisher pair summarized by the `quantiles`
+    /// if they are sketched.
+    pub(crate) fn get_export(&self, quantiles: &[Quantile]) -> Vec<MessageLatencyExport> {
+        self.calculate_stats()
+            .into_iter()
+            .map(|stats| MessageLatencyExport::new(stats, quantiles))
+            .collect()
+    }
+
     pub(crate) fn print_stats(&self) {
         println!("Message latency statistics:");
         let mut stats = self.calculate_stats();
@@ -185,13 +440,21 @@ impl MessageLatency {
             let topic = &stat.topic;
             let publisher = stat.publisher.as_ref().map(|p| p.lock().unwrap());
 
-            println!("- [{i:4}] Topic {topic}:");
+            if stat.intra_process {
+                println!("- [{i:4}] Topic {topic} (intra-process):");
+            } else {
+                println!("- [{i:4}] Topic {topic}:");
+            }
             println!("    Subscriber: {subscriber:#}");
             if let Some(publisher) = publisher {
                 println!("    Publisher: {publisher}");
             } else {
                 println!("    Publisher: Unknown");
             }
+            if stat.latencies.is_empty() {
+                println!("\tNo data");
+                continue;
+            }
             let display = DisplayDurationStats::new(&stat.latencies, "\n\t");
             println!("\t{display}");
             let (mean, std_dev) = display.mean_and_std_dev();
@@ -206,68 +469,129 @@ impl MessageLatency {
 
 impl EventAnalysis for MessageLatency {
     fn initialize(&mut self) {
-        self.messages.clear();
+        self.last_taken.clear();
         self.latencies.clear();
+        self.discarded_windows.clear();
+        self.publisher_hosts.clear();
+        self.subscriber_hosts.clear();
+        self.negative_latencies = 0;
     }
 
-    fn process_event(&mut self, event: &FullEvent) {
-        match &event.event {
+    fn process_event(&mut self, full_event: &FullEvent) {
+        match &full_event.event {
+            Event::Ros2(ros2::Event::RmwPublish(event)) => {
+                if let Some(publisher) = event.message.lock().unwrap().get_publisher() {
+                    self.publisher_hosts
+                        .entry(publisher.into())
+                        .or_insert_with(|| full_event.context.hostname().to_owned());
+                }
+            }
             Event::Ros2(ros2::Event::RmwTake(event)) => {
+                self.add_subscriber_host(&event.message, &full_event.context);
                 self.add_message(event.message.clone());
             }
             Event::Ros2(ros2::Event::RclTake(event)) => {
-                let message = event.message.clone();
+                self.add_subscriber_host(&event.message, &full_event.context);
                 if event.is_new {
-                    self.add_message(message);
-                } else {
-                    assert!(self.messages.contains(&message.into()));
+                    self.add_message(event.message.clone());
                 }
             }
             Event::Ros2(ros2::Event::RclCppTake(event)) => {
-                let message = event.message.clone();
+                self.add_subscriber_host(&event.message, &full_event.context);
                 if event.is_new {
+                    self.add_message(event.message.clone());
+                }
+                self.remove_message(&event.message);
+            }
+            Event::Ros2(ros2::Event::RclcppRingBufferDequeue(event)) => {
+                // Intra-process messages are received when they are taken from the ring buffer.
+                let Some(message) = event.message.as_ref().filter(|_| self.intra_process) else {
+                    return;
+                };
+                if message.lock().unwrap().get_publication_message().is_some() {
+                    self.add_subscriber_host(message, &full_event.context);
                     self.add_message(message.clone());
-                } else {
-                    assert!(self.messages.contains(&message.clone().into()));
+                    self.remove_message(message);
                 }
-
-                self.remove_message(message);
             }
 
             _ => {}
         }
     }
 
+    fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+        self.discarded_windows.add(window);
+    }
+
     fn finalize(&mut self) {
         // Make sure all messages are accounted for. The remaining messages are
         // missing the RclCppTake event.
         self.remove_remaining_messages();
+
+        let excluded = self.discarded_windows.get_excluded_count();
+        if excluded > 0 {
+            log::warn!(
+                "Message latency: excluded {excluded} messages overlapping discarded events"
+            );
+        }
+        let uncertain = self.discarded_windows.get_uncertain_count();
+        if uncertain > 0 {
+            log::warn!(
+                "Message latency: {uncertain} latencies may be inaccurate, events were discarded at unknown times"
+            );
+        }
+        if self.negative_latencies > 0 {
+            log::warn!(
+                "Message latency: {} messages were received before they were published. \
+                The clocks of the hosts are probably not synchronized, \
+                use --correct-clock-offsets to correct them.",
+                self.negative_latencies
+            );
+        }
     }
 }
 
 impl AnalysisOutput for MessageLatency {
     fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
-        let stats = self.calculate_stats();
-        let stats: Vec<MessageLatencyExport> = stats.into_iter().map(Into::into).collect();
-        serde_json::to_writer(file, &stats)
+        let quantiles = Args::get_analyses_args().quantiles();
+        serde_json::to_writer(file, &self.get_export(quantiles))
     }
 }
 
-#[derive(Debug, Serialize)]
-struct MessageLatencyExport {
-    topic: String,
-    subscriber_node: String,
-    publisher_node: String,
-    latencies: Vec<i64>,
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MessageLatencyExport {
+    pub(crate) topic: String,
+    pub(crate) subscriber_node: String,
+    pub(crate) publisher_node: String,
+    /// Identifier of the subscriber in the analyzed trace.
+    #[serde(default)]
+    pub(crate) subscriber_id: Option<SubscriberId>,
+    /// Identifier of the publisher in the analyzed trace, unknown if the messages were
+    /// not matched with their publication.
+    #[serde(default)]
+    pub(crate) publisher_id: Option<PublisherId>,
+    #[serde(default)]
+    pub(crate) intra_process: bool,
+    pub(crate) latencies: SamplesExport,
+    /// `true` if the latencies are in simulation time.
+    #[serde(default)]
+    pub(crate) sim_time: bool,
+    /// `true` if the latencies are corrected by the offset of the host clocks.
+    #[serde(default)]
+    pub(crate) clock_corrected: bool,
 }
 
-impl From<MessageLatencyStats> for MessageLatencyExport {
-    fn from(value: MessageLatencyStats) -> Self {
+impl MessageLatencyExport {
+    fn new(value: MessageLatencyStats, quantiles: &[Quantile]) -> Self {
         let subscriber = value.subscriber.lock().unwrap();
         let subscriber_node = subscriber
             .get_node()
             .map(|node| get_node_name_from_weak(&node.get_weak()).unwrap_or("Unknown".to_string()))
             .unwrap_or("Unknown".to_string());
+        let publisher_id = value
+            .publisher
+            .as_ref()
+            .map(|publisher| publisher.lock().unwrap().get_id());
         let publisher_node = value.publisher.as_ref().map_or_else(
             || "Unknown".to_string(),
             |p| {
@@ -284,7 +608,72 @@ impl From<MessageLatencyStats> for MessageLatencyExport {
             topic: value.topic,
             subscriber_node,
             publisher_node,
-            latencies: value.latencies,
+            subscriber_id: Some(subscriber.get_id()),
+            publisher_id,
+            intra_process: value.intra_process,
+            latencies: value.latencies.export(quantiles),
+            sim_time: value.sim_time,
+            clock_corrected: value.clock_corrected,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::raw_events::ros2::{RmwPublish, RmwTake};
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::super::analyze_events;
+    use super::*;
+
+    #[test]
+    fn export_of_taken_messages() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "publisher")
+            .node(0, 3, "subscriber")
+            .publisher(0, 1, 20, "/topic", 10)
+            .subscription(0, 3, 30, "/topic", 10);
+        for (publish_time, take_time) in [(100, 150), (200, 230)] {
+            trace
+                .event(
+                    publish_time,
+                    VPID,
+                    RmwPublish {
+                        rmw_publisher_handle: Some(21),
+                        message: 1000 + publish_time as u64,
+                        timestamp: Some(publish_time),
+                    },
+                )
+                .event(
+                    take_time,
+                    VPID,
+                    RmwTake {
+                        rmw_subscription_handle: 31,
+                        message: 2000,
+                        source_timestamp: publish_time,
+                        taken: true,
+                    },
+                );
+        }
+
+        let mut analysis = MessageLatency::new();
+        let _model = analyze_events(trace.build(), &mut analysis);
+        let export = serde_json::to_value(analysis.get_export(&[])).unwrap();
+
+        assert_eq!(
+            export,
+            serde_json::json!([{
+                "topic": "/topic",
+                "subscriber_id": 0,
+                "subscriber_node": "/subscriber",
+                "publisher_id": 0,
+                "publisher_node": "/publisher",
+                "intra_process": false,
+                "latencies": [50, 30],
+                "sim_time": false,
+                "clock_corrected": false,
+            }])
+        );
+    }
+}
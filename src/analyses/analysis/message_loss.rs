@@ -0,0 +1,541 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::data_quality::{DiscardedWindow, DiscardedWindows};
+use crate::model::display::get_node_name_from_weak;
+use crate::model::{Node, Publisher, Subscriber, Time};
+use crate::processed_events::{Event, FullEvent, ros2};
+use crate::utils::{ArcWeak, Known};
+
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
+
+/// Process of an object by hostname and vpid.
+type ProcessKey = (String, u32);
+
+/// Messages published but never taken by the subscribers of the same topic.
+///
+/// The publications are matched with the taken messages by their source timestamps,
+/// so only the publishers tracing the timestamp in `rmw_publish` (Jazzy and newer)
+/// are analyzed. Messages delivered by intra-process communication are not taken
+/// from the middleware, so such publisher and subscriber pairs are skipped.
+#[derive(Debug, Default)]
+pub struct MessageLoss {
+    publishers: HashMap<ArcMutWrapper<Publisher>, PublisherData>,
+    subscribers: HashMap<ArcMutWrapper<Subscriber>, SubscriberData>,
+    /// Indices of the publications received by each publisher and subscriber pair.
+    received: HashMap<(ArcMutWrapper<Publisher>, ArcMutWrapper<Subscriber>), HashSet<usize>>,
+    discarded_windows: DiscardedWindows,
+
+    pairs: Vec<PairLoss>,
+}
+
+#[derive(Debug, Default)]
+struct PublisherData {
+    process: Option<ProcessKey>,
+    /// Times of the publications in the order of publishing.
+    publication_times: Vec<Time>,
+    /// Index of the publication by its source timestamp.
+    index_by_timestamp: HashMap<i64, usize>,
+}
+
+#[derive(Debug)]
+struct SubscriberData {
+    process: ProcessKey,
+    init_time: Time,
+    /// Times of the messages taken from any publisher.
+    take_times: Vec<Time>,
+}
+
+#[derive(Debug)]
+struct PairLoss {
+    publisher: Arc<Mutex<Publisher>>,
+    subscriber: Arc<Mutex<Subscriber>>,
+    published: usize,
+    received: usize,
+    lost: usize,
+    /// Lost messages which may have been received while the tracer discarded events.
+    possibly_discarded: usize,
+    bursts: Vec<LossBurst>,
+    publication_rate: Option<f64>,
+    take_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct LossBurst {
+    /// Publication time of the first lost message.
+    start_time: i64,
+    /// Publication time of the last lost message.
+    end_time: i64,
+    count: usize,
+    /// `true` if more messages were published on the topic between the takes around
+    /// the burst than fit into the subscriber's queue.
+    ///
+    /// `None` if the queue depth is unknown.
+    queue_overflow: Option<bool>,
+}
+
+/// Returns the average number of events per second between the first and the last `times`.
+fn rate(times: &[Time]) -> Option<f64> {
+    let (first, last) = (times.first()?, times.last()?);
+    let duration = last.timestamp_nanos() - first.timestamp_nanos();
+    (duration != 0).then(|| (times.len() - 1) as f64 * 1e9 / duration as f64)
+}
+
+/// Returns the number of `times` in the interval from `start` (exclusive) to `end` (inclusive).
+fn count_in(times: &[Time], start: Option<Time>, end: Option<Time>) -> usize {
+    let start_index = start.map_or(0, |start| times.partition_point(|time| *time <= start));
+    let end_index = end.map_or(times.len(), |end| {
+        times.partition_point(|time| *time <= end)
+    });
+    end_index.saturating_sub(start_index)
+}
+
+impl MessageLoss {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn process_rmw_publish(&mut self, event: &ros2::RmwPublish, time: Time) {
+        let message = event.message.lock().unwrap();
+        let (Some(publisher), Some(timestamp)) =
+            (message.get_publisher(), message.get_sender_timestamp())
+        else {
+            return;
+        };
+
+        let publisher = self.publishers.entry(publisher.into()).or_default();
+        publisher.index_by_timestamp.insert(
+            timestamp.timestamp_nanos(),
+            publisher.publication_times.len(),
+        );
+        publisher.publication_times.push(time);
+    }
+
+    fn process_rmw_take(&mut self, event: &ros2::RmwTake, time: Time) {
+        if !event.taken {
+            return;
+        }
+        let message = event.message.lock().unwrap();
+        let Some(subscriber) = message.get_subscriber() else {
+            return;
+        };
+        let subscriber: ArcMutWrapper<_> = subscriber.into();
+        let Some(subscriber_data) = self.subscribers.get_mut(&subscriber) else {
+            // The subscriber was created before the tracing started.
+            return;
+        };
+        subscriber_data.take_times.push(time);
+
+        let Some(publication) = message.get_publication_message() else {
+            return;
+        };
+        let publication = publication.lock().unwrap();
+        let (Some(publisher), Some(timestamp)) = (
+            publication.get_publisher(),
+            publication.get_sender_timestamp(),
+        ) else {
+            return;
+        };
+        let publisher: ArcMutWrapper<_> = publisher.into();
+        if let Some(&index) = self
+            .publishers
+            .get(&publisher)
+            .and_then(|data| data.index_by_timestamp.get(&timestamp.timestamp_nanos()))
+        {
+            self.received
+                .entry((publisher, subscriber))
+                .or_default()
+                .insert(index);
+        }
+    }
+
+    fn calculate_pair(
+        &mut self,
+        publisher: &ArcMutWrapper<Publisher>,
+        subscriber: &ArcMutWrapper<Subscriber>,
+        topic_publication_times: &[Time],
+    ) -> Option<PairLoss> {
+        let publisher_data = &self.publishers[publisher];
+        let subscriber_data = &self.subscribers[subscriber];
+
+        let (queue_depth, is_intra_process) = {
+            let subscriber = subscriber.0.lock().unwrap();
+            (subscriber.get_queue_depth(), subscriber.is_intra_process())
+        };
+        if is_intra_process && publisher_data.process.as_ref() == Some(&subscriber_data.process) {
+            return None;
+        }
+
+        // Messages published before the subscriber was created or after its last take
+        // could not have been received.
+        let last_take = subscriber_data.take_times.last().copied();
+        let considered: Vec<(usize, Time)> = publisher_data
+            .publication_times
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, time)| {
+                time >= subscriber_data.init_time && last_take.is_none_or(|last| time <= last)
+            })
+            .collect();
+        if considered.is_empty() {
+            return None;
+        }
+
+        let received = self.received.get(&(publisher.clone(), subscriber.clone()));
+        let is_received = |index: usize| received.is_some_and(|received| received.contains(&index));
+        let take_times = &subscriber_data.take_times;
+        let hosts: Vec<&str> = std::iter::once(&subscriber_data.process)
+            .chain(&publisher_data.process)
+            .map(|(hostname, _)| hostname.as_str())
+            .collect();
+        let next_take = |time: Time| {
+            take_times
+                .get(take_times.partition_point(|take| *take < time))
+                .copied()
+        };
+
+        let mut lost: Vec<(usize, Time)> = Vec::new();
+        let mut possibly_discarded = 0;
+        for &(index, time) in &considered {
+            if is_received(index) {
+                continue;
+            }
+            let end = next_take(time).unwrap_or(time);
+            if self.discarded_windows.exclude(&hosts, time, end) {
+                possibly_discarded += 1;
+            } else {
+                lost.push((index, time));
+            }
+        }
+
+        let mut bursts: Vec<LossBurst> = Vec::new();
+        let mut previous_index = None;
+        for &(index, time) in &lost {
+            match bursts.last_mut() {
+                Some(burst) if previous_index == Some(index - 1) => {
+                    burst.end_time = time.timestamp_nanos();
+                    burst.count += 1;
+                }
+                _ => bursts.push(LossBurst {
+                    start_time: time.timestamp_nanos(),
+                    end_time: time.timestamp_nanos(),
+                    count: 1,
+                    queue_overflow: None,
+                }),
+            }
+            previous_index = Some(index);
+        }
+        for burst in &mut bursts {
+            let Known::Known(queue_depth) = queue_depth else {
+                break;
+            };
+            let start = Time::from_nanos(burst.start_time);
+            let end = Time::from_nanos(burst.end_time);
+            let previous_take = take_times[..take_times.partition_point(|take| *take < start)]
+                .last()
+                .copied();
+            let published_in_gap = count_in(topic_publication_times, previous_take, next_take(end));
+            burst.queue_overflow = Some(published_in_gap > queue_depth);
+        }
+
+        let considered_times: Vec<Time> = considered.iter().map(|&(_, time)| time).collect();
+        Some(PairLoss {
+            publisher: publisher.0.clone(),
+            subscriber: subscriber.0.clone(),
+            published: considered.len(),
+            received: considered.len() - lost.len() - possibly_discarded,
+            lost: lost.len(),
+            possibly_discarded,
+            bursts,
+            publication_rate: rate(&considered_times),
+            take_rate: rate(take_times),
+        })
+    }
+}
+
+impl EventAnalysis for MessageLoss {
+    fn initialize(&mut self) {
+        *self = Self::default();
+    }
+
+    fn process_event(&mut self, full_event: &FullEvent) {
+        let Event::Ros2(event) = &full_event.event else {
+            return;
+        };
+        match event {
+            ros2::Event::RclPublisherInit(event) => {
+                self.publishers
+                    .entry(event.publisher.clone().into())
+                    .or_default()
+                    .process = Some((
+                    full_event.context.hostname().to_owned(),
+                    full_event.context.vpid(),
+                ));
+            }
+            ros2::Event::RclSubscriptionInit(event) => {
+                self.subscribers.insert(
+                    event.subscription.clone().into(),
+                    SubscriberData {
+                        process: (
+                            full_event.context.hostname().to_owned(),
+                            full_event.context.vpid(),
+                        ),
+                        init_time: full_event.time,
+                        take_times: Vec::new(),
+                    },
+                );
+            }
+            ros2::Event::RmwPublish(event) => self.process_rmw_publish(event, full_event.time),
+            ros2::Event::RmwTake(event) => self.process_rmw_take(event, full_event.time),
+            _ => {}
+        }
+    }
+
+    fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+        self.discarded_windows.add(window);
+    }
+
+    fn finalize(&mut self) {
+        let topic_of = |publisher: &ArcMutWrapper<Publisher>| {
+            publisher
+                .0
+                .lock()
+                .unwrap()
+                .get_topic()
+                .map(ToOwned::to_owned)
+        };
+        let mut publishers_by_topic: HashMap<String, Vec<ArcMutWrapper<Publisher>>> =
+            HashMap::new();
+        for publisher in self.publishers.keys() {
+            if let Known::Known(topic) = topic_of(publisher) {
+                publishers_by_topic
+                    .entry(topic)
+                    .or_default()
+                    .push(publisher.clone());
+            }
+        }
+
+        let subscribers: Vec<_> = self.subscribers.keys().cloned().collect();
+        for subscriber in subscribers {
+            let Known::Known(topic) = subscriber
+                .0
+                .lock()
+                .unwrap()
+                .get_topic()
+                .map(ToOwned::to_owned)
+            else {
+                continue;
+            };
+            let Some(publishers) = publishers_by_topic.get(&topic) else {
+                continue;
+            };
+
+            // Messages of all publishers on the topic share the subscriber's queue.
+            let mut topic_publication_times: Vec<Time> = publishers
+                .iter()
+                .flat_map(|publisher| self.publishers[publisher].publication_times.clone())
+                .collect();
+            topic_publication_times.sort_unstable();
+
+            for publisher in publishers {
+                if let Some(pair) =
+                    self.calculate_pair(publisher, &subscriber, &topic_publication_times)
+                {
+                    self.pairs.push(pair);
+                }
+            }
+        }
+
+        let excluded = self.discarded_windows.get_excluded_count();
+        if excluded > 0 {
+            log::warn!(
+                "Message loss: {excluded} lost messages overlap discarded events and may have been received"
+            );
+        }
+        let uncertain = self.discarded_windows.get_uncertain_count();
+        if uncertain > 0 {
+            log::warn!(
+                "Message loss: {uncertain} lost messages may have been received while events were discarded at unknown times"
+            );
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MessageLossRecord {
+    topic: String,
+    publisher_node: String,
+    subscriber_node: String,
+    published: usize,
+    received: usize,
+    lost: usize,
+    possibly_discarded: usize,
+    /// Average number of publications per second.
+    publication_rate: Option<f64>,
+    /// Average number of messages taken by the subscriber per second.
+    take_rate: Option<f64>,
+    /// `true` if every loss burst is explained by an overflow of the subscriber's queue.
+    ///
+    /// `None` if there are no bursts or the queue depth is unknown.
+    consistent_with_queue_overflow: Option<bool>,
+    subscriber_queue_depth: Option<usize>,
+    publisher_queue_depth: Option<usize>,
+    bursts: Vec<LossBurst>,
+}
+
+impl AnalysisOutput for MessageLoss {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let node_name = |node: Known<ArcWeak<Mutex<Node>>>| {
+            node.map(|node| {
+                get_node_name_from_weak(&node.get_weak()).unwrap_or("Unknown".to_string())
+            })
+            .unwrap_or("Unknown".to_string())
+        };
+
+        let mut records: Vec<_> = self
+            .pairs
+            .iter()
+            .map(|pair| {
+                let publisher = pair.publisher.lock().unwrap();
+                let subscriber = pair.subscriber.lock().unwrap();
+                let consistent_with_queue_overflow = if pair.bursts.is_empty() {
+                    None
+                } else {
+                    pair.bursts
+                        .iter()
+                        .map(|burst| burst.queue_overflow)
+                        .collect::<Option<Vec<_>>>()
+                        .map(|overflows| overflows.into_iter().all(|overflow| overflow))
+                };
+
+                MessageLossRecord {
+                    topic: subscriber.get_topic().to_string(),
+                    publisher_node: node_name(publisher.get_node()),
+                    subscriber_node: node_name(subscriber.get_node()),
+                    published: pair.published,
+                    received: pair.received,
+                    lost: pair.lost,
+                    possibly_discarded: pair.possibly_discarded,
+                    publication_rate: pair.publication_rate,
+                    take_rate: pair.take_rate,
+                    consistent_with_queue_overflow,
+                    subscriber_queue_depth: subscriber.get_queue_depth().into(),
+                    publisher_queue_depth: publisher.get_queue_depth().into(),
+                    bursts: pair.bursts.clone(),
+                }
+            })
+            .collect();
+        records.sort_by(|a, b| {
+            (&a.topic, &a.publisher_node, &a.subscriber_node).cmp(&(
+                &b.topic,
+                &b.publisher_node,
+                &b.subscriber_node,
+            ))
+        });
+
+        serde_json::to_writer(file, &records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raw_events::ros2::{RclSubscriptionInit, RmwPublish, RmwTake};
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::super::analyze_events;
+    use super::*;
+
+    fn publish(trace: &mut TraceBuilder, time: i64) {
+        trace.event(
+            time,
+            VPID,
+            RmwPublish {
+                rmw_publisher_handle: Some(21),
+                message: 1000 + time as u64,
+                timestamp: Some(time),
+            },
+        );
+    }
+
+    fn take(trace: &mut TraceBuilder, time: i64, source_timestamp: i64) {
+        trace.event(
+            time,
+            VPID,
+            RmwTake {
+                rmw_subscription_handle: 31,
+                message: 2000,
+                source_timestamp,
+                taken: true,
+            },
+        );
+    }
+
+    #[test]
+    fn loss_bursts() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .publisher(0, 1, 20, "/topic", 10)
+            .event(
+                0,
+                VPID,
+                RclSubscriptionInit {
+                    subscription_handle: 30,
+                    node_handle: 1,
+                    rmw_subscription_handle: 31,
+                    topic_name: "/topic".to_owned(),
+                    queue_depth: 2,
+                },
+            );
+
+        // Messages published at 120, 130 and 140 are overwritten in the subscriber's queue
+        // before the take at 165. The message published at 180 is lost without an overflow.
+        for time in (100..200).step_by(10) {
+            publish(&mut trace, time);
+            match time {
+                100 | 110 | 170 | 190 => take(&mut trace, time + 5, time),
+                160 => {
+                    take(&mut trace, 165, 150);
+                    take(&mut trace, 166, 160);
+                }
+                _ => {}
+            }
+        }
+
+        let mut analysis = MessageLoss::new();
+        analyze_events(trace.build(), &mut analysis);
+
+        assert_eq!(analysis.pairs.len(), 1);
+        let pair = &analysis.pairs[0];
+        assert_eq!(
+            (
+                pair.published,
+                pair.received,
+                pair.lost,
+                pair.possibly_discarded
+            ),
+            (10, 6, 4, 0)
+        );
+        assert_eq!(pair.publication_rate, Some(1e8));
+
+        let bursts: Vec<_> = pair
+            .bursts
+            .iter()
+            .map(|burst| {
+                (
+                    burst.start_time,
+                    burst.end_time,
+                    burst.count,
+                    burst.queue_overflow,
+                )
+            })
+            .collect();
+        assert_eq!(
+            bursts,
+            [(120, 140, 3, Some(true)), (180, 180, 1, Some(false))]
+        );
+    }
+}
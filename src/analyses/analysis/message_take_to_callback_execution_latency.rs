@@ -2,16 +2,19 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use crate::analysis::utils::DisplayDurationStats;
+use crate::argsv2::Args;
 use crate::model::display::DisplayCallbackSummary;
 use crate::model::{Callback, CallbackInstance, CallbackTrigger};
 use crate::processed_events::{Event, FullEvent, ros2};
+use crate::statistics::{Samples, SamplesExport, StatisticsBackend};
 use crate::utils::DurationDisplayImprecise;
 
 use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
 
 #[derive(Debug, Default)]
 pub struct MessageTakeToCallbackLatency {
-    latencies: HashMap<ArcMutWrapper<Callback>, Vec<i64>>,
+    latencies: HashMap<ArcMutWrapper<Callback>, Samples>,
+    statistics: StatisticsBackend,
 }
 
 impl MessageTakeToCallbackLatency {
@@ -19,6 +22,11 @@ impl MessageTakeToCallbackLatency {
         Self::default()
     }
 
+    /// Keeps the latencies by the `statistics` backend.
+    pub fn set_statistics(&mut self, statistics: StatisticsBackend) {
+        self.statistics = statistics;
+    }
+
     fn process_callback_start(&mut self, callback: Arc<Mutex<CallbackInstance>>) {
         let callback_instance = callback.lock().unwrap();
 
@@ -30,7 +38,7 @@ impl MessageTakeToCallbackLatency {
 
             self.latencies
                 .entry(callback_instance.get_callback().into())
-                .or_default()
+                .or_insert_with(|| self.statistics.samples())
                 .push(latency);
         } else {
             // Ignore other triggers
@@ -75,11 +83,12 @@ impl EventAnalysis for MessageTakeToCallbackLatency {
 #[derive(Debug, serde::Serialize)]
 struct ExportEntry {
     topic: String,
-    latencies: Vec<i64>,
+    latencies: SamplesExport,
 }
 
 impl AnalysisOutput for MessageTakeToCallbackLatency {
     fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let quantiles = Args::get_analyses_args().quantiles();
         let latencies: Vec<ExportEntry> = self
             .latencies
             .iter()
@@ -92,7 +101,7 @@ impl AnalysisOutput for MessageTakeToCallbackLatency {
                     .unwrap()
                     .get_caller_as_string()
                     .unwrap(),
-                latencies: latencies.clone(),
+                latencies: latencies.export(quantiles),
             })
             .collect();
 
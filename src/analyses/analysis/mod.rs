@@ -1,9 +1,10 @@
 use std::fs::File;
 use std::hash::Hash;
-use std::io::BufWriter;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+use crate::data_quality::{DataQuality, DiscardedWindow};
 use crate::processed_events::FullEvent;
 use derive_more::derive::From;
 
@@ -27,13 +28,37 @@ pub use callback_dependency::CallbackDependency;
 pub mod message_take_to_callback_execution_latency;
 pub use message_take_to_callback_execution_latency::MessageTakeToCallbackLatency;
 
+pub mod callback_off_cpu;
+pub use callback_off_cpu::CallbackOffCpu;
+
+pub mod cpu_usage;
+pub use cpu_usage::CpuUsage;
+
 pub mod utilization;
 pub use utilization::Utilization;
 
 pub mod spin_duration;
 pub use spin_duration::SpinDuration;
 
-pub trait EventAnalysis {
+pub mod lifecycle;
+pub use lifecycle::Lifecycle;
+
+pub mod executor_scheduling;
+pub use executor_scheduling::ExecutorScheduling;
+
+pub mod intra_process_buffers;
+pub use intra_process_buffers::IntraProcessBuffers;
+
+pub mod sim_time;
+pub use sim_time::SimTime;
+
+pub mod message_loss;
+pub use message_loss::MessageLoss;
+
+pub mod clock_offset;
+pub use clock_offset::ClockOffset;
+
+pub trait EventAnalysis: Send {
     /// Initialize the analysis
     ///
     /// This method is called before any events are processed
@@ -42,6 +67,11 @@ pub trait EventAnalysis {
     /// Process an event
     fn process_event(&mut self, event: &FullEvent);
 
+    /// Process a window in which the tracer discarded events or packets
+    ///
+    /// Analyses should exclude or flag the samples overlapping the window.
+    fn process_discarded_events(&mut self, _window: &DiscardedWindow) {}
+
     /// Finalize the analysis
     ///
     /// This method is called after all events have been processed
@@ -53,11 +83,24 @@ pub trait AnalysisOutput {
 }
 
 pub trait AnalysisOutputExt: AnalysisOutput {
-    fn write_json_to_output_dir(&self, path: &Path) -> std::io::Result<()> {
+    /// Writes the JSON output together with the quality of the analyzed data.
+    ///
+    /// The output is an object with the `data_quality` and `data` fields where `data`
+    /// is written by [`AnalysisOutput::write_json`].
+    fn write_json_to_output_dir(
+        &self,
+        path: &Path,
+        data_quality: &DataQuality,
+    ) -> std::io::Result<()> {
         std::fs::create_dir_all(path.parent().unwrap())?;
         let out_file = File::create(path)?;
         let mut out_file = BufWriter::new(out_file);
-        self.write_json(&mut out_file).map_err(Into::into)
+        out_file.write_all(b"{\"data_quality\":")?;
+        serde_json::to_writer(&mut out_file, data_quality)?;
+        out_file.write_all(b",\"data\":")?;
+        self.write_json(&mut out_file)?;
+        out_file.write_all(b"}")?;
+        out_file.flush()
     }
 }
 
@@ -85,3 +128,22 @@ impl<T> Hash for ArcMutWrapper<T> {
         Arc::as_ptr(&self.0).hash(state);
     }
 }
+
+/// Processes the raw `events` and passes them to the initialized `analysis` until it is finalized.
+///
+/// Returns the processor, which owns the ROS 2 objects the analysis links to weakly.
+#[cfg(test)]
+fn analyze_events(
+    events: Vec<crate::raw_events::FullEvent>,
+    analysis: &mut dyn EventAnalysis,
+) -> crate::processor::Processor {
+    use super::event_iterator::{EventSink, ProcessedEventsIter};
+
+    analysis.initialize();
+    let mut iter = ProcessedEventsIter::from_events(events, None);
+    let mut sink = EventSink::Analyses(&mut [analysis]);
+    while let Some(event) = iter.next_event(&mut sink) {
+        event.unwrap();
+    }
+    iter.processor
+}
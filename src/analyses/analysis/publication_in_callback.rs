@@ -1,13 +1,17 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
+use serde::Serialize;
+
+use crate::data_quality::{DiscardedWindow, DiscardedWindows};
 use crate::events_common::Context;
 use crate::model::display::DisplayCallbackSummary;
-use crate::model::{Callback, CallbackInstance, PublicationMessage, Publisher};
+use crate::model::{Callback, CallbackInstance, PublicationMessage, Publisher, Time};
 use crate::processed_events::{Event, FullEvent, ros2};
 use crate::utils::DisplayDebug;
 
-use super::{ArcMutWrapper, EventAnalysis};
+use super::utils::CallbackRecordId;
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Id {
@@ -19,6 +23,7 @@ struct Id {
 pub struct PublicationInCallback {
     active_callbacks: HashMap<Id, ArcMutWrapper<CallbackInstance>>,
     dependency: HashSet<(ArcMutWrapper<Publisher>, ArcMutWrapper<Callback>)>,
+    discarded_windows: DiscardedWindows,
 }
 
 impl PublicationInCallback {
@@ -26,50 +31,70 @@ impl PublicationInCallback {
         Self::default()
     }
 
-    fn activate_callback(&mut self, callback: Arc<Mutex<CallbackInstance>>, context: &Context) {
+    fn activate_callback(
+        &mut self,
+        callback: Arc<Mutex<CallbackInstance>>,
+        vtid: u32,
+        context: &Context,
+        time: Time,
+    ) {
         let id = Id {
-            vtid: context.vtid(),
+            vtid,
             hostname: context.hostname().to_string(),
         };
 
-        if let Some(old) = self.active_callbacks.insert(id, callback.into()) {
-            panic!(
-                "Callback {old:?} is already active on vtid {} on host {}",
-                context.vtid(),
-                context.hostname()
-            );
+        if let Some(stale) = self.active_callbacks.insert(id.clone(), callback.into()) {
+            self.drop_stale_callback(&stale, &id, time);
         }
     }
 
-    fn deactivate_callback(&mut self, callback: &Arc<Mutex<CallbackInstance>>, context: &Context) {
+    fn deactivate_callback(
+        &mut self,
+        callback: &Arc<Mutex<CallbackInstance>>,
+        vtid: u32,
+        context: &Context,
+        time: Time,
+    ) {
         let id = Id {
-            vtid: context.vtid(),
+            vtid,
             hostname: context.hostname().to_string(),
         };
 
-        if let Some(old) = self.active_callbacks.remove(&id) {
-            assert!(
-                Arc::ptr_eq(&old.0, callback),
-                "Callback {old:?} is not active on vtid {} on host {}",
-                context.vtid(),
-                context.hostname(),
-            );
-        } else {
-            panic!(
-                "No callback is being executed on vtid {} on host {}",
-                context.vtid(),
-                context.hostname(),
-            );
+        if let Some(stale) = self
+            .active_callbacks
+            .remove(&id)
+            .filter(|active| !Arc::ptr_eq(&active.0, callback))
+        {
+            self.drop_stale_callback(&stale, &id, time);
         }
     }
 
+    /// Drops the `stale` callback still active on the thread `id` at `time` and counts it
+    /// against the discarded windows. Its end was likely discarded by the tracer.
+    fn drop_stale_callback(
+        &mut self,
+        stale: &ArcMutWrapper<CallbackInstance>,
+        id: &Id,
+        time: Time,
+    ) {
+        let stale = stale.0.lock().unwrap();
+        self.discarded_windows
+            .exclude(&[&id.hostname], stale.get_start_time(), time);
+        log::warn!(
+            "Publication in callback: callback {stale} on vtid {} on host {} did not end. Missing callback_end event?",
+            id.vtid,
+            id.hostname,
+        );
+    }
+
     fn process_publication(
         &mut self,
         publication: Arc<Mutex<PublicationMessage>>,
+        vtid: u32,
         context: &Context,
     ) {
         let id = Id {
-            vtid: context.vtid(),
+            vtid,
             hostname: context.hostname().to_string(),
         };
 
@@ -89,6 +114,20 @@ impl PublicationInCallback {
         &self.dependency
     }
 
+    /// Returns the publishers used by the callbacks sorted by the callback and the topic.
+    fn get_records(&self) -> Vec<PublicationInCallbackRecord> {
+        let mut records: Vec<_> = self
+            .dependency
+            .iter()
+            .map(|(publisher, callback)| PublicationInCallbackRecord {
+                callback: (&*callback.0.lock().unwrap()).into(),
+                topic: publisher.0.lock().unwrap().get_topic().to_string(),
+            })
+            .collect();
+        records.sort_by(|a, b| (&a.callback, &a.topic).cmp(&(&b.callback, &b.topic)));
+        records
+    }
+
     pub(crate) fn write_stats(&self, output: &mut impl std::io::Write) -> std::io::Result<()> {
         writeln!(output, "Publication in callbacks statistic:")?;
         for (i, (publisher, callback)) in self.dependency.iter().enumerate() {
@@ -105,27 +144,179 @@ impl PublicationInCallback {
     }
 }
 
+impl AnalysisOutput for PublicationInCallback {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        serde_json::to_writer(file, &self.get_records())
+    }
+}
+
+/// Publisher used by a callback.
+#[derive(Debug, Serialize)]
+struct PublicationInCallbackRecord {
+    #[serde(flatten)]
+    callback: CallbackRecordId,
+    topic: String,
+}
+
 impl EventAnalysis for PublicationInCallback {
     fn initialize(&mut self) {
         *self = Self::default();
     }
 
     fn process_event(&mut self, full_event: &FullEvent) {
+        // Without the thread, the publications cannot be attributed to a callback.
+        let Some(vtid) = full_event.context.vtid() else {
+            return;
+        };
+
         match &full_event.event {
             Event::Ros2(ros2::Event::CallbackStart(event)) => {
-                self.activate_callback(event.callback.clone(), &full_event.context);
+                self.activate_callback(
+                    event.callback.clone(),
+                    vtid,
+                    &full_event.context,
+                    full_event.time,
+                );
             }
             Event::Ros2(ros2::Event::CallbackEnd(event)) => {
-                self.deactivate_callback(&event.callback, &full_event.context);
+                self.deactivate_callback(
+                    &event.callback,
+                    vtid,
+                    &full_event.context,
+                    full_event.time,
+                );
             }
             Event::Ros2(ros2::Event::RmwPublish(event)) => {
-                self.process_publication(event.message.clone(), &full_event.context);
+                self.process_publication(event.message.clone(), vtid, &full_event.context);
             }
             _ => {}
         }
     }
 
+    fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+        self.discarded_windows.add(window);
+    }
+
     fn finalize(&mut self) {
-        // Nothing to do
+        let excluded = self.discarded_windows.get_excluded_count();
+        if excluded > 0 {
+            log::warn!(
+                "Publication in callback: dropped {excluded} callback executions overlapping discarded events"
+            );
+        }
+        let uncertain = self.discarded_windows.get_uncertain_count();
+        if uncertain > 0 {
+            log::warn!(
+                "Publication in callback: dropped {uncertain} callback executions, events were discarded at unknown times"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::arena::ArenaId;
+    use crate::raw_events::ros2::{CallbackEnd, CallbackStart, RmwPublish};
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::super::analyze_events;
+    use super::*;
+
+    #[test]
+    fn export_of_publishers_used_by_timer() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .timer(0, 1, 10, 20)
+            .publisher(0, 1, 30, "/used", 10)
+            .publisher(0, 1, 40, "/unused", 10)
+            .event(
+                1_000,
+                VPID,
+                CallbackStart {
+                    callback: 20,
+                    is_intra_process: false,
+                },
+            )
+            .event(
+                1_050,
+                VPID,
+                RmwPublish {
+                    rmw_publisher_handle: Some(31),
+                    message: 1_000,
+                    timestamp: Some(1_050),
+                },
+            )
+            .event(1_100, VPID, CallbackEnd { callback: 20 })
+            // Publications outside of the callbacks are not attributed to any of them.
+            .event(
+                1_200,
+                VPID,
+                RmwPublish {
+                    rmw_publisher_handle: Some(41),
+                    message: 1_001,
+                    timestamp: Some(1_200),
+                },
+            );
+
+        let mut analysis = PublicationInCallback::new();
+        let _model = analyze_events(trace.build(), &mut analysis);
+        let export = serde_json::to_value(analysis.get_records()).unwrap();
+
+        assert_eq!(
+            export,
+            serde_json::json!([{
+                "node": "/node",
+                "caller_type": "Timer",
+                "caller_param": "1 μs",
+                "topic": "/used",
+            }])
+        );
+    }
+
+    #[test]
+    fn callback_with_discarded_end_replaced() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .timer(0, 1, 10, 20)
+            .timer(0, 1, 50, 60)
+            .publisher(0, 1, 30, "/topic", 10)
+            // The end of this execution was discarded.
+            .event(
+                1_000,
+                VPID,
+                CallbackStart {
+                    callback: 20,
+                    is_intra_process: false,
+                },
+            )
+            .event(
+                2_000,
+                VPID,
+                CallbackStart {
+                    callback: 60,
+                    is_intra_process: false,
+                },
+            )
+            .event(
+                2_050,
+                VPID,
+                RmwPublish {
+                    rmw_publisher_handle: Some(31),
+                    message: 1_000,
+                    timestamp: Some(2_050),
+                },
+            )
+            .event(2_100, VPID, CallbackEnd { callback: 60 });
+
+        let mut analysis = PublicationInCallback::new();
+        let _model = analyze_events(trace.build(), &mut analysis);
+
+        // The publication is attributed to the callback which replaced the stale one.
+        assert_eq!(analysis.dependency.len(), 1);
+        let (_, callback) = analysis.dependency.iter().next().unwrap();
+        assert_eq!(callback.0.lock().unwrap().get_id().index(), 1);
+        assert!(analysis.active_callbacks.is_empty());
     }
 }
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::model::display::get_node_name_from_weak;
+use crate::model::{Callback, CallbackCaller, Time};
+use crate::processed_events::{Event, FullEvent, r2r, ros2};
+use crate::utils::{Known, WeakKnown};
+
+use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
+
+/// Topic of the simulation clock.
+const CLOCK_TOPIC: &str = "/clock";
+
+/// Mapping from the wall time of the trace to the simulation time.
+///
+/// It is built from the clock updates of r2r nodes, which record the new simulation time.
+/// The simulation time between two updates is interpolated linearly. The wall clocks of all
+/// hosts are assumed to be synchronized, so one mapping is used for the whole trace.
+#[derive(Debug, Default)]
+pub struct SimTimeMapping {
+    /// Wall time and simulation time in nanoseconds of each update, sorted by both.
+    updates: Vec<(Time, i64)>,
+}
+
+impl SimTimeMapping {
+    fn add_update(&mut self, wall_time: Time, sim_time: i64) {
+        // Every r2r node subscribed to the clock records the same update.
+        if self
+            .updates
+            .last()
+            .is_some_and(|&(last_wall_time, last_sim_time)| {
+                wall_time < last_wall_time || sim_time <= last_sim_time
+            })
+        {
+            return;
+        }
+        self.updates.push((wall_time, sim_time));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    /// Returns the simulation time in nanoseconds at the `wall_time`.
+    ///
+    /// Returns `None` outside of the interval covered by the clock updates.
+    pub fn to_sim_time(&self, wall_time: Time) -> Option<i64> {
+        let index = self
+            .updates
+            .partition_point(|&(update_time, _)| update_time <= wall_time);
+        let &(prev_wall, prev_sim) = self.updates.get(index.checked_sub(1)?)?;
+        if prev_wall == wall_time {
+            return Some(prev_sim);
+        }
+        let &(next_wall, next_sim) = self.updates.get(index)?;
+
+        let elapsed = (wall_time.timestamp_nanos() - prev_wall.timestamp_nanos()) as f64;
+        let interval = (next_wall.timestamp_nanos() - prev_wall.timestamp_nanos()) as f64;
+        Some(prev_sim + ((next_sim - prev_sim) as f64 * elapsed / interval) as i64)
+    }
+
+    /// Returns the simulation time elapsed between the wall times `start` and `end`.
+    pub fn sim_duration(&self, start: Time, end: Time) -> Option<i64> {
+        Some(self.to_sim_time(end)? - self.to_sim_time(start)?)
+    }
+
+    /// Returns the ratio of the elapsed simulation time to the elapsed wall time.
+    pub fn real_time_factor(&self) -> Option<f64> {
+        let (&(first_wall, first_sim), &(last_wall, last_sim)) =
+            (self.updates.first()?, self.updates.last()?);
+        let wall_duration = last_wall.timestamp_nanos() - first_wall.timestamp_nanos();
+        (wall_duration != 0).then(|| (last_sim - first_sim) as f64 / wall_duration as f64)
+    }
+}
+
+/// Simulation time of the trace and the real-time factor of the simulation.
+#[derive(Debug, Default)]
+pub struct SimTime {
+    mapping: Arc<SimTimeMapping>,
+    /// Wall times of the executions of the rclcpp `/clock` subscription callbacks.
+    ///
+    /// They show the update rate of the clock, the simulation time itself is not traced.
+    clock_callbacks: HashMap<ArcMutWrapper<Callback>, Vec<Time>>,
+}
+
+impl SimTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the mapping to express the results of other analyses in simulation time.
+    pub fn get_mapping(&self) -> Arc<SimTimeMapping> {
+        self.mapping.clone()
+    }
+
+    fn process_clock_callback(&mut self, event: &ros2::CallbackStart, time: Time) {
+        let callback_arc = event.callback.lock().unwrap().get_callback();
+        let callback = callback_arc.lock().unwrap();
+        let Some(CallbackCaller::Subscription(subscriber)) = callback.get_caller() else {
+            return;
+        };
+        if subscriber.get_arc().is_none_or(|subscriber| {
+            !matches!(
+                subscriber.lock().unwrap().get_topic(),
+                Known::Known(CLOCK_TOPIC)
+            )
+        }) {
+            return;
+        }
+        drop(callback);
+
+        self.clock_callbacks
+            .entry(callback_arc.into())
+            .or_default()
+            .push(time);
+    }
+}
+
+impl EventAnalysis for SimTime {
+    fn initialize(&mut self) {
+        *self = Self::default();
+    }
+
+    fn process_event(&mut self, full_event: &FullEvent) {
+        match &full_event.event {
+            Event::R2r(r2r::Event::UpdateTime(event)) => {
+                let sim_time = i64::from(event.time_s) * 1_000_000_000 + i64::from(event.time_ns);
+                Arc::get_mut(&mut self.mapping)
+                    .expect("The mapping is shared only after the analysis")
+                    .add_update(full_event.time, sim_time);
+            }
+            Event::Ros2(ros2::Event::CallbackStart(event)) => {
+                self.process_clock_callback(event, full_event.time);
+            }
+            _ => {}
+        }
+    }
+
+    fn finalize(&mut self) {
+        if let Some(real_time_factor) = self.mapping.real_time_factor() {
+            log::info!("Simulation real-time factor: {real_time_factor:.3}");
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SimTimeRecord {
+    real_time_factor: Option<f64>,
+    /// Wall times of the clock updates recorded by r2r nodes.
+    wall_times: Vec<i64>,
+    /// Simulation times of the clock updates in the same order as `wall_times`.
+    sim_times: Vec<i64>,
+    clock_subscriptions: Vec<ClockSubscriptionRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct ClockSubscriptionRecord {
+    node: String,
+    /// Wall times of the `/clock` callback executions.
+    wall_times: Vec<i64>,
+    /// Average number of clock updates per second of wall time.
+    update_rate: Option<f64>,
+}
+
+impl AnalysisOutput for SimTime {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let mut clock_subscriptions: Vec<_> = self
+            .clock_callbacks
+            .iter()
+            .map(|(callback, times)| {
+                let node = callback
+                    .0
+                    .lock()
+                    .unwrap()
+                    .get_node()
+                    .map_or(WeakKnown::Unknown, |node| {
+                        get_node_name_from_weak(&node.get_weak())
+                    });
+                let wall_duration = times.last().unwrap().timestamp_nanos()
+                    - times.first().unwrap().timestamp_nanos();
+                ClockSubscriptionRecord {
+                    node: node.to_string(),
+                    wall_times: times.iter().map(|time| time.timestamp_nanos()).collect(),
+                    update_rate: (wall_duration != 0)
+                        .then(|| (times.len() - 1) as f64 * 1e9 / wall_duration as f64),
+                }
+            })
+            .collect();
+        clock_subscriptions.sort_by(|a, b| a.node.cmp(&b.node));
+
+        let record = SimTimeRecord {
+            real_time_factor: self.mapping.real_time_factor(),
+            wall_times: self
+                .mapping
+                .updates
+                .iter()
+                .map(|(time, _)| time.timestamp_nanos())
+                .collect(),
+            sim_times: self.mapping.updates.iter().map(|(_, sim)| *sim).collect(),
+            clock_subscriptions,
+        };
+
+        serde_json::to_writer(file, &record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wall_to_sim_time() {
+        let mut mapping = SimTimeMapping::default();
+        mapping.add_update(Time::from_nanos(1000), 0);
+        mapping.add_update(Time::from_nanos(1000), 0);
+        mapping.add_update(Time::from_nanos(2000), 500);
+        mapping.add_update(Time::from_nanos(3000), 1000);
+
+        assert_eq!(mapping.updates.len(), 3);
+        assert_eq!(mapping.to_sim_time(Time::from_nanos(999)), None);
+        assert_eq!(mapping.to_sim_time(Time::from_nanos(1500)), Some(250));
+        assert_eq!(mapping.to_sim_time(Time::from_nanos(3000)), Some(1000));
+        assert_eq!(mapping.to_sim_time(Time::from_nanos(3001)), None);
+        assert_eq!(
+            mapping.sim_duration(Time::from_nanos(1000), Time::from_nanos(2500)),
+            Some(750)
+        );
+        assert_eq!(mapping.real_time_factor(), Some(0.5));
+    }
+}
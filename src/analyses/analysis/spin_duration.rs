@@ -1,15 +1,18 @@
 use std::collections::HashMap;
 
 use crate::analysis::utils::DisplayDurationStats;
+use crate::argsv2::Args;
 use crate::model::Node;
 use crate::processed_events::{self, Event};
+use crate::statistics::{Samples, SamplesExport, StatisticsBackend};
 use crate::utils::DurationDisplayImprecise;
 
 use super::{AnalysisOutput, ArcMutWrapper, EventAnalysis};
 
 #[derive(Debug, Default)]
 pub struct SpinDuration {
-    processing_durations: HashMap<ArcMutWrapper<Node>, Vec<i64>>,
+    processing_durations: HashMap<ArcMutWrapper<Node>, Samples>,
+    statistics: StatisticsBackend,
 }
 
 impl SpinDuration {
@@ -17,6 +20,11 @@ impl SpinDuration {
         Self::default()
     }
 
+    /// Keeps the spin durations by the `statistics` backend.
+    pub fn set_statistics(&mut self, statistics: StatisticsBackend) {
+        self.statistics = statistics;
+    }
+
     pub(crate) fn print_stats(&self) {
         println!("Spin duration statistics:");
         for (i, (node, durations)) in self.processing_durations.iter().enumerate() {
@@ -56,7 +64,7 @@ impl EventAnalysis for SpinDuration {
 
             self.processing_durations
                 .entry(node.into())
-                .or_default()
+                .or_insert_with(|| self.statistics.samples())
                 .push(duration);
         }
     }
@@ -67,17 +75,18 @@ impl EventAnalysis for SpinDuration {
 #[derive(Debug, serde::Serialize)]
 struct SpinDurationEntry {
     node: String,
-    spin_duration: Vec<i64>,
+    spin_duration: SamplesExport,
 }
 
 impl AnalysisOutput for SpinDuration {
     fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let quantiles = Args::get_analyses_args().quantiles();
         let spin_durations: Vec<SpinDurationEntry> = self
             .processing_durations
             .iter()
             .map(|(node, durations)| SpinDurationEntry {
                 node: node.0.lock().unwrap().get_full_name().unwrap().to_owned(),
-                spin_duration: durations.clone(),
+                spin_duration: durations.export(quantiles),
             })
             .collect();
 
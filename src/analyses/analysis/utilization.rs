@@ -1,46 +1,57 @@
 use std::collections::{HashMap, HashSet};
 
+use serde::Serialize;
+
 use crate::model::display::DisplayCallbackSummary;
 use crate::model::{Callback, CallbackType};
-use crate::statistics::{Mean, Quantile, Sorted};
+use crate::statistics::Quantile;
+use crate::utils::DurationDisplayImprecise;
 
-use super::callback_duration::ExecutionData;
-use super::{ArcMutWrapper, CallbackDuration};
+use super::callback_duration::{ExecutionSummary, ThreadExecutions};
+use super::cpu_usage::{CpuUsageRecord, ThreadUsageRecord};
+use super::utils::CallbackRecordId;
+use super::{AnalysisOutput, ArcMutWrapper, CallbackDuration, CpuUsage};
 
 pub struct Utilization<'a> {
-    callback_analysis: &'a CallbackDuration,
+    executions: HashMap<ArcMutWrapper<Callback>, ExecutionSummary>,
+    cpu_usage: Option<&'a CpuUsage>,
 }
 
-trait ReductionFunction:
-    Fn(HashMap<u32, (usize, i64)>, &[ExecutionData], f64) -> HashMap<u32, f64>
-{
-}
+trait ReductionFunction: Fn(&HashMap<u32, ThreadExecutions>, usize, f64) -> HashMap<u32, f64> {}
 
 impl<F> ReductionFunction for F where
-    F: Fn(HashMap<u32, (usize, i64)>, &[ExecutionData], f64) -> HashMap<u32, f64>
+    F: Fn(&HashMap<u32, ThreadExecutions>, usize, f64) -> HashMap<u32, f64>
 {
 }
 
 impl<'a> Utilization<'a> {
-    pub fn new(callback_analysis: &'a CallbackDuration) -> Self {
-        Self { callback_analysis }
+    pub fn new(callback_analysis: &CallbackDuration) -> Self {
+        Self {
+            executions: callback_analysis.get_execution_summaries(),
+            cpu_usage: None,
+        }
+    }
+
+    /// Adds the utilization measured by the kernel scheduler to the real utilization.
+    ///
+    /// It is ignored if the trace contains no kernel scheduler events.
+    pub fn with_cpu_usage(mut self, cpu_usage: &'a CpuUsage) -> Self {
+        self.cpu_usage = Some(cpu_usage).filter(|cpu_usage| !cpu_usage.is_empty());
+        self
     }
 
     fn get_thread_callback_map(&self) -> HashMap<(String, u32), HashSet<ArcMutWrapper<Callback>>> {
         let mut thread_callback_map: HashMap<(String, u32), HashSet<ArcMutWrapper<Callback>>> =
             HashMap::new();
-        for (callback_arc, execution_data) in self.callback_analysis.get_execution_data() {
-            execution_data
-                .iter()
-                .map(|data| data.tid)
-                .for_each(|thread| {
-                    let callback = callback_arc.0.lock().unwrap();
-                    let hostname = callback.get_hostname().to_string();
-                    thread_callback_map
-                        .entry((hostname.clone(), thread))
-                        .or_default()
-                        .insert(callback_arc.clone());
-                });
+        for (callback_arc, executions) in &self.executions {
+            executions.threads.keys().for_each(|&thread| {
+                let callback = callback_arc.0.lock().unwrap();
+                let hostname = callback.get_hostname().to_string();
+                thread_callback_map
+                    .entry((hostname.clone(), thread))
+                    .or_default()
+                    .insert(callback_arc.clone());
+            });
         }
 
         thread_callback_map
@@ -51,7 +62,7 @@ impl<'a> Utilization<'a> {
         reduction_function: impl ReductionFunction,
     ) -> HashMap<ArcMutWrapper<Callback>, HashMap<u32, f64>> {
         let mut thread_utilization = HashMap::new();
-        for (callback_arc, execution_data) in self.callback_analysis.get_execution_data() {
+        for (callback_arc, executions) in &self.executions {
             let callback = callback_arc.0.lock().unwrap();
             let callback_is_timer = callback
                 .get_type()
@@ -70,36 +81,16 @@ impl<'a> Utilization<'a> {
                     .into()
             });
 
-            let Some(inter_arrival_time) = inter_arrival_time.map_or_else(
-                || {
-                    let ict = self
-                        .callback_analysis
-                        .get_inter_arrival_time(callback_arc)?;
-                    ict.as_slice().mean()
-                },
-                Some,
-            ) else {
+            let Some(inter_arrival_time) =
+                inter_arrival_time.or(executions.mean_inter_arrival_time)
+            else {
                 // Skip this callback if it is not a timer with known period or if the inter-arrival time is not known.
                 continue;
             };
 
-            let mut execution_times_and_counts_per_thread = HashMap::new();
-
-            for data in execution_data {
-                let thread = data.tid;
-                let execution_time = data.duration;
-
-                let execution_stats = execution_times_and_counts_per_thread
-                    .entry(thread)
-                    .or_insert_with(|| (0, 0));
-
-                execution_stats.0 += 1; // Count
-                execution_stats.1 += execution_time; // Time
-            }
-
             let utilization_per_thread = reduction_function(
-                execution_times_and_counts_per_thread,
-                execution_data,
+                &executions.threads,
+                executions.count,
                 inter_arrival_time as f64,
             );
 
@@ -113,12 +104,12 @@ impl<'a> Utilization<'a> {
         &self,
     ) -> HashMap<ArcMutWrapper<Callback>, HashMap<u32, f64>> {
         self.calculate_utilization_per_callback_internal(
-            |execution_times_and_counts_per_thread, execution_data, inter_arrival_time| {
-                let total_count = execution_data.len();
-                execution_times_and_counts_per_thread
-                    .into_iter()
-                    .map(|(thread, (_count, time))| {
-                        let utilization = (time as f64) / (inter_arrival_time * total_count as f64);
+            |executions_per_thread, total_count, inter_arrival_time| {
+                executions_per_thread
+                    .iter()
+                    .map(|(&thread, executions)| {
+                        let utilization = (executions.total_duration as f64)
+                            / (inter_arrival_time * total_count as f64);
                         (thread, utilization)
                     })
                     .collect()
@@ -131,31 +122,18 @@ impl<'a> Utilization<'a> {
         execution_duration_quantile: Quantile,
     ) -> HashMap<ArcMutWrapper<Callback>, HashMap<u32, f64>> {
         self.calculate_utilization_per_callback_internal(
-            |execution_times_and_counts_per_thread, execution_data, inter_arrival_time| {
-                let total_count = execution_data.len();
-                let mut duration_data_per_thread = HashMap::new();
-                for data in execution_data {
-                    let thread = data.tid;
-                    let duration_data: &mut Vec<_> =
-                        duration_data_per_thread.entry(thread).or_default();
-                    duration_data.push(data.duration);
-                }
-
-                let quantile_duration = duration_data_per_thread
-                    .into_iter()
-                    .map(|(thread, durations)| {
-                        let sorted_durations = Sorted::from(durations);
-                        let quantile_duration = *sorted_durations
-                            .quantile(execution_duration_quantile)
-                            .unwrap();
-                        (thread, quantile_duration)
-                    })
-                    .collect::<HashMap<_, _>>();
-
-                execution_times_and_counts_per_thread
-                    .into_iter()
-                    .map(|(thread, (count, _time))| {
-                        let utilization = (quantile_duration[&thread] as f64 * count as f64)
+            |executions_per_thread, total_count, inter_arrival_time| {
+                executions_per_thread
+                    .iter()
+                    .map(|(&thread, executions)| {
+                        let [(_, quantile_duration)] = executions
+                            .durations
+                            .quantiles(&[execution_duration_quantile])
+                            .unwrap()[..]
+                        else {
+                            unreachable!("One quantile was requested");
+                        };
+                        let utilization = (quantile_duration as f64 * executions.count as f64)
                             / (inter_arrival_time * total_count as f64);
                         (thread, utilization)
                     })
@@ -184,6 +162,35 @@ impl<'a> Utilization<'a> {
         utilization_per_thread_map
     }
 
+    fn sorted_thread_utilization(
+        per_callback_utilization: &HashMap<ArcMutWrapper<Callback>, HashMap<u32, f64>>,
+    ) -> Vec<((String, u32), f64)> {
+        let utilization_per_thread = Self::calculate_total_utilization(per_callback_utilization);
+        let mut utilization_per_thread: Vec<_> = utilization_per_thread.into_iter().collect();
+        utilization_per_thread.sort_unstable_by(|a, b| a.1.total_cmp(&b.1).reverse());
+        utilization_per_thread
+    }
+
+    fn sorted_callback_utilization<'c>(
+        callbacks: &'c HashSet<ArcMutWrapper<Callback>>,
+        per_callback_utilization: &HashMap<ArcMutWrapper<Callback>, HashMap<u32, f64>>,
+        thread: u32,
+    ) -> Vec<(&'c ArcMutWrapper<Callback>, f64)> {
+        let mut utilization_per_callback = callbacks
+            .iter()
+            .filter_map(|callback_arc| {
+                per_callback_utilization
+                    .get(callback_arc)
+                    .map(|utilization| {
+                        let utilization = utilization.get(&thread).copied().unwrap_or(0.0);
+                        (callback_arc, utilization)
+                    })
+            })
+            .collect::<Vec<_>>();
+        utilization_per_callback.sort_unstable_by(|a, b| a.1.total_cmp(&b.1).reverse());
+        utilization_per_callback
+    }
+
     pub fn write_stats(
         &self,
         writer: &mut impl std::io::Write,
@@ -191,9 +198,7 @@ impl<'a> Utilization<'a> {
     ) -> std::io::Result<()> {
         let thread_callback_map = self.get_thread_callback_map();
         let per_callback_utilization = self.calculate_utilization_per_callback(quantile);
-        let utilization_per_thread = Self::calculate_total_utilization(&per_callback_utilization);
-        let mut utilization_per_thread: Vec<_> = utilization_per_thread.into_iter().collect();
-        utilization_per_thread.sort_unstable_by(|a, b| a.1.total_cmp(&b.1).reverse());
+        let utilization_per_thread = Self::sorted_thread_utilization(&per_callback_utilization);
 
         writeln!(
             writer,
@@ -210,9 +215,7 @@ impl<'a> Utilization<'a> {
     pub fn write_stats_real(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
         let thread_callback_map = self.get_thread_callback_map();
         let per_callback_utilization = self.calculate_utilization_per_callback_real();
-        let utilization_per_thread = Self::calculate_total_utilization(&per_callback_utilization);
-        let mut utilization_per_thread: Vec<_> = utilization_per_thread.into_iter().collect();
-        utilization_per_thread.sort_unstable_by(|a, b| a.1.total_cmp(&b.1).reverse());
+        let utilization_per_thread = Self::sorted_thread_utilization(&per_callback_utilization);
 
         writeln!(writer, "Utilization statistics for real execution times:")?;
         Self::write_utilization(
@@ -220,7 +223,51 @@ impl<'a> Utilization<'a> {
             &utilization_per_thread,
             &per_callback_utilization,
             &thread_callback_map,
-        )
+        )?;
+
+        if let Some(cpu_usage) = self.cpu_usage {
+            Self::write_cpu_usage(writer, cpu_usage)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_cpu_usage(
+        writer: &mut impl std::io::Write,
+        cpu_usage: &CpuUsage,
+    ) -> std::io::Result<()> {
+        let format_ratio = |ratio: Option<f64>| {
+            ratio.map_or_else(
+                || "unknown".to_owned(),
+                |ratio| format!("{:8.5} %", ratio * 100.0),
+            )
+        };
+
+        writeln!(writer, "Utilization measured by the kernel scheduler:")?;
+        for record in cpu_usage.get_thread_records() {
+            writeln!(
+                writer,
+                "Thread {} ({}) on {} has utilization {}, preempted for {}",
+                record.tid,
+                record.name,
+                record.hostname,
+                format_ratio(record.utilization),
+                DurationDisplayImprecise(record.preempted_time),
+            )?;
+        }
+        for record in cpu_usage.get_cpu_records() {
+            writeln!(
+                writer,
+                "CPU {} on {} has utilization {} ({} by callback threads, {} by other processes)",
+                record.cpu,
+                record.hostname,
+                format_ratio(record.utilization),
+                format_ratio(record.callback_threads_utilization),
+                format_ratio(record.other_utilization),
+            )?;
+        }
+
+        Ok(())
     }
 
     fn write_utilization(
@@ -238,20 +285,10 @@ impl<'a> Utilization<'a> {
                 hostname,
                 utilization * 100.0,
             )?;
-            let mut utilization_per_callback = callbacks
-                .iter()
-                .filter_map(|callback_arc| {
-                    per_callback_utilization
-                        .get(callback_arc)
-                        .map(|utilization| {
-                            let utilization = utilization.get(thread).copied().unwrap_or(0.0);
-                            (callback_arc, utilization)
-                        })
-                })
-                .collect::<Vec<_>>();
-            utilization_per_callback.sort_unstable_by(|a, b| a.1.total_cmp(&b.1).reverse());
 
-            for (callback_arc, utilization) in utilization_per_callback {
+            for (callback_arc, utilization) in
+                Self::sorted_callback_utilization(callbacks, per_callback_utilization, *thread)
+            {
                 let callback = callback_arc.0.lock().unwrap();
                 writeln!(
                     writer,
@@ -264,4 +301,197 @@ impl<'a> Utilization<'a> {
 
         Ok(())
     }
+
+    /// Returns the utilization based on the `quantile` of callback durations for the JSON output.
+    pub fn get_export(&self, quantile: Quantile) -> UtilizationExport {
+        let per_callback_utilization = self.calculate_utilization_per_callback(quantile);
+        self.export_utilization(Some(quantile), &per_callback_utilization, None)
+    }
+
+    /// Returns the utilization based on real execution times for the JSON output.
+    pub fn get_export_real(&self) -> UtilizationExport {
+        let per_callback_utilization = self.calculate_utilization_per_callback_real();
+        let kernel = self.cpu_usage.map(|cpu_usage| KernelUtilizationRecord {
+            threads: cpu_usage.get_thread_records(),
+            cpus: cpu_usage.get_cpu_records(),
+        });
+        self.export_utilization(None, &per_callback_utilization, kernel)
+    }
+
+    fn export_utilization(
+        &self,
+        quantile: Option<Quantile>,
+        per_callback_utilization: &HashMap<ArcMutWrapper<Callback>, HashMap<u32, f64>>,
+        kernel: Option<KernelUtilizationRecord>,
+    ) -> UtilizationExport {
+        let thread_callback_map = self.get_thread_callback_map();
+
+        let threads = Self::sorted_thread_utilization(per_callback_utilization)
+            .into_iter()
+            .map(|(key, utilization)| {
+                let callbacks = Self::sorted_callback_utilization(
+                    &thread_callback_map[&key],
+                    per_callback_utilization,
+                    key.1,
+                )
+                .into_iter()
+                .map(|(callback_arc, utilization)| CallbackUtilizationRecord {
+                    callback: (&*callback_arc.0.lock().unwrap()).into(),
+                    utilization,
+                })
+                .collect();
+
+                let (hostname, tid) = key;
+                ThreadUtilizationRecord {
+                    hostname,
+                    tid,
+                    utilization,
+                    callbacks,
+                }
+            })
+            .collect();
+
+        UtilizationExport {
+            quantile: quantile.map(Quantile::value),
+            threads,
+            kernel,
+        }
+    }
+}
+
+/// Utilization of threads by callbacks.
+///
+/// Utilizations are fractions of the thread time, i.e. `1.0` means 100 %.
+#[derive(Debug, Serialize)]
+pub struct UtilizationExport {
+    /// Callback duration quantile used to estimate the utilization.
+    ///
+    /// It is `None` if the utilization is based on real execution times.
+    quantile: Option<f64>,
+    threads: Vec<ThreadUtilizationRecord>,
+    /// Utilization measured by the kernel scheduler if the kernel trace is available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kernel: Option<KernelUtilizationRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct KernelUtilizationRecord {
+    /// Threads which executed a callback.
+    threads: Vec<ThreadUsageRecord>,
+    cpus: Vec<CpuUsageRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct ThreadUtilizationRecord {
+    hostname: String,
+    tid: u32,
+    utilization: f64,
+    callbacks: Vec<CallbackUtilizationRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct CallbackUtilizationRecord {
+    #[serde(flatten)]
+    callback: CallbackRecordId,
+    utilization: f64,
+}
+
+impl AnalysisOutput for UtilizationExport {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        serde_json::to_writer(file, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::processor::Processor;
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::super::{EventAnalysis, analyze_events};
+    use super::*;
+
+    /// Two timer callbacks with the period of 1 μs executing on separate threads.
+    fn callback_analysis() -> (CallbackDuration, Processor) {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .timer(0, 1, 10, 20)
+            .timer(0, 1, 11, 21)
+            .execution(1_000, 1_100, VPID, 20)
+            .execution(2_000, 2_300, VPID, 20)
+            .execution(1_000, 1_500, VPID + 1, 21);
+
+        let mut analysis = CallbackDuration::new();
+        let model = analyze_events(trace.build(), &mut analysis);
+        analysis.set_objects(model.objects());
+        (analysis, model)
+    }
+
+    #[test]
+    fn export_by_duration_quantile() {
+        let (analysis, _model) = callback_analysis();
+        let utilization = Utilization::new(&analysis);
+        let export =
+            serde_json::to_value(utilization.get_export(Quantile::new(1.0).unwrap())).unwrap();
+
+        assert_eq!(
+            export,
+            serde_json::json!({
+                "quantile": 1.0,
+                "threads": [
+                    {
+                        "hostname": "host",
+                        "tid": VPID + 1,
+                        "utilization": 0.5,
+                        "callbacks": [{
+                            "node": "/node",
+                            "caller_type": "Timer",
+                            "caller_param": "1 μs",
+                            "utilization": 0.5,
+                        }],
+                    },
+                    {
+                        "hostname": "host",
+                        "tid": VPID,
+                        "utilization": 0.3,
+                        "callbacks": [{
+                            "node": "/node",
+                            "caller_type": "Timer",
+                            "caller_param": "1 μs",
+                            "utilization": 0.3,
+                        }],
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn export_by_real_durations() {
+        let (analysis, _model) = callback_analysis();
+        let mut cpu_usage = CpuUsage::new();
+        cpu_usage.initialize();
+        let utilization = Utilization::new(&analysis).with_cpu_usage(&cpu_usage);
+        let export = serde_json::to_value(utilization.get_export_real()).unwrap();
+
+        // Without the kernel scheduler events, the kernel utilization is left out.
+        assert_eq!(export["quantile"], serde_json::Value::Null);
+        assert!(export.get("kernel").is_none());
+        let threads: Vec<_> = export["threads"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|thread| {
+                (
+                    thread["tid"].as_u64().unwrap(),
+                    thread["utilization"].as_f64().unwrap(),
+                    thread["callbacks"].as_array().unwrap().len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            threads,
+            [(u64::from(VPID) + 1, 0.5, 1), (u64::from(VPID), 0.2, 1)]
+        );
+    }
 }
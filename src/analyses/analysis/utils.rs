@@ -1,20 +1,52 @@
+use serde::Serialize;
+
 use crate::argsv2::Args;
-use crate::statistics::Sorted;
-use crate::utils::DurationDisplayImprecise;
+use crate::model::display::get_node_name_from_weak;
+use crate::model::{Callback, CallbackCaller};
+use crate::statistics::{QuantileSketch, Samples, Sorted};
+use crate::utils::{DurationDisplayImprecise, WeakKnown};
+
+/// Durations summarized by [`DisplayDurationStats`].
+pub enum DurationSeries<'a> {
+    Slice(&'a [i64]),
+    Sorted(&'a Sorted<i64>),
+    Sketch(&'a QuantileSketch),
+}
+
+impl<'a> From<&'a [i64]> for DurationSeries<'a> {
+    fn from(slice: &'a [i64]) -> Self {
+        Self::Slice(slice)
+    }
+}
+
+impl<'a> From<&'a Vec<i64>> for DurationSeries<'a> {
+    fn from(values: &'a Vec<i64>) -> Self {
+        Self::Slice(values)
+    }
+}
+
+impl<'a> From<&'a Samples> for DurationSeries<'a> {
+    fn from(samples: &'a Samples) -> Self {
+        match samples {
+            Samples::Exact(values) => Self::Sorted(values.sorted()),
+            Samples::Sketch(sketch) => Self::Sketch(sketch),
+        }
+    }
+}
 
-pub struct DisplayDurationStats<'a>(&'a [i64], &'a str);
+pub struct DisplayDurationStats<'a>(DurationSeries<'a>, &'a str);
 
 impl<'a> DisplayDurationStats<'a> {
-    pub fn with_newline(slice: &'a [i64]) -> Self {
-        Self(slice, "\n")
+    pub fn with_newline(series: impl Into<DurationSeries<'a>>) -> Self {
+        Self(series.into(), "\n")
     }
 
-    pub fn with_comma(slice: &'a [i64]) -> Self {
-        Self(slice, ", ")
+    pub fn with_comma(series: impl Into<DurationSeries<'a>>) -> Self {
+        Self(series.into(), ", ")
     }
 
-    pub fn new(slice: &'a [i64], separator: &'a str) -> Self {
-        Self(slice, separator)
+    pub fn new(series: impl Into<DurationSeries<'a>>, separator: &'a str) -> Self {
+        Self(series.into(), separator)
     }
 
     pub(crate) fn print(&self) {
@@ -22,18 +54,26 @@ impl<'a> DisplayDurationStats<'a> {
     }
 
     pub fn mean_and_std_dev(&self) -> (i64, f64) {
+        let slice = match self.0 {
+            DurationSeries::Slice(slice) => slice,
+            DurationSeries::Sorted(sorted) => sorted,
+            DurationSeries::Sketch(sketch) => {
+                return sketch
+                    .mean_and_std_dev()
+                    .expect("Sketch should not be empty");
+            }
+        };
         let mean =
-            (self.0.iter().copied().map(i128::from).sum::<i128>() / self.0.len() as i128) as i64;
-        if self.0.len() == 1 {
+            (slice.iter().copied().map(i128::from).sum::<i128>() / slice.len() as i128) as i64;
+        if slice.len() == 1 {
             return (mean, f64::NAN);
         }
-        let variance = self
-            .0
+        let variance = slice
             .iter()
             .map(|&x| (x - mean))
             .map(|x| i128::from(x) * i128::from(x))
             .sum::<i128>()
-            / (self.0.len() - 1) as i128;
+            / (slice.len() - 1) as i128;
         let std_dev = (variance as f64).sqrt();
         (mean, std_dev)
     }
@@ -41,16 +81,60 @@ impl<'a> DisplayDurationStats<'a> {
 
 impl std::fmt::Display for DisplayDurationStats<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.0.is_empty() {
-            return write!(f, "No data");
-        }
+        match self.0 {
+            DurationSeries::Slice(slice) => {
+                let sorted = Sorted::from_unsorted(slice);
+                return DisplayDurationStats(DurationSeries::Sorted(&sorted), self.1).fmt(f);
+            }
+            DurationSeries::Sorted(sorted) => {
+                if sorted.is_empty() {
+                    return write!(f, "No data");
+                }
 
-        let sorted = Sorted::from_unsorted(self.0);
-        write!(f, "count={}", sorted.len())?;
-        for q in Args::get_analyses_args().quantiles() {
-            let quantile = *sorted.quantile(*q).unwrap();
-            write!(f, "{}{}={}", self.1, q, DurationDisplayImprecise(quantile))?;
+                write!(f, "count={}", sorted.len())?;
+                for q in Args::get_analyses_args().quantiles() {
+                    let quantile = *sorted.quantile(*q).unwrap();
+                    write!(f, "{}{}={}", self.1, q, DurationDisplayImprecise(quantile))?;
+                }
+            }
+            DurationSeries::Sketch(sketch) => {
+                if sketch.is_empty() {
+                    return write!(f, "No data");
+                }
+
+                write!(f, "count={}", sketch.len())?;
+                for q in Args::get_analyses_args().quantiles() {
+                    let quantile = sketch.quantile(*q).unwrap();
+                    write!(f, "{}{}~{}", self.1, q, DurationDisplayImprecise(quantile))?;
+                }
+            }
         }
         Ok(())
     }
 }
+
+/// Identification of a callback in the JSON outputs.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct CallbackRecordId {
+    pub(crate) node: String,
+    pub(crate) caller_type: String,
+    /// The main parameter of the caller, i.e. the topic name, timer period or service name.
+    pub(crate) caller_param: String,
+}
+
+impl From<&Callback> for CallbackRecordId {
+    fn from(callback: &Callback) -> Self {
+        let node = callback.get_node().map_or(WeakKnown::Unknown, |node_weak| {
+            get_node_name_from_weak(&node_weak.get_weak())
+        });
+        let caller_param = callback
+            .get_caller()
+            .map_or(WeakKnown::Unknown, CallbackCaller::get_caller_as_string);
+
+        Self {
+            node: node.to_string(),
+            caller_type: callback.get_type().to_string(),
+            caller_param: caller_param.to_string(),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::events_common::Context;
+use crate::raw_events::ros2::RosDistro;
+
+use super::analysis::AnalysisOutput;
+
+/// Tracepoints seen in each traced process compared with its ROS 2 distribution.
+///
+/// It tells whether unexpected results may come from a ROS 2 version or a tracer
+/// which is not supported.
+#[derive(Debug, Default)]
+pub struct CompatibilityReport {
+    /// Processes by hostname and vpid.
+    processes: HashMap<String, HashMap<u32, ProcessData>>,
+}
+
+#[derive(Debug, Default)]
+struct ProcessData {
+    procname: String,
+    /// Handles of the rcl contexts initialized by the process.
+    context_handles: Vec<u64>,
+    tracetools_version: Option<String>,
+    /// Number of events of each tracepoint including the provider.
+    seen_tracepoints: HashMap<String, usize>,
+    /// Number of events of each tracepoint which were not decoded or processed.
+    unsupported_events: HashMap<String, usize>,
+}
+
+impl ProcessData {
+    fn distro(&self) -> Option<RosDistro> {
+        self.tracetools_version
+            .as_deref()
+            .and_then(RosDistro::from_tracetools_version)
+    }
+
+    /// Returns the `ros2` tracepoints of the detected distribution which were not seen.
+    ///
+    /// They are not necessarily missing, e.g., processes without lifecycle nodes never
+    /// emit the lifecycle events.
+    fn expected_not_seen(&self) -> BTreeSet<&'static str> {
+        self.distro().map_or_else(BTreeSet::new, |distro| {
+            distro
+                .tracepoints()
+                .filter(|name| {
+                    !self
+                        .seen_tracepoints
+                        .contains_key(&format!("{PROVIDER}{name}"))
+                })
+                .collect()
+        })
+    }
+
+    /// Returns the `ros2` tracepoints which were seen but do not belong to the detected
+    /// distribution.
+    fn unexpected(&self) -> BTreeSet<&str> {
+        let Some(distro) = self.distro() else {
+            return BTreeSet::new();
+        };
+        let expected: BTreeSet<_> = distro.tracepoints().collect();
+        self.seen_tracepoints
+            .keys()
+            .filter_map(|name| name.strip_prefix(PROVIDER))
+            .filter(|name| !expected.contains(name))
+            .collect()
+    }
+}
+
+const PROVIDER: &str = "ros2:";
+
+fn increment(counts: &mut HashMap<String, usize>, name: &str) {
+    if let Some(count) = counts.get_mut(name) {
+        *count += 1;
+    } else {
+        counts.insert(name.to_owned(), 1);
+    }
+}
+
+impl CompatibilityReport {
+    fn process(&mut self, context: &Context) -> &mut ProcessData {
+        if !self.processes.contains_key(context.hostname()) {
+            self.processes
+                .insert(context.hostname().to_owned(), HashMap::new());
+        }
+        self.processes
+            .get_mut(context.hostname())
+            .unwrap()
+            .entry(context.vpid())
+            .or_insert_with(|| ProcessData {
+                procname: context.procname().to_owned(),
+                ..Default::default()
+            })
+    }
+
+    /// Counts a userspace event `name` including the provider.
+    pub fn add_event(&mut self, context: &Context, name: &str) {
+        increment(&mut self.process(context).seen_tracepoints, name);
+    }
+
+    /// Counts an event which was not decoded or not processed.
+    pub fn add_unsupported_event(&mut self, context: &Context, name: &str) {
+        increment(&mut self.process(context).unsupported_events, name);
+    }
+
+    pub fn add_rcl_init(&mut self, context: &Context, context_handle: u64, version: &str) {
+        let process = self.process(context);
+        process.context_handles.push(context_handle);
+        process.tracetools_version = Some(version.to_owned());
+    }
+
+    /// Logs a warning for every process with an unknown distribution or unsupported events.
+    pub fn log_summary(&self) {
+        for (hostname, processes) in &self.processes {
+            for (vpid, process) in processes {
+                if process.distro().is_none() {
+                    log::warn!(
+                        "Unknown ROS 2 distribution of process {} ({vpid}) on {hostname}. \
+                        Missing rcl_init event or unsupported tracetools version {:?}.",
+                        process.procname,
+                        process.tracetools_version
+                    );
+                }
+                if !process.unsupported_events.is_empty() {
+                    let mut events: Vec<_> = process.unsupported_events.iter().collect();
+                    events.sort();
+                    log::warn!(
+                        "Unsupported events of process {} ({vpid}) on {hostname}: {events:?}",
+                        process.procname,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ProcessRecord<'a> {
+    hostname: &'a str,
+    vpid: u32,
+    procname: &'a str,
+    context_handles: &'a [u64],
+    tracetools_version: Option<&'a str>,
+    distro: Option<String>,
+    seen_tracepoints: BTreeMap<&'a str, usize>,
+    /// Tracepoints of the distribution which were not seen.
+    expected_not_seen: BTreeSet<&'static str>,
+    /// Tracepoints which do not belong to the distribution.
+    unexpected_tracepoints: BTreeSet<&'a str>,
+    unsupported_events: BTreeMap<&'a str, usize>,
+}
+
+impl AnalysisOutput for CompatibilityReport {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        let mut records: Vec<_> = self
+            .processes
+            .iter()
+            .flat_map(|(hostname, processes)| {
+                processes.iter().map(|(&vpid, process)| ProcessRecord {
+                    hostname,
+                    vpid,
+                    procname: &process.procname,
+                    context_handles: &process.context_handles,
+                    tracetools_version: process.tracetools_version.as_deref(),
+                    distro: process.distro().map(|distro| distro.to_string()),
+                    seen_tracepoints: process
+                        .seen_tracepoints
+                        .iter()
+                        .map(|(name, &count)| (name.as_str(), count))
+                        .collect(),
+                    expected_not_seen: process.expected_not_seen(),
+                    unexpected_tracepoints: process.unexpected(),
+                    unsupported_events: process
+                        .unsupported_events
+                        .iter()
+                        .map(|(name, &count)| (name.as_str(), count))
+                        .collect(),
+                })
+            })
+            .collect();
+        records.sort_by_key(|record| (record.hostname, record.vpid));
+
+        serde_json::to_writer(file, &records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn humble_process_without_intra_process_buffers() {
+        let context = Context::new(
+            Some(0),
+            10,
+            Some(10),
+            "talker".to_owned(),
+            "host".to_owned(),
+        );
+        let mut report = CompatibilityReport::default();
+        report.add_rcl_init(&context, 0x1000, "4.1.1");
+        for name in RosDistro::Humble.tracepoints() {
+            report.add_event(&context, &format!("{PROVIDER}{name}"));
+        }
+        report.add_event(&context, "ros2:rclcpp_ring_buffer_enqueue");
+        report.add_unsupported_event(&context, "ros2:rclcpp_ring_buffer_enqueue");
+
+        let process = &report.processes["host"][&10];
+        assert_eq!(process.distro(), Some(RosDistro::Humble));
+        assert!(process.expected_not_seen().is_empty());
+        assert_eq!(
+            process.unexpected(),
+            BTreeSet::from(["rclcpp_ring_buffer_enqueue"])
+        );
+        assert_eq!(
+            process.unsupported_events["ros2:rclcpp_ring_buffer_enqueue"],
+            1
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::processor::{ErrorKind, ObjectType, classify_error};
+use crate::raw_events;
+
+use super::analysis::AnalysisOutput;
+
+/// Processing errors collected in lenient mode.
+///
+/// The errors are counted per error kind and object type. The first event causing each
+/// of them is kept as a sample.
+#[derive(Debug, Default)]
+pub struct ErrorReport {
+    errors: HashMap<(ErrorKind, Option<ObjectType>), ErrorData>,
+}
+
+#[derive(Debug)]
+struct ErrorData {
+    count: usize,
+    sample: ErrorSample,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ErrorSample {
+    /// Time of the event in nanoseconds.
+    time: i64,
+    hostname: String,
+    vpid: u32,
+    vtid: Option<u32>,
+    /// Debug representation of the raw event.
+    event: String,
+    /// Error message with all its causes.
+    message: String,
+}
+
+impl ErrorReport {
+    pub fn add(&mut self, error: &color_eyre::Report, event: &raw_events::FullEvent) {
+        self.errors
+            .entry(classify_error(error))
+            .and_modify(|data| data.count += 1)
+            .or_insert_with(|| ErrorData {
+                count: 1,
+                sample: ErrorSample {
+                    time: event.time.timestamp_nanos(),
+                    hostname: event.context.hostname().to_owned(),
+                    vpid: event.context.vpid(),
+                    vtid: event.context.vtid(),
+                    event: format!("{:?}", event.event),
+                    message: format!("{error:#}"),
+                },
+            });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn log_summary(&self) {
+        let mut errors: Vec<_> = self.errors.iter().collect();
+        errors.sort_by(|(a_key, a), (b_key, b)| b.count.cmp(&a.count).then(a_key.cmp(b_key)));
+        for ((kind, object_type), data) in errors {
+            log::warn!(
+                "Skipped {} events failing with {kind:?} of {}. First at {}: {}",
+                data.count,
+                object_type.map_or_else(|| "unknown object".to_owned(), |t| format!("{t:?}")),
+                data.sample.time,
+                data.sample.message,
+            );
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorRecord {
+    kind: ErrorKind,
+    object_type: Option<ObjectType>,
+    count: usize,
+    /// The first event which failed with this error.
+    sample: ErrorSample,
+}
+
+impl ErrorReport {
+    /// Returns the errors sorted by their kind and object type.
+    fn get_records(&self) -> Vec<ErrorRecord> {
+        let mut records: Vec<_> = self
+            .errors
+            .iter()
+            .map(|(&(kind, object_type), data)| ErrorRecord {
+                kind,
+                object_type,
+                count: data.count,
+                sample: data.sample.clone(),
+            })
+            .collect();
+        records.sort_by_key(|record| (record.kind, record.object_type));
+        records
+    }
+}
+
+impl AnalysisOutput for ErrorReport {
+    fn write_json(&self, file: &mut std::io::BufWriter<std::fs::File>) -> serde_json::Result<()> {
+        serde_json::to_writer(file, &self.get_records())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::processor::Processor;
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::*;
+
+    #[test]
+    fn counts_per_kind_and_object_type() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .timer(10, 2, 20, 21)
+            .execution(100, 110, VPID, 30)
+            .execution(200, 210, VPID, 30);
+
+        let mut processor = Processor::new();
+        let mut report = ErrorReport::default();
+        for event in trace.build() {
+            let sample = event.clone();
+            if let Err(error) = processor.process_raw_event(event) {
+                report.add(&error, &sample);
+            }
+        }
+
+        let records: Vec<_> = report
+            .get_records()
+            .into_iter()
+            .map(|record| {
+                (
+                    record.kind,
+                    record.object_type,
+                    record.count,
+                    record.sample.time,
+                )
+            })
+            .collect();
+        // Only the first failing event of each kind and object type is kept as a sample.
+        assert_eq!(
+            records,
+            [
+                (ErrorKind::NotFound, Some(ObjectType::Callback), 4, 100),
+                (
+                    ErrorKind::ObjectMissingDependency,
+                    Some(ObjectType::Node),
+                    1,
+                    10
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use regex::Regex;
+
+use crate::model::{
+    Callback, CallbackCaller, Node, PublicationMessage, Publisher, RingBuffer, Subscriber,
+    SubscriptionMessage,
+};
+use crate::processed_events::{Event, FullEvent, r2r, ros2};
+use crate::utils::ArcWeak;
+
+/// Patterns including and excluding the names of one kind.
+#[derive(Debug, Clone, Default)]
+pub struct Patterns {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+/// Converts a glob with the `*` and `?` wildcards into a regular expression matching whole names.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+impl Patterns {
+    /// Compiles the patterns, which are globs unless `regex` is set.
+    ///
+    /// Regular expressions match any part of the name, globs match the whole name.
+    pub fn new(include: &[String], exclude: &[String], regex: bool) -> Result<Self, regex::Error> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    if regex {
+                        Regex::new(pattern)
+                    } else {
+                        Regex::new(&glob_to_regex(pattern))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Returns `true` if the `name` is included and not excluded.
+    ///
+    /// Unknown names are allowed only if there are no include patterns.
+    fn allows(&self, name: Option<&str>) -> bool {
+        let Some(name) = name else {
+            return self.include.is_empty();
+        };
+        (self.include.is_empty() || self.include.iter().any(|regex| regex.is_match(name)))
+            && !self.exclude.iter().any(|regex| regex.is_match(name))
+    }
+
+    /// Returns `true` if any of the `names` is allowed, or if the names are unknown and
+    /// there are no include patterns.
+    fn allows_any<'a>(&self, names: Option<impl IntoIterator<Item = &'a str>>) -> bool {
+        match names {
+            Some(names) => names.into_iter().any(|name| self.allows(Some(name))),
+            None => self.allows(None),
+        }
+    }
+}
+
+/// Selection of the events passed to the analyses.
+///
+/// The events are still processed to keep the model complete, the filter only decides
+/// whether the analyses see them. Each event is matched by the node and the topic of the
+/// object it belongs to, and by the process and the host which emitted it. Names which
+/// are unknown or do not apply to the event, e.g., the topic of a timer, are left out by
+/// the include patterns of their kind. The executor events are matched by the nodes
+/// whose callbacks the executor thread ran so far. The kernel events are matched only by
+/// the host, and the clock updates are never left out by the node and topic patterns.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    nodes: Patterns,
+    topics: Patterns,
+    processes: Patterns,
+    hosts: Patterns,
+
+    /// Nodes of the callbacks executed on each thread by hostname and thread id.
+    thread_nodes: HashMap<(String, u32), HashSet<String>>,
+}
+
+/// Node and topic of the object an event belongs to.
+#[derive(Debug, Default)]
+struct ObjectNames {
+    node: Option<String>,
+    topic: Option<String>,
+}
+
+fn node_name(node: Option<ArcWeak<Mutex<Node>>>) -> Option<String> {
+    let node = node?.get_arc()?;
+    let node = node.lock().unwrap();
+    Option::from(node.get_full_name().map(ToOwned::to_owned))
+}
+
+impl ObjectNames {
+    fn of_node(node: &Arc<Mutex<Node>>) -> Self {
+        Self {
+            node: Option::from(node.lock().unwrap().get_full_name().map(ToOwned::to_owned)),
+            topic: None,
+        }
+    }
+
+    fn of_publisher(publisher: &Arc<Mutex<Publisher>>) -> Self {
+        let publisher = publisher.lock().unwrap();
+        Self {
+            node: node_name(publisher.get_node().into()),
+            topic: Option::from(publisher.get_topic().map(ToOwned::to_owned)),
+        }
+    }
+
+    fn of_subscriber(subscriber: &Arc<Mutex<Subscriber>>) -> Self {
+        let subscriber = subscriber.lock().unwrap();
+        Self {
+            node: node_name(subscriber.get_node().into()),
+            topic: Option::from(subscriber.get_topic().map(ToOwned::to_owned)),
+        }
+    }
+
+    fn of_callback(callback: &Arc<Mutex<Callback>>) -> Self {
+        let callback = callback.lock().unwrap();
+        let topic = match callback.get_caller() {
+            Some(CallbackCaller::Subscription(subscriber)) => {
+                subscriber.get_arc().and_then(|subscriber| {
+                    Option::from(
+                        subscriber
+                            .lock()
+                            .unwrap()
+                            .get_topic()
+                            .map(ToOwned::to_owned),
+                    )
+                })
+            }
+            _ => None,
+        };
+        Self {
+            node: node_name(callback.get_node()),
+            topic,
+        }
+    }
+
+    fn of_publication(message: &Arc<Mutex<PublicationMessage>>) -> Self {
+        let publisher = message.lock().unwrap().get_publisher();
+        publisher.map_or_else(Self::default, |publisher| Self::of_publisher(&publisher))
+    }
+
+    fn of_subscription(message: &Arc<Mutex<SubscriptionMessage>>) -> Self {
+        let subscriber = message.lock().unwrap().get_subscriber();
+        subscriber.map_or_else(Self::default, |subscriber| Self::of_subscriber(&subscriber))
+    }
+
+    fn of_ring_buffer(ring_buffer: &Arc<Mutex<RingBuffer>>) -> Self {
+        let subscriber = ring_buffer.lock().unwrap().get_subscriber();
+        subscriber.map_or_else(Self::default, |subscriber| Self::of_subscriber(&subscriber))
+    }
+
+    /// Returns the names of the object of the `event`.
+    ///
+    /// The executor events are matched by their thread instead.
+    fn of_ros2_event(event: &ros2::Event) -> Self {
+        match event {
+            ros2::Event::RclInit(_)
+            | ros2::Event::RclcppExecutorGetNextReady(_)
+            | ros2::Event::RclcppExecutorWaitForWork(_)
+            | ros2::Event::RclcppExecutorExecute(_) => Self::default(),
+            ros2::Event::RclNodeInit(event) => Self::of_node(&event.node),
+            ros2::Event::RmwPublisherInit(ros2::RmwPublisherInit { publisher })
+            | ros2::Event::RclPublisherInit(ros2::RclPublisherInit { publisher }) => {
+                Self::of_publisher(publisher)
+            }
+            ros2::Event::RclcppPublish(ros2::RclcppPublish { message })
+            | ros2::Event::RclcppIntraPublish(ros2::RclcppIntraPublish { message })
+            | ros2::Event::RclPublish(ros2::RclPublish { message })
+            | ros2::Event::RmwPublish(ros2::RmwPublish { message }) => {
+                Self::of_publication(message)
+            }
+            ros2::Event::RmwSubscriptionInit(ros2::RmwSubscriptionInit { subscription })
+            | ros2::Event::RclSubscriptionInit(ros2::RclSubscriptionInit { subscription })
+            | ros2::Event::RclcppSubscriptionInit(ros2::RclcppSubscriptionInit { subscription }) => {
+                Self::of_subscriber(subscription)
+            }
+            ros2::Event::RmwTake(ros2::RmwTake { message, .. })
+            | ros2::Event::RclTake(ros2::RclTake { message, .. })
+            | ros2::Event::RclCppTake(ros2::RclCppTake { message, .. }) => {
+                Self::of_subscription(message)
+            }
+            ros2::Event::RclcppSubscriptionCallbackAdded(
+                ros2::RclcppSubscriptionCallbackAdded { callback },
+            )
+            | ros2::Event::RclCppServiceCallbackAdded(ros2::RclCppServiceCallbackAdded {
+                callback,
+            })
+            | ros2::Event::RclcppTimerCallbackAdded(ros2::RclcppTimerCallbackAdded { callback })
+            | ros2::Event::RclcppCallbackRegister(ros2::RclcppCallbackRegister { callback }) => {
+                Self::of_callback(callback)
+            }
+            ros2::Event::CallbackStart(ros2::CallbackStart { callback, .. })
+            | ros2::Event::CallbackEnd(ros2::CallbackEnd { callback, .. }) => {
+                Self::of_callback(&callback.lock().unwrap().get_callback())
+            }
+            ros2::Event::RclServiceInit(event) => Self {
+                node: node_name(event.service.lock().unwrap().get_node().into()),
+                topic: None,
+            },
+            ros2::Event::RclClientInit(event) => Self {
+                node: node_name(event.client.lock().unwrap().get_node().into()),
+                topic: None,
+            },
+            ros2::Event::RclTimerInit(ros2::RclTimerInit { timer })
+            | ros2::Event::RclcppTimerLinkNode(ros2::RclcppTimerLinkNode { timer }) => Self {
+                node: node_name(timer.lock().unwrap().get_node().into()),
+                topic: None,
+            },
+            ros2::Event::RclLifecycleStateMachineInit(ros2::RclLifecycleStateMachineInit {
+                state_machine,
+            })
+            | ros2::Event::RclLifecycleTransition(ros2::RclLifecycleTransition {
+                state_machine,
+                ..
+            }) => {
+                let node = state_machine.lock().unwrap().get_node().get_arc();
+                node.map_or_else(Self::default, |node| Self::of_node(&node))
+            }
+            ros2::Event::RclcppIpbToSubscription(event) => Self::of_subscriber(&event.subscriber),
+            ros2::Event::RclcppConstructRingBuffer(ros2::RclcppConstructRingBuffer {
+                ring_buffer,
+            })
+            | ros2::Event::RclcppBufferToIpb(ros2::RclcppBufferToIpb { ring_buffer })
+            | ros2::Event::RclcppRingBufferEnqueue(ros2::RclcppRingBufferEnqueue {
+                ring_buffer,
+                ..
+            })
+            | ros2::Event::RclcppRingBufferDequeue(ros2::RclcppRingBufferDequeue {
+                ring_buffer,
+                ..
+            })
+            | ros2::Event::RclcppRingBufferClear(ros2::RclcppRingBufferClear {
+                ring_buffer, ..
+            }) => Self::of_ring_buffer(ring_buffer),
+        }
+    }
+
+    fn of_r2r_event(event: &r2r::Event) -> Self {
+        match event {
+            r2r::Event::SpinStart(r2r::SpinStart { node, .. })
+            | r2r::Event::SpinEnd(r2r::SpinEnd { node, .. })
+            | r2r::Event::SpinWake(r2r::SpinWake { node, .. })
+            | r2r::Event::SpinTimeout(r2r::SpinTimeout { node, .. }) => Self::of_node(node),
+            r2r::Event::UpdateTime(_) => Self::default(),
+        }
+    }
+}
+
+impl EventFilter {
+    pub fn new(nodes: Patterns, topics: Patterns, processes: Patterns, hosts: Patterns) -> Self {
+        Self {
+            nodes,
+            topics,
+            processes,
+            hosts,
+            thread_nodes: HashMap::new(),
+        }
+    }
+
+    /// Returns `true` if the `event` should be passed to the analyses.
+    ///
+    /// All processed events must be passed here in the trace order, so that the threads of
+    /// the executors are matched with the nodes.
+    pub fn matches(&mut self, event: &FullEvent) -> bool {
+        if !self.hosts.allows(Some(event.context.hostname())) {
+            return false;
+        }
+        // The kernel events belong to no traced process.
+        if matches!(event.event, Event::Kernel(_)) {
+            return true;
+        }
+        let procname = event.context.procname();
+        if !self
+            .processes
+            .allows((!procname.is_empty()).then_some(procname))
+        {
+            return false;
+        }
+        if self.nodes.is_empty() && self.topics.is_empty() {
+            return true;
+        }
+
+        let names = match &event.event {
+            Event::Kernel(_) => unreachable!(),
+            // The clock updates are needed for the simulation time of all nodes.
+            Event::R2r(r2r::Event::UpdateTime(_)) => return true,
+            Event::R2r(event) => ObjectNames::of_r2r_event(event),
+            Event::Ros2(
+                ros2::Event::RclcppExecutorGetNextReady(_)
+                | ros2::Event::RclcppExecutorWaitForWork(_)
+                | ros2::Event::RclcppExecutorExecute(_),
+            ) => {
+                let nodes = event
+                    .context
+                    .vtid()
+                    .and_then(|vtid| {
+                        self.thread_nodes
+                            .get(&(event.context.hostname().to_owned(), vtid))
+                    })
+                    .map(|nodes| nodes.iter().map(String::as_str));
+                return self.nodes.allows_any(nodes) && self.topics.allows(None);
+            }
+            Event::Ros2(ros2_event) => {
+                let names = ObjectNames::of_ros2_event(ros2_event);
+                if let ros2::Event::CallbackStart(_) = ros2_event
+                    && let Some(node) = &names.node
+                    && let Some(vtid) = event.context.vtid()
+                {
+                    self.thread_nodes
+                        .entry((event.context.hostname().to_owned(), vtid))
+                        .or_default()
+                        .insert(node.clone());
+                }
+                names
+            }
+        };
+
+        self.nodes.allows(names.node.as_deref()) && self.topics.allows(names.topic.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::processor::{MaybeProcessed, Processor};
+    use crate::raw_events;
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::*;
+
+    #[test]
+    fn glob_and_regex_patterns() {
+        let globs = Patterns::new(
+            &["/robot/*".to_owned()],
+            &["/robot/rviz?".to_owned()],
+            false,
+        )
+        .unwrap();
+        assert!(globs.allows(Some("/robot/planner")));
+        assert!(!globs.allows(Some("/robot/rviz2")));
+        assert!(!globs.allows(Some("/other/robot/planner")));
+        assert!(!globs.allows(None));
+
+        let regexes = Patterns::new(&[], &["rviz|robot_state".to_owned()], true).unwrap();
+        assert!(regexes.allows(Some("/planner")));
+        assert!(!regexes.allows(Some("/robot_state_publisher")));
+        assert!(regexes.allows(None));
+
+        assert!(Patterns::new(&["(".to_owned()], &[], true).is_err());
+        assert!(Patterns::new(&["(".to_owned()], &[], false).is_ok());
+    }
+
+    #[test]
+    fn include_patterns_leave_out_unknown_names() {
+        const EXECUTOR_THREAD: u32 = VPID + 1;
+        const OTHER_THREAD: u32 = VPID + 2;
+
+        let mut trace = TraceBuilder::new();
+        trace
+            .event(
+                0,
+                VPID,
+                raw_events::ros2::RclInit {
+                    context_handle: 1,
+                    version: "8.2.3".to_owned(),
+                },
+            )
+            .node(0, 10, "planner")
+            .timer(0, 10, 20, 30)
+            .event(
+                100,
+                EXECUTOR_THREAD,
+                raw_events::ros2::RclCppExecutorGetNextReady,
+            )
+            .execution(110, 120, EXECUTOR_THREAD, 30)
+            .event(
+                130,
+                EXECUTOR_THREAD,
+                raw_events::ros2::RclCppExecutorGetNextReady,
+            )
+            .event(
+                130,
+                OTHER_THREAD,
+                raw_events::ros2::RclCppExecutorGetNextReady,
+            );
+
+        let nodes = Patterns::new(&["/planner".to_owned()], &[], false).unwrap();
+        let mut filter = EventFilter::new(
+            nodes,
+            Patterns::default(),
+            Patterns::default(),
+            Patterns::default(),
+        );
+        let mut processor = Processor::new();
+        let matches: Vec<_> = trace
+            .build()
+            .into_iter()
+            .map(|event| {
+                let Ok(MaybeProcessed::Processed(event)) = processor.process_raw_event(event)
+                else {
+                    panic!("The event should be processed");
+                };
+                filter.matches(&event)
+            })
+            .collect();
+
+        assert_eq!(
+            matches,
+            [
+                false, // The rcl_init event belongs to no node.
+                true,  // rcl_node_init
+                false, // The timer is linked to its node only later.
+                false, // The callback of the timer belongs to no node yet.
+                true,  // rclcpp_timer_link_node
+                false, // The executor did not run any callback of the node yet.
+                true,  // callback_start
+                true,  // callback_end
+                true,  // The executor ran a callback of the node.
+                false, // The other thread did not run any callback.
+            ]
+        );
+    }
+
+    #[test]
+    fn kernel_events_matched_by_host() {
+        let mut processor = Processor::new();
+        let Ok(MaybeProcessed::Processed(event)) = processor.process_raw_event(
+            TraceBuilder::new()
+                .sched_switch(0, 0, 0, 0, 10)
+                .build()
+                .remove(0),
+        ) else {
+            panic!("The event should be processed");
+        };
+        let filter = |hosts: &str| {
+            EventFilter::new(
+                Patterns::new(&["/planner".to_owned()], &[], false).unwrap(),
+                Patterns::new(&["/topic".to_owned()], &[], false).unwrap(),
+                Patterns::new(&["talker".to_owned()], &[], false).unwrap(),
+                Patterns::new(&[hosts.to_owned()], &[], false).unwrap(),
+            )
+        };
+
+        // The kernel events have no node, topic or process.
+        assert!(filter("host").matches(&event));
+        assert!(!filter("other").matches(&event));
+    }
+}
@@ -1,27 +1,355 @@
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::fs::File;
 use std::io::BufWriter;
 use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
-use bt2_sys::message::BtMessageType;
+use bt2_sys::message::{BtMessageConstCast, BtMessageType};
 
 use color_eyre::eyre::{Context, Result};
 
 use bt2_sys::logging::LogLevel;
 
-use bt2_sys::iterator::MessageIterator;
+use bt2_sys::iterator::{MessageIterator, TimeWindow, TraceSource};
 
 use crate::analyses::analysis;
+use crate::analyses::compatibility_report::CompatibilityReport;
+use crate::analyses::error_report::ErrorReport;
+use crate::analyses::event_filter::EventFilter;
+use crate::analyses::pipeline::ParallelAnalyses;
+use crate::data_quality::{DataQuality, DiscardedWindow};
+use crate::model::Time;
+use crate::preflight::TraceContexts;
 use crate::{processed_events, processor, raw_events};
 
-pub(crate) struct ProcessedEventsIter<'a> {
-    pub(crate) iter: MessageIterator,
+/// Messages decoded on the decoding thread are sent in batches of this size.
+const BATCH_SIZE: usize = 1024;
+/// Number of batches decoded ahead of the processing.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Trace message converted to a raw event or to the information needed by the processing.
+enum DecodedMessage {
+    /// Supported event with its name if it is a userspace event.
+    Event(raw_events::FullEvent, Option<String>),
+    /// Supported event before the time window, which is processed but not analyzed.
+    Setup(raw_events::FullEvent),
+    /// Event which was not decoded with its context and name if it is a userspace event.
+    Unsupported(Option<(crate::events_common::Context, String)>),
+    Discarded(DiscardedWindow),
+    /// Message without events.
+    Other,
+    /// A stream which cannot be processed began.
+    Error(color_eyre::Report),
+}
+
+/// Converts the messages of the trace to raw events.
+///
+/// The babeltrace objects cannot leave the thread of their graph, so the messages are
+/// decoded and converted to raw events on the same thread.
+struct Decoder {
+    iter: MessageIterator,
+    /// Iterator over the messages before the time window.
+    ///
+    /// Its events are processed without being analyzed, so the objects, the running
+    /// callbacks and the states from before the window are known.
+    setup_iter: Option<MessageIterator>,
+    begin: Option<Time>,
+}
+
+/// Formats the `time` as seconds from the clock origin for the `utils.trimmer` component.
+fn trimmer_bound(time: Time) -> CString {
+    let nanos = time.timestamp_nanos();
+    CString::new(format!(
+        "{}.{:09}",
+        nanos.div_euclid(1_000_000_000),
+        nanos.rem_euclid(1_000_000_000)
+    ))
+    .unwrap()
+}
+
+impl Decoder {
+    fn new(
+        source: TraceSource,
+        begin: Option<Time>,
+        end: Option<Time>,
+        log_level: LogLevel,
+    ) -> Self {
+        let begin_bound = begin.map(trimmer_bound);
+        let end_bound = end.map(trimmer_bound);
+        let window = (begin.is_some() || end.is_some()).then_some(TimeWindow {
+            begin: begin_bound.as_deref(),
+            end: end_bound.as_deref(),
+        });
+        let setup_iter = begin_bound.as_deref().map(|begin| {
+            let window = TimeWindow {
+                begin: None,
+                end: Some(begin),
+            };
+            MessageIterator::from_source(source, Some(window), log_level)
+        });
+
+        Self {
+            iter: MessageIterator::from_source(source, window, log_level),
+            setup_iter,
+            begin,
+        }
+    }
+
+    /// Spawns a thread decoding the `source` and returns the receiver of the decoded batches.
+    fn spawn(
+        source: TraceSource,
+        begin: Option<Time>,
+        end: Option<Time>,
+        log_level: LogLevel,
+    ) -> (Receiver<Vec<DecodedMessage>>, JoinHandle<()>) {
+        enum OwnedSource {
+            Files(Vec<CString>),
+            Live(CString),
+        }
+        let owned_source = match source {
+            TraceSource::Files(paths) => {
+                OwnedSource::Files(paths.iter().map(|&path| path.to_owned()).collect())
+            }
+            TraceSource::Live(url) => OwnedSource::Live(url.to_owned()),
+        };
+
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let handle = std::thread::Builder::new()
+            .name("decoder".to_owned())
+            .spawn(move || {
+                let paths: Vec<&CStr>;
+                let source = match &owned_source {
+                    OwnedSource::Files(owned_paths) => {
+                        paths = owned_paths.iter().map(CString::as_c_str).collect();
+                        TraceSource::Files(&paths)
+                    }
+                    OwnedSource::Live(url) => TraceSource::Live(url),
+                };
+
+                let mut batch = Vec::with_capacity(BATCH_SIZE);
+                for message in Self::new(source, begin, end, log_level) {
+                    // A live source sends messages without events while it waits for new ones.
+                    let idle = matches!(message, DecodedMessage::Other);
+                    batch.push(message);
+                    if batch.len() == BATCH_SIZE || idle {
+                        let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                        if sender.send(full).is_err() {
+                            // The processing stopped.
+                            return;
+                        }
+                    }
+                }
+                if !batch.is_empty() {
+                    let _ = sender.send(batch);
+                }
+            })
+            .expect("Failed to spawn the decoding thread");
+
+        (receiver, handle)
+    }
+}
+
+impl Iterator for Decoder {
+    type Item = DecodedMessage;
+
+    fn next(&mut self) -> Option<DecodedMessage> {
+        loop {
+            let (message, setup) = if let Some(setup_iter) = &mut self.setup_iter {
+                let Some(message) = setup_iter.next() else {
+                    self.setup_iter = None;
+                    continue;
+                };
+                (message, true)
+            } else {
+                (self.iter.next()?, false)
+            };
+            if setup && message.get_type() != BtMessageType::Event {
+                continue;
+            }
+
+            let event_msg = match message.get_type() {
+                BtMessageType::StreamBeginning => {
+                    let BtMessageConstCast::StreamBeginning(message) = message.cast() else {
+                        unreachable!();
+                    };
+                    // Streams beginning after the pre-flight check could be missing contexts.
+                    let mut contexts = TraceContexts::default();
+                    contexts.check_stream(&message.get_stream());
+                    if let Err(error) = contexts.ensure_processable() {
+                        return Some(DecodedMessage::Error(error));
+                    }
+                    continue;
+                }
+                BtMessageType::StreamEnd
+                | BtMessageType::PacketBeginning
+                | BtMessageType::PacketEnd => {
+                    // Silently skip these messages
+                    continue;
+                }
+                BtMessageType::DiscardedEvents | BtMessageType::DiscardedPackets => {
+                    let window = match message.cast() {
+                        BtMessageConstCast::DiscardedEvents(message) => {
+                            raw_events::discarded_events_window(&message)
+                        }
+                        BtMessageConstCast::DiscardedPackets(message) => {
+                            raw_events::discarded_packets_window(&message)
+                        }
+                        _ => unreachable!(),
+                    };
+                    return Some(DecodedMessage::Discarded(window));
+                }
+                BtMessageType::MessageIteratorInactivity => {
+                    // The live source sends them while no new events arrive.
+                    log::debug!(
+                        "Skipping babeltrace2 message of type {:?}",
+                        message.get_type()
+                    );
+                    return Some(DecodedMessage::Other);
+                }
+                BtMessageType::Event => message.into_event_msg(),
+            };
+
+            let event_class = event_msg.get_event().get_class();
+            let event_name = event_class.get_name().unwrap_or_default();
+            // Only the userspace events belong to a ROS 2 process.
+            let is_userspace = event_name.starts_with("ros2:") || event_name.starts_with("r2r:");
+
+            let event = raw_events::get_full_event(&event_msg);
+            if setup {
+                // Both bounds of the trimmer are inclusive, events at the beginning of
+                // the window are converted from the main iterator.
+                if let Some(event) = event
+                    && self.begin.is_some_and(|begin| event.time < begin)
+                {
+                    return Some(DecodedMessage::Setup(event));
+                }
+                continue;
+            }
+            let Some(event) = event else {
+                let event = event_msg.get_event();
+                log::debug!("Unsupported event: {event:?}");
+                return Some(DecodedMessage::Unsupported(is_userspace.then(|| {
+                    (
+                        raw_events::context_from_event(&event),
+                        event_name.to_owned(),
+                    )
+                })));
+            };
+            return Some(DecodedMessage::Event(
+                event,
+                is_userspace.then(|| event_name.to_owned()),
+            ));
+        }
+    }
+}
+
+/// Source of the decoded messages.
+enum DecodedMessages {
+    Inline(Decoder),
+    /// Messages decoded in advance.
+    #[cfg(test)]
+    Decoded(std::vec::IntoIter<DecodedMessage>),
+    /// Messages decoded on a separate thread.
+    Thread {
+        receiver: Receiver<Vec<DecodedMessage>>,
+        batch: std::vec::IntoIter<DecodedMessage>,
+        handle: Option<JoinHandle<()>>,
+    },
+}
+
+impl Iterator for DecodedMessages {
+    type Item = DecodedMessage;
+
+    fn next(&mut self) -> Option<DecodedMessage> {
+        match self {
+            Self::Inline(decoder) => decoder.next(),
+            #[cfg(test)]
+            Self::Decoded(messages) => messages.next(),
+            Self::Thread {
+                receiver,
+                batch,
+                handle,
+            } => loop {
+                if let Some(message) = batch.next() {
+                    return Some(message);
+                }
+                if let Ok(next_batch) = receiver.recv() {
+                    *batch = next_batch.into_iter();
+                    continue;
+                }
+                // The decoding thread ended, resume its panic if it failed.
+                if let Some(handle) = handle.take()
+                    && let Err(panic) = handle.join()
+                {
+                    std::panic::resume_unwind(panic);
+                }
+                return None;
+            },
+        }
+    }
+}
+
+/// Analyses receiving the events from [`ProcessedEventsIter::next_event`].
+pub(crate) enum EventSink<'a, 'b> {
+    /// The processed events are passed to the analyses.
+    Analyses(&'a mut [&'b mut dyn analysis::EventAnalysis]),
+    /// The processed events are passed to the analysis threads.
+    Parallel(&'a mut ParallelAnalyses),
+}
+
+impl EventSink<'_, '_> {
+    fn process_event(&mut self, event: &processed_events::FullEvent) {
+        match self {
+            Self::Analyses(analyses) => {
+                for analysis in analyses.iter_mut() {
+                    analysis.process_event(event);
+                }
+            }
+            Self::Parallel(parallel) => parallel.process_event(event),
+        }
+    }
+
+    fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+        match self {
+            Self::Analyses(analyses) => {
+                for analysis in analyses.iter_mut() {
+                    analysis.process_discarded_events(window);
+                }
+            }
+            Self::Parallel(parallel) => parallel.process_discarded_events(window),
+        }
+    }
+
+    fn finalize(&mut self) {
+        match self {
+            Self::Analyses(analyses) => {
+                for analysis in analyses.iter_mut() {
+                    analysis.finalize();
+                }
+            }
+            Self::Parallel(parallel) => parallel.finalize(),
+        }
+    }
+}
+
+pub(crate) struct ProcessedEventsIter {
+    messages: DecodedMessages,
     pub(crate) on_unprocessed_event: fn(raw_events::FullEvent),
-    pub(crate) analyses: Vec<&'a mut dyn analysis::EventAnalysis>,
     pub(crate) processor: processor::Processor,
+    pub(crate) data_quality: DataQuality,
+    /// Errors skipped in lenient mode. Processing stops at the first error if `None`.
+    pub(crate) error_report: Option<ErrorReport>,
+    pub(crate) compatibility_report: CompatibilityReport,
+    /// Selection of the processed events passed to the analyses.
+    pub(crate) filter: EventFilter,
+    /// Beginning of the time window.
+    begin: Option<Time>,
 
     // Counters
     pub(crate) ros_processed_events: usize,
+    pub(crate) ros_filtered_events: usize,
     pub(crate) ros_unsupported_events: usize,
     pub(crate) ros_processing_failures: usize,
     pub(crate) other_events: usize,
@@ -38,9 +366,15 @@ pub(crate) fn convert(level: clap_verbosity_flag::log::Level) -> LogLevel {
     }
 }
 
-impl<'a> ProcessedEventsIter<'a> {
+impl ProcessedEventsIter {
+    /// Creates an iterator over the events of the `source` in the time window.
+    ///
+    /// If `decode_in_thread` is set, the trace is decoded on a separate thread.
     pub(crate) fn new<L: clap_verbosity_flag::LogLevel>(
-        trace_paths: &[&CStr],
+        source: TraceSource,
+        begin: Option<Time>,
+        end: Option<Time>,
+        decode_in_thread: bool,
         verbosity: &clap_verbosity_flag::Verbosity<L>,
     ) -> Self {
         let log_level = convert(
@@ -48,13 +382,34 @@ impl<'a> ProcessedEventsIter<'a> {
                 .log_level()
                 .unwrap_or(clap_verbosity_flag::log::Level::Error),
         );
+        let messages = if decode_in_thread {
+            let (receiver, handle) = Decoder::spawn(source, begin, end, log_level);
+            DecodedMessages::Thread {
+                receiver,
+                batch: Vec::new().into_iter(),
+                handle: Some(handle),
+            }
+        } else {
+            DecodedMessages::Inline(Decoder::new(source, begin, end, log_level))
+        };
+        let mut iter = Self::with_messages(messages);
+        iter.begin = begin;
+        iter
+    }
+
+    fn with_messages(messages: DecodedMessages) -> Self {
         Self {
-            iter: MessageIterator::new(trace_paths, log_level),
+            messages,
             on_unprocessed_event: |_event| {}, // Do nothing by default
-            analyses: Vec::new(),
             processor: processor::Processor::new(),
+            data_quality: DataQuality::default(),
+            error_report: None,
+            compatibility_report: CompatibilityReport::default(),
+            filter: EventFilter::default(),
+            begin: None,
 
             ros_processed_events: 0,
+            ros_filtered_events: 0,
             ros_unsupported_events: 0,
             ros_processing_failures: 0,
             other_events: 0,
@@ -62,22 +417,22 @@ impl<'a> ProcessedEventsIter<'a> {
         }
     }
 
-    pub(crate) fn add_analysis(&mut self, analysis: &'a mut dyn analysis::EventAnalysis) {
-        analysis.initialize();
-        self.analyses.push(analysis);
-    }
-
-    pub(crate) fn add_add_analysis(
-        &mut self,
-        analyses: impl IntoIterator<Item = &'a mut dyn analysis::EventAnalysis>,
-    ) {
-        // False positive - fix breaks the code
-        // https://github.com/rust-lang/rust-clippy/issues/13185
-        #[allow(clippy::manual_inspect)]
-        self.analyses.extend(analyses.into_iter().map(|analysis| {
-            analysis.initialize();
-            analysis
-        }));
+    /// Creates an iterator over already decoded `events` in the time window from `begin`.
+    #[cfg(test)]
+    pub(crate) fn from_events(events: Vec<raw_events::FullEvent>, begin: Option<Time>) -> Self {
+        let messages: Vec<_> = events
+            .into_iter()
+            .map(|event| {
+                if begin.is_some_and(|begin| event.time < begin) {
+                    DecodedMessage::Setup(event)
+                } else {
+                    DecodedMessage::Event(event, None)
+                }
+            })
+            .collect();
+        let mut iter = Self::with_messages(DecodedMessages::Decoded(messages.into_iter()));
+        iter.begin = begin;
+        iter
     }
 
     pub(crate) fn set_on_unprocessed_event(
@@ -87,31 +442,50 @@ impl<'a> ProcessedEventsIter<'a> {
         self.on_unprocessed_event = on_unprocessed_event;
     }
 
+    /// Continue processing after processor errors and collect them into an error report.
+    pub(crate) fn set_lenient(&mut self) {
+        self.error_report = Some(ErrorReport::default());
+    }
+
+    pub(crate) fn set_filter(&mut self, filter: EventFilter) {
+        self.filter = filter;
+    }
+
+    /// Releases the messages which were not taken by all subscribers within the `horizon`.
+    pub(crate) fn set_message_horizon(&mut self, horizon: Duration) {
+        self.processor.set_message_horizon(horizon);
+    }
+
     pub(crate) fn log_counters(&self) {
         log::info!(target: "trace_counters",
             "Ros events:\n\
         - processed: {}\n\
+        - filtered out: {}\n\
         - failed to process: {}\n\
         - unsupported: {}\n\
         Other events: {}\n\
         Other messages: {}",
             self.ros_processed_events,
+            self.ros_filtered_events,
             self.ros_processing_failures,
             self.ros_unsupported_events,
             self.other_events,
             self.other_messages
         );
+        self.processor.log_object_counts();
     }
 
     pub(crate) fn print_counters(&self) {
         println!(
             "Ros events:\n\
             - processed: {}\n\
+            - filtered out: {}\n\
             - failed to process: {}\n\
             - unsupported: {}\n\
             Other events: {}\n\
             Other messages: {}",
             self.ros_processed_events,
+            self.ros_filtered_events,
             self.ros_processing_failures,
             self.ros_unsupported_events,
             self.other_events,
@@ -120,67 +494,155 @@ impl<'a> ProcessedEventsIter<'a> {
     }
 }
 
-impl Iterator for ProcessedEventsIter<'_> {
-    type Item = Result<processed_events::FullEvent>;
+/// Returns `true` if the `event` ends a callback execution or takes a message which started
+/// before `begin`.
+///
+/// The analyses did not see the start, so the event is not analyzed.
+fn started_before(event: &processed_events::FullEvent, begin: Time) -> bool {
+    use processed_events::ros2;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        for message in self.iter.by_ref() {
-            let event = match message.get_type() {
-                BtMessageType::StreamBeginning
-                | BtMessageType::StreamEnd
-                | BtMessageType::PacketBeginning
-                | BtMessageType::PacketEnd => {
-                    // Silently skip these messages
+    let message = match &event.event {
+        processed_events::Event::Ros2(ros2::Event::CallbackEnd(event)) => {
+            return event.callback.lock().unwrap().get_start_time() < begin;
+        }
+        processed_events::Event::Ros2(ros2::Event::RclTake(ros2::RclTake { message, .. }))
+        | processed_events::Event::Ros2(ros2::Event::RclCppTake(ros2::RclCppTake {
+            message,
+            ..
+        })) => message,
+        _ => return false,
+    };
+    let message = message.lock().unwrap();
+    message
+        .get_rmw_receive_time()
+        .or_else(|| message.get_rcl_receive_time())
+        .is_some_and(|receive_time| receive_time < begin)
+}
+
+/// Progress of the [`ProcessedEventsIter`].
+pub(crate) enum Step {
+    /// Processed event, which was passed to the analyses.
+    Event(processed_events::FullEvent),
+    /// The live source waits for new events.
+    Idle,
+}
+
+impl ProcessedEventsIter {
+    /// Returns the next processed event after passing it to the `analyses`.
+    ///
+    /// The analyses are finalized when the trace ends. They are not borrowed between
+    /// the calls, so their results can be written while the trace is being processed.
+    #[cfg(test)]
+    pub(crate) fn next_event(
+        &mut self,
+        analyses: &mut EventSink,
+    ) -> Option<Result<processed_events::FullEvent>> {
+        loop {
+            match self.next_step(analyses)? {
+                Ok(Step::Event(event)) => return Some(Ok(event)),
+                Ok(Step::Idle) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+
+    /// Like [`Self::next_event`], but also returns when the live source is idle.
+    pub(crate) fn next_step(&mut self, analyses: &mut EventSink) -> Option<Result<Step>> {
+        for message in self.messages.by_ref() {
+            let (event, event_name) = match message {
+                DecodedMessage::Event(event, event_name) => (event, event_name),
+                DecodedMessage::Setup(event) => {
+                    // The event only updates the model, it is not analyzed.
+                    match self.processor.process_raw_event(event) {
+                        Ok(processor::MaybeProcessed::Processed(processed)) => {
+                            // The filter follows the executor threads.
+                            self.filter.matches(&processed);
+                        }
+                        Ok(processor::MaybeProcessed::Raw(_)) => {}
+                        Err(err) => {
+                            log::debug!("Failed to process event before the time window: {err:?}");
+                        }
+                    }
                     continue;
                 }
-                BtMessageType::DiscardedEvents
-                | BtMessageType::DiscardedPackets
-                | BtMessageType::MessageIteratorInactivity => {
-                    log::warn!(
-                        "Skipping babeltrace2 message of type {:?}",
-                        message.get_type()
-                    );
+                DecodedMessage::Unsupported(userspace) => {
+                    if let Some((context, event_name)) = userspace {
+                        self.compatibility_report.add_event(&context, &event_name);
+                        self.compatibility_report
+                            .add_unsupported_event(&context, &event_name);
+                    }
+
+                    // Skip unsupported events
+                    self.other_events += 1;
+                    continue;
+                }
+                DecodedMessage::Discarded(window) => {
+                    log::warn!("Tracer discarded {window}");
+                    analyses.process_discarded_events(&window);
+                    self.data_quality.add_window(window);
                     self.other_messages += 1;
                     continue;
                 }
-                BtMessageType::Event => {
-                    let event_msg = message.into_event_msg();
-                    raw_events::get_full_event(&event_msg).ok_or(event_msg)
+                DecodedMessage::Other => {
+                    self.other_messages += 1;
+                    return Some(Ok(Step::Idle));
                 }
+                DecodedMessage::Error(error) => return Some(Err(error)),
             };
-
-            let Ok(event) = event else {
-                let event_msg = event.unwrap_err();
-                let event = event_msg.get_event();
-                log::debug!("Unsupported event: {event:?}");
-
-                // Skip unsupported events
-                self.other_events += 1;
-                continue;
-            };
+            if let Some(event_name) = &event_name {
+                self.compatibility_report
+                    .add_event(&event.context, event_name);
+            }
+            // The raw event is consumed by the processor, keep it for the error report.
+            let kept = self.error_report.is_some().then(|| event.clone());
             match self.processor.process_raw_event(event) {
                 Ok(processor::MaybeProcessed::Processed(processed)) => {
                     self.ros_processed_events += 1;
-                    for analysis in &mut self.analyses {
-                        (*analysis).process_event(&processed);
+                    if let processed_events::Event::Ros2(processed_events::ros2::Event::RclInit(
+                        init,
+                    )) = &processed.event
+                    {
+                        self.compatibility_report.add_rcl_init(
+                            &processed.context,
+                            init.context_handle,
+                            &init.version,
+                        );
+                    }
+                    let matches = self.filter.matches(&processed);
+                    if !matches {
+                        self.ros_filtered_events += 1;
                     }
-                    return Some(Ok(processed));
+                    let analyze = matches
+                        && !self
+                            .begin
+                            .is_some_and(|begin| started_before(&processed, begin));
+                    if analyze {
+                        analyses.process_event(&processed);
+                    }
+                    return Some(Ok(Step::Event(processed)));
                 }
                 Ok(processor::MaybeProcessed::Raw(raw)) => {
                     self.ros_unsupported_events += 1;
+                    if let Some(event_name) = &event_name {
+                        self.compatibility_report
+                            .add_unsupported_event(&raw.context, event_name);
+                    }
                     (self.on_unprocessed_event)(raw);
                     continue;
                 }
                 Err(err) => {
                     self.ros_processing_failures += 1;
+                    if let (Some(error_report), Some(sample)) = (&mut self.error_report, &kept) {
+                        log::debug!("Skipping event which failed to process: {err:?}");
+                        error_report.add(&err, sample);
+                        continue;
+                    }
                     return Some(Err(err));
                 }
             }
         }
 
-        for analysis in &mut self.analyses {
-            analysis.finalize();
-        }
+        analyses.finalize();
 
         None
     }
@@ -193,3 +655,91 @@ pub(crate) fn get_buf_writer_for_path(path: &Path) -> Result<BufWriter<File>> {
         File::create(path).wrap_err_with(|| format!("Failed to create file: `{path:?}`"))?;
     Ok(BufWriter::new(out_file))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::analyses::analysis::{CallbackDuration, EventAnalysis};
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::*;
+
+    #[test]
+    fn callback_spanning_the_beginning() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 10, "node")
+            .timer(0, 10, 20, 30)
+            .execution(50, 60, VPID, 30)
+            .execution(90, 110, VPID, 30)
+            .execution(200, 210, VPID, 30);
+
+        let mut analysis = CallbackDuration::new();
+        analysis.initialize();
+        let mut iter = ProcessedEventsIter::from_events(trace.build(), Some(Time::from_nanos(100)));
+        let mut sink = EventSink::Analyses(&mut [&mut analysis]);
+        let mut processed = 0;
+        while let Some(event) = iter.next_event(&mut sink) {
+            event.unwrap();
+            processed += 1;
+        }
+        analysis.set_objects(iter.processor.objects());
+
+        // The end of the execution started before the window is processed, but not analyzed.
+        assert_eq!(processed, 3);
+        let records = analysis.get_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].durations.clone().into_raw(), Some(vec![10]));
+    }
+
+    #[test]
+    fn lenient_processing_continues_after_error() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 10, "node")
+            .timer(0, 10, 20, 30)
+            .execution(50, 60, VPID, 30)
+            // The callback 40 was never added, so its execution fails to process.
+            .execution(70, 80, VPID, 40)
+            .execution(90, 110, VPID, 30);
+
+        let mut analysis = CallbackDuration::new();
+        analysis.initialize();
+        let mut iter = ProcessedEventsIter::from_events(trace.build(), None);
+        iter.set_lenient();
+        let mut sink = EventSink::Analyses(&mut [&mut analysis]);
+        while let Some(event) = iter.next_event(&mut sink) {
+            event.unwrap();
+        }
+        analysis.set_objects(iter.processor.objects());
+
+        assert_eq!(iter.ros_processing_failures, 2);
+        assert!(!iter.error_report.as_ref().unwrap().is_empty());
+        let records = analysis.get_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].durations.clone().into_raw(), Some(vec![10, 20]));
+    }
+
+    #[test]
+    fn idle_source_returns_control() {
+        let mut trace = TraceBuilder::new();
+        trace.node(0, 10, "first").node(20, 30, "second");
+        let mut messages: Vec<_> = trace
+            .build()
+            .into_iter()
+            .map(|event| DecodedMessage::Event(event, None))
+            .collect();
+        messages.insert(1, DecodedMessage::Other);
+
+        let mut iter =
+            ProcessedEventsIter::with_messages(DecodedMessages::Decoded(messages.into_iter()));
+        let mut sink = EventSink::Analyses(&mut []);
+        let mut steps = Vec::new();
+        while let Some(step) = iter.next_step(&mut sink) {
+            steps.push(matches!(step.unwrap(), Step::Idle));
+        }
+
+        // The idle step between the events lets the caller flush the outputs.
+        assert_eq!(steps, [false, true, false]);
+        assert_eq!(iter.other_messages, 1);
+    }
+}
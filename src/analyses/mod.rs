@@ -1,23 +1,101 @@
-use std::ffi::CStr;
+use std::borrow::Cow;
 use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-use color_eyre::eyre::Context;
+use bt2_sys::iterator::TraceSource;
+use color_eyre::eyre::{Context, bail};
 
 use crate::analyses::analysis::AnalysisOutputExt;
 use crate::analyses::event_iterator::get_buf_writer_for_path;
+use crate::argsv2::Args;
 use crate::argsv2::analysis_args::AnalysisArgs;
+use crate::data_quality::DataQuality;
+use crate::model::Time;
+use crate::preflight::{Domain, RequiredContext, TraceContexts};
+use crate::statistics::StatisticsBackend;
 
 pub mod analysis;
+pub mod compatibility_report;
+pub mod error_report;
+pub mod event_filter;
 pub mod event_iterator;
+pub mod pipeline;
+
+/// Contexts needed by the analyses which follow the threads of the ROS 2 applications.
+const USERSPACE_THREADS: &[(Domain, RequiredContext)] =
+    &[(Domain::Userspace, RequiredContext::Vtid)];
+
+/// Contexts needed by the analyses which match the ROS 2 threads with the kernel scheduler events.
+const KERNEL_THREADS: &[(Domain, RequiredContext)] = &[
+    (Domain::Userspace, RequiredContext::Vtid),
+    (Domain::Kernel, RequiredContext::Hostname),
+    (Domain::Kernel, RequiredContext::CpuId),
+];
 
 #[derive(Default)]
 pub struct Analyses {
     message_latency_analysis: Option<analysis::MessageLatency>,
     callback_analysis: Option<analysis::CallbackDuration>,
+    cpu_usage_analysis: Option<analysis::CpuUsage>,
     callback_dependency_analysis: Option<analysis::CallbackDependency>,
     message_take_to_callback_analysis: Option<analysis::MessageTakeToCallbackLatency>,
     dependency_graph: Option<analysis::DependencyGraph>,
     spin_duration_analysis: Option<analysis::SpinDuration>,
+    lifecycle_analysis: Option<analysis::Lifecycle>,
+    executor_scheduling_analysis: Option<analysis::ExecutorScheduling>,
+    intra_process_buffers_analysis: Option<analysis::IntraProcessBuffers>,
+    callback_off_cpu_analysis: Option<analysis::CallbackOffCpu>,
+    sim_time_analysis: Option<analysis::SimTime>,
+    message_loss_analysis: Option<analysis::MessageLoss>,
+    clock_offset_analysis: Option<analysis::ClockOffset>,
+
+    lenient: bool,
+    /// Read the trace and run the analyses on separate threads.
+    parallel: bool,
+    in_sim_time: bool,
+    correct_clock_offsets: bool,
+    statistics: StatisticsBackend,
+    /// The simulation time mapping and the clock offsets were estimated before the analysis,
+    /// so the sketched samples can be corrected as they are added.
+    corrections_estimated: bool,
+    /// Interval of writing the outputs while a live session is analyzed.
+    flush_interval: Option<Duration>,
+    /// Time after which the messages not taken by all subscribers are released.
+    message_horizon: Option<Duration>,
+    /// Time window of the analyzed events.
+    begin: Option<Time>,
+    end: Option<Time>,
+    filter: event_filter::EventFilter,
+
+    data_quality: DataQuality,
+    error_report: Option<error_report::ErrorReport>,
+    compatibility_report: compatibility_report::CompatibilityReport,
+    /// The ROS 2 objects built from the trace. The analyses link to most of them weakly,
+    /// so they are kept until the outputs are written.
+    model: Option<crate::processor::Processor>,
+}
+
+/// Processes the events until the trace ends or the outputs should be written.
+///
+/// Returns `true` if the `flush_interval` elapsed since the `last_flush`.
+fn process_events(
+    iter: &mut event_iterator::ProcessedEventsIter,
+    mut analyses: event_iterator::EventSink,
+    flush_interval: Option<Duration>,
+    last_flush: Instant,
+) -> color_eyre::eyre::Result<bool> {
+    // The live source is idle between its events, which is still a chance to flush.
+    while let Some(step) = iter.next_step(&mut analyses) {
+        if let event_iterator::Step::Event(event) = step.wrap_err("Failed to process event")? {
+            log::trace!("{event}");
+        }
+
+        if flush_interval.is_some_and(|interval| last_flush.elapsed() >= interval) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
 }
 
 impl Analyses {
@@ -30,152 +108,552 @@ impl Analyses {
                 .map(|x| x as &mut dyn analysis::EventAnalysis)
         }
 
+        let corrections_estimated = self.corrections_estimated;
         option_to_dyn_iter(&mut self.message_latency_analysis)
             .chain(option_to_dyn_iter(&mut self.callback_analysis))
+            .chain(option_to_dyn_iter(&mut self.cpu_usage_analysis))
             .chain(option_to_dyn_iter(&mut self.callback_dependency_analysis))
             .chain(option_to_dyn_iter(
                 &mut self.message_take_to_callback_analysis,
             ))
             .chain(option_to_dyn_iter(&mut self.dependency_graph))
             .chain(option_to_dyn_iter(&mut self.spin_duration_analysis))
+            .chain(option_to_dyn_iter(&mut self.lifecycle_analysis))
+            .chain(option_to_dyn_iter(&mut self.executor_scheduling_analysis))
+            .chain(option_to_dyn_iter(&mut self.intra_process_buffers_analysis))
+            .chain(option_to_dyn_iter(&mut self.callback_off_cpu_analysis))
+            .chain(option_to_dyn_iter(&mut self.message_loss_analysis))
+            .chain(
+                option_to_dyn_iter(&mut self.sim_time_analysis)
+                    .chain(option_to_dyn_iter(&mut self.clock_offset_analysis))
+                    .filter(move |_| !corrections_estimated),
+            )
     }
 
-    pub fn add_analyses_from_args(&mut self, args: &crate::argsv2::analysis_args::AnalysisArgs) {
+    pub fn add_analyses_from_args(
+        &mut self,
+        args: &crate::argsv2::analysis_args::AnalysisArgs,
+        contexts: &TraceContexts,
+    ) -> color_eyre::eyre::Result<()> {
+        let statistics = args.statistics_backend();
+        if !statistics.is_exact()
+            && (args.in_sim_time() || args.correct_clock_offsets())
+            && args.live_url().is_some()
+        {
+            bail!(
+                "The quantile sketches of a live session cannot be expressed in simulation time \
+                or corrected by the clock offsets"
+            );
+        }
+
         if args.message_latency_enabled() {
-            self.message_latency_analysis = Some(analysis::MessageLatency::new());
+            let mut analysis = analysis::MessageLatency::new();
+            analysis.set_statistics(statistics);
+            if !contexts.supports("intra-process message latency", USERSPACE_THREADS) {
+                analysis.skip_intra_process();
+            }
+            self.message_latency_analysis = Some(analysis);
         }
 
         if args.callback_duration_enabled()
             || args.utilization_enabled()
             || args.real_utilization_enabled()
         {
-            self.callback_analysis = Some(analysis::CallbackDuration::new());
+            let mut analysis = analysis::CallbackDuration::new();
+            analysis.set_statistics(statistics);
+            self.callback_analysis = Some(analysis);
         }
 
-        if args.callback_dependency_enabled() || args.callback_publications_enabled() {
+        if args.real_utilization_enabled() && contexts.supports("real utilization", KERNEL_THREADS)
+        {
+            self.cpu_usage_analysis = Some(analysis::CpuUsage::new());
+        }
+
+        if (args.callback_dependency_enabled() || args.callback_publications_enabled())
+            && contexts.supports("callback dependency", USERSPACE_THREADS)
+        {
             self.callback_dependency_analysis = Some(analysis::CallbackDependency::new());
         }
 
         if args.message_take_to_callback_latency_enabled() {
-            self.message_take_to_callback_analysis =
-                Some(analysis::MessageTakeToCallbackLatency::new());
+            let mut analysis = analysis::MessageTakeToCallbackLatency::new();
+            analysis.set_statistics(statistics);
+            self.message_take_to_callback_analysis = Some(analysis);
         }
 
-        if args.dependency_graph_enabled() {
-            self.dependency_graph = Some(analysis::DependencyGraph::new());
+        if (args.dependency_graph_enabled() || args.dependency_graph_data_enabled())
+            && contexts.supports("dependency graph", USERSPACE_THREADS)
+        {
+            let mut analysis = analysis::DependencyGraph::new();
+            analysis.set_statistics(statistics);
+            self.dependency_graph = Some(analysis);
         }
 
         if args.spin_duration_enabled() {
-            self.spin_duration_analysis = Some(analysis::SpinDuration::new());
+            let mut analysis = analysis::SpinDuration::new();
+            analysis.set_statistics(statistics);
+            self.spin_duration_analysis = Some(analysis);
+        }
+
+        if args.lifecycle_enabled() {
+            self.lifecycle_analysis = Some(analysis::Lifecycle::new());
+        }
+
+        if args.executor_scheduling_enabled()
+            && contexts.supports("executor scheduling", USERSPACE_THREADS)
+        {
+            let mut analysis = analysis::ExecutorScheduling::new();
+            analysis.set_statistics(statistics);
+            self.executor_scheduling_analysis = Some(analysis);
+        }
+
+        if args.intra_process_buffers_enabled()
+            && contexts.supports("intra-process buffers", USERSPACE_THREADS)
+        {
+            self.intra_process_buffers_analysis = Some(analysis::IntraProcessBuffers::new());
+        }
+
+        if args.callback_off_cpu_enabled() && contexts.supports("callback off-CPU", KERNEL_THREADS)
+        {
+            let mut analysis = analysis::CallbackOffCpu::new();
+            analysis.set_statistics(statistics);
+            self.callback_off_cpu_analysis = Some(analysis);
+        }
+
+        if args.sim_time_enabled() || args.in_sim_time() {
+            self.sim_time_analysis = Some(analysis::SimTime::new());
+        }
+
+        if args.message_loss_enabled() {
+            self.message_loss_analysis = Some(analysis::MessageLoss::new());
+        }
+
+        if args.clock_offset_enabled() || args.correct_clock_offsets() {
+            let mut analysis = analysis::ClockOffset::new();
+            if args.one_directional_clock_offsets() {
+                analysis.use_one_directional();
+            }
+            self.clock_offset_analysis = Some(analysis);
         }
+
+        self.lenient = args.lenient();
+        self.parallel = args.parallel();
+        self.in_sim_time = args.in_sim_time();
+        self.correct_clock_offsets = args.correct_clock_offsets();
+        self.statistics = statistics;
+        self.flush_interval = args.live_url().is_some().then(|| args.flush_interval());
+        self.message_horizon = args.message_horizon();
+        self.begin = args
+            .begin()
+            .and_then(|begin| begin.resolve(contexts.trace_start()));
+        self.end = args
+            .end()
+            .and_then(|end| end.resolve(contexts.trace_start()));
+        self.filter = args
+            .event_filter()
+            .wrap_err("Invalid event filter pattern")?;
+
+        Ok(())
     }
 
     pub fn analyze_trace<L: clap_verbosity_flag::LogLevel>(
         &mut self,
-        trace_paths: Vec<&CStr>,
+        source: TraceSource,
         verbose: &clap_verbosity_flag::Verbosity<L>,
     ) -> color_eyre::eyre::Result<()> {
-        let mut iter = event_iterator::ProcessedEventsIter::new(&trace_paths, verbose);
+        if let (Some(begin), Some(end)) = (self.begin, self.end)
+            && begin > end
+        {
+            bail!("The beginning of the time window {begin} is after its end {end}");
+        }
 
-        iter.add_add_analysis(self.all_as_mut());
+        // The sketches cannot be corrected after the samples were added.
+        if !self.statistics.is_exact() && (self.in_sim_time || self.correct_clock_offsets) {
+            self.estimate_corrections(source, verbose)?;
+        }
+
+        let mut iter = self.events_iter(source, self.parallel, verbose);
+        if let Some(analysis) = &mut self.callback_analysis {
+            analysis.set_objects(iter.processor.objects());
+        }
+        for analysis in self.all_as_mut() {
+            analysis.initialize();
+        }
 
         iter.set_on_unprocessed_event(|event| {
             log::debug!("Unprocessed event: {event:?}");
         });
 
-        for event in &mut iter {
-            let event = event.wrap_err("Failed to process event")?;
-            log::trace!("{event}");
+        let flush_interval = self.flush_interval;
+        let parallel = self.parallel;
+        let mut last_flush = Instant::now();
+        loop {
+            let mut analyses: Vec<_> = self.all_as_mut().collect();
+            let flush = if parallel {
+                std::thread::scope(|scope| {
+                    let mut parallel = pipeline::ParallelAnalyses::spawn(scope, analyses);
+                    let sink = event_iterator::EventSink::Parallel(&mut parallel);
+                    process_events(&mut iter, sink, flush_interval, last_flush)
+                })?
+            } else {
+                let sink = event_iterator::EventSink::Analyses(&mut analyses);
+                process_events(&mut iter, sink, flush_interval, last_flush)?
+            };
+
+            if !flush {
+                break;
+            }
+            self.flush_output(&mut iter)?;
+            last_flush = Instant::now();
         }
 
         iter.log_counters();
 
+        let data_quality = std::mem::take(&mut iter.data_quality);
+        let error_report = iter.error_report.take();
+        let compatibility_report = std::mem::take(&mut iter.compatibility_report);
+        self.model = Some(iter.processor);
+        if !data_quality.is_complete() {
+            log::warn!(
+                "The tracer discarded some events. \
+                Results overlapping the discarded windows may be excluded or inaccurate."
+            );
+        }
+        self.data_quality = data_quality;
+
+        if let Some(error_report) = &error_report
+            && !error_report.is_empty()
+        {
+            log::warn!("Some events failed to process and were skipped:");
+            error_report.log_summary();
+        }
+        self.error_report = error_report;
+
+        compatibility_report.log_summary();
+        self.compatibility_report = compatibility_report;
+
+        if !self.corrections_estimated {
+            self.apply_corrections();
+        }
+
         Ok(())
     }
 
+    fn events_iter<L: clap_verbosity_flag::LogLevel>(
+        &self,
+        source: TraceSource,
+        parallel: bool,
+        verbose: &clap_verbosity_flag::Verbosity<L>,
+    ) -> event_iterator::ProcessedEventsIter {
+        let mut iter = event_iterator::ProcessedEventsIter::new(
+            source, self.begin, self.end, parallel, verbose,
+        );
+        if self.lenient {
+            iter.set_lenient();
+        }
+        iter.set_filter(self.filter.clone());
+        if let Some(horizon) = self.message_horizon {
+            iter.set_message_horizon(horizon);
+        }
+        iter
+    }
+
+    /// Estimates the simulation time mapping and the clock offsets in a separate pass over
+    /// the trace and passes them to the analyses before their events are processed.
+    fn estimate_corrections<L: clap_verbosity_flag::LogLevel>(
+        &mut self,
+        source: TraceSource,
+        verbose: &clap_verbosity_flag::Verbosity<L>,
+    ) -> color_eyre::eyre::Result<()> {
+        log::info!("Estimating the corrections of the sketched samples");
+        let mut iter = self.events_iter(source, false, verbose);
+        let mut analyses: Vec<&mut dyn analysis::EventAnalysis> = Vec::new();
+        if let Some(analysis) = &mut self.sim_time_analysis {
+            analyses.push(analysis);
+        }
+        if let Some(analysis) = &mut self.clock_offset_analysis {
+            analyses.push(analysis);
+        }
+        for analysis in &mut analyses {
+            analysis.initialize();
+        }
+        let sink = event_iterator::EventSink::Analyses(&mut analyses);
+        process_events(&mut iter, sink, None, Instant::now())?;
+        drop(iter);
+
+        self.apply_corrections();
+        self.corrections_estimated = true;
+        Ok(())
+    }
+
+    /// Passes the simulation time mapping and the clock offsets to the analyses.
+    fn apply_corrections(&mut self) {
+        if self.correct_clock_offsets
+            && let Some(clock_offset) = &self.clock_offset_analysis
+            && let Some(analysis) = &mut self.message_latency_analysis
+        {
+            let offsets = clock_offset.get_offsets();
+            if offsets.is_empty() {
+                log::warn!(
+                    "No messages were sent between hosts in both directions. \
+                    The message latencies are not corrected."
+                );
+            } else {
+                analysis.set_clock_offsets(offsets);
+            }
+        }
+
+        if self.in_sim_time
+            && let Some(sim_time) = &self.sim_time_analysis
+        {
+            let mapping = sim_time.get_mapping();
+            if mapping.is_empty() {
+                log::warn!(
+                    "The trace contains no clock updates of r2r nodes. \
+                    The results are expressed in wall time."
+                );
+            } else {
+                if let Some(analysis) = &mut self.callback_analysis {
+                    analysis.set_sim_time(mapping.clone());
+                }
+                if let Some(analysis) = &mut self.message_latency_analysis {
+                    analysis.set_sim_time(mapping);
+                }
+            }
+        }
+    }
+
+    /// Writes the outputs of a live session processed so far.
+    ///
+    /// The analyses are not finalized, so the results computed at the end of the session,
+    /// e.g., the message loss or the callback dependency graph, are written only then.
+    fn flush_output(
+        &mut self,
+        iter: &mut event_iterator::ProcessedEventsIter,
+    ) -> color_eyre::eyre::Result<()> {
+        self.data_quality = iter.data_quality.clone();
+        self.error_report = iter.error_report.take();
+        self.compatibility_report = std::mem::take(&mut iter.compatibility_report);
+
+        let result = self.save_output(Args::get_analyses_args());
+
+        iter.error_report = self.error_report.take();
+        iter.compatibility_report = std::mem::take(&mut self.compatibility_report);
+        log::info!("Outputs of the live session were written");
+        result
+    }
+
     pub fn save_output(&self, args: &AnalysisArgs) -> color_eyre::eyre::Result<()> {
-        if let Some(path) = args.message_latency_path() {
-            let analysis = self.message_latency_analysis.as_ref().unwrap();
-            analysis.write_json_to_output_dir(&path)?;
+        if let Some(path) = args.message_latency_path()
+            && let Some(analysis) = &self.message_latency_analysis
+        {
+            analysis.write_json_to_output_dir(&path, &self.data_quality)?;
         }
 
-        if let Some(path) = args.callback_duration_path() {
-            let analysis = self.callback_analysis.as_ref().unwrap();
-            analysis.write_json_to_output_dir(&path)?;
+        if let Some(path) = args.callback_duration_path()
+            && let Some(analysis) = &self.callback_analysis
+        {
+            analysis.write_json_to_output_dir(&path, &self.data_quality)?;
         }
 
-        if let Some(path) = args.callback_publications_path() {
-            let analysis = self.callback_dependency_analysis.as_ref().unwrap();
+        if let Some(path) = args.callback_publications_path()
+            && let Some(analysis) = &self.callback_dependency_analysis
+        {
             let analysis = analysis.get_publication_in_callback_analysis();
-            let mut writer = get_buf_writer_for_path(&path)?;
+            if is_text_output(&path) {
+                let mut writer = get_buf_writer_for_path(&path)?;
+                self.data_quality.write_summary(&mut writer, "")?;
+                analysis
+                    .write_stats(&mut writer)
+                    .wrap_err("Failed to write publication in callback stats")?;
+            }
             analysis
-                .write_stats(&mut writer)
+                .write_json_to_output_dir(&json_output_path(&path), &self.data_quality)
                 .wrap_err("Failed to write publication in callback stats")?;
-
-            // TODO: Implement JSON output
-            // analysis.write_json_to_output_dir(&path, None)?;
         }
 
-        if let Some(path) = args.callback_dependency_path() {
-            let analysis = self.callback_dependency_analysis.as_ref().unwrap();
-
-            let graph = analysis.get_graph().unwrap();
+        if let Some(path) = args.callback_dependency_path()
+            && let Some(analysis) = &self.callback_dependency_analysis
+            && let Some(graph) = analysis.get_graph()
+        {
             let mut writer = get_buf_writer_for_path(&path)?;
+            self.data_quality.write_summary(&mut writer, "// ")?;
             writer
                 .write_fmt(format_args!("{}", graph.as_dot()))
                 .wrap_err("Failed to write dependency graph")?;
         }
 
-        if let Some(path) = args.message_take_to_callback_latency_path() {
-            let analysis = self.message_take_to_callback_analysis.as_ref().unwrap();
+        if let Some(path) = args.message_take_to_callback_latency_path()
+            && let Some(analysis) = &self.message_take_to_callback_analysis
+        {
             analysis
-                .write_json_to_output_dir(&path)
+                .write_json_to_output_dir(&path, &self.data_quality.not_adjusted())
                 .wrap_err("Failed to write message take to callback latency stats")?;
         }
 
-        if let Some(path) = args.utilization_path() {
-            let analysis = self.callback_analysis.as_ref().unwrap();
+        if let Some(path) = args.utilization_path()
+            && let Some(analysis) = &self.callback_analysis
+        {
             let utilization = analysis::Utilization::new(analysis);
 
-            let mut writer = get_buf_writer_for_path(&path)?;
+            if is_text_output(&path) {
+                let mut writer = get_buf_writer_for_path(&path)?;
+                self.data_quality
+                    .not_adjusted()
+                    .write_summary(&mut writer, "")?;
+                utilization
+                    .write_stats(&mut writer, args.utilization_quantile())
+                    .wrap_err("Failed to write utilization stats")?;
+            }
             utilization
-                .write_stats(&mut writer, args.utilization_quantile())
+                .get_export(args.utilization_quantile())
+                .write_json_to_output_dir(
+                    &json_output_path(&path),
+                    &self.data_quality.not_adjusted(),
+                )
                 .wrap_err("Failed to write utilization stats")?;
-
-            // TODO: Implement JSON output
-            // utilization.write_json_to_output_dir(&path, None)?;
         }
 
-        if let Some(path) = args.real_utilization_path() {
-            let analysis = self.callback_analysis.as_ref().unwrap();
-            let utilization = analysis::Utilization::new(analysis);
-
-            let mut writer = get_buf_writer_for_path(&path)?;
+        if let Some(path) = args.real_utilization_path()
+            && let Some(analysis) = &self.callback_analysis
+            && let Some(cpu_usage) = &self.cpu_usage_analysis
+        {
+            let utilization = analysis::Utilization::new(analysis).with_cpu_usage(cpu_usage);
+
+            if is_text_output(&path) {
+                let mut writer = get_buf_writer_for_path(&path)?;
+                self.data_quality
+                    .not_adjusted()
+                    .write_summary(&mut writer, "")?;
+                utilization
+                    .write_stats_real(&mut writer)
+                    .wrap_err("Failed to write real utilization stats")?;
+            }
             utilization
-                .write_stats_real(&mut writer)
+                .get_export_real()
+                .write_json_to_output_dir(
+                    &json_output_path(&path),
+                    &self.data_quality.not_adjusted(),
+                )
                 .wrap_err("Failed to write real utilization stats")?;
-
-            // TODO: Implement JSON output
-            // utilization.write_json_to_output_dir(&path)?;
         }
 
-        if let Some(path) = args.spin_duration_path() {
-            let analysis = self.spin_duration_analysis.as_ref().unwrap();
+        if let Some(path) = args.spin_duration_path()
+            && let Some(analysis) = &self.spin_duration_analysis
+        {
             analysis
-                .write_json_to_output_dir(&path)
+                .write_json_to_output_dir(&path, &self.data_quality.not_adjusted())
                 .wrap_err("Failed to write spin duration stats")?;
         }
 
-        if let Some(path) = args.dependency_graph_path() {
-            let analysis = self.dependency_graph.as_ref().unwrap();
+        if let Some(path) = args.lifecycle_path()
+            && let Some(analysis) = &self.lifecycle_analysis
+        {
+            analysis
+                .write_json_to_output_dir(&path, &self.data_quality)
+                .wrap_err("Failed to write lifecycle analysis")?;
+        }
+
+        if let Some(path) = args.executor_scheduling_path()
+            && let Some(analysis) = &self.executor_scheduling_analysis
+        {
+            analysis
+                .write_json_to_output_dir(&path, &self.data_quality.not_adjusted())
+                .wrap_err("Failed to write executor scheduling analysis")?;
+        }
+
+        if let Some(path) = args.intra_process_buffers_path()
+            && let Some(analysis) = &self.intra_process_buffers_analysis
+        {
+            analysis
+                .write_json_to_output_dir(&path, &self.data_quality)
+                .wrap_err("Failed to write intra-process buffers analysis")?;
+        }
+
+        if let Some(path) = args.callback_off_cpu_path()
+            && let Some(analysis) = &self.callback_off_cpu_analysis
+        {
+            analysis
+                .write_json_to_output_dir(&path, &self.data_quality.not_adjusted())
+                .wrap_err("Failed to write callback off-CPU analysis")?;
+        }
+
+        if let Some(path) = args.dependency_graph_path()
+            && let Some(analysis) = &self.dependency_graph
+        {
             let dot_output =
                 analysis.display_as_dot(args.color(), args.thickness(), args.min_multiplier());
             let mut writer = get_buf_writer_for_path(&path)?;
+            self.data_quality
+                .not_adjusted()
+                .write_summary(&mut writer, "// ")?;
             writer
                 .write_fmt(format_args!("{dot_output}"))
                 .wrap_err("Failed to write dependency graph")?;
         }
 
+        if let Some(path) = args.processing_errors_path() {
+            let Some(error_report) = &self.error_report else {
+                bail!("The processing error report requires the lenient mode");
+            };
+            error_report
+                .write_json_to_output_dir(&path, &self.data_quality)
+                .wrap_err("Failed to write processing error report")?;
+        }
+
+        if let Some(path) = args.sim_time_path()
+            && let Some(analysis) = &self.sim_time_analysis
+        {
+            analysis
+                .write_json_to_output_dir(&path, &self.data_quality)
+                .wrap_err("Failed to write simulation time analysis")?;
+        }
+
+        if let Some(path) = args.message_loss_path()
+            && let Some(analysis) = &self.message_loss_analysis
+        {
+            analysis
+                .write_json_to_output_dir(&path, &self.data_quality)
+                .wrap_err("Failed to write message loss analysis")?;
+        }
+
+        if let Some(path) = args.clock_offset_path()
+            && let Some(analysis) = &self.clock_offset_analysis
+        {
+            analysis
+                .write_json_to_output_dir(&path, &self.data_quality)
+                .wrap_err("Failed to write clock offset analysis")?;
+        }
+
+        if let Some(path) = args.compatibility_path() {
+            self.compatibility_report
+                .write_json_to_output_dir(&path, &self.data_quality)
+                .wrap_err("Failed to write compatibility report")?;
+        }
+
+        if let Some(path) = args.dependency_graph_data_path()
+            && let Some(analysis) = &self.dependency_graph
+        {
+            analysis
+                .write_json_to_output_dir(&path, &self.data_quality.not_adjusted())
+                .wrap_err("Failed to write dependency graph data")?;
+        }
+
         Ok(())
     }
 }
+
+/// Returns `true` if the analysis supporting both formats should write the text output
+/// to `path` in addition to the JSON output.
+fn is_text_output(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "txt")
+}
+
+/// Returns the path of the JSON output written next to the text output at `path`.
+fn json_output_path(path: &Path) -> Cow<'_, Path> {
+    if is_text_output(path) {
+        Cow::Owned(path.with_extension("json"))
+    } else {
+        Cow::Borrowed(path)
+    }
+}
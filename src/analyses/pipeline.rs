@@ -0,0 +1,245 @@
+use std::sync::Arc;
+use std::sync::mpsc::{self, SyncSender};
+use std::thread::Scope;
+
+use crate::data_quality::DiscardedWindow;
+use crate::processed_events;
+
+use super::analysis::EventAnalysis;
+
+/// Events are sent to the analysis threads in batches of this size.
+const BATCH_SIZE: usize = 1024;
+/// Number of batches an analysis can lag behind the processing.
+const CHANNEL_CAPACITY: usize = 16;
+
+enum Work {
+    Event(processed_events::FullEvent),
+    DiscardedEvents(DiscardedWindow),
+    Finalize,
+}
+
+/// Analyses running on separate threads.
+///
+/// The events are processed once by the main thread and the processed events are shared
+/// by the analysis threads, which trail the processing by up to [`CHANNEL_CAPACITY`] batches.
+/// The ROS 2 objects may be in a later state by then, so the state an analysis needs
+/// at an event, e.g. the size of a ring buffer, is recorded in the processed event.
+pub struct ParallelAnalyses {
+    senders: Vec<SyncSender<Arc<Vec<Work>>>>,
+    batch: Vec<Work>,
+}
+
+impl ParallelAnalyses {
+    /// Spawns a thread in the `scope` for each of the `analyses`.
+    ///
+    /// The threads end when this is dropped.
+    pub fn spawn<'scope, 'env>(
+        scope: &'scope Scope<'scope, 'env>,
+        analyses: Vec<&'env mut dyn EventAnalysis>,
+    ) -> Self {
+        let senders = analyses
+            .into_iter()
+            .map(|analysis| {
+                let (sender, receiver) = mpsc::sync_channel::<Arc<Vec<Work>>>(CHANNEL_CAPACITY);
+                scope.spawn(move || {
+                    for batch in receiver {
+                        for work in batch.iter() {
+                            match work {
+                                Work::Event(event) => analysis.process_event(event),
+                                Work::DiscardedEvents(window) => {
+                                    analysis.process_discarded_events(window);
+                                }
+                                Work::Finalize => analysis.finalize(),
+                            }
+                        }
+                    }
+                });
+                sender
+            })
+            .collect();
+
+        Self {
+            senders,
+            batch: Vec::with_capacity(BATCH_SIZE),
+        }
+    }
+
+    fn push(&mut self, work: Work) {
+        self.batch.push(work);
+        if self.batch.len() >= BATCH_SIZE {
+            self.send_batch();
+        }
+    }
+
+    fn send_batch(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+        let batch = Arc::new(std::mem::replace(
+            &mut self.batch,
+            Vec::with_capacity(BATCH_SIZE),
+        ));
+        for sender in &self.senders {
+            // A failed analysis thread panicked, the panic is resumed when the scope ends.
+            let _ = sender.send(Arc::clone(&batch));
+        }
+    }
+
+    /// Passes the processed `event` to the analysis threads.
+    pub fn process_event(&mut self, event: &processed_events::FullEvent) {
+        self.push(Work::Event(event.clone()));
+    }
+
+    pub fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+        self.push(Work::DiscardedEvents(window.clone()));
+    }
+
+    pub fn finalize(&mut self) {
+        self.push(Work::Finalize);
+        self.send_batch();
+    }
+}
+
+impl Drop for ParallelAnalyses {
+    fn drop(&mut self) {
+        self.send_batch();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::analyses::event_iterator::{EventSink, ProcessedEventsIter};
+    use crate::data_quality::DiscardedItems;
+    use crate::model::CallbackInstance;
+    use crate::processed_events::{self, FullEvent};
+    use crate::raw_events::ros2;
+    use crate::raw_events::testing::{TraceBuilder, VPID};
+
+    use super::*;
+
+    /// Records the discarded windows and the started callbacks.
+    #[derive(Default)]
+    struct Recorder {
+        counts: Vec<u64>,
+        started_instances: Vec<Arc<Mutex<CallbackInstance>>>,
+        started_callbacks: Vec<Option<String>>,
+        finalized: bool,
+    }
+
+    impl EventAnalysis for Recorder {
+        fn initialize(&mut self) {}
+
+        fn process_event(&mut self, event: &FullEvent) {
+            if let processed_events::Event::Ros2(processed_events::ros2::Event::CallbackStart(
+                event,
+            )) = &event.event
+            {
+                let callback = event.callback.lock().unwrap().get_callback();
+                let name = callback.lock().unwrap().get_name().map(ToOwned::to_owned);
+                self.started_instances.push(event.callback.clone());
+                self.started_callbacks.push(name);
+            }
+        }
+
+        fn process_discarded_events(&mut self, window: &DiscardedWindow) {
+            assert!(!self.finalized);
+            self.counts.push(window.count.unwrap());
+        }
+
+        fn finalize(&mut self) {
+            self.finalized = true;
+        }
+    }
+
+    #[test]
+    fn forward_in_order_to_every_analysis() {
+        let mut first = Recorder::default();
+        let mut second = Recorder::default();
+        std::thread::scope(|scope| {
+            let mut parallel = ParallelAnalyses::spawn(scope, vec![&mut first, &mut second]);
+            for count in 0..(BATCH_SIZE as u64 + 10) {
+                parallel.process_discarded_events(&DiscardedWindow {
+                    items: DiscardedItems::Events,
+                    hostname: "host".to_owned(),
+                    stream: None,
+                    cpu: None,
+                    count: Some(count),
+                    start_time: None,
+                    end_time: None,
+                });
+            }
+            parallel.finalize();
+        });
+
+        for recorder in [first, second] {
+            assert!(recorder.finalized);
+            assert!(
+                recorder
+                    .counts
+                    .iter()
+                    .copied()
+                    .eq(0..(BATCH_SIZE as u64 + 10))
+            );
+        }
+    }
+
+    #[test]
+    fn events_processed_once() {
+        // An event processed again by each analysis thread would create its own objects.
+        let mut trace = TraceBuilder::new();
+        trace.node(0, 1, "node").timer(0, 1, 10, 20).event(
+            0,
+            VPID,
+            ros2::RclcppCallbackRegister {
+                callback: 20,
+                symbol: "callback".to_owned(),
+            },
+        );
+        let mut time = 100;
+        for _ in 0..(BATCH_SIZE + 10) {
+            trace.execution(time, time + 10, VPID, 20);
+            time += 100;
+        }
+        let events = trace.build();
+        let event_count = events.len();
+
+        let mut first = Recorder::default();
+        let mut second = Recorder::default();
+        let mut iter = ProcessedEventsIter::from_events(events, None);
+        let mut processed = Vec::new();
+        std::thread::scope(|scope| {
+            let mut analyses = ParallelAnalyses::spawn(scope, vec![&mut first, &mut second]);
+            let mut sink = EventSink::Parallel(&mut analyses);
+            while let Some(event) = iter.next_event(&mut sink) {
+                if let processed_events::Event::Ros2(
+                    processed_events::ros2::Event::CallbackStart(event),
+                ) = event.unwrap().event
+                {
+                    processed.push(event.callback);
+                }
+            }
+        });
+
+        assert_eq!(iter.ros_processed_events, event_count);
+        assert_eq!(processed.len(), BATCH_SIZE + 10);
+        for recorder in [first, second] {
+            assert!(recorder.finalized);
+            assert_eq!(recorder.started_instances.len(), processed.len());
+            assert!(
+                recorder
+                    .started_instances
+                    .iter()
+                    .zip(&processed)
+                    .all(|(received, processed)| Arc::ptr_eq(received, processed))
+            );
+            assert!(
+                recorder
+                    .started_callbacks
+                    .iter()
+                    .all(|name| name.as_deref() == Some("callback"))
+            );
+        }
+    }
+}
@@ -1,14 +1,21 @@
 use std::borrow::Cow;
 use std::ffi::CString;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
+use chrono::TimeZone;
 use clap::builder::ArgPredicate;
 use clap::{Parser, ValueHint};
+use thiserror::Error;
 
-use crate::statistics::Quantile;
+use crate::analyses::event_filter::{EventFilter, Patterns};
+use crate::model::Time;
+use crate::statistics::{Quantile, RelativeError, StatisticsBackend};
 
-mod filenames {
+pub(crate) mod filenames {
     pub const DEPENDENCY_GRAPH: &str = "dependency_graph.dot";
+    pub const DEPENDENCY_GRAPH_DATA: &str = "dependency_graph.json";
     pub const MESSAGE_LATENCY: &str = "message_latency.json";
     pub const CALLBACK_DURATION: &str = "callback_duration.json";
     pub const CALLBACK_PUBLICATIONS: &str = "callback_publications.txt";
@@ -17,6 +24,15 @@ mod filenames {
     pub const UTILIZATION: &str = "utilization.txt";
     pub const REAL_UTILIZATION: &str = "real_utilization.txt";
     pub const SPIN_DURATION: &str = "spin_duration.json";
+    pub const LIFECYCLE: &str = "lifecycle.json";
+    pub const EXECUTOR_SCHEDULING: &str = "executor_scheduling.json";
+    pub const INTRA_PROCESS_BUFFERS: &str = "intra_process_buffers.json";
+    pub const CALLBACK_OFF_CPU: &str = "callback_off_cpu.json";
+    pub const PROCESSING_ERRORS: &str = "processing_errors.json";
+    pub const COMPATIBILITY: &str = "compatibility.json";
+    pub const SIM_TIME: &str = "sim_time.json";
+    pub const MESSAGE_LOSS: &str = "message_loss.json";
+    pub const CLOCK_OFFSET: &str = "clock_offset.json";
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -31,6 +47,7 @@ pub struct AnalysisArgs {
         default_value = "true",
         default_value_ifs([
             ("dependency_graph", ArgPredicate::IsPresent, "false"),
+            ("dependency_graph_data", ArgPredicate::IsPresent, "false"),
             ("message_latency", ArgPredicate::IsPresent, "false"),
             ("callback_duration", ArgPredicate::IsPresent, "false"),
             ("callback_publications", ArgPredicate::IsPresent, "false"),
@@ -39,13 +56,29 @@ pub struct AnalysisArgs {
             ("utilization", ArgPredicate::IsPresent, "false"),
             ("real_utilization", ArgPredicate::IsPresent, "false"),
             ("spin_duration", ArgPredicate::IsPresent, "false"),
+            ("lifecycle", ArgPredicate::IsPresent, "false"),
+            ("executor_scheduling", ArgPredicate::IsPresent, "false"),
+            ("intra_process_buffers", ArgPredicate::IsPresent, "false"),
+            ("callback_off_cpu", ArgPredicate::IsPresent, "false"),
+            ("compatibility", ArgPredicate::IsPresent, "false"),
+            ("sim_time", ArgPredicate::IsPresent, "false"),
+            ("message_loss", ArgPredicate::IsPresent, "false"),
+            ("clock_offset", ArgPredicate::IsPresent, "false"),
             ]))]
     all: bool,
 
+    /// Also run the analyses which are not run by `--all` with their default output filenames
+    #[arg(long)]
+    extra: bool,
+
     /// Construct a detailed dependency graph with timing statistics in DOT format.
     #[arg(long, value_name = "FILENAME", default_missing_value = filenames::DEPENDENCY_GRAPH, num_args = 0..=1, require_equals = true, default_value_if("all", "true", filenames::DEPENDENCY_GRAPH))]
     dependency_graph: Option<PathBuf>,
 
+    /// Export the raw timing series of the dependency graph nodes and edges in JSON format.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::DEPENDENCY_GRAPH_DATA, num_args = 0..=1, require_equals = true, default_value_if("all", "true", filenames::DEPENDENCY_GRAPH_DATA))]
+    dependency_graph_data: Option<PathBuf>,
+
     /// Analyze the latency of messages
     #[arg(long, value_name = "FILENAME", default_missing_value = filenames::MESSAGE_LATENCY, num_args = 0..=1, require_equals = true, default_value_if("all", "true", filenames::MESSAGE_LATENCY))]
     message_latency: Option<PathBuf>,
@@ -55,6 +88,9 @@ pub struct AnalysisArgs {
     callback_duration: Option<PathBuf>,
 
     /// Analyze the publications made by callbacks
+    ///
+    /// With the `.txt` extension of `FILENAME`, the text output is written together with
+    /// the JSON output to the file with the `.json` extension. Otherwise, only JSON is written.
     #[arg(long, value_name = "FILENAME", default_missing_value = filenames::CALLBACK_PUBLICATIONS, num_args = 0..=1, require_equals = true, default_value_if("all", "true", filenames::CALLBACK_PUBLICATIONS))]
     callback_publications: Option<PathBuf>,
 
@@ -67,10 +103,16 @@ pub struct AnalysisArgs {
     message_take_to_callback_latency: Option<PathBuf>,
 
     /// Analyze system utilization based on quantile callback durations
+    ///
+    /// With the `.txt` extension of `FILENAME`, the text output is written together with
+    /// the JSON output to the file with the `.json` extension. Otherwise, only JSON is written.
     #[arg(long, value_name = "FILENAME", default_missing_value = filenames::UTILIZATION, num_args = 0..=1, require_equals = true, default_value_if("all", "true", filenames::UTILIZATION))]
     utilization: Option<PathBuf>,
 
     /// Analyze system utilization based on real execution times
+    ///
+    /// With the `.txt` extension of `FILENAME`, the text output is written together with
+    /// the JSON output to the file with the `.json` extension. Otherwise, only JSON is written.
     #[arg(long, value_name = "FILENAME", default_missing_value = filenames::REAL_UTILIZATION, num_args = 0..=1, require_equals = true, default_value_if("all", "true", filenames::REAL_UTILIZATION))]
     real_utilization: Option<PathBuf>,
 
@@ -78,6 +120,77 @@ pub struct AnalysisArgs {
     #[arg(long, value_name = "FILENAME", default_missing_value = filenames::SPIN_DURATION, num_args = 0..=1, require_equals = true, default_value_if("all", "true", filenames::SPIN_DURATION))]
     spin_duration: Option<PathBuf>,
 
+    /// Analyze the transitions of lifecycle nodes and the time spent in each state
+    ///
+    /// Callbacks executed while their node was not active are also reported.
+    ///
+    /// It is not run by `--all`, see `--extra`.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::LIFECYCLE, num_args = 0..=1, require_equals = true, default_value_if("extra", "true", filenames::LIFECYCLE))]
+    lifecycle: Option<PathBuf>,
+
+    /// Analyze the scheduling of callbacks by rclcpp executors
+    ///
+    /// Reports the time each executor thread spends waiting for work, executing and
+    /// searching for ready handles, and the delay between the executor waking up with work
+    /// and the start of each callback.
+    ///
+    /// It is not run by `--all`, see `--extra`.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::EXECUTOR_SCHEDULING, num_args = 0..=1, require_equals = true, default_value_if("extra", "true", filenames::EXECUTOR_SCHEDULING))]
+    executor_scheduling: Option<PathBuf>,
+
+    /// Analyze the occupancy of intra-process ring buffers and the messages overwritten in them
+    ///
+    /// It is not run by `--all`, see `--extra`.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::INTRA_PROCESS_BUFFERS, num_args = 0..=1, require_equals = true, default_value_if("extra", "true", filenames::INTRA_PROCESS_BUFFERS))]
+    intra_process_buffers: Option<PathBuf>,
+
+    /// Analyze why callbacks were not running on a CPU
+    ///
+    /// Breaks each callback execution into the time running, waiting for a CPU and blocked,
+    /// and reports the threads that woke up the blocked callbacks. Requires the kernel
+    /// `sched_switch` and `sched_wakeup` events in the trace.
+    ///
+    /// It is not run by `--all`, see `--extra`.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::CALLBACK_OFF_CPU, num_args = 0..=1, require_equals = true, default_value_if("extra", "true", filenames::CALLBACK_OFF_CPU))]
+    callback_off_cpu: Option<PathBuf>,
+
+    /// Report the ROS 2 distribution and the tracepoints of every traced process
+    ///
+    /// Compares the tracepoints seen in each process with those expected for its distribution
+    /// detected from the `rcl_init` event and counts the events which are not supported.
+    ///
+    /// It is not run by `--all`, see `--extra`.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::COMPATIBILITY, num_args = 0..=1, require_equals = true, default_value_if("extra", "true", filenames::COMPATIBILITY))]
+    compatibility: Option<PathBuf>,
+
+    /// Analyze the simulation time and the real-time factor of the simulation
+    ///
+    /// The simulation time is taken from the clock updates of r2r nodes. The executions
+    /// of rclcpp `/clock` callbacks are reported too.
+    ///
+    /// It is not run by `--all`, see `--extra`.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::SIM_TIME, num_args = 0..=1, require_equals = true, default_value_if("extra", "true", filenames::SIM_TIME))]
+    sim_time: Option<PathBuf>,
+
+    /// Detect messages lost between each publisher and subscriber of a topic
+    ///
+    /// Publications are matched with the taken messages by their source timestamps, which
+    /// are traced since Jazzy. Each burst of lost messages is checked against the depth
+    /// of the subscriber's queue.
+    ///
+    /// It is not run by `--all`, see `--extra`.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::MESSAGE_LOSS, num_args = 0..=1, require_equals = true, default_value_if("extra", "true", filenames::MESSAGE_LOSS))]
+    message_loss: Option<PathBuf>,
+
+    /// Estimate the offset and drift between the clocks of each pair of hosts
+    ///
+    /// The offsets are estimated from the minimal latencies of messages sent between
+    /// the hosts, in both directions if possible.
+    ///
+    /// It is not run by `--all`, see `--extra`.
+    #[arg(long, value_name = "FILENAME", default_missing_value = filenames::CLOCK_OFFSET, num_args = 0..=1, require_equals = true, default_value_if("extra", "true", filenames::CLOCK_OFFSET))]
+    clock_offset: Option<PathBuf>,
+
     /// Directory to write output files
     ///
     /// If not provided, the current working directory is used.
@@ -126,12 +239,137 @@ pub struct AnalysisArgs {
     /// Paths to directories to search for the trace to analyze
     ///
     /// All subdirectories are automatically searched too.
-    #[arg(value_parser, num_args = 1.., required = true, value_hint = ValueHint::DirPath)]
+    #[arg(value_parser, num_args = 1.., required_unless_present = "live", value_hint = ValueHint::DirPath)]
     trace_paths: Vec<PathBuf>,
 
     /// Only the directories specified by `TRACE_PATHS` are searched for traces, not their subdirectories.
     #[arg(long)]
     exact_trace_path: bool,
+
+    /// Analyze a running tracing session of an LTTng relay daemon instead of trace files.
+    ///
+    /// The URL has the form `net://HOST/host/TARGET_HOSTNAME/SESSION_NAME`.
+    /// The outputs are written every `FLUSH_INTERVAL` seconds and when the session ends.
+    #[arg(long, value_name = "URL", conflicts_with_all = ["trace_paths", "exact_trace_path"])]
+    live: Option<String>,
+
+    /// Seconds between writing the outputs of a live analysis. Defaults to 10 seconds.
+    #[arg(long, value_name = "SECONDS", conflicts_with = "trace_paths")]
+    flush_interval: Option<u64>,
+
+    /// Analyze only the events from `BEGIN` on.
+    ///
+    /// The time is given in seconds since the UNIX epoch, as `YYYY-MM-DD HH:MM:SS[.FRACTION]`
+    /// in local time, or as `+SECONDS` relative to the first event of the traces.
+    /// The events before `BEGIN` are still processed to know all ROS 2 objects and their state.
+    #[arg(long, value_name = "TIME", conflicts_with = "live")]
+    begin: Option<TimeBound>,
+
+    /// Analyze only the events up to `END`.
+    ///
+    /// The time is given in the same formats as `BEGIN`.
+    #[arg(long, value_name = "TIME", conflicts_with = "live")]
+    end: Option<TimeBound>,
+
+    /// Analyze only the events of the nodes with a full name matching the pattern.
+    ///
+    /// Can be given multiple times. The patterns are globs with the `*` and `?` wildcards
+    /// matching the whole name, or regular expressions with `--filter-regex`. Events of
+    /// an unknown node are left out. The executor events are matched by the nodes whose
+    /// callbacks the executor thread ran.
+    #[arg(long, value_name = "PATTERN")]
+    include_node: Vec<String>,
+
+    /// Leave out the events of the nodes with a full name matching the pattern.
+    #[arg(long, value_name = "PATTERN")]
+    exclude_node: Vec<String>,
+
+    /// Analyze only the events of the publishers, subscribers and subscription callbacks
+    /// of the topics matching the pattern.
+    ///
+    /// The events without a topic, e.g., of the timers and the executors, are left out.
+    #[arg(long, value_name = "PATTERN")]
+    include_topic: Vec<String>,
+
+    /// Leave out the events of the topics matching the pattern.
+    #[arg(long, value_name = "PATTERN")]
+    exclude_topic: Vec<String>,
+
+    /// Analyze only the events of the processes with a name matching the pattern.
+    #[arg(long, value_name = "PATTERN")]
+    include_process: Vec<String>,
+
+    /// Leave out the events of the processes with a name matching the pattern.
+    #[arg(long, value_name = "PATTERN")]
+    exclude_process: Vec<String>,
+
+    /// Analyze only the events of the hosts with a hostname matching the pattern.
+    #[arg(long, value_name = "PATTERN")]
+    include_host: Vec<String>,
+
+    /// Leave out the events of the hosts with a hostname matching the pattern.
+    #[arg(long, value_name = "PATTERN")]
+    exclude_host: Vec<String>,
+
+    /// Interpret the filter patterns as regular expressions matching any part of the name.
+    #[arg(long)]
+    filter_regex: bool,
+
+    /// Skip events that fail to process instead of stopping the analysis.
+    ///
+    /// The errors are counted per error kind and object type and written with a sample
+    /// event to `processing_errors.json` in `OUT_DIR`.
+    #[arg(long)]
+    lenient: bool,
+
+    /// Read and process the trace on one thread and run each analysis on a separate thread.
+    ///
+    /// The events are processed once and shared by the analysis threads. The results are
+    /// the same as without this option.
+    #[arg(long)]
+    parallel: bool,
+
+    /// Release the published messages not taken by all subscribers after this many seconds.
+    ///
+    /// A message is released when all subscribers on its topic took it regardless of this
+    /// option. Messages taken after the horizon are not matched with their publication.
+    /// Without this option, the messages not taken by all subscribers are kept until the end
+    /// of the trace.
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    message_horizon: Option<u64>,
+
+    /// Express the callback inter-arrival times and the message latencies in simulation time.
+    ///
+    /// The wall time of the trace is mapped to the simulation time by the clock updates
+    /// of r2r nodes. Samples outside of the simulation are left out.
+    #[arg(long)]
+    in_sim_time: bool,
+
+    /// Correct the latencies of messages sent between hosts by the offsets of their clocks.
+    ///
+    /// Use it when the clocks of the traced hosts are not synchronized, e.g., by PTP.
+    /// The offsets are estimated as with `--clock-offset`.
+    #[arg(long)]
+    correct_clock_offsets: bool,
+
+    /// Correct the latencies also by the clock offsets estimated from messages sent
+    /// in one direction only.
+    ///
+    /// Without messages in the other direction, the offset includes the minimum network
+    /// latency between the hosts, so the corrected latencies are lower by this bias.
+    /// By default, only the offsets estimated from messages in both directions are applied.
+    #[arg(long, requires = "correct_clock_offsets")]
+    one_directional_clock_offsets: bool,
+
+    /// Compute the quantiles from sketches with the relative error instead of keeping all samples.
+    ///
+    /// The latency, duration and utilization analyses then keep a bounded number of buckets
+    /// per series regardless of the length of the trace. Their JSON outputs contain the count,
+    /// mean and `--quantiles` of each series instead of the raw samples needed by the `chart`
+    /// command. With `--in-sim-time` or `--correct-clock-offsets`, the trace is read twice
+    /// to correct the samples before they are added to the sketches.
+    #[arg(long, value_name = "RELATIVE_ERROR", num_args = 0..=1, require_equals = true, default_missing_value = "0.01")]
+    quantile_sketch: Option<RelativeError>,
 }
 
 impl AnalysisArgs {
@@ -150,6 +388,37 @@ impl AnalysisArgs {
         self.exact_trace_path
     }
 
+    pub fn live_url(&self) -> Option<CString> {
+        self.live
+            .as_ref()
+            .map(|url| CString::new(url.as_str()).unwrap())
+    }
+
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_secs(self.flush_interval.unwrap_or(10))
+    }
+
+    pub const fn begin(&self) -> Option<TimeBound> {
+        self.begin
+    }
+
+    pub const fn end(&self) -> Option<TimeBound> {
+        self.end
+    }
+
+    /// Returns the filter of the events passed to the analyses.
+    pub fn event_filter(&self) -> Result<EventFilter, regex::Error> {
+        let patterns = |include: &[String], exclude: &[String]| {
+            Patterns::new(include, exclude, self.filter_regex)
+        };
+        Ok(EventFilter::new(
+            patterns(&self.include_node, &self.exclude_node)?,
+            patterns(&self.include_topic, &self.exclude_topic)?,
+            patterns(&self.include_process, &self.exclude_process)?,
+            patterns(&self.include_host, &self.exclude_host)?,
+        ))
+    }
+
     fn concatenate_with_out_path<'a>(&'a self, path: &'a Path) -> Cow<'a, Path> {
         if path.is_absolute() {
             path.into()
@@ -164,6 +433,10 @@ impl AnalysisArgs {
         self.dependency_graph.is_some()
     }
 
+    pub fn dependency_graph_data_enabled(&self) -> bool {
+        self.dependency_graph_data.is_some()
+    }
+
     pub fn message_latency_enabled(&self) -> bool {
         self.message_latency.is_some()
     }
@@ -196,12 +469,34 @@ impl AnalysisArgs {
         self.spin_duration.is_some()
     }
 
+    pub fn lifecycle_enabled(&self) -> bool {
+        self.lifecycle.is_some()
+    }
+
+    pub fn executor_scheduling_enabled(&self) -> bool {
+        self.executor_scheduling.is_some()
+    }
+
+    pub fn intra_process_buffers_enabled(&self) -> bool {
+        self.intra_process_buffers.is_some()
+    }
+
+    pub fn callback_off_cpu_enabled(&self) -> bool {
+        self.callback_off_cpu.is_some()
+    }
+
     pub fn dependency_graph_path(&self) -> Option<Cow<Path>> {
         self.dependency_graph
             .as_ref()
             .map(|p| self.concatenate_with_out_path(p))
     }
 
+    pub fn dependency_graph_data_path(&self) -> Option<Cow<'_, Path>> {
+        self.dependency_graph_data
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
     pub fn message_latency_path(&self) -> Option<Cow<Path>> {
         self.message_latency
             .as_ref()
@@ -250,6 +545,102 @@ impl AnalysisArgs {
             .map(|p| self.concatenate_with_out_path(p))
     }
 
+    pub fn lifecycle_path(&self) -> Option<Cow<'_, Path>> {
+        self.lifecycle
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
+    pub fn executor_scheduling_path(&self) -> Option<Cow<'_, Path>> {
+        self.executor_scheduling
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
+    pub fn intra_process_buffers_path(&self) -> Option<Cow<'_, Path>> {
+        self.intra_process_buffers
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
+    pub fn callback_off_cpu_path(&self) -> Option<Cow<'_, Path>> {
+        self.callback_off_cpu
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
+    pub fn sim_time_enabled(&self) -> bool {
+        self.sim_time.is_some()
+    }
+
+    pub fn sim_time_path(&self) -> Option<Cow<'_, Path>> {
+        self.sim_time
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
+    pub fn message_loss_enabled(&self) -> bool {
+        self.message_loss.is_some()
+    }
+
+    pub fn message_loss_path(&self) -> Option<Cow<'_, Path>> {
+        self.message_loss
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
+    pub fn clock_offset_enabled(&self) -> bool {
+        self.clock_offset.is_some()
+    }
+
+    pub fn clock_offset_path(&self) -> Option<Cow<'_, Path>> {
+        self.clock_offset
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
+    pub const fn correct_clock_offsets(&self) -> bool {
+        self.correct_clock_offsets
+    }
+
+    pub const fn one_directional_clock_offsets(&self) -> bool {
+        self.one_directional_clock_offsets
+    }
+
+    pub const fn in_sim_time(&self) -> bool {
+        self.in_sim_time
+    }
+
+    pub fn compatibility_path(&self) -> Option<Cow<'_, Path>> {
+        self.compatibility
+            .as_ref()
+            .map(|p| self.concatenate_with_out_path(p))
+    }
+
+    /// Returns the path of the processing error report written in lenient mode.
+    pub fn processing_errors_path(&self) -> Option<Cow<'_, Path>> {
+        self.lenient
+            .then(|| self.concatenate_with_out_path(Path::new(filenames::PROCESSING_ERRORS)))
+    }
+
+    pub const fn lenient(&self) -> bool {
+        self.lenient
+    }
+
+    pub const fn parallel(&self) -> bool {
+        self.parallel
+    }
+
+    pub fn message_horizon(&self) -> Option<Duration> {
+        self.message_horizon.map(Duration::from_secs)
+    }
+
+    /// Returns how the samples of the latency and duration series are kept.
+    pub fn statistics_backend(&self) -> StatisticsBackend {
+        self.quantile_sketch
+            .map_or(StatisticsBackend::Exact, StatisticsBackend::Sketch)
+    }
+
     pub fn quantiles(&self) -> &[Quantile] {
         &self.quantiles
     }
@@ -271,15 +662,84 @@ impl AnalysisArgs {
     }
 }
 
+/// Bound of the analyzed time window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    /// Nanoseconds since the UNIX epoch.
+    Absolute(i64),
+    /// Nanoseconds since the first event of the traces.
+    Offset(i64),
+}
+
+impl TimeBound {
+    /// Returns the time of the bound.
+    ///
+    /// Returns `None` for an offset if the traces have no events.
+    pub fn resolve(self, trace_start: Option<Time>) -> Option<Time> {
+        match self {
+            Self::Absolute(nanos) => Some(Time::from_nanos(nanos)),
+            Self::Offset(nanos) => {
+                trace_start.map(|start| Time::from_nanos(start.timestamp_nanos() + nanos))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Invalid time `{0}`, expected `SECONDS`, `+SECONDS` or `YYYY-MM-DD HH:MM:SS[.FRACTION]`")]
+pub struct TimeBoundParseError(String);
+
+/// Parses `SECONDS[.FRACTION]` into nanoseconds.
+fn parse_seconds(s: &str) -> Option<i64> {
+    let (seconds, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if seconds.is_empty()
+        || fraction.len() > 9
+        || !seconds
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let nanos: i64 = format!("{fraction:0<9}").parse().ok()?;
+    seconds
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(1_000_000_000)?
+        .checked_add(nanos)
+}
+
+impl FromStr for TimeBound {
+    type Err = TimeBoundParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bound = if let Some(offset) = s.strip_prefix('+') {
+            parse_seconds(offset).map(Self::Offset)
+        } else if let Some(nanos) = parse_seconds(s) {
+            Some(Self::Absolute(nanos))
+        } else {
+            chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+                .ok()
+                .and_then(|datetime| chrono::Local.from_local_datetime(&datetime).single())
+                .and_then(|datetime| datetime.timestamp_nanos_opt())
+                .map(Self::Absolute)
+        };
+        bound.ok_or_else(|| TimeBoundParseError(s.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::borrow::Cow;
     use std::path::{Path, PathBuf};
+    use std::time::Duration;
 
     use clap::Parser;
 
     use crate::argsv2::Args;
-    use crate::argsv2::analysis_args::filenames;
+    use crate::argsv2::analysis_args::{TimeBound, filenames};
+    use crate::model::Time;
+    use crate::statistics::{RelativeError, StatisticsBackend};
 
     #[test]
     fn test_basic_args_parsing() {
@@ -305,6 +765,117 @@ mod test {
         assert_eq!(args.trace_paths[1], PathBuf::from("/tmp/trace2"));
     }
 
+    #[test]
+    fn test_live_session() {
+        let args = Args::try_parse_from([
+            "program",
+            "analyze",
+            "--live=net://localhost/host/robot/session",
+            "--flush-interval=5",
+        ])
+        .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+        .into_analysis_args();
+
+        assert!(args.trace_paths.is_empty());
+        assert_eq!(
+            args.live_url().unwrap().to_str(),
+            Ok("net://localhost/host/robot/session")
+        );
+        assert_eq!(args.flush_interval().as_secs(), 5);
+
+        assert!(Args::try_parse_from(["program", "analyze"]).is_err());
+        assert!(
+            Args::try_parse_from(["program", "analyze", "--flush-interval=5", "/tmp/trace"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_message_horizon() {
+        let args = Args::try_parse_from(["program", "analyze", "/tmp/trace"])
+            .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+            .into_analysis_args();
+        assert_eq!(args.message_horizon(), None);
+
+        let args =
+            Args::try_parse_from(["program", "analyze", "--message-horizon=5", "/tmp/trace"])
+                .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+                .into_analysis_args();
+        assert_eq!(args.message_horizon(), Some(Duration::from_secs(5)));
+
+        assert!(
+            Args::try_parse_from(["program", "analyze", "--message-horizon=0", "/tmp/trace"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_time_window() {
+        let args = Args::try_parse_from([
+            "program",
+            "analyze",
+            "--begin=+1.5",
+            "--end=1714566600.25",
+            "/tmp/trace",
+        ])
+        .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+        .into_analysis_args();
+
+        assert_eq!(args.begin(), Some(TimeBound::Offset(1_500_000_000)));
+        assert_eq!(
+            args.end(),
+            Some(TimeBound::Absolute(1_714_566_600_250_000_000))
+        );
+        assert_eq!(
+            args.begin().unwrap().resolve(Some(Time::from_nanos(10))),
+            Some(Time::from_nanos(1_500_000_010))
+        );
+        assert_eq!(args.begin().unwrap().resolve(None), None);
+
+        assert!("2024-05-01 12:30:00.5".parse::<TimeBound>().is_ok());
+        assert!("1.0000000001".parse::<TimeBound>().is_err());
+        assert!("+-1".parse::<TimeBound>().is_err());
+        assert!(
+            Args::try_parse_from([
+                "program",
+                "analyze",
+                "--live=net://localhost/host/robot/session",
+                "--begin=+1",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_quantile_sketch() {
+        let parse = |args: &[&str]| {
+            Args::try_parse_from(
+                ["program", "analyze"]
+                    .iter()
+                    .chain(args)
+                    .chain(&["/tmp/trace"]),
+            )
+            .map(Args::into_analysis_args)
+        };
+
+        assert_eq!(
+            parse(&[]).unwrap().statistics_backend(),
+            StatisticsBackend::Exact
+        );
+        assert_eq!(
+            parse(&["--quantile-sketch"]).unwrap().statistics_backend(),
+            StatisticsBackend::Sketch(RelativeError::new(0.01).unwrap())
+        );
+        assert_eq!(
+            parse(&["--quantile-sketch=0.05"])
+                .unwrap()
+                .statistics_backend(),
+            StatisticsBackend::Sketch(RelativeError::new(0.05).unwrap())
+        );
+        assert!(parse(&["--quantile-sketch=1"]).is_err());
+        assert!(parse(&["--quantile-sketch", "--in-sim-time"]).is_ok());
+    }
+
     #[test]
     fn test_exact_trace_path_flag() {
         let args = Args::try_parse_from(["program", "analyze", "--exact-trace-path", "/tmp/trace"])
@@ -379,6 +950,86 @@ mod test {
         assert_eq!(args.callback_duration, None);
     }
 
+    #[test]
+    fn test_extra_analyses() {
+        let args = Args::try_parse_from(["program", "analyze", "/tmp/trace"])
+            .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+            .into_analysis_args();
+        assert!(args.all);
+        assert!(args.callback_duration_enabled());
+        assert!(!args.lifecycle_enabled());
+
+        let args = Args::try_parse_from(["program", "analyze", "--extra", "/tmp/trace"])
+            .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+            .into_analysis_args();
+        assert!(args.all);
+        assert!(args.callback_duration_enabled());
+        assert!(args.lifecycle_enabled());
+
+        let args = Args::try_parse_from([
+            "program",
+            "analyze",
+            "--extra",
+            "--callback-duration",
+            "/tmp/trace",
+        ])
+        .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+        .into_analysis_args();
+        assert!(!args.all);
+        assert!(args.callback_duration_enabled());
+        assert!(!args.message_latency_enabled());
+        assert!(args.lifecycle_enabled());
+    }
+
+    #[test]
+    fn test_dependency_graph_data_flag() {
+        let args = Args::try_parse_from([
+            "program",
+            "analyze",
+            "--dependency-graph-data",
+            "/tmp/trace",
+        ])
+        .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+        .into_analysis_args();
+
+        assert!(!args.all);
+        assert_eq!(
+            args.dependency_graph_data,
+            Some(PathBuf::from(filenames::DEPENDENCY_GRAPH_DATA))
+        );
+        assert_eq!(args.dependency_graph, None);
+        assert!(args.dependency_graph_data_enabled());
+        assert!(!args.dependency_graph_enabled());
+    }
+
+    #[test]
+    fn test_lenient_flag() {
+        let args = Args::try_parse_from(["program", "analyze", "-o", "/out", "/tmp/trace"])
+            .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+            .into_analysis_args();
+        assert!(!args.lenient());
+        assert_eq!(args.processing_errors_path(), None);
+
+        let args = Args::try_parse_from([
+            "program",
+            "analyze",
+            "--lenient",
+            "-o",
+            "/out",
+            "/tmp/trace",
+        ])
+        .unwrap_or_else(|e| panic!("Failed to parse arguments: {e}"))
+        .into_analysis_args();
+        assert!(args.lenient());
+        assert!(args.all);
+        assert_eq!(
+            args.processing_errors_path(),
+            Some(Cow::Owned(
+                Path::new("/out").join(filenames::PROCESSING_ERRORS)
+            ))
+        );
+    }
+
     #[test]
     fn test_empty_quantiles_rejected() {
         let result = Args::try_parse_from(["program", "analyze", "--quantiles", "", "/tmp/trace"]);
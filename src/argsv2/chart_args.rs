@@ -1,5 +1,6 @@
 use clap::{Args, Subcommand, ValueEnum, ValueHint};
 use derive_more::Display;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Args)]
@@ -10,6 +11,13 @@ pub struct ChartArgs {
     #[clap(long, short = 'n')]
     node: String,
 
+    /// Name of the node's interface to draw the chart for
+    ///
+    /// For example `Timer(100 ms)`, `Subscription(/topic)` or `Subscriber(/topic)`.
+    /// If not specified, all interfaces of the node are drawn into the chart.
+    #[clap(long)]
+    interface: Option<String>,
+
     /// The input path, either a file of the data or a folder containing the default named file with the necessary data
     #[clap(long, short = 'i', value_name = "INPUT", value_hint = ValueHint::AnyPath)]
     input_path: Option<PathBuf>,
@@ -33,6 +41,10 @@ impl ChartArgs {
         &self.node
     }
 
+    pub fn interface(&self) -> Option<&str> {
+        self.interface.as_deref()
+    }
+
     pub fn input_path(&self) -> Option<&Path> {
         self.input_path.as_deref()
     }
@@ -50,7 +62,7 @@ impl ChartArgs {
     }
 }
 
-#[derive(Debug, Display, Args, Clone)]
+#[derive(Debug, Display, Args, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[display("ChartOf {{ value: {value}, {plot} }}")]
 pub struct ChartRequest {
     /// The value to plot into the chart
@@ -74,7 +86,9 @@ pub struct ChartRequest {
     pub output_format: ChartOutputFormat,
 }
 
-#[derive(Debug, Display, ValueEnum, Clone, Copy, Default)]
+#[derive(
+    Debug, Display, ValueEnum, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
 pub enum ChartOutputFormat {
     #[default]
     #[display("svg")]
@@ -83,7 +97,7 @@ pub enum ChartOutputFormat {
     PNG,
 }
 
-#[derive(Debug, Display, ValueEnum, Clone, Copy)]
+#[derive(Debug, Display, ValueEnum, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChartedValue {
     /// Callback execution durations
     #[display("Callback execution time")]
@@ -105,7 +119,7 @@ pub enum ChartedValue {
     MessagesLatency,
 }
 
-#[derive(Debug, Display, Subcommand, Clone, Copy)]
+#[derive(Debug, Display, Subcommand, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChartVariants {
     #[display("Histogram")]
     Histogram(HistogramData),
@@ -113,7 +127,7 @@ pub enum ChartVariants {
     Scatter,
 }
 
-#[derive(Debug, Display, Args, Clone, Copy)]
+#[derive(Debug, Display, Args, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[display("Histogram data {{ bins: {bins:?} }}")]
 pub struct HistogramData {
     /// Number of bins to split the data into
@@ -6,15 +6,33 @@ pub struct ViewerArgs {
     /// The dotfile to open
     pub dotfile: PathBuf,
 
-    #[clap(long, value_name = "VIEWER", value_hint = ValueHint::FilePath)]
-    /// The entry point to the python viewer (defaults to ./xdotviewer/main.py)
-    pub viewer: Option<PathBuf>,
+    /// The address on which the viewer listens for HTTP requests
+    ///
+    /// Use the default local address together with SSH port forwarding to use the viewer
+    /// on a headless machine.
+    #[clap(
+        long,
+        short = 'a',
+        value_name = "ADDRESS",
+        default_value = "127.0.0.1:8080"
+    )]
+    pub address: String,
 
-    /// The executable to run to invoke the Ros2TraceAnalyzer (defaults to ./target/release/Ros2TraceAnalyzer)
-    #[clap(long, short = 't', value_name = "Ros2TraceAnalyzer", value_hint = ValueHint::ExecutablePath)]
-    pub tracer_exec: Option<PathBuf>,
+    /// The Graphviz executable used to render the dotfile
+    #[clap(long, value_name = "DOT", default_value = "dot", value_hint = ValueHint::ExecutablePath)]
+    pub dot: PathBuf,
 
     /// The directory with the datafiles (defaults to CWD)
+    ///
+    /// Charts are rendered into its `charts` subdirectory.
     #[clap(long, short = 'd', value_name = "DATA", value_hint = ValueHint::DirPath)]
     pub data: Option<PathBuf>,
+
+    /// Deprecated and ignored, the viewer is built in
+    #[clap(long, value_name = "VIEWER", value_hint = ValueHint::FilePath)]
+    pub viewer: Option<PathBuf>,
+
+    /// Deprecated and ignored, the viewer is built in
+    #[clap(long, short = 't', value_name = "Ros2TraceAnalyzer", value_hint = ValueHint::ExecutablePath)]
+    pub tracer_exec: Option<PathBuf>,
 }
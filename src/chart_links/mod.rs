@@ -0,0 +1,55 @@
+//! Names of the charted interfaces and links to their charts.
+//!
+//! Shared by the analyses writing the links, the charting reading the data and the viewer
+//! serving the charts.
+
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+use crate::argsv2::chart_args::ChartedValue;
+
+pub mod query;
+
+/// Returns the interface name of a callback, e.g. `Subscription(/topic)` or `Timer(100 ms)`.
+pub fn callback_interface(caller_type: impl Display, caller_param: impl Display) -> String {
+    format!("{caller_type}({caller_param})")
+}
+
+/// Returns the interface name of a publisher, e.g. `Publisher(/topic)`.
+pub fn publisher_interface(topic: impl Display) -> String {
+    format!("Publisher({topic})")
+}
+
+/// Returns the interface name of a subscriber, e.g. `Subscriber(/topic)`.
+pub fn subscriber_interface(topic: impl Display) -> String {
+    format!("Subscriber({topic})")
+}
+
+/// Returns the interface name of a service, e.g. `Service(/name)`.
+pub fn service_interface(name: impl Display) -> String {
+    format!("Service({name})")
+}
+
+/// Returns the link to the viewer chart endpoint for an interface of a ROS node.
+///
+/// The link is relative, so it can be stored in the DOT file as the `URL` attribute
+/// and resolved by the viewer serving the graph.
+pub fn chart_link(node: &str, interface: &str, values: &[ChartedValue]) -> String {
+    let values: Vec<_> = values
+        .iter()
+        .map(|value| {
+            value
+                .to_possible_value()
+                .expect("ChartedValue has no skipped variants")
+        })
+        .collect();
+
+    let query = query::build_query(
+        [("node", node), ("interface", interface)]
+            .into_iter()
+            .chain(values.iter().map(|value| ("value", value.get_name()))),
+    );
+
+    format!("chart?{query}")
+}
@@ -0,0 +1,100 @@
+//! Minimal URL query string handling used by the chart links.
+
+use std::fmt::Write;
+
+/// Percent-encodes everything except the unreserved characters of RFC 3986.
+pub fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            write!(encoded, "%{byte:02X}").expect("Writing to String cannot fail");
+        }
+    }
+    encoded
+}
+
+/// Decodes a percent-encoded query component. `+` is decoded as a space.
+///
+/// Invalid escape sequences are kept verbatim.
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', None) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, None) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Parses a query string into decoded key-value pairs in their original order.
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Builds a query string from key-value pairs.
+pub fn build_query<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    pairs
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let value = "Timer(100 μs) /ns/topic&x=1";
+        assert_eq!(percent_decode(&percent_encode(value)), value);
+    }
+
+    #[test]
+    fn parse() {
+        let pairs = parse_query("node=%2Ftalker&interface=Timer%28100+ms%29&value=a&value=b&flag");
+        assert_eq!(
+            pairs,
+            vec![
+                ("node".to_owned(), "/talker".to_owned()),
+                ("interface".to_owned(), "Timer(100 ms)".to_owned()),
+                ("value".to_owned(), "a".to_owned()),
+                ("value".to_owned(), "b".to_owned()),
+                ("flag".to_owned(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_escape_is_kept() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+}
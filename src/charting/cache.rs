@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use color_eyre::eyre::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::argsv2::chart_args::ChartRequest;
+
+/// Name of the manifest file stored next to the rendered charts.
+pub const MANIFEST_FILE_NAME: &str = "chart_cache.json";
+
+/// Identification of the input data file.
+///
+/// The fingerprint changes whenever the analysis output is rewritten.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct InputFingerprint {
+    path: PathBuf,
+    len: u64,
+    modified_ns: u128,
+}
+
+impl InputFingerprint {
+    fn new(path: &Path) -> color_eyre::Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .wrap_err_with(|| format!("Failed to read metadata of {}", path.display()))?;
+        let modified_ns = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_nanos());
+
+        Ok(Self {
+            path: std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+            len: metadata.len(),
+            modified_ns,
+        })
+    }
+}
+
+/// Parameters from which a chart was rendered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    node: String,
+    interface: Option<String>,
+    request: ChartRequest,
+    input: InputFingerprint,
+}
+
+impl CacheEntry {
+    pub fn new(
+        node: &str,
+        interface: Option<&str>,
+        request: &ChartRequest,
+        input_path: &Path,
+    ) -> color_eyre::Result<Self> {
+        Ok(Self {
+            node: node.to_owned(),
+            interface: interface.map(ToOwned::to_owned),
+            request: request.clone(),
+            input: InputFingerprint::new(input_path)?,
+        })
+    }
+}
+
+/// Manifest of the charts rendered into one directory keyed by the chart file name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ChartCache {
+    #[serde(skip)]
+    manifest_path: PathBuf,
+
+    charts: HashMap<String, CacheEntry>,
+}
+
+impl ChartCache {
+    /// Opens the manifest of the directory containing `chart_path`.
+    ///
+    /// A missing or unreadable manifest results in an empty cache.
+    pub fn open_for(chart_path: &Path) -> Self {
+        let directory = chart_path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let manifest_path = directory.join(MANIFEST_FILE_NAME);
+
+        let charts = match File::open(&manifest_path) {
+            Ok(file) => serde_json::from_reader::<_, Self>(BufReader::new(file))
+                .map(|cache| cache.charts)
+                .unwrap_or_else(|error| {
+                    log::warn!(
+                        "Ignoring invalid chart cache manifest {}: {error}",
+                        manifest_path.display()
+                    );
+                    HashMap::new()
+                }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            manifest_path,
+            charts,
+        }
+    }
+
+    fn key(chart_path: &Path) -> String {
+        chart_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the chart at `chart_path` exists and was rendered from `entry`.
+    pub fn is_fresh(&self, chart_path: &Path, entry: &CacheEntry) -> bool {
+        chart_path.is_file() && self.charts.get(&Self::key(chart_path)) == Some(entry)
+    }
+
+    pub fn insert(&mut self, chart_path: &Path, entry: CacheEntry) {
+        self.charts.insert(Self::key(chart_path), entry);
+    }
+
+    pub fn save(&self) -> color_eyre::Result<()> {
+        let file = File::create(&self.manifest_path).wrap_err_with(|| {
+            format!(
+                "Failed to create chart cache manifest {}",
+                self.manifest_path.display()
+            )
+        })?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self).wrap_err_with(|| {
+            format!(
+                "Failed to write chart cache manifest {}",
+                self.manifest_path.display()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::argsv2::chart_args::{
+        ChartOutputFormat, ChartVariants, ChartedValue, HistogramData,
+    };
+
+    use super::*;
+
+    /// Creates an empty directory for the test with the input data file and a rendered chart.
+    fn test_directory(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let directory = std::env::temp_dir().join(format!(
+            "ros2_trace_analyzer_chart_cache_{}_{name}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let input_path = directory.join("callback_duration.json");
+        std::fs::write(&input_path, "[]").unwrap();
+        let chart_path = directory.join("talker_callback-duration_histogram_800.svg");
+        std::fs::write(&chart_path, "<svg/>").unwrap();
+
+        (directory, input_path, chart_path)
+    }
+
+    fn request() -> ChartRequest {
+        ChartRequest {
+            value: ChartedValue::CallbackDuration,
+            plot: ChartVariants::Histogram(HistogramData { bins: None }),
+            size: 800,
+            output_format: ChartOutputFormat::SVG,
+        }
+    }
+
+    #[test]
+    fn hit_after_reopening() {
+        let (directory, input_path, chart_path) = test_directory("hit");
+        let entry = CacheEntry::new("/talker", None, &request(), &input_path).unwrap();
+
+        let mut cache = ChartCache::open_for(&chart_path);
+        assert!(!cache.is_fresh(&chart_path, &entry));
+        cache.insert(&chart_path, entry);
+        cache.save().unwrap();
+
+        // The key depends only on the chart file name and the entry only on the parameters
+        // and the unchanged input, so both are equal when recreated.
+        let entry = CacheEntry::new("/talker", None, &request(), &input_path).unwrap();
+        let chart_path = directory.join(".").join(chart_path.file_name().unwrap());
+        let cache = ChartCache::open_for(&chart_path);
+        assert!(cache.is_fresh(&chart_path, &entry));
+
+        let mut other_request = request();
+        other_request.size = 600;
+        let other = CacheEntry::new("/talker", None, &other_request, &input_path).unwrap();
+        assert!(!cache.is_fresh(&chart_path, &other));
+        let other = CacheEntry::new("/talker", Some("Timer(1 s)"), &request(), &input_path);
+        assert!(!cache.is_fresh(&chart_path, &other.unwrap()));
+
+        std::fs::remove_file(&chart_path).unwrap();
+        assert!(!cache.is_fresh(&chart_path, &entry));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn invalidated_by_input_change() {
+        let (directory, input_path, chart_path) = test_directory("invalidation");
+        let entry = CacheEntry::new("/talker", None, &request(), &input_path).unwrap();
+        let mut cache = ChartCache::open_for(&chart_path);
+        cache.insert(&chart_path, entry.clone());
+        cache.save().unwrap();
+
+        // Rewritten with the same length, only the modification time differs.
+        let modified = std::fs::metadata(&input_path).unwrap().modified().unwrap();
+        let file = File::options().write(true).open(&input_path).unwrap();
+        file.set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        drop(file);
+        let rewritten = CacheEntry::new("/talker", None, &request(), &input_path).unwrap();
+        assert_ne!(rewritten, entry);
+        assert!(!ChartCache::open_for(&chart_path).is_fresh(&chart_path, &rewritten));
+
+        std::fs::write(&input_path, "[{}]").unwrap();
+        let extended = CacheEntry::new("/talker", None, &request(), &input_path).unwrap();
+        assert!(!ChartCache::open_for(&chart_path).is_fresh(&chart_path, &extended));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_manifest_is_empty() {
+        let (directory, input_path, chart_path) = test_directory("invalid");
+        std::fs::write(directory.join(MANIFEST_FILE_NAME), "{").unwrap();
+        let entry = CacheEntry::new("/talker", None, &request(), &input_path).unwrap();
+
+        let mut cache = ChartCache::open_for(&chart_path);
+        assert!(!cache.is_fresh(&chart_path, &entry));
+        cache.insert(&chart_path, entry.clone());
+        cache.save().unwrap();
+        assert!(ChartCache::open_for(&chart_path).is_fresh(&chart_path, &entry));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
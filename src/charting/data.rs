@@ -0,0 +1,264 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context, bail};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::analysis::callback_duration::Record as CallbackDurationRecord;
+use crate::analysis::dependency_graph::{DependencyGraphExport, NodeRecordData};
+use crate::analysis::message_latency::MessageLatencyExport;
+use crate::argsv2::analysis_args::filenames;
+use crate::argsv2::chart_args::ChartedValue;
+use crate::chart_links::{callback_interface, subscriber_interface};
+use crate::statistics::SamplesExport;
+
+/// A single data series of one interface of the charted node.
+#[derive(Debug, Clone)]
+pub struct Series {
+    /// Human readable identification of the interface within the node.
+    ///
+    /// See [`callback_interface`] and [`subscriber_interface`].
+    pub interface: String,
+
+    /// Node on the other side of a communication channel, if the series describes one.
+    pub source: Option<String>,
+
+    /// Values in nanoseconds.
+    pub values: Vec<i64>,
+}
+
+impl Series {
+    /// Returns the label of the series in the chart legend.
+    pub fn label(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{} from {source}", self.interface),
+            None => self.interface.clone(),
+        }
+    }
+}
+
+/// Returns the name of the `analyze` output file containing the data for `value`.
+pub const fn default_file_name(value: ChartedValue) -> &'static str {
+    match value {
+        ChartedValue::CallbackDuration | ChartedValue::ActivationsDelay => {
+            filenames::CALLBACK_DURATION
+        }
+        ChartedValue::MessagesLatency => filenames::MESSAGE_LATENCY,
+        ChartedValue::PublicationsDelay | ChartedValue::MessagesDelay => {
+            filenames::DEPENDENCY_GRAPH_DATA
+        }
+    }
+}
+
+/// Resolves the data file for `value`.
+///
+/// The `input` can be either the data file itself or a directory containing the
+/// default named file. If it is not specified, the current directory is used.
+pub fn resolve_input_path(
+    input: Option<&Path>,
+    value: ChartedValue,
+) -> color_eyre::Result<PathBuf> {
+    let input = input.unwrap_or_else(|| Path::new("."));
+    if !input.is_dir() {
+        return Ok(input.to_path_buf());
+    }
+
+    Ok(input.join(default_file_name(value)))
+}
+
+/// Analysis output with or without the data quality section.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnalysisFile<T> {
+    WithDataQuality { data: T },
+    Bare(T),
+}
+
+/// Returns the raw samples of an exported series.
+///
+/// Fails if the analysis exported only a summary of the samples.
+fn raw_samples(samples: SamplesExport, path: &Path) -> color_eyre::Result<Vec<i64>> {
+    let Some(values) = samples.into_raw() else {
+        bail!(
+            "Chart input file {} contains only summaries of the samples. \
+            Run the analysis without --quantile-sketch to export the raw samples.",
+            path.display()
+        );
+    };
+    Ok(values)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> color_eyre::Result<T> {
+    let file = File::open(path)
+        .wrap_err_with(|| format!("Failed to open chart input file {}", path.display()))?;
+
+    let file: AnalysisFile<T> = serde_json::from_reader(BufReader::new(file))
+        .wrap_err_with(|| format!("Failed to parse chart input file {}", path.display()))?;
+    Ok(match file {
+        AnalysisFile::WithDataQuality { data } | AnalysisFile::Bare(data) => data,
+    })
+}
+
+/// Loads the series of the interfaces of `node` for the charted `value`.
+///
+/// If `interface` is specified, only the series of that interface are loaded.
+/// Interfaces without any data are skipped.
+pub fn load_series(
+    path: &Path,
+    node: &str,
+    interface: Option<&str>,
+    value: ChartedValue,
+) -> color_eyre::Result<Vec<Series>> {
+    // Exported samples with the interface and the source of their series. Only the samples
+    // of the charted interfaces must be raw, the others may be summaries.
+    let exported: Vec<(String, Option<String>, SamplesExport)> = match value {
+        ChartedValue::CallbackDuration | ChartedValue::ActivationsDelay => {
+            let records: Vec<CallbackDurationRecord> = read_json(path)?;
+            records
+                .into_iter()
+                .filter(|record| record.node == node)
+                .map(|record| {
+                    (
+                        callback_interface(record.caller_type, record.caller_param),
+                        None,
+                        if matches!(value, ChartedValue::CallbackDuration) {
+                            record.durations
+                        } else {
+                            record.inter_arrival_times
+                        },
+                    )
+                })
+                .collect()
+        }
+        ChartedValue::MessagesLatency => {
+            let records: Vec<MessageLatencyExport> = read_json(path)?;
+            records
+                .into_iter()
+                .filter(|record| record.subscriber_node == node)
+                .map(|record| {
+                    (
+                        subscriber_interface(record.topic),
+                        Some(if record.intra_process {
+                            format!("{} (intra-process)", record.publisher_node)
+                        } else {
+                            record.publisher_node
+                        }),
+                        record.latencies,
+                    )
+                })
+                .collect()
+        }
+        ChartedValue::PublicationsDelay | ChartedValue::MessagesDelay => {
+            let export: DependencyGraphExport = read_json(path)?;
+            export
+                .nodes
+                .into_iter()
+                .filter(|record| record.id.node == node)
+                .filter_map(|record| {
+                    let values = match (value, record.data) {
+                        (
+                            ChartedValue::PublicationsDelay,
+                            NodeRecordData::Publisher { publication_delay },
+                        ) => publication_delay,
+                        (
+                            ChartedValue::MessagesDelay,
+                            NodeRecordData::Subscriber { take_delay },
+                        ) => take_delay,
+                        _ => return None,
+                    };
+                    Some((record.id.interface, None, values))
+                })
+                .collect()
+        }
+    };
+
+    let mut series = exported
+        .into_iter()
+        .filter(|(name, _, _)| interface.is_none_or(|interface| name == interface))
+        .map(|(interface, source, samples)| {
+            Ok(Series {
+                interface,
+                source,
+                values: raw_samples(samples, path)?,
+            })
+        })
+        .collect::<color_eyre::Result<Vec<_>>>()?;
+
+    series.retain(|series| !series.values.is_empty());
+    series.sort_by(|a, b| (&a.interface, &a.source).cmp(&(&b.interface, &b.source)));
+
+    if series.is_empty() {
+        let interface = interface.map_or(String::new(), |interface| format!(" {interface}"));
+        bail!(
+            "No data for \"{value}\" of node {node}{interface} found in {}",
+            path.display()
+        );
+    }
+
+    Ok(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::statistics::SamplesSummary;
+
+    use super::*;
+
+    fn record(caller_param: &str, durations: SamplesExport) -> CallbackDurationRecord {
+        CallbackDurationRecord {
+            node: "/talker".to_owned(),
+            caller_type: "Timer".to_owned(),
+            caller_param: caller_param.to_owned(),
+            callback_id: None,
+            durations,
+            on_cpu_times: Vec::new(),
+            inter_arrival_times: SamplesExport::Raw(Vec::new()),
+            sim_time: false,
+        }
+    }
+
+    #[test]
+    fn interface_with_raw_samples_next_to_summaries() {
+        let path = std::env::temp_dir().join(format!(
+            "ros2_trace_analyzer_chart_data_{}_mixed.json",
+            std::process::id()
+        ));
+        let summary = SamplesExport::Summary(SamplesSummary {
+            count: 3,
+            mean: Some(20),
+            relative_error: 0.01,
+            quantiles: Vec::new(),
+        });
+        let records = vec![
+            record("100 ms", SamplesExport::Raw(vec![10, 20, 30])),
+            record("1 s", summary),
+        ];
+        std::fs::write(&path, serde_json::to_string(&records).unwrap()).unwrap();
+
+        let series = load_series(
+            &path,
+            "/talker",
+            Some("Timer(100 ms)"),
+            ChartedValue::CallbackDuration,
+        )
+        .unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].values, vec![10, 20, 30]);
+
+        // The summary cannot be charted.
+        assert!(load_series(&path, "/talker", None, ChartedValue::CallbackDuration).is_err());
+        assert!(
+            load_series(
+                &path,
+                "/talker",
+                Some("Timer(1 s)"),
+                ChartedValue::CallbackDuration
+            )
+            .is_err()
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use color_eyre::eyre::Context;
+
+use crate::argsv2::chart_args::{ChartArgs, ChartRequest, ChartVariants};
+
+mod cache;
+pub mod data;
+mod render;
+
+use cache::{CacheEntry, ChartCache};
+
+/// Renders the chart requested by the `chart` subcommand.
+///
+/// Returns the path of the rendered image.
+pub fn run(args: &ChartArgs) -> color_eyre::Result<PathBuf> {
+    render_chart(
+        args.node(),
+        args.interface(),
+        args.input_path(),
+        args.output_path(),
+        args.chart(),
+        args.clean(),
+    )
+}
+
+/// Renders the chart of `node` described by `request`.
+///
+/// - `interface` limits the chart to a single interface of the node.
+/// - `input` is either the data file or a directory containing the default named data file.
+/// - `output` is either the image file or a directory into which a file named by
+///   [`chart_file_name`] is written.
+///
+/// Both default to the current directory.
+///
+/// Unless `clean` is set, a previously rendered chart is reused if the cache manifest
+/// next to it shows it was rendered from the same parameters and input data.
+pub fn render_chart(
+    node: &str,
+    interface: Option<&str>,
+    input: Option<&Path>,
+    output: Option<&Path>,
+    request: &ChartRequest,
+    clean: bool,
+) -> color_eyre::Result<PathBuf> {
+    let input_path = data::resolve_input_path(input, request.value)?;
+    let output_path = resolve_output_path(output, node, interface, request);
+
+    let mut cache = ChartCache::open_for(&output_path);
+    let cache_entry = CacheEntry::new(node, interface, request, &input_path)?;
+    if !clean && cache.is_fresh(&output_path, &cache_entry) {
+        log::info!("Reusing cached chart {}", output_path.display());
+        return Ok(output_path);
+    }
+
+    let series = data::load_series(&input_path, node, interface, request.value)?;
+
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)
+            .wrap_err_with(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+
+    let title = match interface {
+        Some(interface) => format!("{} of {node} {interface}", request.value),
+        None => format!("{} of {node}", request.value),
+    };
+    render::render(&output_path, &title, request, &series)
+        .wrap_err_with(|| format!("Failed to render chart {}", output_path.display()))?;
+
+    cache.insert(&output_path, cache_entry);
+    cache.save()?;
+
+    Ok(output_path)
+}
+
+fn resolve_output_path(
+    output: Option<&Path>,
+    node: &str,
+    interface: Option<&str>,
+    request: &ChartRequest,
+) -> PathBuf {
+    match output {
+        Some(path) if !path.is_dir() => path.to_path_buf(),
+        Some(dir) => dir.join(chart_file_name(node, interface, request)),
+        None => PathBuf::from(chart_file_name(node, interface, request)),
+    }
+}
+
+/// Replaces characters which are not safe in file names.
+fn sanitize_file_name_part(part: &str) -> String {
+    part.trim_start_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Returns the default file name of the chart uniquely identifying the charted data.
+pub fn chart_file_name(node: &str, interface: Option<&str>, request: &ChartRequest) -> String {
+    let node = match interface {
+        Some(interface) => format!(
+            "{}_{}",
+            sanitize_file_name_part(node),
+            sanitize_file_name_part(interface)
+        ),
+        None => sanitize_file_name_part(node),
+    };
+    let value = request
+        .value
+        .to_possible_value()
+        .expect("ChartedValue has no skipped variants");
+    let plot = match request.plot {
+        ChartVariants::Histogram(histogram) => match histogram.bins {
+            Some(bins) => format!("histogram{bins}"),
+            None => "histogram".to_owned(),
+        },
+        ChartVariants::Scatter => "scatter".to_owned(),
+    };
+
+    format!(
+        "{node}_{value}_{plot}_{size}.{format}",
+        value = value.get_name(),
+        size = request.size,
+        format = request.output_format,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::argsv2::chart_args::{ChartOutputFormat, ChartedValue, HistogramData};
+
+    use super::*;
+
+    #[test]
+    fn chart_file_name_contains_parameters() {
+        let request = ChartRequest {
+            value: ChartedValue::CallbackDuration,
+            plot: ChartVariants::Histogram(HistogramData { bins: Some(20) }),
+            size: 600,
+            output_format: ChartOutputFormat::PNG,
+        };
+
+        assert_eq!(
+            chart_file_name("/ns/talker", None, &request),
+            "ns_talker_callback-duration_histogram20_600.png"
+        );
+        assert_eq!(
+            chart_file_name("/ns/talker", Some("Timer(100 ms)"), &request),
+            "ns_talker_Timer_100_ms__callback-duration_histogram20_600.png"
+        );
+    }
+}
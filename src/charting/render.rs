@@ -0,0 +1,279 @@
+use std::ops::Range;
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::argsv2::chart_args::{ChartOutputFormat, ChartRequest, ChartVariants};
+
+use super::data::Series;
+
+const FONT: &str = "sans-serif";
+
+/// Opacity of histogram bars, so that overlapping series remain visible.
+const HISTOGRAM_OPACITY: f64 = 0.5;
+const SCATTER_POINT_SIZE: u32 = 2;
+
+/// Unit used on the value axis.
+#[derive(Debug, Clone, Copy)]
+struct TimeUnit {
+    name: &'static str,
+    nanoseconds: f64,
+}
+
+impl TimeUnit {
+    const UNITS: [Self; 4] = [
+        Self::new("ns", 1.0),
+        Self::new("μs", 1e3),
+        Self::new("ms", 1e6),
+        Self::new("s", 1e9),
+    ];
+
+    const fn new(name: &'static str, nanoseconds: f64) -> Self {
+        Self { name, nanoseconds }
+    }
+
+    /// Selects the largest unit in which `max` is at least one.
+    fn for_max_value(max: i64) -> Self {
+        let max = max.unsigned_abs() as f64;
+        Self::UNITS
+            .into_iter()
+            .rev()
+            .find(|unit| max >= unit.nanoseconds)
+            .unwrap_or(Self::UNITS[0])
+    }
+
+    fn convert(self, value: i64) -> f64 {
+        value as f64 / self.nanoseconds
+    }
+}
+
+/// Number of histogram bins when not specified by the user (Sturges' rule).
+fn default_bin_count(sample_count: usize) -> usize {
+    sample_count.max(1).ilog2() as usize + 1
+}
+
+/// Returns a non-empty range covering `min..=max`.
+fn padded_range(min: f64, max: f64) -> Range<f64> {
+    if max > min {
+        min..max
+    } else {
+        let padding = if min == 0.0 { 1.0 } else { min.abs() * 0.1 };
+        (min - padding)..(max + padding)
+    }
+}
+
+/// Extends the range by a small margin so that the extreme values are not drawn on the border.
+fn with_margin(range: Range<f64>) -> Range<f64> {
+    const MARGIN_RATIO: f64 = 0.05;
+
+    let margin = (range.end - range.start) * MARGIN_RATIO;
+    (range.start - margin)..(range.end + margin)
+}
+
+pub fn render(
+    path: &Path,
+    title: &str,
+    request: &ChartRequest,
+    series: &[Series],
+) -> color_eyre::Result<()> {
+    let size = (request.size, request.size);
+    match request.output_format {
+        ChartOutputFormat::SVG => {
+            let root = SVGBackend::new(path, size).into_drawing_area();
+            draw(&root, title, request, series)
+        }
+        ChartOutputFormat::PNG => {
+            let root = BitMapBackend::new(path, size).into_drawing_area();
+            draw(&root, title, request, series)
+        }
+    }
+}
+
+fn draw<DB>(
+    root: &DrawingArea<DB, Shift>,
+    title: &str,
+    request: &ChartRequest,
+    series: &[Series],
+) -> color_eyre::Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    match request.plot {
+        ChartVariants::Histogram(histogram) => {
+            draw_histogram(root, title, request, histogram.bins, series)?;
+        }
+        ChartVariants::Scatter => draw_scatter(root, title, request, series)?,
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+fn draw_histogram<DB>(
+    root: &DrawingArea<DB, Shift>,
+    title: &str,
+    request: &ChartRequest,
+    bins: Option<usize>,
+    series: &[Series],
+) -> color_eyre::Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let values = series
+        .iter()
+        .flat_map(|series| series.values.iter().copied());
+    let min = values.clone().min().unwrap_or_default();
+    let max = values.clone().max().unwrap_or_default();
+    let unit = TimeUnit::for_max_value(max);
+
+    let bin_count = bins
+        .unwrap_or_else(|| default_bin_count(values.count()))
+        .max(1);
+    let x_range = padded_range(unit.convert(min), unit.convert(max));
+    let bin_width = (x_range.end - x_range.start) / bin_count as f64;
+
+    let counts: Vec<Vec<usize>> = series
+        .iter()
+        .map(|series| {
+            let mut counts = vec![0; bin_count];
+            for &value in &series.values {
+                let bin = ((unit.convert(value) - x_range.start) / bin_width) as usize;
+                counts[bin.min(bin_count - 1)] += 1;
+            }
+            counts
+        })
+        .collect();
+    let max_count = counts.iter().flatten().copied().max().unwrap_or_default();
+
+    let mut chart = ChartBuilder::on(root)
+        .caption(title, (FONT, 20))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(x_range.clone(), 0..(max_count + max_count / 10 + 1))?;
+
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_desc(format!("{} [{}]", request.value, unit.name))
+        .y_desc("Count")
+        .draw()?;
+
+    for (i, (series, counts)) in series.iter().zip(counts).enumerate() {
+        let color = Palette99::pick(i).mix(HISTOGRAM_OPACITY);
+        chart
+            .draw_series(
+                counts
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, count)| *count > 0)
+                    .map(|(bin, count)| {
+                        let x0 = x_range.start + bin as f64 * bin_width;
+                        Rectangle::new([(x0, 0), (x0 + bin_width, count)], color.filled())
+                    }),
+            )?
+            .label(series.label())
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], color.filled()));
+    }
+
+    draw_legend(&mut chart)
+}
+
+fn draw_scatter<DB>(
+    root: &DrawingArea<DB, Shift>,
+    title: &str,
+    request: &ChartRequest,
+    series: &[Series],
+) -> color_eyre::Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let values = series
+        .iter()
+        .flat_map(|series| series.values.iter().copied());
+    let min = values.clone().min().unwrap_or_default();
+    let max = values.max().unwrap_or_default();
+    let unit = TimeUnit::for_max_value(max);
+    let max_len = series
+        .iter()
+        .map(|series| series.values.len())
+        .max()
+        .unwrap_or_default();
+
+    let mut chart = ChartBuilder::on(root)
+        .caption(title, (FONT, 20))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(
+            0..max_len,
+            with_margin(padded_range(unit.convert(min), unit.convert(max))),
+        )?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Sample")
+        .y_desc(format!("{} [{}]", request.value, unit.name))
+        .draw()?;
+
+    for (i, series) in series.iter().enumerate() {
+        let color = Palette99::pick(i);
+        chart
+            .draw_series(series.values.iter().enumerate().map(|(index, &value)| {
+                Circle::new(
+                    (index, unit.convert(value)),
+                    SCATTER_POINT_SIZE,
+                    color.filled(),
+                )
+            }))?
+            .label(series.label())
+            .legend(move |(x, y)| Circle::new((x + 5, y), SCATTER_POINT_SIZE, color.filled()));
+    }
+
+    draw_legend(&mut chart)
+}
+
+fn draw_legend<'a, DB, CT>(chart: &mut ChartContext<'a, DB, CT>) -> color_eyre::Result<()>
+where
+    DB: DrawingBackend + 'a,
+    DB::ErrorType: 'static,
+    CT: CoordTranslate,
+{
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .label_font((FONT, 14))
+        .draw()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_unit_selection() {
+        assert_eq!(TimeUnit::for_max_value(0).name, "ns");
+        assert_eq!(TimeUnit::for_max_value(999).name, "ns");
+        assert_eq!(TimeUnit::for_max_value(1_000).name, "μs");
+        assert_eq!(TimeUnit::for_max_value(25_000_000).name, "ms");
+        assert_eq!(TimeUnit::for_max_value(3_000_000_000).name, "s");
+    }
+
+    #[test]
+    fn default_bins() {
+        assert_eq!(default_bin_count(0), 1);
+        assert_eq!(default_bin_count(1), 1);
+        assert_eq!(default_bin_count(1024), 11);
+    }
+}
@@ -0,0 +1,280 @@
+use serde::Serialize;
+
+use crate::model::Time;
+
+/// Kind of the items discarded by the tracer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, derive_more::Display)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscardedItems {
+    #[display("events")]
+    Events,
+    #[display("packets")]
+    Packets,
+}
+
+/// Time window in which the tracer discarded events or packets of a stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscardedWindow {
+    pub items: DiscardedItems,
+    pub hostname: String,
+    /// Name of the stream, i.e., the path of its data file.
+    pub stream: Option<String>,
+    /// CPU of the stream if the stream is per-CPU.
+    pub cpu: Option<u32>,
+    /// Number of discarded items if it is known.
+    pub count: Option<u64>,
+    #[serde(serialize_with = "serialize_optional_time")]
+    pub start_time: Option<Time>,
+    #[serde(serialize_with = "serialize_optional_time")]
+    pub end_time: Option<Time>,
+}
+
+fn serialize_optional_time<S>(time: &Option<Time>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    time.map(Time::timestamp_nanos).serialize(s)
+}
+
+impl std::fmt::Display for DiscardedWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let display_optional =
+            |value: Option<String>| value.unwrap_or_else(|| "unknown".to_owned());
+
+        match self.count {
+            Some(count) => write!(f, "{count} {}", self.items)?,
+            None => write!(f, "unknown number of {}", self.items)?,
+        }
+        write!(
+            f,
+            " on {} (stream {}, CPU {}) from {} to {}",
+            self.hostname,
+            self.stream.as_deref().unwrap_or("unknown"),
+            display_optional(self.cpu.map(|cpu| cpu.to_string())),
+            display_optional(self.start_time.map(|time| time.to_string())),
+            display_optional(self.end_time.map(|time| time.to_string())),
+        )
+    }
+}
+
+/// Overlap of a sample with a discarded window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlap {
+    None,
+    /// The window overlaps the sample.
+    Known,
+    /// The window is on a host of the sample, but its time range is unknown.
+    Unknown,
+}
+
+impl DiscardedWindow {
+    /// Returns the overlap of the interval from `start` to `end` on the `hosts` with this window.
+    pub fn overlaps(&self, hosts: &[&str], start: Time, end: Time) -> Overlap {
+        if !hosts.contains(&self.hostname.as_str()) {
+            return Overlap::None;
+        }
+        let (Some(window_start), Some(window_end)) = (self.start_time, self.end_time) else {
+            return Overlap::Unknown;
+        };
+        if start <= window_end && window_start <= end {
+            Overlap::Known
+        } else {
+            Overlap::None
+        }
+    }
+}
+
+/// Quality of the analyzed trace data.
+///
+/// It is written with every analysis output, so the results computed from an incomplete
+/// trace are not mistaken for exact ones.
+#[derive(Debug, Clone, Serialize)]
+pub struct DataQuality {
+    /// `true` if the tracer did not discard any events or packets.
+    complete: bool,
+    /// Total number of discarded events with a known count.
+    discarded_events: u64,
+    /// Total number of discarded packets with a known count.
+    discarded_packets: u64,
+    windows: Vec<DiscardedWindow>,
+    /// `false` if the analysis does not exclude or count the samples overlapping the windows.
+    adjusted: bool,
+}
+
+impl Default for DataQuality {
+    fn default() -> Self {
+        Self {
+            complete: true,
+            discarded_events: 0,
+            discarded_packets: 0,
+            windows: Vec::new(),
+            adjusted: true,
+        }
+    }
+}
+
+impl DataQuality {
+    pub fn add_window(&mut self, window: DiscardedWindow) {
+        let count = window.count.unwrap_or_default();
+        match window.items {
+            DiscardedItems::Events => self.discarded_events += count,
+            DiscardedItems::Packets => self.discarded_packets += count,
+        }
+        self.complete = false;
+        self.windows.push(window);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Returns the data quality for the output of an analysis which does not take
+    /// the discarded windows into account.
+    pub fn not_adjusted(&self) -> Self {
+        Self {
+            adjusted: false,
+            ..self.clone()
+        }
+    }
+
+    /// Writes the human readable data quality section.
+    ///
+    /// Every line is prefixed with `prefix`, e.g., to write it as a comment.
+    pub fn write_summary(
+        &self,
+        writer: &mut impl std::io::Write,
+        prefix: &str,
+    ) -> std::io::Result<()> {
+        if self.complete {
+            return writeln!(writer, "{prefix}Data quality: no events were discarded");
+        }
+
+        writeln!(
+            writer,
+            "{prefix}Data quality: {} events and {} packets were discarded in {} windows",
+            self.discarded_events,
+            self.discarded_packets,
+            self.windows.len()
+        )?;
+        for window in &self.windows {
+            writeln!(writer, "{prefix}  - {window}")?;
+        }
+        if self.adjusted {
+            writeln!(
+                writer,
+                "{prefix}Samples overlapping these windows are excluded or may be inaccurate."
+            )
+        } else {
+            writeln!(
+                writer,
+                "{prefix}Samples overlapping these windows are not excluded and may be inaccurate."
+            )
+        }
+    }
+}
+
+/// Discarded windows seen so far by an analysis.
+///
+/// Analyses use it to exclude samples that overlap a window, e.g., callbacks whose end
+/// could have been discarded.
+#[derive(Debug, Default, Clone)]
+pub struct DiscardedWindows {
+    windows: Vec<DiscardedWindow>,
+    /// Number of samples excluded because they overlap a window.
+    excluded: usize,
+    /// Number of samples kept although a window with an unknown time range could overlap them.
+    uncertain: usize,
+}
+
+impl DiscardedWindows {
+    pub fn add(&mut self, window: &DiscardedWindow) {
+        self.windows.push(window.clone());
+    }
+
+    pub fn clear(&mut self) {
+        self.windows.clear();
+        self.excluded = 0;
+        self.uncertain = 0;
+    }
+
+    /// Returns `true` and counts the sample as excluded if the sample from `start` to `end`
+    /// on the `hosts` overlaps any window.
+    ///
+    /// The windows with an unknown time range could overlap any sample of their host, so
+    /// the sample is kept and counted as uncertain instead.
+    pub fn exclude(&mut self, hosts: &[&str], start: Time, end: Time) -> bool {
+        let mut uncertain = false;
+        for window in &self.windows {
+            match window.overlaps(hosts, start, end) {
+                Overlap::Known => {
+                    self.excluded += 1;
+                    return true;
+                }
+                Overlap::Unknown => uncertain = true,
+                Overlap::None => {}
+            }
+        }
+        if uncertain {
+            self.uncertain += 1;
+        }
+        false
+    }
+
+    pub fn get_excluded_count(&self) -> usize {
+        self.excluded
+    }
+
+    pub fn get_uncertain_count(&self) -> usize {
+        self.uncertain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_overlap() {
+        let window = DiscardedWindow {
+            items: DiscardedItems::Events,
+            hostname: "host".to_owned(),
+            stream: None,
+            cpu: None,
+            count: Some(10),
+            start_time: Some(Time::from_nanos(100)),
+            end_time: Some(Time::from_nanos(200)),
+        };
+
+        let overlaps =
+            |start, end| window.overlaps(&["host"], Time::from_nanos(start), Time::from_nanos(end));
+        assert_eq!(overlaps(50, 100), Overlap::Known);
+        assert_eq!(overlaps(150, 160), Overlap::Known);
+        assert_eq!(overlaps(50, 250), Overlap::Known);
+        assert_eq!(overlaps(50, 99), Overlap::None);
+        assert_eq!(overlaps(201, 300), Overlap::None);
+        assert_eq!(
+            window.overlaps(&["other"], Time::from_nanos(150), Time::from_nanos(160)),
+            Overlap::None
+        );
+
+        let mut windows = DiscardedWindows::default();
+        windows.add(&window);
+        windows.add(&DiscardedWindow {
+            hostname: "other".to_owned(),
+            start_time: None,
+            end_time: None,
+            ..window.clone()
+        });
+        assert!(windows.exclude(&["host"], Time::from_nanos(150), Time::from_nanos(160)));
+        assert!(!windows.exclude(&["other"], Time::from_nanos(150), Time::from_nanos(160)));
+        assert!(!windows.exclude(&["host"], Time::from_nanos(300), Time::from_nanos(400)));
+        assert_eq!(windows.get_excluded_count(), 1);
+        assert_eq!(windows.get_uncertain_count(), 1);
+
+        let mut data_quality = DataQuality::default();
+        assert!(data_quality.is_complete());
+        data_quality.add_window(window);
+        assert!(!data_quality.is_complete());
+        assert_eq!(data_quality.discarded_events, 10);
+    }
+}
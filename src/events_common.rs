@@ -3,18 +3,18 @@ pub use crate::model::Time;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Context {
-    cpu_id: u32,
+    cpu_id: Option<u32>,
     vpid: u32,
-    vtid: u32,
+    vtid: Option<u32>,
     procname: String,
     hostname: String,
 }
 
 impl Context {
     pub const fn new(
-        cpu_id: u32,
+        cpu_id: Option<u32>,
         vpid: u32,
-        vtid: u32,
+        vtid: Option<u32>,
         procname: String,
         hostname: String,
     ) -> Self {
@@ -27,7 +27,8 @@ impl Context {
         }
     }
 
-    pub const fn cpu_id(&self) -> u32 {
+    /// Returns the CPU of the event or `None` if the trace did not record it.
+    pub const fn cpu_id(&self) -> Option<u32> {
         self.cpu_id
     }
 
@@ -35,7 +36,8 @@ impl Context {
         self.vpid
     }
 
-    pub const fn vtid(&self) -> u32 {
+    /// Returns the thread of the event or `None` if the trace did not record it.
+    pub const fn vtid(&self) -> Option<u32> {
         self.vtid
     }
 
@@ -2,19 +2,25 @@
 
 mod analyses;
 mod argsv2;
+mod chart_links;
+mod charting;
+mod data_quality;
 mod events_common;
 mod model;
+mod preflight;
 mod processed_events;
 mod processor;
 mod raw_events;
 mod statistics;
 mod utils;
+mod viewer;
 mod visualization;
 
 use std::ffi::CString;
 
 use argsv2::Args;
 use argsv2::helpers::prepare_trace_paths;
+use bt2_sys::iterator::TraceSource;
 
 use crate::argsv2::analysis_args::AnalysisArgs;
 use crate::argsv2::chart_args::ChartArgs;
@@ -26,14 +32,37 @@ fn run_analysis<L: clap_verbosity_flag::LogLevel>(
     args: &AnalysisArgs,
     verbose: &clap_verbosity_flag::Verbosity<L>,
 ) -> color_eyre::eyre::Result<()> {
-    let trace_paths = prepare_trace_paths()?;
+    let live_url = args.live_url();
+    let trace_paths = if live_url.is_some() {
+        Vec::new()
+    } else {
+        prepare_trace_paths()?
+    };
     let trace_paths_cstr: Vec<_> = trace_paths.iter().map(CString::as_c_str).collect();
 
+    let (source, contexts) = if let Some(live_url) = &live_url {
+        // The relay daemon serves one viewer of a session at a time, so the streams
+        // are checked by the event iterator when they begin.
+        log::info!(
+            "Attaching to the live session {}",
+            live_url.to_string_lossy()
+        );
+        (
+            TraceSource::Live(live_url),
+            preflight::TraceContexts::default(),
+        )
+    } else {
+        let contexts = preflight::TraceContexts::check_traces(&trace_paths_cstr, verbose);
+        contexts.ensure_processable()?;
+        contexts.log_missing();
+        (TraceSource::Files(&trace_paths_cstr), contexts)
+    };
+
     let mut analyses = analyses::Analyses::default();
 
-    analyses.add_analyses_from_args(args);
+    analyses.add_analyses_from_args(args, &contexts)?;
 
-    analyses.analyze_trace(trace_paths_cstr, verbose)?;
+    analyses.analyze_trace(source, verbose)?;
 
     analyses.save_output(args)?;
 
@@ -41,11 +70,15 @@ fn run_analysis<L: clap_verbosity_flag::LogLevel>(
 }
 
 fn run_charting(args: &ChartArgs) -> color_eyre::eyre::Result<()> {
+    let chart_path = charting::run(args)?;
+
+    println!("Chart written to {}", chart_path.display());
+
     Ok(())
 }
 
 fn run_viewer(args: &ViewerArgs) -> color_eyre::eyre::Result<()> {
-    Ok(())
+    viewer::run(args)
 }
 
 fn main() -> color_eyre::eyre::Result<()> {
@@ -0,0 +1,205 @@
+use std::fmt::{self, Debug, Display};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Callback, Client, Node, Publisher, Service, Subscriber, Timer};
+
+/// Typed index of an object in its [`Arena`].
+pub trait ArenaId: Copy {
+    fn from_index(index: usize) -> Self;
+
+    fn index(self) -> usize;
+}
+
+macro_rules! arena_id {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(u32);
+
+        impl ArenaId for $name {
+            fn from_index(index: usize) -> Self {
+                Self(index.try_into().expect(concat!(stringify!($name), " overflow")))
+            }
+
+            fn index(self) -> usize {
+                self.0 as usize
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                Display::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+arena_id!(
+    /// Stable identifier of a [`Node`](super::Node).
+    NodeId
+);
+arena_id!(
+    /// Stable identifier of a [`Publisher`](super::Publisher).
+    PublisherId
+);
+arena_id!(
+    /// Stable identifier of a [`Subscriber`](super::Subscriber).
+    SubscriberId
+);
+arena_id!(
+    /// Stable identifier of a [`Service`](super::Service).
+    ServiceId
+);
+arena_id!(
+    /// Stable identifier of a [`Client`](super::Client).
+    ClientId
+);
+arena_id!(
+    /// Stable identifier of a [`Timer`](super::Timer).
+    TimerId
+);
+arena_id!(
+    /// Stable identifier of a [`Callback`](super::Callback).
+    CallbackId
+);
+
+/// Objects of one kind indexed by their identifiers.
+///
+/// The identifiers are assigned in the order the objects are created. The objects are
+/// never removed, so an identifier refers to the same object until the end of the analysis,
+/// even when the object is replaced in the maps by its handles.
+///
+/// The clones of an arena share its objects, so the [`ObjectsView`] sees the objects added
+/// by the processor.
+pub struct Arena<I, T> {
+    objects: Arc<RwLock<Vec<Arc<Mutex<T>>>>>,
+    id: PhantomData<I>,
+}
+
+impl<I, T> Default for Arena<I, T> {
+    fn default() -> Self {
+        Self {
+            objects: Arc::default(),
+            id: PhantomData,
+        }
+    }
+}
+
+impl<I, T> Clone for Arena<I, T> {
+    fn clone(&self) -> Self {
+        Self {
+            objects: self.objects.clone(),
+            id: PhantomData,
+        }
+    }
+}
+
+impl<I, T> Debug for Arena<I, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena").field("len", &self.len()).finish()
+    }
+}
+
+impl<I: ArenaId, T> Arena<I, T> {
+    /// Adds the object created by `new` from its identifier.
+    pub fn insert(&mut self, new: impl FnOnce(I) -> T) -> Arc<Mutex<T>> {
+        let mut objects = self.objects.write().unwrap();
+        let object = Arc::new(Mutex::new(new(I::from_index(objects.len()))));
+        objects.push(object.clone());
+        object
+    }
+
+    /// Calls `read` with the object of the `id`, or returns `None` if there is no such object.
+    fn read<R>(&self, id: I, read: impl FnOnce(&T) -> R) -> Option<R> {
+        let object = self.objects.read().unwrap().get(id.index())?.clone();
+        let object = object.lock().unwrap();
+        Some(read(&object))
+    }
+}
+
+impl<I, T> Arena<I, T> {
+    pub fn len(&self) -> usize {
+        self.objects.read().unwrap().len()
+    }
+}
+
+/// Every object created by the processor by its identifier.
+#[derive(Debug, Default)]
+pub struct Objects {
+    pub nodes: Arena<NodeId, Node>,
+    pub publishers: Arena<PublisherId, Publisher>,
+    pub subscribers: Arena<SubscriberId, Subscriber>,
+    pub services: Arena<ServiceId, Service>,
+    pub clients: Arena<ClientId, Client>,
+    pub timers: Arena<TimerId, Timer>,
+    pub callbacks: Arena<CallbackId, Callback>,
+}
+
+impl Objects {
+    /// Returns a read-only view of the objects, including the ones created later.
+    pub fn view(&self) -> ObjectsView {
+        ObjectsView {
+            callbacks: self.callbacks.clone(),
+        }
+    }
+}
+
+/// Read-only view of the objects created by the processor, shared with the analyses.
+///
+/// The analyses keep the identifiers of the objects and read the objects by them when they
+/// write their outputs.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectsView {
+    callbacks: Arena<CallbackId, Callback>,
+}
+
+impl ObjectsView {
+    /// Calls `read` with the callback of the `id`.
+    pub fn read_callback<R>(&self, id: CallbackId, read: impl FnOnce(&Callback) -> R) -> Option<R> {
+        self.callbacks.read(id, read)
+    }
+
+    /// Returns the shared callback of the `id` for the analyses which still work on
+    /// the shared objects.
+    pub fn callback_arc(&self, id: CallbackId) -> Option<Arc<Mutex<Callback>>> {
+        self.callbacks
+            .objects
+            .read()
+            .unwrap()
+            .get(id.index())
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_in_creation_order() {
+        let mut arena: Arena<NodeId, &str> = Arena::default();
+        let first = arena.insert(|id| {
+            assert_eq!(id, NodeId(0));
+            "first"
+        });
+        let second = arena.insert(|id| {
+            assert_eq!(id, NodeId(1));
+            "second"
+        });
+        assert_eq!(arena.len(), 2);
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(*second.lock().unwrap(), "second");
+        assert_eq!(NodeId(1).to_string(), "1");
+        assert_eq!(serde_json::to_string(&NodeId(1)).unwrap(), "1");
+
+        // The clones share the objects added later.
+        let view = arena.clone();
+        arena.insert(|_| "third");
+        assert_eq!(view.read(NodeId(2), |object| *object), Some("third"));
+        assert_eq!(view.read(NodeId(3), |object| *object), None);
+    }
+}
@@ -7,9 +7,9 @@ use crate::utils::{
 };
 
 use super::{
-    Callback, CallbackCaller, CallbackInstance, CallbackType, Client, Gid, Name, Node,
-    PartiallyKnown, PublicationMessage, Publisher, Service, SpinInstance, Subscriber,
-    SubscriptionMessage, Time, Timer,
+    Callback, CallbackCaller, CallbackInstance, CallbackType, Client, Executor, Gid,
+    LifecycleStateMachine, Name, Node, PartiallyKnown, PublicationMessage, Publisher, RingBuffer,
+    Service, SpinInstance, Subscriber, SubscriptionMessage, Thread, Time, Timer,
 };
 
 impl std::fmt::Debug for Time {
@@ -50,8 +50,8 @@ impl std::fmt::Display for Node {
         let name = self.get_full_name().map(DisplayDebug);
         write!(
             f,
-            "(name={name}, handles={{rmw={:x}, rcl={:x}}})",
-            self.rmw_handle, self.rcl_handle
+            "(id={}, name={name}, handles={{rmw={:x}, rcl={:x}}})",
+            self.id, self.rmw_handle, self.rcl_handle
         )
     }
 }
@@ -73,7 +73,8 @@ impl std::fmt::Display for Publisher {
 
         write!(
             f,
-            "(topic={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, queue_depth={}, node={node})",
+            "(id={}, topic={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, queue_depth={}, node={node})",
+            self.id,
             self.topic_name.as_ref().map(DisplayDebug),
             self.rmw_handle,
             self.rcl_handle,
@@ -89,7 +90,8 @@ impl std::fmt::Display for DisplayPublisherWithoutNode<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "(topic={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, queue_depth={})",
+            "(id={}, topic={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, queue_depth={})",
+            self.0.id,
             self.0.topic_name.as_ref().map(DisplayDebug),
             self.0.rmw_handle,
             self.0.rcl_handle,
@@ -112,7 +114,8 @@ impl std::fmt::Display for Subscriber {
 
         write!(
             f,
-            "(topic={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, queue_depth={}, node={node}, callback={callback:#})",
+            "(id={}, topic={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, queue_depth={}, node={node}, callback={callback:#})",
+            self.id,
             self.topic_name.as_ref().map(DisplayDebug),
             self.rmw_handle,
             self.rcl_handle,
@@ -144,7 +147,8 @@ impl std::fmt::Display for DisplaySubscriberWithoutNode<'_> {
 
         write!(
             f,
-            "(topic={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, queue_depth={}, callback={callback:#})",
+            "(id={}, topic={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, queue_depth={}, callback={callback:#})",
+            self.0.id,
             self.0.topic_name.as_ref().map(DisplayDebug),
             self.0.rmw_handle,
             self.0.rcl_handle,
@@ -167,7 +171,8 @@ impl std::fmt::Display for Service {
 
         write!(
             f,
-            "(service={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, node={node}, callback={callback:#})",
+            "(id={}, service={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, node={node}, callback={callback:#})",
+            self.id,
             self.name.as_ref().map(DisplayDebug),
             self.rmw_handle,
             self.rcl_handle,
@@ -196,7 +201,8 @@ impl std::fmt::Display for DisplayServiceWithoutNode<'_> {
 
         write!(
             f,
-            "(service={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, callback={callback:#})",
+            "(id={}, service={}, handles={{rmw={:x}, rcl={:x}, rclcpp={:x}}}, callback={callback:#})",
+            self.0.id,
             self.0.name.as_ref().map(DisplayDebug),
             self.0.rmw_handle,
             self.0.rcl_handle,
@@ -214,7 +220,8 @@ impl std::fmt::Display for Client {
 
         write!(
             f,
-            "(service={}, handles={{rmw={:x}, rcl={:x}}}, node={node})",
+            "(id={}, service={}, handles={{rmw={:x}, rcl={:x}}}, node={node})",
+            self.id,
             self.service_name.as_ref().map(DisplayDebug),
             self.rmw_handle,
             self.rcl_handle
@@ -228,7 +235,8 @@ impl std::fmt::Display for DisplayClientWithoutNode<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "(service={}, handles={{rmw={:x}, rcl={:x}}})",
+            "(id={}, service={}, handles={{rmw={:x}, rcl={:x}}})",
+            self.0.id,
             self.0.service_name.as_ref().map(DisplayDebug),
             self.0.rmw_handle,
             self.0.rcl_handle
@@ -250,8 +258,8 @@ impl std::fmt::Display for Timer {
 
         write!(
             f,
-            "(period={period}, rcl_handle={}, node={node} callback={callback:#})",
-            self.rcl_handle,
+            "(id={}, period={period}, rcl_handle={}, node={node} callback={callback:#})",
+            self.id, self.rcl_handle,
         )
     }
 }
@@ -278,8 +286,8 @@ impl std::fmt::Display for TimerDisplayWithoutNode<'_> {
 
         write!(
             f,
-            "(period={period}, rcl_handle={}, callback={callback:#})",
-            self.0.rcl_handle,
+            "(id={}, period={period}, rcl_handle={}, callback={callback:#})",
+            self.0.id, self.0.rcl_handle,
         )
     }
 }
@@ -289,7 +297,8 @@ impl std::fmt::Display for Callback {
         if f.alternate() {
             write!(
                 f,
-                "(handle={:x}, name={})",
+                "(id={}, handle={:x}, name={})",
+                self.id,
                 self.handle,
                 self.name.as_ref().map(DisplayDebug)
             )
@@ -298,7 +307,8 @@ impl std::fmt::Display for Callback {
                 Known::Known(CallbackCaller::Subscription(subscriber)) => {
                     write!(
                         f,
-                        "(handle={:x}, caller=Subscriber{}, name={})",
+                        "(id={}, handle={:x}, caller=Subscriber{}, name={})",
+                        self.id,
                         self.handle,
                         DisplayArcWeakMutex::new(subscriber, false),
                         self.name.as_ref().map(DisplayDebug)
@@ -307,7 +317,8 @@ impl std::fmt::Display for Callback {
                 Known::Known(CallbackCaller::Service(service)) => {
                     write!(
                         f,
-                        "(handle={:x}, caller=Service{}, name={})",
+                        "(id={}, handle={:x}, caller=Service{}, name={})",
+                        self.id,
                         self.handle,
                         DisplayArcWeakMutex::new(service, false),
                         self.name.as_ref().map(DisplayDebug)
@@ -316,7 +327,8 @@ impl std::fmt::Display for Callback {
                 Known::Known(CallbackCaller::Timer(timer)) => {
                     write!(
                         f,
-                        "(handle={:x}, caller=Timer{}, name={})",
+                        "(id={}, handle={:x}, caller=Timer{}, name={})",
+                        self.id,
                         self.handle,
                         DisplayArcWeakMutex::new(timer, false),
                         self.name.as_ref().map(DisplayDebug)
@@ -325,7 +337,8 @@ impl std::fmt::Display for Callback {
                 Known::Unknown => {
                     write!(
                         f,
-                        "(handle={:x}, caller=Unknown, name={})",
+                        "(id={}, handle={:x}, caller=Unknown, name={})",
+                        self.id,
                         self.handle,
                         self.name.as_ref().map(DisplayDebug)
                     )
@@ -513,3 +526,49 @@ impl std::fmt::Display for SpinInstance {
         )
     }
 }
+
+impl std::fmt::Display for LifecycleStateMachine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let node = DisplayArcWeakMutex::new(&self.node, false);
+        write!(
+            f,
+            "(handle={:x}, state={}, node={node})",
+            self.handle,
+            self.state.as_ref().map(DisplayDebug)
+        )
+    }
+}
+
+impl std::fmt::Display for Executor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(hostname={}, pid={}, tid={})",
+            self.hostname, self.pid, self.tid
+        )
+    }
+}
+
+impl std::fmt::Display for Thread {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(hostname={}, tid={}, name={})",
+            self.hostname, self.tid, self.name
+        )
+    }
+}
+
+impl std::fmt::Display for RingBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let topic = self
+            .subscriber
+            .as_ref()
+            .map(|subscriber| get_subscriber_topic_from_weak(&subscriber.get_weak()));
+        write!(
+            f,
+            "(handle={:x}, ipb={:x}, capacity={}, size={}, subscriber_topic={topic})",
+            self.handle, self.ipb, self.capacity, self.size
+        )
+    }
+}
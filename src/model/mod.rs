@@ -1,6 +1,7 @@
+pub(crate) mod arena;
 pub(crate) mod display;
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex, Weak};
 use std::time::Duration;
@@ -12,6 +13,8 @@ use display::{
 };
 use thiserror::Error;
 
+pub use arena::{CallbackId, ClientId, NodeId, PublisherId, ServiceId, SubscriberId, TimerId};
+
 use crate::raw_events;
 use crate::utils::{ArcWeak, CyclicDependency, DisplayDuration, Known, WeakKnown};
 
@@ -88,6 +91,7 @@ impl Name {
 
 #[derive(Debug)]
 pub struct Node {
+    id: NodeId,
     rcl_handle: u64,
     rmw_handle: Known<u64>,
     full_name: Known<Name>,
@@ -99,11 +103,13 @@ pub struct Node {
     timers: Vec<Arc<Mutex<Timer>>>,
 
     spin_instance: Option<Arc<Mutex<SpinInstance>>>,
+    lifecycle_state_machine: Option<Arc<Mutex<LifecycleStateMachine>>>,
 }
 
 impl Node {
-    pub fn new(rcl_handle: u64) -> Self {
+    pub fn new(id: NodeId, rcl_handle: u64) -> Self {
         Self {
+            id,
             rcl_handle,
             rmw_handle: Known::Unknown,
             full_name: Known::Unknown,
@@ -113,6 +119,7 @@ impl Node {
             clients: Vec::new(),
             timers: Vec::new(),
             spin_instance: None,
+            lifecycle_state_machine: None,
         }
     }
 
@@ -193,6 +200,18 @@ impl Node {
         self.spin_instance.as_ref()
     }
 
+    pub fn replace_lifecycle_state_machine(
+        &mut self,
+        state_machine: Arc<Mutex<LifecycleStateMachine>>,
+    ) -> Option<Arc<Mutex<LifecycleStateMachine>>> {
+        self.lifecycle_state_machine.replace(state_machine)
+    }
+
+    /// Returns the lifecycle state machine if the node is a managed (lifecycle) node.
+    pub fn get_lifecycle_state_machine(&self) -> Option<&Arc<Mutex<LifecycleStateMachine>>> {
+        self.lifecycle_state_machine.as_ref()
+    }
+
     pub fn print_node_info(&self) {
         println!("Node{self}");
         for subscriber in self.subscribers() {
@@ -228,8 +247,9 @@ impl Node {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Subscriber {
+    id: SubscriberId,
     rmw_handle: Known<u64>,
     rcl_handle: Known<u64>,
     rclcpp_handle: Known<u64>,
@@ -241,6 +261,11 @@ pub struct Subscriber {
     queue_depth: Known<usize>,
 
     callback: Known<Arc<Mutex<Callback>>>,
+
+    /// Address of the rclcpp `SubscriptionIntraProcess` if intra-process communication is enabled.
+    rclcpp_intra_process_handle: Known<u64>,
+    intra_process_callback: Known<Arc<Mutex<Callback>>>,
+
     // TODO: messages that are taken but not yet processed by the callback cause a memory leak
     // at the end of the program. (strong reference count cycle: Subscriber -> Message -> Subscriber)
     // For now, we just limit the number of messages stored here.
@@ -254,6 +279,24 @@ impl Subscriber {
     // This constant was chosen to match the number of messages stored by the R2R's channels.
     const TAKEN_MESSAGES_MAX: usize = 11;
 
+    pub fn new(id: SubscriberId) -> Self {
+        Self {
+            id,
+            rmw_handle: Known::Unknown,
+            rcl_handle: Known::Unknown,
+            rclcpp_handle: Known::Unknown,
+            rmw_gid: Known::Unknown,
+            topic_name: Known::Unknown,
+            node: Known::Unknown,
+            queue_depth: Known::Unknown,
+            callback: Known::Unknown,
+            rclcpp_intra_process_handle: Known::Unknown,
+            intra_process_callback: Known::Unknown,
+            taken_message: VecDeque::new(),
+            removed: false,
+        }
+    }
+
     pub fn rmw_init(
         &mut self,
         rmw_handle: u64,
@@ -309,6 +352,22 @@ impl Subscriber {
         }
     }
 
+    pub fn rclcpp_intra_process_init(
+        &mut self,
+        rclcpp_intra_process_handle: u64,
+    ) -> Result<(), AlreadyInitializedError> {
+        assert!(!self.is_removed());
+        if self.rclcpp_intra_process_handle.is_unknown() {
+            self.rclcpp_intra_process_handle = Known::new(rclcpp_intra_process_handle);
+            Ok(())
+        } else {
+            Err(AlreadyInitializedError::new(
+                "Subscriber",
+                "rclcpp_ipb_to_subscription",
+            ))
+        }
+    }
+
     pub(crate) fn set_rmw_handle(
         &mut self,
         rmw_subscription_handle: u64,
@@ -343,6 +402,23 @@ impl Subscriber {
         }
     }
 
+    pub fn set_intra_process_callback(
+        &mut self,
+        callback: Arc<Mutex<Callback>>,
+    ) -> Result<(), AlreadySetError<&Self, Arc<Mutex<Callback>>>> {
+        assert!(!self.is_removed());
+        if self.intra_process_callback.is_unknown() {
+            self.intra_process_callback = Known::new(callback);
+            Ok(())
+        } else {
+            Err(AlreadySetError {
+                object: self,
+                new_value: callback,
+                msg: "intra-process callback already set",
+            })
+        }
+    }
+
     pub fn replace_taken_message(
         &mut self,
         message: Arc<Mutex<SubscriptionMessage>>,
@@ -368,6 +444,10 @@ impl Subscriber {
         ret
     }
 
+    pub fn get_id(&self) -> SubscriberId {
+        self.id
+    }
+
     pub fn get_rmw_handle(&self) -> Known<u64> {
         self.rmw_handle
     }
@@ -388,6 +468,16 @@ impl Subscriber {
         self.topic_name.as_deref()
     }
 
+    pub fn get_queue_depth(&self) -> Known<usize> {
+        self.queue_depth
+    }
+
+    /// Returns `true` if the subscription receives the messages published in the same process
+    /// by intra-process communication.
+    pub fn is_intra_process(&self) -> bool {
+        self.rclcpp_intra_process_handle.is_known()
+    }
+
     pub fn get_node(&self) -> Known<ArcWeak<Mutex<Node>>> {
         self.node.clone()
     }
@@ -418,8 +508,9 @@ impl CyclicDependency for Subscriber {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Publisher {
+    id: PublisherId,
     rmw_handle: Known<u64>,
     rcl_handle: Known<u64>,
     rclcpp_handle: Known<u64>,
@@ -434,6 +525,20 @@ pub struct Publisher {
 }
 
 impl Publisher {
+    pub fn new(id: PublisherId) -> Self {
+        Self {
+            id,
+            rmw_handle: Known::Unknown,
+            rcl_handle: Known::Unknown,
+            rclcpp_handle: Known::Unknown,
+            rmw_gid: Known::Unknown,
+            topic_name: Known::Unknown,
+            node: Known::Unknown,
+            queue_depth: Known::Unknown,
+            removed: false,
+        }
+    }
+
     pub fn rmw_init(
         &mut self,
         rmw_handle: u64,
@@ -509,6 +614,10 @@ impl Publisher {
         }
     }
 
+    pub fn get_id(&self) -> PublisherId {
+        self.id
+    }
+
     pub fn get_rmw_handle(&self) -> Known<u64> {
         self.rmw_handle
     }
@@ -525,6 +634,10 @@ impl Publisher {
         self.node.clone()
     }
 
+    pub fn get_queue_depth(&self) -> Known<usize> {
+        self.queue_depth
+    }
+
     pub fn mark_removed(&mut self) {
         self.removed = true;
     }
@@ -563,6 +676,7 @@ impl CyclicDependency for Publisher {
 
 #[derive(Debug)]
 pub struct Service {
+    id: ServiceId,
     rmw_handle: Known<u64>,
     rcl_handle: u64,
     rclcpp_handle: Known<u64>,
@@ -575,8 +689,9 @@ pub struct Service {
 }
 
 impl Service {
-    pub fn new(rcl_handle: u64) -> Self {
+    pub fn new(id: ServiceId, rcl_handle: u64) -> Self {
         Self {
+            id,
             rmw_handle: Known::Unknown,
             rcl_handle,
             rclcpp_handle: Known::Unknown,
@@ -655,8 +770,9 @@ impl CyclicDependency for Service {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Client {
+    id: ClientId,
     rcl_handle: u64,
     rmw_handle: Known<u64>,
     node: Known<ArcWeak<Mutex<Node>>>,
@@ -666,9 +782,10 @@ pub struct Client {
 }
 
 impl Client {
-    pub fn new(id: u64) -> Self {
+    pub fn new(id: ClientId, rcl_handle: u64) -> Self {
         Self {
-            rcl_handle: id,
+            id,
+            rcl_handle,
             rmw_handle: Known::Unknown,
             node: Known::Unknown,
             service_name: Known::Unknown,
@@ -697,6 +814,10 @@ impl Client {
         Ok(())
     }
 
+    pub fn get_node(&self) -> Known<ArcWeak<Mutex<Node>>> {
+        self.node.clone()
+    }
+
     pub fn mark_removed(&mut self) {
         self.removed = true;
     }
@@ -721,6 +842,7 @@ impl CyclicDependency for Client {
 
 #[derive(Debug)]
 pub struct Timer {
+    id: TimerId,
     rcl_handle: u64,
     period: Known<i64>,
 
@@ -731,9 +853,10 @@ pub struct Timer {
 }
 
 impl Timer {
-    pub fn new(id: u64) -> Self {
+    pub fn new(id: TimerId, rcl_handle: u64) -> Self {
         Self {
-            rcl_handle: id,
+            id,
+            rcl_handle,
             period: Known::Unknown,
             callback: Known::Unknown,
             node: Known::Unknown,
@@ -892,6 +1015,7 @@ impl<'a> From<&'a CallbackCaller> for CallbackType {
 
 #[derive(Debug)]
 pub struct Callback {
+    id: CallbackId,
     handle: u64,
     caller: Known<CallbackCaller>,
     name: Known<String>,
@@ -902,42 +1026,78 @@ pub struct Callback {
 }
 
 impl Callback {
-    fn new(handle: u64, caller: CallbackCaller, hostname: String) -> Arc<Mutex<Self>> {
-        Arc::new(Mutex::new(Self {
+    fn new(id: CallbackId, handle: u64, caller: CallbackCaller, hostname: String) -> Self {
+        Self {
+            id,
             handle,
             caller: Known::Known(caller),
             name: Known::Unknown,
             running_instance: None,
             hostname,
             is_removed: false,
-        }))
+        }
     }
 
     pub fn new_subscription(
+        id: CallbackId,
         handle: u64,
         caller: &Arc<Mutex<Subscriber>>,
         hostname: String,
-    ) -> Arc<Mutex<Self>> {
+    ) -> Self {
         let caller = CallbackCaller::Subscription(Arc::downgrade(caller).into());
-        Self::new(handle, caller, hostname)
+        Self::new(id, handle, caller, hostname)
+    }
+
+    /// Creates a callback added to an rclcpp subscription before its initialization.
+    ///
+    /// Its caller is unknown until it is linked by [`Self::link_subscription`].
+    pub fn new_unlinked_subscription(id: CallbackId, handle: u64, hostname: String) -> Self {
+        Self {
+            id,
+            handle,
+            caller: Known::Unknown,
+            name: Known::Unknown,
+            running_instance: None,
+            hostname,
+            is_removed: false,
+        }
+    }
+
+    pub fn link_subscription(
+        &mut self,
+        caller: &Arc<Mutex<Subscriber>>,
+    ) -> Result<(), AlreadySetError<&Self, Arc<Mutex<Subscriber>>>> {
+        assert!(!self.is_removed());
+        if self.caller.is_unknown() {
+            self.caller = Known::Known(CallbackCaller::Subscription(Arc::downgrade(caller).into()));
+            Ok(())
+        } else {
+            Err(AlreadySetError {
+                object: self,
+                new_value: caller.clone(),
+                msg: "callback caller already set",
+            })
+        }
     }
 
     pub fn new_service(
+        id: CallbackId,
         handle: u64,
         caller: &Arc<Mutex<Service>>,
         hostname: String,
-    ) -> Arc<Mutex<Self>> {
+    ) -> Self {
         let caller = CallbackCaller::Service(Arc::downgrade(caller).into());
-        Self::new(handle, caller, hostname)
+        Self::new(id, handle, caller, hostname)
     }
 
     pub fn new_timer(
+        id: CallbackId,
         handle: u64,
         caller: &Arc<Mutex<Timer>>,
         hostname: String,
-    ) -> Arc<Mutex<Self>> {
+    ) -> Self {
         let caller = CallbackCaller::Timer(Arc::downgrade(caller).into());
-        Self::new(handle, caller, hostname)
+        Self::new(id, handle, caller, hostname)
     }
 
     pub fn set_name(&mut self, name: String) -> Result<(), AlreadySetError<&Self, String>> {
@@ -954,6 +1114,10 @@ impl Callback {
         }
     }
 
+    pub fn get_id(&self) -> CallbackId {
+        self.id
+    }
+
     pub fn take_running_instance(&mut self) -> Option<Arc<Mutex<CallbackInstance>>> {
         self.running_instance.take()
     }
@@ -1099,6 +1263,11 @@ impl PublicationMessage {
         self.rmw_publish_time = Known::new(time);
     }
 
+    /// Returns the source timestamp used to match the message with its subscription messages.
+    pub fn get_sender_timestamp(&self) -> Option<Time> {
+        self.sender_timestamp.into()
+    }
+
     pub fn get_rmw_publication_time(&self) -> Option<Time> {
         self.rmw_publish_time.into()
     }
@@ -1133,6 +1302,7 @@ pub struct SubscriptionMessage {
     rmw_receive_time: Known<Time>,
     rcl_receive_time: Known<Time>,
     rclcpp_receive_time: Known<Time>,
+    intra_process: bool,
 }
 
 impl SubscriptionMessage {
@@ -1144,6 +1314,29 @@ impl SubscriptionMessage {
             rmw_receive_time: Known::Unknown,
             rcl_receive_time: Known::Unknown,
             rclcpp_receive_time: Known::Unknown,
+            intra_process: false,
+        }
+    }
+
+    /// Creates a message received through the intra-process ring buffer of the `subscriber`.
+    ///
+    /// The `time` of the ring buffer dequeue is used as the rclcpp receive time.
+    pub fn new_intra_process(
+        subscriber: Arc<Mutex<Subscriber>>,
+        published_message: Option<Arc<Mutex<PublicationMessage>>>,
+        time: Time,
+    ) -> Self {
+        let ptr = published_message
+            .as_ref()
+            .map_or(0, |message| message.lock().unwrap().ptr);
+        Self {
+            ptr,
+            message: published_message.map_or(PartiallyKnown::Unknown, PartiallyKnown::Fully),
+            subscriber: Known::new(subscriber),
+            rmw_receive_time: Known::Unknown,
+            rcl_receive_time: Known::Unknown,
+            rclcpp_receive_time: Known::new(time),
+            intra_process: true,
         }
     }
 
@@ -1238,6 +1431,10 @@ impl SubscriptionMessage {
     pub fn get_subscriber(&self) -> Option<Arc<Mutex<Subscriber>>> {
         self.subscriber.clone().into()
     }
+
+    pub fn is_intra_process(&self) -> bool {
+        self.intra_process
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -1299,10 +1496,24 @@ pub struct CallbackInstance {
     end_time: Known<Time>,
     callback: Arc<Mutex<Callback>>,
     trigger: CallbackTrigger,
+
+    /// Total on-CPU time of the executing thread when the callback started.
+    ///
+    /// It is known only if the kernel scheduler events were traced.
+    thread_on_cpu_time_at_start: Option<i64>,
+    on_cpu_time: Known<i64>,
 }
 
 impl CallbackInstance {
-    pub fn new(callback: Arc<Mutex<Callback>>, start_time: Time) -> Arc<Mutex<Self>> {
+    /// Starts the `callback`.
+    ///
+    /// Subscription callbacks are triggered by the `message` if it is given or by the message
+    /// taken by the subscriber otherwise.
+    pub fn new(
+        callback: Arc<Mutex<Callback>>,
+        message: Option<Arc<Mutex<SubscriptionMessage>>>,
+        start_time: Time,
+    ) -> Arc<Mutex<Self>> {
         let callback_arc = callback;
         let mut callback = callback_arc.lock().unwrap();
         assert!(
@@ -1314,7 +1525,7 @@ impl CallbackInstance {
             CallbackCaller::Subscription(weak) => {
                 let subscriber = weak.get_arc().unwrap();
                 let mut subscriber = subscriber.lock().unwrap();
-                let message = subscriber.take_message().unwrap_or_else(|| {
+                let message = message.or_else(|| subscriber.take_message()).unwrap_or_else(|| {
                     panic!(
                         "Subscriber does not have a message to trigger the callback at time {start_time}.\n{subscriber:#?}\n{callback:#?}",
                     )
@@ -1336,6 +1547,8 @@ impl CallbackInstance {
             end_time: Known::Unknown,
             callback: callback_arc.clone(),
             trigger,
+            thread_on_cpu_time_at_start: None,
+            on_cpu_time: Known::Unknown,
         }));
 
         callback.running_instance = Some(new.clone());
@@ -1352,6 +1565,19 @@ impl CallbackInstance {
         self.end_time = Known::Known(time);
     }
 
+    /// Sets the total on-CPU time of the executing thread at the start of the callback.
+    pub fn set_thread_on_cpu_time_at_start(&mut self, thread_on_cpu_time: i64) {
+        self.thread_on_cpu_time_at_start = Some(thread_on_cpu_time);
+    }
+
+    /// Calculates the on-CPU time of the callback from the total on-CPU time of the
+    /// executing thread at the end of the callback.
+    pub fn set_thread_on_cpu_time_at_end(&mut self, thread_on_cpu_time: i64) {
+        if let Some(at_start) = self.thread_on_cpu_time_at_start {
+            self.on_cpu_time = Known::Known(thread_on_cpu_time - at_start);
+        }
+    }
+
     pub fn get_callback(&self) -> Arc<Mutex<Callback>> {
         self.callback.clone()
     }
@@ -1367,6 +1593,13 @@ impl CallbackInstance {
     pub fn get_trigger(&self) -> &CallbackTrigger {
         &self.trigger
     }
+
+    /// Returns the time the callback spent running on a CPU.
+    ///
+    /// Unlike the duration, it excludes the time in which the thread was preempted or blocked.
+    pub fn get_on_cpu_time(&self) -> Option<i64> {
+        self.on_cpu_time.into()
+    }
 }
 
 #[derive(Debug)]
@@ -1443,3 +1676,311 @@ impl CyclicDependency for SpinInstance {
         self.node.upgrade_in_place()
     }
 }
+
+#[derive(Debug)]
+pub struct LifecycleStateMachine {
+    handle: u64,
+    node: ArcWeak<Mutex<Node>>,
+    state: Known<String>,
+}
+
+impl LifecycleStateMachine {
+    /// Label of the state in which every state machine starts.
+    pub const INITIAL_STATE: &'static str = "unconfigured";
+
+    /// Label of the only state in which the node is expected to do its work.
+    pub const ACTIVE_STATE: &'static str = "active";
+
+    /// Labels of the primary states. The remaining states are transition states.
+    pub const PRIMARY_STATES: [&'static str; 4] =
+        ["unconfigured", "inactive", "active", "finalized"];
+
+    pub fn new(handle: u64, node: &Arc<Mutex<Node>>) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            handle,
+            node: Arc::downgrade(node).into(),
+            state: Known::Known(Self::INITIAL_STATE.to_owned()),
+        }))
+    }
+
+    pub fn is_primary_state(label: &str) -> bool {
+        Self::PRIMARY_STATES.contains(&label)
+    }
+
+    /// Moves the state machine to the `goal_label` state.
+    ///
+    /// If the current state differs from `start_label`, some transitions were not traced.
+    /// The state is then corrected and a warning is logged.
+    pub fn transition(&mut self, start_label: &str, goal_label: &str) {
+        if !self.get_state().eq_inner(&start_label) {
+            log::warn!(
+                "Lifecycle transition from {start_label:?} while in state {}. Missing transition events? {self}",
+                self.state
+            );
+        }
+
+        self.state = Known::Known(goal_label.to_owned());
+    }
+
+    pub fn get_state(&self) -> Known<&str> {
+        self.state.as_deref()
+    }
+
+    pub fn get_node(&self) -> &ArcWeak<Mutex<Node>> {
+        &self.node
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExecutorState {
+    /// The executor searches for a ready handle.
+    GetNextReady,
+    /// The executor waits until some handle is ready.
+    WaitForWork,
+    /// The executor executes a ready handle.
+    Execute,
+}
+
+/// Time interval spent by an executor in a single state.
+#[derive(Debug, Clone, Copy)]
+pub struct ExecutorActivity {
+    pub state: ExecutorState,
+    pub start_time: Time,
+    pub end_time: Time,
+}
+
+impl ExecutorActivity {
+    pub const fn duration(&self) -> i64 {
+        self.end_time.timestamp_nanos() - self.start_time.timestamp_nanos()
+    }
+}
+
+/// Executor running on a single thread.
+///
+/// The instance is created with the first executor event on the thread. A multi-threaded
+/// executor is therefore modeled as multiple instances.
+#[derive(Debug)]
+pub struct Executor {
+    hostname: String,
+    pid: u32,
+    tid: u32,
+    state: Known<(ExecutorState, Time)>,
+    ready_since: Option<Time>,
+}
+
+impl Executor {
+    pub fn new(hostname: &str, pid: u32, tid: u32) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            hostname: hostname.to_owned(),
+            pid,
+            tid,
+            state: Known::Unknown,
+            ready_since: None,
+        }))
+    }
+
+    /// Moves the executor to the `state` and returns the activity in the previous state.
+    ///
+    /// The time when the executor stops waiting for work is remembered as the time since
+    /// which the following executed handles were ready. After waking up, rclcpp searches
+    /// for the ready handle again before executing it.
+    pub fn enter_state(&mut self, state: ExecutorState, time: Time) -> Option<ExecutorActivity> {
+        let previous = std::mem::replace(&mut self.state, Known::Known((state, time)));
+
+        match (previous, state) {
+            (_, ExecutorState::WaitForWork) => self.ready_since = None,
+            (
+                Known::Known((ExecutorState::WaitForWork, _)),
+                ExecutorState::GetNextReady | ExecutorState::Execute,
+            ) => {
+                self.ready_since = Some(time);
+            }
+            _ => {}
+        }
+
+        Option::from(previous).map(|(previous_state, start_time)| ExecutorActivity {
+            state: previous_state,
+            start_time,
+            end_time: time,
+        })
+    }
+
+    pub fn get_hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    pub fn get_pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn get_tid(&self) -> u32 {
+        self.tid
+    }
+
+    /// Returns the time when the executor last woke up with some work to do.
+    ///
+    /// It is unknown until the executor waits for work for the first time.
+    pub fn get_ready_since(&self) -> Option<Time> {
+        self.ready_since
+    }
+}
+
+/// Ring buffer of an rclcpp intra-process buffer (IPB) of a subscription.
+#[derive(Debug)]
+pub struct RingBuffer {
+    handle: u64,
+    capacity: u64,
+    ipb: Known<u64>,
+    subscriber: Known<ArcWeak<Mutex<Subscriber>>>,
+
+    /// Number of messages stored in the buffer.
+    size: u64,
+    /// Stored messages by their index. Messages enqueued by an unknown publication are missing.
+    messages: HashMap<u64, Arc<Mutex<PublicationMessage>>>,
+}
+
+impl RingBuffer {
+    pub fn new(handle: u64, capacity: u64) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            handle,
+            capacity,
+            ipb: Known::Unknown,
+            subscriber: Known::Unknown,
+            size: 0,
+            messages: HashMap::new(),
+        }))
+    }
+
+    pub fn set_ipb(&mut self, ipb: u64) {
+        assert!(
+            self.ipb.is_unknown(),
+            "RingBuffer ipb already set. {self:#?}"
+        );
+        self.ipb = Known::new(ipb);
+    }
+
+    pub fn set_subscriber(&mut self, subscriber: &Arc<Mutex<Subscriber>>) {
+        assert!(
+            self.subscriber.is_unknown(),
+            "RingBuffer subscriber already set. {self:#?}"
+        );
+        self.subscriber = Known::new(Arc::downgrade(subscriber).into());
+    }
+
+    /// Stores the `message` at `index` and returns the overwritten message if it is known.
+    ///
+    /// The `size` is the number of messages after the enqueue as reported by rclcpp.
+    /// It exceeds the capacity by one when the oldest message was overwritten.
+    pub fn enqueue(
+        &mut self,
+        index: u64,
+        size: u64,
+        message: Option<Arc<Mutex<PublicationMessage>>>,
+    ) -> Option<Arc<Mutex<PublicationMessage>>> {
+        self.size = size.min(self.capacity);
+        match message {
+            Some(message) => self.messages.insert(index, message),
+            None => self.messages.remove(&index),
+        }
+    }
+
+    /// Removes the message at `index` and returns it if it is known.
+    ///
+    /// The `size` is the number of messages after the dequeue.
+    pub fn dequeue(&mut self, index: u64, size: u64) -> Option<Arc<Mutex<PublicationMessage>>> {
+        self.size = size;
+        self.messages.remove(&index)
+    }
+
+    /// Removes all messages and returns the known ones.
+    pub fn clear(&mut self) -> Vec<Arc<Mutex<PublicationMessage>>> {
+        self.size = 0;
+        self.messages.drain().map(|(_, message)| message).collect()
+    }
+
+    pub fn get_handle(&self) -> u64 {
+        self.handle
+    }
+
+    pub fn get_capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn get_subscriber(&self) -> Option<Arc<Mutex<Subscriber>>> {
+        Option::<&ArcWeak<_>>::from(self.subscriber.as_ref())?.get_arc()
+    }
+}
+
+/// Operating system thread observed by the kernel scheduler events.
+#[derive(Debug)]
+pub struct Thread {
+    hostname: String,
+    tid: u32,
+    name: String,
+
+    /// CPU on which the thread runs and the time since when it runs there.
+    running: Option<(u32, Time)>,
+    /// Time spent on a CPU in the finished scheduling intervals.
+    on_cpu_time: i64,
+}
+
+impl Thread {
+    pub fn new(hostname: &str, tid: u32, name: &str) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            hostname: hostname.to_owned(),
+            tid,
+            name: name.to_owned(),
+            running: None,
+            on_cpu_time: 0,
+        }))
+    }
+
+    /// Updates the thread name, which can change during the thread lifetime.
+    pub fn set_name(&mut self, name: &str) {
+        if self.name != name {
+            name.clone_into(&mut self.name);
+        }
+    }
+
+    /// Marks the thread as running on `cpu` since `time`.
+    pub fn schedule_in(&mut self, cpu: u32, time: Time) {
+        if let Some((running_cpu, _)) = self.running {
+            log::debug!(
+                "Thread {self} scheduled on CPU {cpu} while running on CPU {running_cpu}. Missing sched_switch event?"
+            );
+            self.schedule_out(time);
+        }
+        self.running = Some((cpu, time));
+    }
+
+    /// Marks the thread as not running since `time`.
+    pub fn schedule_out(&mut self, time: Time) {
+        if let Some((_, since)) = self.running.take() {
+            self.on_cpu_time += time.timestamp_nanos() - since.timestamp_nanos();
+        }
+    }
+
+    pub fn get_hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    pub fn get_tid(&self) -> u32 {
+        self.tid
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the total time spent by the thread on a CPU up to `time`.
+    pub fn get_on_cpu_time(&self, time: Time) -> i64 {
+        let running_time = self.running.map_or(0, |(_, since)| {
+            time.timestamp_nanos() - since.timestamp_nanos()
+        });
+        self.on_cpu_time + running_time
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::CStr;
+use std::fmt::Write;
+
+use bt2_sys::field::BtFieldClassConst;
+use bt2_sys::iterator::MessageIterator;
+use bt2_sys::message::{BtMessageConstCast, BtMessageType};
+use bt2_sys::stream::BtStreamConst;
+use bt2_sys::trace::BtEnvironmentEntry;
+use color_eyre::eyre::bail;
+
+use crate::analyses::event_iterator::convert;
+use crate::model::Time;
+use crate::raw_events;
+
+/// Context of the events needed by the processing or by some analyses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, derive_more::Display)]
+pub enum RequiredContext {
+    /// The `hostname` environment entry of the trace.
+    #[display("hostname")]
+    Hostname,
+    /// The `cpu_id` field of the packet context.
+    #[display("cpu_id")]
+    CpuId,
+    #[display("vpid")]
+    Vpid,
+    #[display("vtid")]
+    Vtid,
+    /// The name of the process, needed by the process filters.
+    #[display("procname")]
+    Procname,
+}
+
+/// Tracing domain of a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, derive_more::Display)]
+pub enum Domain {
+    #[display("userspace")]
+    Userspace,
+    #[display("kernel")]
+    Kernel,
+}
+
+/// Contexts missing in the streams of the analyzed traces.
+///
+/// The ROS 2 objects are identified by the host and the process, so the userspace events
+/// cannot be processed at all without the `hostname` and the `vpid`. Missing other contexts
+/// only disables the analyses which need them.
+#[derive(Debug, Default)]
+pub struct TraceContexts {
+    /// Names of the streams missing each context.
+    missing: BTreeMap<(Domain, RequiredContext), BTreeSet<String>>,
+    /// Time of the first event of the traces.
+    trace_start: Option<Time>,
+}
+
+impl TraceContexts {
+    /// Inspects the classes of the streams in the traces before their events are processed.
+    ///
+    /// The streams beginning after the first event are checked by the event iterator.
+    pub fn check_traces<L: clap_verbosity_flag::LogLevel>(
+        trace_paths: &[&CStr],
+        verbosity: &clap_verbosity_flag::Verbosity<L>,
+    ) -> Self {
+        let log_level = convert(
+            verbosity
+                .log_level()
+                .unwrap_or(clap_verbosity_flag::log::Level::Error),
+        );
+
+        let mut contexts = Self::default();
+        for message in MessageIterator::new(trace_paths, log_level) {
+            match message.get_type() {
+                BtMessageType::Event => {
+                    contexts.trace_start =
+                        Some(raw_events::time_from_message(&message.into_event_msg()));
+                    break;
+                }
+                BtMessageType::StreamBeginning => {
+                    let BtMessageConstCast::StreamBeginning(message) = message.cast() else {
+                        unreachable!();
+                    };
+                    contexts.check_stream(&message.get_stream());
+                }
+                _ => {}
+            }
+        }
+
+        contexts
+    }
+
+    /// Records the contexts missing in the class of the `stream`.
+    pub fn check_stream(&mut self, stream: &BtStreamConst) {
+        let trace = stream.get_trace();
+        let domain = match trace.get_environment_entry_by_name_cstr(c"domain") {
+            Some(BtEnvironmentEntry::String(domain))
+                if domain.get().is_ok_and(|domain| domain == "kernel") =>
+            {
+                Domain::Kernel
+            }
+            _ => Domain::Userspace,
+        };
+
+        let has_member = |field_class: Option<BtFieldClassConst>, name: &CStr| {
+            field_class
+                .and_then(BtFieldClassConst::into_struct)
+                .is_some_and(|field_class| field_class.get_member_by_name_cstr(name).is_some())
+        };
+        let stream_class = stream.get_class();
+
+        let mut missing = Vec::new();
+        if trace
+            .get_environment_entry_by_name_cstr(c"hostname")
+            .is_none()
+        {
+            missing.push(RequiredContext::Hostname);
+        }
+        if !has_member(stream_class.get_packet_context_field_class(), c"cpu_id") {
+            missing.push(RequiredContext::CpuId);
+        }
+        // The process context of kernel events is taken from the scheduler events.
+        if domain == Domain::Userspace {
+            for (context, name) in [
+                (RequiredContext::Vpid, c"vpid"),
+                (RequiredContext::Vtid, c"vtid"),
+                (RequiredContext::Procname, c"procname"),
+            ] {
+                if !has_member(stream_class.get_event_common_context_field_class(), name) {
+                    missing.push(context);
+                }
+            }
+        }
+
+        let stream_name = stream.get_name().unwrap_or("<unnamed>");
+        for context in missing {
+            self.missing
+                .entry((domain, context))
+                .or_default()
+                .insert(stream_name.to_owned());
+        }
+    }
+
+    pub const fn trace_start(&self) -> Option<Time> {
+        self.trace_start
+    }
+
+    fn is_missing(&self, domain: Domain, context: RequiredContext) -> bool {
+        self.missing.contains_key(&(domain, context))
+    }
+
+    /// Returns an error describing the missing contexts if the userspace events cannot
+    /// be processed.
+    pub fn ensure_processable(&self) -> color_eyre::Result<()> {
+        if self.is_missing(Domain::Userspace, RequiredContext::Hostname)
+            || self.is_missing(Domain::Userspace, RequiredContext::Vpid)
+        {
+            bail!(
+                "The ROS 2 events cannot be processed without the hostname and vpid.\n{}",
+                self.report()
+            );
+        }
+        Ok(())
+    }
+
+    /// Logs a warning describing the missing contexts, if any.
+    pub fn log_missing(&self) {
+        if !self.missing.is_empty() {
+            log::warn!("{}", self.report());
+        }
+    }
+
+    /// Returns `true` if the `analysis` can run with the contexts in the trace.
+    ///
+    /// Logs a warning with the missing contexts otherwise.
+    pub fn supports(&self, analysis: &str, required: &[(Domain, RequiredContext)]) -> bool {
+        let missing: Vec<_> = required
+            .iter()
+            .filter(|(domain, context)| self.is_missing(*domain, *context))
+            .map(|(domain, context)| format!("{domain} {context}"))
+            .collect();
+        if missing.is_empty() {
+            return true;
+        }
+
+        log::warn!(
+            "Skipping the {analysis} analysis. The trace is missing: {}",
+            missing.join(", ")
+        );
+        false
+    }
+
+    /// Describes the missing contexts and how to record them.
+    pub fn report(&self) -> String {
+        let mut report = String::from("The trace is missing contexts:\n");
+        for ((domain, context), streams) in &self.missing {
+            let example = streams.first().unwrap();
+            writeln!(
+                report,
+                "  - {context} in {} {domain} stream(s), e.g., {example}",
+                streams.len()
+            )
+            .unwrap();
+        }
+
+        let add_context_types: Vec<_> = [
+            RequiredContext::Vpid,
+            RequiredContext::Vtid,
+            RequiredContext::Procname,
+        ]
+        .into_iter()
+        .filter(|context| self.is_missing(Domain::Userspace, *context))
+        .map(|context| format!("--type={context}"))
+        .collect();
+        if !add_context_types.is_empty() {
+            writeln!(
+                report,
+                "Add the contexts to the tracing session before starting it:\n  \
+                lttng add-context --userspace {}",
+                add_context_types.join(" ")
+            )
+            .unwrap();
+        }
+        if self.is_missing(Domain::Userspace, RequiredContext::Hostname)
+            || self.is_missing(Domain::Kernel, RequiredContext::Hostname)
+            || self.is_missing(Domain::Userspace, RequiredContext::CpuId)
+            || self.is_missing(Domain::Kernel, RequiredContext::CpuId)
+        {
+            writeln!(
+                report,
+                "LTTng always records the hostname and cpu_id. \
+                The trace was probably written or converted by another tool."
+            )
+            .unwrap();
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_vtid_skips_thread_analyses() {
+        let mut contexts = TraceContexts::default();
+        contexts.missing.insert(
+            (Domain::Userspace, RequiredContext::Vtid),
+            BTreeSet::from(["ust/uid/1000/64-bit/ros2_0".to_owned()]),
+        );
+
+        assert!(contexts.ensure_processable().is_ok());
+        assert!(contexts.supports("callback duration", &[]));
+        assert!(!contexts.supports(
+            "executor scheduling",
+            &[(Domain::Userspace, RequiredContext::Vtid)]
+        ));
+        assert!(
+            contexts
+                .report()
+                .contains("lttng add-context --userspace --type=vtid\n")
+        );
+
+        contexts.missing.insert(
+            (Domain::Userspace, RequiredContext::Vpid),
+            BTreeSet::from(["ust/uid/1000/64-bit/ros2_0".to_owned()]),
+        );
+        assert!(contexts.ensure_processable().is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use derive_more::derive::{Display, From};
+
+use crate::model::Thread;
+
+use super::RefCount;
+
+#[derive(Debug, Clone, From, Display)]
+pub enum Event {
+    #[display("sched_switch({_0})")]
+    SchedSwitch(SchedSwitch),
+    #[display("sched_wakeup({_0})")]
+    SchedWakeup(SchedWakeup),
+}
+
+#[derive(Debug, Clone)]
+pub struct SchedSwitch {
+    pub cpu: u32,
+    /// Thread switched out of the CPU. It is `None` for the idle task.
+    pub prev: Option<RefCount<Thread>>,
+    /// `true` if the previous thread was preempted while runnable.
+    pub prev_preempted: bool,
+    /// Thread switched to the CPU. It is `None` for the idle task.
+    pub next: Option<RefCount<Thread>>,
+}
+
+fn fmt_thread(
+    thread: Option<&RefCount<Thread>>,
+    f: &mut std::fmt::Formatter<'_>,
+) -> std::fmt::Result {
+    match thread {
+        Some(thread) => write!(f, "{}", thread.lock().unwrap()),
+        None => write!(f, "idle"),
+    }
+}
+
+impl std::fmt::Display for SchedSwitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cpu={}, prev=", self.cpu)?;
+        fmt_thread(self.prev.as_ref(), f)?;
+        write!(f, ", prev_preempted={}, next=", self.prev_preempted)?;
+        fmt_thread(self.next.as_ref(), f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SchedWakeup {
+    pub thread: RefCount<Thread>,
+    /// Thread running on the CPU which recorded the wakeup.
+    ///
+    /// It is `None` if the CPU was idle, e.g., when woken by an interrupt, or if the running
+    /// thread is not known yet.
+    pub waker: Option<RefCount<Thread>>,
+    /// Name of the waker at the time of the wakeup. The thread can be renamed later.
+    pub waker_name: Option<String>,
+    pub target_cpu: u32,
+}
+
+impl std::fmt::Display for SchedWakeup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "thread={}, waker=", self.thread.lock().unwrap())?;
+        fmt_thread(self.waker.as_ref(), f)?;
+        write!(f, ", target_cpu={}", self.target_cpu)
+    }
+}
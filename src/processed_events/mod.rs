@@ -8,12 +8,15 @@ pub mod ros2;
 
 pub mod r2r;
 
+pub mod kernel;
+
 type RefCount<T> = Arc<Mutex<T>>;
 
 #[derive(Debug, Clone, From)]
 pub enum Event {
     Ros2(ros2::Event),
     R2r(r2r::Event),
+    Kernel(kernel::Event),
 }
 
 impl std::fmt::Display for Event {
@@ -21,6 +24,7 @@ impl std::fmt::Display for Event {
         match self {
             Self::Ros2(e) => write!(f, "ros2:{e}"),
             Self::R2r(e) => write!(f, "r2r:{e}"),
+            Self::Kernel(e) => write!(f, "{e}"),
         }
     }
 }
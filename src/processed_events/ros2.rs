@@ -1,8 +1,9 @@
 use derive_more::derive::{Display, From};
 
 use crate::model::{
-    Callback, CallbackInstance, Client, Node, PublicationMessage, Publisher, Service, Subscriber,
-    SubscriptionMessage, Timer,
+    Callback, CallbackId, CallbackInstance, Client, Executor, ExecutorActivity,
+    LifecycleStateMachine, Node, PublicationMessage, Publisher, RingBuffer, Service, Subscriber,
+    SubscriptionMessage, Time, Timer,
 };
 
 use super::RefCount;
@@ -80,6 +81,39 @@ pub enum Event {
 
     #[display("callback_end({_0})")]
     CallbackEnd(CallbackEnd),
+
+    #[display("rcl_lifecycle_state_machine_init({_0})")]
+    RclLifecycleStateMachineInit(RclLifecycleStateMachineInit),
+
+    #[display("rcl_lifecycle_transition({_0})")]
+    RclLifecycleTransition(RclLifecycleTransition),
+
+    #[display("rclcpp_executor_get_next_ready({_0})")]
+    RclcppExecutorGetNextReady(RclcppExecutorGetNextReady),
+
+    #[display("rclcpp_executor_wait_for_work({_0})")]
+    RclcppExecutorWaitForWork(RclcppExecutorWaitForWork),
+
+    #[display("rclcpp_executor_execute({_0})")]
+    RclcppExecutorExecute(RclcppExecutorExecute),
+
+    #[display("rclcpp_construct_ring_buffer({_0})")]
+    RclcppConstructRingBuffer(RclcppConstructRingBuffer),
+
+    #[display("rclcpp_buffer_to_ipb({_0})")]
+    RclcppBufferToIpb(RclcppBufferToIpb),
+
+    #[display("rclcpp_ipb_to_subscription({_0})")]
+    RclcppIpbToSubscription(RclcppIpbToSubscription),
+
+    #[display("rclcpp_ring_buffer_enqueue({_0})")]
+    RclcppRingBufferEnqueue(RclcppRingBufferEnqueue),
+
+    #[display("rclcpp_ring_buffer_dequeue({_0})")]
+    RclcppRingBufferDequeue(RclcppRingBufferDequeue),
+
+    #[display("rclcpp_ring_buffer_clear({_0})")]
+    RclcppRingBufferClear(RclcppRingBufferClear),
 }
 
 #[derive(Debug, Clone, Display)]
@@ -241,10 +275,114 @@ pub struct RclcppCallbackRegister {
 pub struct CallbackStart {
     pub is_intra_process: bool,
     pub callback: RefCount<CallbackInstance>,
+    /// Identifier of the executed callback.
+    pub callback_id: CallbackId,
 }
 
 #[derive(Debug, Clone, Display)]
 #[display("CallbackInstance({})", callback.lock().unwrap())]
 pub struct CallbackEnd {
     pub callback: RefCount<CallbackInstance>,
+    /// Identifier of the executed callback.
+    pub callback_id: CallbackId,
+}
+
+// Lifecycle
+
+#[derive(Debug, Clone, Display)]
+#[display("StateMachine({})", state_machine.lock().unwrap())]
+pub struct RclLifecycleStateMachineInit {
+    pub state_machine: RefCount<LifecycleStateMachine>,
+}
+
+#[derive(Debug, Clone, Display)]
+#[display("start={start_label:?}, goal={goal_label:?}, StateMachine({})", state_machine.lock().unwrap())]
+pub struct RclLifecycleTransition {
+    pub state_machine: RefCount<LifecycleStateMachine>,
+    pub start_label: String,
+    pub goal_label: String,
+}
+
+// Executor
+
+#[derive(Debug, Clone, Display)]
+#[display("Executor({})", executor.lock().unwrap())]
+pub struct RclcppExecutorGetNextReady {
+    pub executor: RefCount<Executor>,
+    /// Activity of the executor finished by this event.
+    pub previous: Option<ExecutorActivity>,
+}
+
+#[derive(Debug, Clone, Display)]
+#[display("timeout={timeout}, Executor({})", executor.lock().unwrap())]
+pub struct RclcppExecutorWaitForWork {
+    pub executor: RefCount<Executor>,
+    /// Timeout in nanoseconds. Negative value means no timeout.
+    pub timeout: i64,
+    /// Activity of the executor finished by this event.
+    pub previous: Option<ExecutorActivity>,
+}
+
+#[derive(Debug, Clone, Display)]
+#[display("handle={handle:#x}, Executor({})", executor.lock().unwrap())]
+pub struct RclcppExecutorExecute {
+    pub executor: RefCount<Executor>,
+    pub handle: u64,
+    /// Activity of the executor finished by this event.
+    pub previous: Option<ExecutorActivity>,
+    /// Time since which the executed handle was ready at the latest.
+    pub ready_since: Option<Time>,
+}
+
+// Intra-process communication
+
+#[derive(Debug, Clone, Display)]
+#[display("RingBuffer({})", ring_buffer.lock().unwrap())]
+pub struct RclcppConstructRingBuffer {
+    pub ring_buffer: RefCount<RingBuffer>,
+}
+
+#[derive(Debug, Clone, Display)]
+#[display("RingBuffer({})", ring_buffer.lock().unwrap())]
+pub struct RclcppBufferToIpb {
+    pub ring_buffer: RefCount<RingBuffer>,
+}
+
+#[derive(Debug, Clone, Display)]
+#[display("RingBuffer({}), Subscriber({})", ring_buffer.lock().unwrap(), subscriber.lock().unwrap())]
+pub struct RclcppIpbToSubscription {
+    pub ring_buffer: RefCount<RingBuffer>,
+    pub subscriber: RefCount<Subscriber>,
+}
+
+#[derive(Debug, Clone, Display)]
+#[display("overwritten={overwritten}, RingBuffer({})", ring_buffer.lock().unwrap())]
+pub struct RclcppRingBufferEnqueue {
+    pub ring_buffer: RefCount<RingBuffer>,
+    /// The enqueued message if its intra-process publication is known.
+    pub message: Option<RefCount<PublicationMessage>>,
+    /// Whether the oldest message was overwritten (dropped) because the buffer was full.
+    pub overwritten: bool,
+    /// The overwritten message if it is known.
+    pub overwritten_message: Option<RefCount<PublicationMessage>>,
+    /// Number of messages in the buffer after the enqueue.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Display)]
+#[display("RingBuffer({})", ring_buffer.lock().unwrap())]
+pub struct RclcppRingBufferDequeue {
+    pub ring_buffer: RefCount<RingBuffer>,
+    /// The message taken by the subscriber if the subscriber is known.
+    pub message: Option<RefCount<SubscriptionMessage>>,
+    /// Number of messages in the buffer after the dequeue.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Display)]
+#[display("RingBuffer({})", ring_buffer.lock().unwrap())]
+pub struct RclcppRingBufferClear {
+    pub ring_buffer: RefCount<RingBuffer>,
+    /// Known messages removed from the buffer.
+    pub cleared_messages: Vec<RefCount<PublicationMessage>>,
 }
@@ -1,6 +1,7 @@
 use std::sync::{Arc, Mutex};
 
 use derive_more::derive::Display;
+use serde::Serialize;
 use thiserror::Error;
 
 use crate::events_common::Context;
@@ -167,7 +168,7 @@ impl Drop for Object {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
 pub enum ObjectType {
     Node,
     Subscriber,
@@ -178,6 +179,8 @@ pub enum ObjectType {
     Callback,
     PublishedMessage,
     SubscribedMessage,
+    LifecycleStateMachine,
+    RingBuffer,
 }
 
 #[derive(Debug, Error)]
@@ -261,8 +264,16 @@ impl NotFound {
         Self::new(key, "id", ObjectType::Callback)
     }
 
-    pub fn published_message(key: u64) -> Self {
-        Self::new(key, "address", ObjectType::PublishedMessage)
+    pub fn lifecycle_state_machine(key: u64) -> Self {
+        Self::new(key, "state_machine", ObjectType::LifecycleStateMachine)
+    }
+
+    pub fn ring_buffer(key: u64) -> Self {
+        Self::new(key, "buffer", ObjectType::RingBuffer)
+    }
+
+    pub fn ring_buffer_by_ipb(key: u64) -> Self {
+        Self::new(key, "ipb", ObjectType::RingBuffer)
     }
 
     pub fn dependent_object(self, object: impl Into<Object>) -> ObjectMissingDependency {
@@ -360,3 +371,44 @@ pub struct ProcessingEvent {
     #[source]
     cause: Causes,
 }
+
+/// Kind of an error returned by the processor, used to aggregate the errors in lenient mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    AlreadyExists,
+    NotFound,
+    ObjectMissingDependency,
+    AlreadyInitialized,
+    /// Error not caused by a known or missing object, e.g., an inconsistent event.
+    Other,
+}
+
+impl ProcessingEvent {
+    /// Returns the kind of the error and the type of the object it concerns.
+    ///
+    /// For [`NotFound`] and [`ObjectMissingDependency`], it is the type of the missing object.
+    pub fn classify(&self) -> (ErrorKind, ObjectType) {
+        match &self.cause {
+            Causes::AlreadyExists(error) => (ErrorKind::AlreadyExists, error.new_object.as_type()),
+            Causes::NotFound(error) => (ErrorKind::NotFound, error.object_type),
+            Causes::ObjectMissingDependency(error) => {
+                (ErrorKind::ObjectMissingDependency, error.cause.object_type)
+            }
+            Causes::AlreadyInitialized(_, object) => {
+                (ErrorKind::AlreadyInitialized, object.as_type())
+            }
+        }
+    }
+}
+
+/// Returns the kind of the `error` returned by the processor and the type of the object
+/// it concerns, if known.
+pub fn classify_error(error: &color_eyre::Report) -> (ErrorKind, Option<ObjectType>) {
+    error
+        .downcast_ref::<ProcessingEvent>()
+        .map_or((ErrorKind::Other, None), |error| {
+            let (kind, object_type) = error.classify();
+            (kind, Some(object_type))
+        })
+}
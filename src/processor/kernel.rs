@@ -0,0 +1,111 @@
+use std::sync::{Arc, Mutex};
+
+use crate::events_common::Context;
+use crate::model::{Thread, Time};
+use crate::{processed_events, raw_events};
+
+/// The kernel events without a CPU are left out when they are decoded.
+const KERNEL_CPU_ID: &str = "Kernel events always have a cpu_id";
+
+/// Bits of the `prev_state` of `sched_switch` reporting that the task stopped being runnable.
+///
+/// A preempted task reports `0` or, since Linux 4.14, `TASK_REPORT_MAX` (shown as `R+`),
+/// which is above all of these bits.
+const SLEEP_STATE_MASK: i64 = 0xff;
+
+impl super::Processor {
+    pub fn process_raw_kernel_event(
+        &mut self,
+        event: &raw_events::kernel::Event,
+        context: &Context,
+        time: Time,
+    ) -> processed_events::kernel::Event {
+        match event {
+            raw_events::kernel::Event::SchedSwitch(event) => {
+                self.process_sched_switch(event, time, context).into()
+            }
+            raw_events::kernel::Event::SchedWakeup(event) => {
+                self.process_sched_wakeup(event, context).into()
+            }
+        }
+    }
+
+    /// Returns the thread with `tid` on the host of the `context`.
+    ///
+    /// The idle task (tid 0) runs on all CPUs at once, so it is not represented by a thread.
+    fn get_or_create_thread(
+        &mut self,
+        tid: i32,
+        name: &str,
+        context: &Context,
+    ) -> Option<Arc<Mutex<Thread>>> {
+        let tid = u32::try_from(tid).ok().filter(|&tid| tid != 0)?;
+        let host_id = self.host_to_host_id(context.hostname());
+
+        let thread = self
+            .threads
+            .entry((host_id, tid))
+            .or_insert_with(|| Thread::new(context.hostname(), tid, name));
+        thread.lock().unwrap().set_name(name);
+        Some(thread.clone())
+    }
+
+    fn process_sched_switch(
+        &mut self,
+        event: &raw_events::kernel::SchedSwitch,
+        time: Time,
+        context: &Context,
+    ) -> processed_events::kernel::SchedSwitch {
+        let cpu = context.cpu_id().expect(KERNEL_CPU_ID);
+
+        let prev = self.get_or_create_thread(event.prev_tid, &event.prev_comm, context);
+        if let Some(prev) = &prev {
+            prev.lock().unwrap().schedule_out(time);
+        }
+
+        let next = self.get_or_create_thread(event.next_tid, &event.next_comm, context);
+        if let Some(next) = &next {
+            next.lock().unwrap().schedule_in(cpu, time);
+        }
+
+        let cpu_key = (self.host_to_host_id(context.hostname()), cpu);
+        match &next {
+            Some(next) => self.running_threads_by_cpu.insert(cpu_key, next.clone()),
+            None => self.running_threads_by_cpu.remove(&cpu_key),
+        };
+
+        processed_events::kernel::SchedSwitch {
+            cpu,
+            prev,
+            prev_preempted: event.prev_state & SLEEP_STATE_MASK == 0,
+            next,
+        }
+    }
+
+    fn process_sched_wakeup(
+        &mut self,
+        event: &raw_events::kernel::SchedWakeup,
+        context: &Context,
+    ) -> processed_events::kernel::SchedWakeup {
+        let thread = self
+            .get_or_create_thread(event.tid, &event.comm, context)
+            .expect("The idle task is never woken up");
+
+        // The wakeup is recorded on the CPU of the waker.
+        let host_id = self.host_to_host_id(context.hostname());
+        let waker = self
+            .running_threads_by_cpu
+            .get(&(host_id, context.cpu_id().expect(KERNEL_CPU_ID)))
+            .cloned();
+        let waker_name = waker
+            .as_ref()
+            .map(|waker| waker.lock().unwrap().get_name().to_owned());
+
+        processed_events::kernel::SchedWakeup {
+            thread,
+            waker,
+            waker_name,
+            target_cpu: event.target_cpu.try_into().unwrap_or_default(),
+        }
+    }
+}
@@ -1,22 +1,27 @@
 mod error;
+mod kernel;
 mod r2r;
 mod ros2;
 
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use color_eyre::{Report, Result};
 use thiserror::Error;
 
 use crate::events_common::{Context, Time};
+use crate::model::arena::{Objects, ObjectsView};
 use crate::model::{
-    Callback, Client, Node, PublicationMessage, Publisher, Service, Subscriber,
-    SubscriptionMessage, Timer,
+    Callback, Client, Executor, LifecycleStateMachine, Node, PublicationMessage, Publisher,
+    RingBuffer, Service, Subscriber, SubscriptionMessage, Thread, Timer,
 };
 use crate::utils::Known;
 use crate::{processed_events, raw_events};
 
+pub use error::{ErrorKind, ObjectType, classify_error};
+
 pub enum MaybeProcessed<P, R> {
     Processed(P),
     Raw(R),
@@ -144,15 +149,38 @@ impl MapGetAsResult<Id<u64>, Service> for HashMap<Id<u64>, Arc<Mutex<Service>>>
     }
 }
 
+/// Publication message waiting to be taken by the subscribers.
+#[derive(Debug)]
+struct PendingPublication {
+    message: Arc<Mutex<PublicationMessage>>,
+    /// Number of subscribers on the topic at the time of publication which did not take
+    /// the message yet.
+    ///
+    /// It is unknown if the topic of the message is unknown or if no subscriber was
+    /// initialized on the topic yet.
+    remaining_takes: Option<usize>,
+}
+
 #[derive(Debug, Default)]
 pub struct Processor {
     hostname_to_host_id: HashMap<String, u32>,
 
+    /// ROS 2 distribution of each process detected from the `rcl_init` event.
+    distros: HashMap<ContextId, raw_events::ros2::RosDistro>,
+
+    /// Every created object by its stable identifier, including the replaced ones.
+    objects: Objects,
+
     nodes_by_rcl: HashMap<Id<u64>, Arc<Mutex<Node>>>,
 
     subscribers_by_rmw: HashMap<Id<u64>, Arc<Mutex<Subscriber>>>,
     subscribers_by_rcl: HashMap<Id<u64>, Arc<Mutex<Subscriber>>>,
     subscribers_by_rclcpp: HashMap<Id<u64>, Arc<Mutex<Subscriber>>>,
+    /// Id by address of rclcpp `SubscriptionIntraProcess`
+    subscribers_by_rclcpp_intra_process: HashMap<Id<u64>, Arc<Mutex<Subscriber>>>,
+    /// Callbacks added to rclcpp subscriptions before their initialization by the address
+    /// of the rclcpp subscription
+    unlinked_subscription_callbacks: HashMap<Id<u64>, Arc<Mutex<Callback>>>,
 
     publishers_by_rmw: HashMap<Id<u64>, Arc<Mutex<Publisher>>>,
     publishers_by_rcl: HashMap<Id<u64>, Arc<Mutex<Publisher>>>,
@@ -165,8 +193,18 @@ pub struct Processor {
 
     callbacks_by_id: HashMap<Id<u64>, Arc<Mutex<Callback>>>,
 
+    lifecycle_state_machines: HashMap<Id<u64>, Arc<Mutex<LifecycleStateMachine>>>,
+
+    /// Id by thread id
+    executors_by_thread: HashMap<Id<u32>, Arc<Mutex<Executor>>>,
+
+    /// Id by buffer address
+    ring_buffers: HashMap<Id<u64>, Arc<Mutex<RingBuffer>>>,
+    /// Id by intra-process buffer address
+    ring_buffers_by_ipb: HashMap<Id<u64>, Arc<Mutex<RingBuffer>>>,
+
     /// Id by publication timestamp and topic
-    published_messages: HashMap<(i64, Known<String>), Arc<Mutex<PublicationMessage>>>,
+    published_messages: HashMap<(i64, Known<String>), PendingPublication>,
     /// Id by message ptr
     received_messages: HashMap<Id<u64>, Arc<Mutex<SubscriptionMessage>>>,
 
@@ -175,6 +213,22 @@ pub struct Processor {
     published_messages_by_rclcpp: HashMap<Id<u64>, Arc<Mutex<PublicationMessage>>>,
     /// Id by message ptr
     published_messages_by_rcl: HashMap<Id<u64>, Arc<Mutex<PublicationMessage>>>,
+    /// Last intra-process publication by thread id
+    intra_process_messages_by_thread: HashMap<Id<u32>, Arc<Mutex<PublicationMessage>>>,
+    /// Last subscriber initialized by `rcl_subscription_init` by thread id
+    rcl_subscribers_by_thread: HashMap<Id<u32>, Arc<Mutex<Subscriber>>>,
+
+    /// Threads observed by kernel scheduler events by host id and thread id
+    threads: HashMap<(u32, u32), Arc<Mutex<Thread>>>,
+    /// Thread running on a CPU by host id and CPU id. Idle CPUs are missing.
+    running_threads_by_cpu: HashMap<(u32, u32), Arc<Mutex<Thread>>>,
+
+    /// Number of subscribers initialized on each topic and not removed yet.
+    subscriber_counts: HashMap<String, usize>,
+    /// Nanoseconds after which the messages not taken by all subscribers are released.
+    message_horizon: Option<i64>,
+    /// Time of the last release of the messages older than the horizon.
+    last_release: Option<Time>,
 }
 
 impl Processor {
@@ -194,6 +248,99 @@ impl Processor {
             .or_insert(next_id)
     }
 
+    /// Releases the published messages not taken by all subscribers when they are older than
+    /// the `horizon`, e.g., because a subscriber never takes them.
+    ///
+    /// The messages are released once all subscribers took them regardless of the horizon.
+    /// Without the horizon, such messages are kept until the end of the trace.
+    pub fn set_message_horizon(&mut self, horizon: Duration) {
+        self.message_horizon = Some(
+            horizon
+                .as_nanos()
+                .try_into()
+                .expect("Message horizon overflow"),
+        );
+    }
+
+    /// Releases the messages published before the message horizon.
+    ///
+    /// The messages are checked once per horizon, so they are kept at most twice as long.
+    fn release_old_messages(&mut self, time: Time) {
+        let Some(horizon) = self.message_horizon else {
+            return;
+        };
+        let last_release = *self.last_release.get_or_insert(time);
+        if time.timestamp_nanos() - last_release.timestamp_nanos() < horizon {
+            return;
+        }
+        self.last_release = Some(time);
+
+        let oldest = Time::from_nanos(time.timestamp_nanos() - horizon);
+        let is_recent = |message_time: Option<Time>| message_time.is_none_or(|t| t >= oldest);
+        self.published_messages
+            .retain(|_, pending| is_recent(pending.message.lock().unwrap().get_publication_time()));
+        for messages in [
+            &mut self.published_messages_by_rclcpp,
+            &mut self.published_messages_by_rcl,
+        ] {
+            messages.retain(|_, message| is_recent(message.lock().unwrap().get_publication_time()));
+        }
+    }
+
+    /// Returns the number of takes after which a message published on the `topic` is released.
+    fn expected_takes(&self, topic: &Known<String>) -> Option<usize> {
+        let Known::Known(topic) = topic else {
+            return None;
+        };
+        self.subscriber_counts
+            .get(topic)
+            .copied()
+            .filter(|&count| count > 0)
+    }
+
+    /// Marks the `subscriber` as removed, so that it is no longer expected to take messages.
+    fn remove_subscriber(
+        subscriber_counts: &mut HashMap<String, usize>,
+        subscriber: &mut Subscriber,
+    ) {
+        if subscriber.is_removed() {
+            return;
+        }
+        subscriber.mark_removed();
+        if let Known::Known(topic) = subscriber.get_topic()
+            && let Some(count) = subscriber_counts.get_mut(topic)
+        {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// Returns the publication message with the source `timestamp` sent on the `topic`.
+    ///
+    /// A `taken` message is released once all subscribers on its topic took it.
+    fn match_published_message(
+        &mut self,
+        timestamp: i64,
+        topic: Known<String>,
+        taken: bool,
+    ) -> Option<Arc<Mutex<PublicationMessage>>> {
+        let key = Some((timestamp, topic))
+            .filter(|key| self.published_messages.contains_key(key))
+            .or_else(|| {
+                Some((timestamp, Known::Unknown))
+                    .filter(|key| self.published_messages.contains_key(key))
+            })?;
+        let pending = self.published_messages.get_mut(&key)?;
+        let message = pending.message.clone();
+        if taken && let Some(remaining_takes) = &mut pending.remaining_takes {
+            *remaining_takes = remaining_takes.saturating_sub(1);
+            if *remaining_takes == 0 {
+                self.published_messages.remove(&key);
+            }
+        }
+
+        Some(message)
+    }
+
     pub fn get_all_nodes(&self) -> Vec<Arc<Mutex<Node>>> {
         self.nodes_by_rcl.values().cloned().collect()
     }
@@ -207,6 +354,49 @@ impl Processor {
         }
     }
 
+    /// Returns a read-only view of the objects created by this processor.
+    pub fn objects(&self) -> ObjectsView {
+        self.objects.view()
+    }
+
+    /// Logs the number of created objects of each kind.
+    pub fn log_object_counts(&self) {
+        log::info!(target: "trace_counters",
+            "ROS 2 objects:\n\
+            - nodes: {}\n\
+            - publishers: {}\n\
+            - subscribers: {}\n\
+            - services: {}\n\
+            - clients: {}\n\
+            - timers: {}\n\
+            - callbacks: {}",
+            self.objects.nodes.len(),
+            self.objects.publishers.len(),
+            self.objects.subscribers.len(),
+            self.objects.services.len(),
+            self.objects.clients.len(),
+            self.objects.timers.len(),
+            self.objects.callbacks.len()
+        );
+    }
+
+    /// Returns `true` if the process is known to trace the intra-process ring buffers.
+    ///
+    /// Processes of an unknown distribution are handled as if they did not, because the
+    /// intra-process subscriptions of the older distributions are linked by their handles
+    /// once they are initialized.
+    fn traces_intra_process_buffers(&self, context_id: ContextId) -> bool {
+        self.distros
+            .get(&context_id)
+            .is_some_and(|distro| distro.traces_intra_process_buffers())
+    }
+
+    /// Records the distribution of the process inferred from the payload layout or the kind
+    /// of its events unless it is already known, e.g., from the `rcl_init` event.
+    fn infer_distro(&mut self, context_id: ContextId, distro: raw_events::ros2::RosDistro) {
+        self.distros.entry(context_id).or_insert(distro);
+    }
+
     fn get_timer_by_rcl_handle(&self, id: Id<u64>) -> Result<&Arc<Mutex<Timer>>, error::NotFound> {
         self.timers_by_rcl
             .get(&id)
@@ -223,6 +413,8 @@ impl Processor {
         &mut self,
         full_event: raw_events::FullEvent,
     ) -> Result<MaybeProcessed<processed_events::FullEvent, raw_events::FullEvent>> {
+        self.release_old_messages(full_event.time);
+
         Ok(match full_event.event {
             raw_events::Event::Ros2(event) => {
                 match self.process_raw_ros2_event(&event, &full_event.context, full_event.time) {
@@ -246,6 +438,10 @@ impl Processor {
                     }
                 }
             }
+            raw_events::Event::Kernel(event) => MaybeProcessed::Processed(
+                self.process_raw_kernel_event(&event, &full_event.context, full_event.time)
+                    .into(),
+            ),
         }
         .into_full_event(full_event.context, full_event.time))
     }
@@ -258,7 +454,24 @@ impl Processor {
     ) -> Result<processed_events::ros2::Event, UnsupportedOrError<raw_events::ros2::Event>> {
         let context_id = ContextId::new(context.vpid(), self.host_to_host_id(context.hostname()));
 
+        // The executors and the intra-process subscriptions are found by the thread
+        // of their events, so these events cannot be processed without the vtid.
+        if context.vtid().is_none()
+            && matches!(
+                event,
+                raw_events::ros2::Event::RclcppExecutorGetNextReady(_)
+                    | raw_events::ros2::Event::RclcppExecutorWaitForWork(_)
+                    | raw_events::ros2::Event::RclcppExecutorExecute(_)
+                    | raw_events::ros2::Event::RclcppIpbToSubscription(_)
+            )
+        {
+            return Err(UnsupportedOrError::Unsupported(event.clone()));
+        }
+
         Ok(match event {
+            raw_events::ros2::Event::RclInit(event) => self
+                .process_rcl_init(event, time, context_id, context)
+                .into(),
             raw_events::ros2::Event::RclNodeInit(event) => self
                 .process_rcl_node_init(event, time, context_id, context)
                 .into(),
@@ -328,10 +541,366 @@ impl Processor {
             raw_events::ros2::Event::CallbackEnd(event) => self
                 .process_callback_end(event, time, context_id, context)?
                 .into(),
+            raw_events::ros2::Event::RclLifecycleStateMachineInit(event) => self
+                .process_rcl_lifecycle_state_machine_init(event, time, context_id, context)?
+                .into(),
+            raw_events::ros2::Event::RclLifecycleTransition(event) => self
+                .process_rcl_lifecycle_transition(event, time, context_id, context)?
+                .into(),
+            raw_events::ros2::Event::RclcppExecutorGetNextReady(event) => self
+                .process_rclcpp_executor_get_next_ready(event, time, context_id, context)
+                .into(),
+            raw_events::ros2::Event::RclcppExecutorWaitForWork(event) => self
+                .process_rclcpp_executor_wait_for_work(event, time, context_id, context)
+                .into(),
+            raw_events::ros2::Event::RclcppExecutorExecute(event) => self
+                .process_rclcpp_executor_execute(event, time, context_id, context)
+                .into(),
+            raw_events::ros2::Event::RclcppConstructRingBuffer(event) => self
+                .process_rclcpp_construct_ring_buffer(event, time, context_id, context)
+                .into(),
+            raw_events::ros2::Event::RclcppBufferToIpb(event) => self
+                .process_rclcpp_buffer_to_ipb(event, time, context_id, context)?
+                .into(),
+            raw_events::ros2::Event::RclcppIpbToSubscription(event) => self
+                .process_rclcpp_ipb_to_subscription(event, time, context_id, context)?
+                .into(),
+            raw_events::ros2::Event::RclcppRingBufferEnqueue(event) => self
+                .process_rclcpp_ring_buffer_enqueue(event, time, context_id, context)?
+                .into(),
+            raw_events::ros2::Event::RclcppRingBufferDequeue(event) => self
+                .process_rclcpp_ring_buffer_dequeue(event, time, context_id, context)?
+                .into(),
+            raw_events::ros2::Event::RclcppRingBufferClear(event) => self
+                .process_rclcpp_ring_buffer_clear(event, time, context_id, context)?
+                .into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::CallbackCaller;
+    use crate::raw_events::testing::{HOSTNAME, TraceBuilder, VPID};
+
+    use super::*;
+
+    #[test]
+    fn events_without_thread() {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 10, "node")
+            .timer(0, 10, 20, 30)
+            .event(
+                40,
+                VPID,
+                raw_events::ros2::RclCppExecutorExecute { handle: 20 },
+            )
+            .execution(50, 60, VPID, 30);
+
+        let mut processor = Processor::new();
+        for mut event in trace.build() {
+            event.context = Context::new(None, VPID, None, String::new(), HOSTNAME.to_owned());
+            // The executor is identified by the thread, the other objects by their handles.
+            let is_executor = matches!(
+                event.event,
+                raw_events::Event::Ros2(raw_events::ros2::Event::RclcppExecutorExecute(_))
+            );
+            let processed = processor.process_raw_event(event).unwrap();
+            assert_eq!(matches!(processed, MaybeProcessed::Raw(_)), is_executor);
+        }
+    }
+
+    /// The subscription to `/topic` with the rcl handle 30 and its callbacks.
+    #[derive(Debug, PartialEq)]
+    struct SubscriptionModel {
+        /// Names of the callbacks 71 and 81 and whether they are called by the subscription.
+        callbacks: Vec<(Option<String>, bool)>,
+        /// The callback 81 is the callback of the subscription.
+        callback_linked: bool,
+        /// The rclcpp subscription 80 and the intra-process subscription 70 belong
+        /// to the subscription.
+        rclcpp_linked: bool,
+        intra_process_linked: bool,
+        /// Topic of the publisher of the published message.
+        published_topic: Option<String>,
+    }
 
-            _ => {
-                return Err(UnsupportedOrError::Unsupported(event.clone()));
+    /// Processes a process of the distribution with the `tracetools_version` which publishes
+    /// a message and subscribes to the same topic with the intra-process communication enabled.
+    fn subscription_model(tracetools_version: &str) -> SubscriptionModel {
+        use raw_events::ros2::{
+            RclCppBufferToIpb, RclCppConstructRingBuffer, RclCppIpbToSubscription, RclInit,
+            RclPublish, RclcppCallbackRegister, RclcppSubscriptionCallbackAdded,
+            RclcppSubscriptionInit, RmwPublish, RosDistro,
+        };
+
+        let distro = RosDistro::from_tracetools_version(tracetools_version).unwrap();
+        let mut trace = TraceBuilder::new();
+        trace
+            .event(
+                0,
+                VPID,
+                RclInit {
+                    context_handle: 1,
+                    version: tracetools_version.to_owned(),
+                },
+            )
+            .node(0, 1, "node")
+            .publisher(0, 1, 20, "/topic", 10)
+            .subscription(0, 1, 30, "/topic", 10);
+        if distro.traces_intra_process_buffers() {
+            trace
+                .event(
+                    0,
+                    VPID,
+                    RclCppConstructRingBuffer {
+                        buffer: 50,
+                        capacity: 10,
+                    },
+                )
+                .event(
+                    0,
+                    VPID,
+                    RclCppBufferToIpb {
+                        buffer: 50,
+                        ipb: 60,
+                    },
+                )
+                .event(
+                    0,
+                    VPID,
+                    RclCppIpbToSubscription {
+                        ipb: 60,
+                        subscription: 70,
+                    },
+                );
+        }
+        let add_callback = |trace: &mut TraceBuilder, subscription, callback, symbol: &str| {
+            trace
+                .event(
+                    0,
+                    VPID,
+                    RclcppSubscriptionCallbackAdded {
+                        subscription,
+                        callback,
+                    },
+                )
+                .event(
+                    0,
+                    VPID,
+                    RclcppCallbackRegister {
+                        callback,
+                        symbol: symbol.to_owned(),
+                    },
+                );
+        };
+        let rclcpp_init = |trace: &mut TraceBuilder, subscription| {
+            trace.event(
+                0,
+                VPID,
+                RclcppSubscriptionInit {
+                    subscription_handle: 30,
+                    subscription,
+                },
+            );
+        };
+        // The intra-process subscription is initialized after its callback is added.
+        add_callback(&mut trace, 70, 71, "intra_process_callback");
+        rclcpp_init(&mut trace, 70);
+        rclcpp_init(&mut trace, 80);
+        add_callback(&mut trace, 80, 81, "callback");
+        // Humble does not record the publisher handle and the timestamp.
+        let jazzy = distro.traces_intra_process_buffers();
+        trace
+            .event(
+                100,
+                VPID,
+                RclPublish {
+                    publisher_handle: 20,
+                    message: 1000,
+                },
+            )
+            .event(
+                100,
+                VPID,
+                RmwPublish {
+                    rmw_publisher_handle: jazzy.then_some(21),
+                    message: 1000,
+                    timestamp: jazzy.then_some(100),
+                },
+            );
+
+        let mut processor = Processor::new();
+        let mut published_topic = None;
+        for event in trace.build() {
+            let MaybeProcessed::Processed(processed) = processor.process_raw_event(event).unwrap()
+            else {
+                panic!("All events should be processed");
+            };
+            if let processed_events::Event::Ros2(processed_events::ros2::Event::RmwPublish(
+                publish,
+            )) = processed.event
+            {
+                let message = publish.message.lock().unwrap();
+                let publisher = message.get_publisher().unwrap();
+                published_topic = publisher
+                    .lock()
+                    .unwrap()
+                    .get_topic()
+                    .map(ToOwned::to_owned)
+                    .into();
             }
-        })
+        }
+
+        let context_id = ContextId::new(VPID, processor.hostname_to_host_id[HOSTNAME]);
+        let subscriber_arc = &processor.subscribers_by_rcl[&30.into_id(context_id)];
+        let is_subscriber = |subscriber: Option<&Arc<Mutex<Subscriber>>>| {
+            subscriber.is_some_and(|subscriber| Arc::ptr_eq(subscriber, subscriber_arc))
+        };
+        let callbacks = [71, 81]
+            .into_iter()
+            .map(|callback| {
+                let callback = processor.callbacks_by_id[&callback.into_id(context_id)]
+                    .lock()
+                    .unwrap();
+                let caller = match callback.get_caller() {
+                    Some(CallbackCaller::Subscription(subscriber)) => subscriber.get_arc(),
+                    _ => None,
+                };
+                (
+                    callback.get_name().map(ToOwned::to_owned),
+                    is_subscriber(caller.as_ref()),
+                )
+            })
+            .collect();
+        let callback: Option<_> = subscriber_arc.lock().unwrap().get_callback().into();
+        let callback_linked = callback.is_some_and(|callback| {
+            Arc::ptr_eq(
+                &callback,
+                &processor.callbacks_by_id[&81.into_id(context_id)],
+            )
+        });
+
+        SubscriptionModel {
+            callbacks,
+            callback_linked,
+            rclcpp_linked: is_subscriber(
+                processor.subscribers_by_rclcpp.get(&80.into_id(context_id)),
+            ),
+            intra_process_linked: is_subscriber(
+                processor
+                    .subscribers_by_rclcpp_intra_process
+                    .get(&70.into_id(context_id)),
+            ),
+            published_topic,
+        }
+    }
+
+    #[test]
+    fn humble_trace_matches_jazzy_model() {
+        let jazzy = subscription_model("8.2.3");
+        assert_eq!(
+            jazzy,
+            SubscriptionModel {
+                callbacks: vec![
+                    (Some("intra_process_callback".to_owned()), true),
+                    (Some("callback".to_owned()), true),
+                ],
+                callback_linked: true,
+                rclcpp_linked: true,
+                intra_process_linked: true,
+                published_topic: Some("/topic".to_owned()),
+            }
+        );
+        assert_eq!(subscription_model("4.1.1"), jazzy);
+    }
+
+    /// Adds a publisher and two subscriptions with the rmw handles 31 and 41 to `/topic`.
+    fn two_subscribers() -> TraceBuilder {
+        let mut trace = TraceBuilder::new();
+        trace
+            .node(0, 1, "node")
+            .publisher(0, 1, 20, "/topic", 10)
+            .subscription(0, 1, 30, "/topic", 10)
+            .subscription(0, 1, 40, "/topic", 10);
+        trace
+    }
+
+    fn publish(trace: &mut TraceBuilder, time: i64) -> &mut TraceBuilder {
+        trace.event(
+            time,
+            VPID,
+            raw_events::ros2::RmwPublish {
+                rmw_publisher_handle: Some(21),
+                message: 1000,
+                timestamp: Some(time),
+            },
+        )
+    }
+
+    fn take(
+        trace: &mut TraceBuilder,
+        time: i64,
+        rmw_subscription_handle: u64,
+        source_timestamp: i64,
+    ) -> &mut TraceBuilder {
+        trace.event(
+            time,
+            VPID,
+            raw_events::ros2::RmwTake {
+                rmw_subscription_handle,
+                message: 2000,
+                source_timestamp,
+                taken: true,
+            },
+        )
+    }
+
+    /// Processes the events of the `trace` and returns the timestamps of the messages
+    /// waiting to be taken.
+    fn pending_publications(processor: &mut Processor, trace: &mut TraceBuilder) -> Vec<i64> {
+        for event in trace.build() {
+            processor.process_raw_event(event).unwrap();
+        }
+        let mut timestamps: Vec<_> = processor
+            .published_messages
+            .keys()
+            .map(|&(timestamp, _)| timestamp)
+            .collect();
+        timestamps.sort_unstable();
+        timestamps
+    }
+
+    #[test]
+    fn message_released_on_last_take_without_horizon() {
+        let mut processor = Processor::new();
+        let mut trace = two_subscribers();
+        publish(&mut trace, 100);
+        take(&mut trace, 150, 31, 100);
+        assert_eq!(pending_publications(&mut processor, &mut trace), [100]);
+
+        take(&mut trace, 160, 41, 100);
+        assert!(pending_publications(&mut processor, &mut trace).is_empty());
+    }
+
+    #[test]
+    fn horizon_releases_messages_of_subscriber_never_taking() {
+        let mut processor = Processor::new();
+        processor.set_message_horizon(Duration::from_nanos(1_000));
+        let mut trace = two_subscribers();
+        // The subscriber 41 never takes the messages.
+        publish(&mut trace, 100);
+        publish(&mut trace, 200);
+        take(&mut trace, 150, 31, 100);
+        assert_eq!(pending_publications(&mut processor, &mut trace), [100, 200]);
+
+        // Both messages are older than the horizon, taken by one subscriber or not.
+        publish(&mut trace, 5_000);
+        take(&mut trace, 5_100, 31, 5_000);
+        assert_eq!(pending_publications(&mut processor, &mut trace), [5_000]);
+
+        publish(&mut trace, 10_000);
+        assert_eq!(pending_publications(&mut processor, &mut trace), [10_000]);
     }
 }
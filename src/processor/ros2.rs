@@ -4,19 +4,44 @@ use std::sync::{Arc, Mutex};
 
 use crate::events_common::Context;
 use crate::model::{
-    Callback, CallbackCaller, CallbackInstance, Client, Node, PublicationMessage, Publisher,
-    Service, Subscriber, SubscriptionMessage, Time, Timer,
+    Callback, CallbackCaller, CallbackInstance, Client, Executor, ExecutorState,
+    LifecycleStateMachine, Node, PublicationMessage, Publisher, RingBuffer, Service, Subscriber,
+    SubscriptionMessage, Time, Timer,
 };
 use crate::utils::Known;
 use crate::{processed_events, raw_events};
 
-use super::{ContextId, IntoId, MapGetAsResult, Processor, error};
+use super::{ContextId, IntoId, MapGetAsResult, PendingPublication, Processor, error};
 
 use color_eyre::Result;
 use color_eyre::eyre::{Context as _, eyre};
 
 // Event processing methods
 impl Processor {
+    pub(super) fn process_rcl_init(
+        &mut self,
+        event: &raw_events::ros2::RclInit,
+        _time: Time,
+        context_id: ContextId,
+        _context: &Context,
+    ) -> processed_events::ros2::RclInit {
+        match raw_events::ros2::RosDistro::from_tracetools_version(&event.version) {
+            Some(distro) => {
+                self.distros.insert(context_id, distro);
+            }
+            None => log::warn!(
+                target: "rcl_init",
+                "Unknown tracetools version {:?}. Assuming the process uses the oldest supported ROS 2 distribution.",
+                event.version
+            ),
+        }
+
+        processed_events::ros2::RclInit {
+            context_handle: event.context_handle,
+            version: event.version.clone(),
+        }
+    }
+
     pub(super) fn process_rcl_node_init(
         &mut self,
         event: &raw_events::ros2::RclNodeInit,
@@ -34,9 +59,11 @@ impl Processor {
                 entry.get().clone()
             }
             Entry::Vacant(entry) => {
-                let mut node = Node::new(event.rmw_handle);
-                node.rcl_init(event.rmw_handle, &event.node_name, &event.namespace);
-                let node_arc = Arc::new(Mutex::new(node));
+                let node_arc = self.objects.nodes.insert(|id| {
+                    let mut node = Node::new(id, event.rmw_handle);
+                    node.rcl_init(event.rmw_handle, &event.node_name, &event.namespace);
+                    node
+                });
                 entry.insert(node_arc.clone());
                 node_arc
             }
@@ -55,7 +82,7 @@ impl Processor {
         let publisher_arc = self
             .publishers_by_rmw
             .entry(event.rmw_publisher_handle.into_id(context_id))
-            .or_default();
+            .or_insert_with(|| self.objects.publishers.insert(Publisher::new));
 
         let init_result = publisher_arc
             .lock()
@@ -72,11 +99,13 @@ impl Processor {
             publisher.mark_removed();
             drop(publisher);
 
-            let mut publisher = Publisher::default();
-            publisher
-                .rmw_init(event.rmw_publisher_handle, event.gid)
-                .expect("New Publisher should not be initialized yet");
-            *publisher_arc = Arc::new(Mutex::new(publisher));
+            *publisher_arc = self.objects.publishers.insert(|id| {
+                let mut publisher = Publisher::new(id);
+                publisher
+                    .rmw_init(event.rmw_publisher_handle, event.gid)
+                    .expect("New Publisher should not be initialized yet");
+                publisher
+            });
         }
 
         processed_events::ros2::RmwPublisherInit {
@@ -95,11 +124,13 @@ impl Processor {
             .publishers_by_rmw
             .entry(event.rmw_publisher_handle.into_id(context_id))
             .or_insert_with_key(|key| {
-                let mut publisher = Publisher::default();
-                publisher
-                    .set_rmw_handle(key.id)
-                    .expect("New Publisher should not be initialized yet");
-                Arc::new(Mutex::new(publisher))
+                self.objects.publishers.insert(|id| {
+                    let mut publisher = Publisher::new(id);
+                    publisher
+                        .set_rmw_handle(key.id)
+                        .expect("New Publisher should not be initialized yet");
+                    publisher
+                })
             });
 
         let node_arc = self
@@ -112,8 +143,9 @@ impl Processor {
                     event.topic_name, "/rosout",
                     "Node not found for publisher: {event:?}"
                 );
-                let node = Node::new(event.node_handle);
-                Arc::new(Mutex::new(node))
+                self.objects
+                    .nodes
+                    .insert(|id| Node::new(id, event.node_handle))
             });
 
         let init_result = publisher_by_rmw_arc.lock().unwrap().rcl_init(
@@ -133,16 +165,18 @@ impl Processor {
             old_publisher.mark_removed();
             drop(old_publisher);
 
-            let mut publisher = Publisher::default();
-            publisher
-                .rcl_init(
-                    event.publisher_handle,
-                    event.topic_name.clone(),
-                    event.queue_depth,
-                    Arc::downgrade(node_arc),
-                )
-                .expect("New Publisher should not be initialized yet");
-            let publisher_arc = Arc::new(Mutex::new(publisher));
+            let publisher_arc = self.objects.publishers.insert(|id| {
+                let mut publisher = Publisher::new(id);
+                publisher
+                    .rcl_init(
+                        event.publisher_handle,
+                        event.topic_name.clone(),
+                        event.queue_depth,
+                        Arc::downgrade(node_arc),
+                    )
+                    .expect("New Publisher should not be initialized yet");
+                publisher
+            });
             *publisher_by_rmw_arc = publisher_arc.clone();
 
             publisher_arc
@@ -211,12 +245,13 @@ impl Processor {
             .or_insert_with_key(|key| {
                 // TODO: rosout publisher can change handle address
                 // For now we just create a new publisher
-                let mut publisher = Publisher::default();
-                publisher
-                    .set_rcl_handle(key.id)
-                    .expect("New Publisher should not be initialized yet");
-
-                Arc::new(Mutex::new(publisher))
+                self.objects.publishers.insert(|id| {
+                    let mut publisher = Publisher::new(id);
+                    publisher
+                        .set_rcl_handle(key.id)
+                        .expect("New Publisher should not be initialized yet");
+                    publisher
+                })
             })
             .clone();
 
@@ -238,6 +273,10 @@ impl Processor {
         context_id: ContextId,
         context: &Context,
     ) -> processed_events::ros2::RmwPublish {
+        // Humble does not record the publisher handle in the rmw_publish event.
+        if event.rmw_publisher_handle.is_none() {
+            self.infer_distro(context_id, raw_events::ros2::RosDistro::Humble);
+        }
         let message_arc = self
             .published_messages_by_rcl
             .remove(&event.message.into_id(context_id))
@@ -288,14 +327,18 @@ impl Processor {
         if let Some(timestamp) = event.timestamp {
             message.rmw_publish(time, timestamp);
 
+            let pending = PendingPublication {
+                message: message_arc.clone(),
+                remaining_takes: self.expected_takes(&topic),
+            };
             self.published_messages
-                        .insert((timestamp, topic), message_arc.clone())
-                        .inspect(|old| {
-                            log::warn!(
-                                target: "rmw_publish",
-                                "Replacing different PublicationMessage with same sender timestamp. old_message={old:?}"
-                            );
-                        });
+                .insert((timestamp, topic), pending)
+                .inspect(|old| {
+                    log::warn!(
+                        target: "rmw_publish",
+                        "Replacing different PublicationMessage with same sender timestamp. old_message={old:?}"
+                    );
+                });
         } else {
             log::warn!(target: "rmw_publish",
                         "Missing timestamp for RMW publish event. Subscription messages will not match it: [{time}] {event:?} {context:?}");
@@ -316,21 +359,25 @@ impl Processor {
         context_id: ContextId,
         context: &Context,
     ) -> Result<processed_events::ros2::RclcppIntraPublish> {
-        let message_arc = self
-            .published_messages_by_rclcpp
-            .get(&event.message.into_id(context_id))
-            .ok_or(error::NotFound::published_message(event.message))
-            .map_err(|e| e.with_ros2_event(event, time, context))?
-            .clone();
-
-        // TODO: check if event has rcl handle, rclcpp handle or other.
         let publisher_arc = self
             .publishers_by_rcl
             .get_or_err(event.publisher_handle.into_id(context_id), "rcl_handle")
             .map_err(|e| e.with_ros2_event(event, time, context))?
             .clone();
 
-        message_arc.lock().unwrap().set_publisher(publisher_arc);
+        // The intra-process message is a separate object even if the same message is
+        // published to other processes afterwards.
+        let mut message = PublicationMessage::new(event.message);
+        message.set_publisher(publisher_arc);
+        message.rclcpp_publish(time);
+        let message_arc = Arc::new(Mutex::new(message));
+
+        // The message is enqueued to the ring buffers of the subscriptions by the same thread.
+        // Without the thread, the enqueued messages are unknown.
+        if let Some(vtid) = context.vtid() {
+            self.intra_process_messages_by_thread
+                .insert(vtid.into_id(context_id), message_arc.clone());
+        }
 
         Ok(processed_events::ros2::RclcppIntraPublish {
             message: message_arc,
@@ -347,7 +394,7 @@ impl Processor {
         let subscriber_arc = self
             .subscribers_by_rmw
             .entry(event.rmw_subscription_handle.into_id(context_id))
-            .or_default();
+            .or_insert_with(|| self.objects.subscribers.insert(Subscriber::new));
 
         let init_result = subscriber_arc
             .lock()
@@ -361,14 +408,16 @@ impl Processor {
                 event.rmw_subscription_handle
             );
             let mut old_subscriber = subscriber_arc.lock().unwrap();
-            old_subscriber.mark_removed();
+            Self::remove_subscriber(&mut self.subscriber_counts, &mut old_subscriber);
             drop(old_subscriber);
 
-            let mut subscriber = Subscriber::default();
-            subscriber
-                .rmw_init(event.rmw_subscription_handle, event.gid)
-                .expect("New Subscriber should not be initialized yet");
-            *subscriber_arc = Arc::new(Mutex::new(subscriber));
+            *subscriber_arc = self.objects.subscribers.insert(|id| {
+                let mut subscriber = Subscriber::new(id);
+                subscriber
+                    .rmw_init(event.rmw_subscription_handle, event.gid)
+                    .expect("New Subscriber should not be initialized yet");
+                subscriber
+            });
         }
 
         processed_events::ros2::RmwSubscriptionInit {
@@ -387,11 +436,13 @@ impl Processor {
             .subscribers_by_rmw
             .entry(event.rmw_subscription_handle.into_id(context_id))
             .or_insert_with_key(|key| {
-                let mut subscriber = Subscriber::default();
-                subscriber
-                    .set_rmw_handle(key.id)
-                    .expect("New Subscriber should not have rwm handle yet");
-                Arc::new(Mutex::new(subscriber))
+                self.objects.subscribers.insert(|id| {
+                    let mut subscriber = Subscriber::new(id);
+                    subscriber
+                        .set_rmw_handle(key.id)
+                        .expect("New Subscriber should not have rwm handle yet");
+                    subscriber
+                })
             });
 
         let node_arc = self
@@ -415,24 +466,31 @@ impl Processor {
                 event.subscription_handle
             );
             let mut old_subscriber = subscriber_arc.lock().unwrap();
-            old_subscriber.mark_removed();
+            Self::remove_subscriber(&mut self.subscriber_counts, &mut old_subscriber);
             drop(old_subscriber);
 
-            let mut subscriber = Subscriber::default();
-            subscriber
-                .set_rmw_handle(event.rmw_subscription_handle)
-                .expect("New Subscriber should not have rwm handle yet");
-            subscriber
-                .rcl_init(
-                    event.subscription_handle,
-                    event.topic_name.clone(),
-                    event.queue_depth,
-                    Arc::downgrade(&node_arc),
-                )
-                .expect("New Subscriber should not be initialized yet");
-            *subscriber_arc = Arc::new(Mutex::new(subscriber));
+            *subscriber_arc = self.objects.subscribers.insert(|id| {
+                let mut subscriber = Subscriber::new(id);
+                subscriber
+                    .set_rmw_handle(event.rmw_subscription_handle)
+                    .expect("New Subscriber should not have rwm handle yet");
+                subscriber
+                    .rcl_init(
+                        event.subscription_handle,
+                        event.topic_name.clone(),
+                        event.queue_depth,
+                        Arc::downgrade(&node_arc),
+                    )
+                    .expect("New Subscriber should not be initialized yet");
+                subscriber
+            });
         }
 
+        *self
+            .subscriber_counts
+            .entry(event.topic_name.clone())
+            .or_default() += 1;
+
         self.subscribers_by_rcl
             .insert(event.subscription_handle.into_id(context_id), subscriber_arc.clone())
             .inspect(|old| {
@@ -440,7 +498,7 @@ impl Processor {
                     target: "rcl_subscription_init",
                     "Found different Subscriber with same address. Assuming old Subscriber was deleted: old={old:?}"
                 );
-                old.lock().unwrap().mark_removed();
+                Self::remove_subscriber(&mut self.subscriber_counts, &mut old.lock().unwrap());
             });
 
         node_arc
@@ -448,6 +506,13 @@ impl Processor {
             .unwrap()
             .add_subscriber(subscriber_arc.clone());
 
+        // The intra-process buffer events do not contain the subscription handle, but they are
+        // emitted by the same thread during the construction of the subscription.
+        if let Some(vtid) = context.vtid() {
+            self.rcl_subscribers_by_thread
+                .insert(vtid.into_id(context_id), subscriber_arc.clone());
+        }
+
         Ok(processed_events::ros2::RclSubscriptionInit {
             subscription: subscriber_arc.clone(),
         })
@@ -460,10 +525,37 @@ impl Processor {
         context_id: ContextId,
         context: &Context,
     ) -> Result<processed_events::ros2::RclcppSubscriptionInit> {
+        if let Some(subscriber_arc) = self
+            .subscribers_by_rclcpp_intra_process
+            .get(&event.subscription.into_id(context_id))
+        {
+            // Initialization of the intra-process subscription. It was already linked
+            // to the subscriber by the rclcpp_ipb_to_subscription event or, in distributions
+            // without it, by the rclcpp_subscription_callback_added event.
+            return Ok(processed_events::ros2::RclcppSubscriptionInit {
+                subscription: subscriber_arc.clone(),
+            });
+        }
+
         let subscriber_arc = self
             .subscribers_by_rcl
             .get_or_err(event.subscription_handle.into_id(context_id), "rcl_handle")
-            .map_err(|e| e.with_ros2_event(event, time, context))?;
+            .map_err(|e| e.with_ros2_event(event, time, context))?
+            .clone();
+
+        if let Some(callback_arc) = self
+            .unlinked_subscription_callbacks
+            .remove(&event.subscription.into_id(context_id))
+        {
+            // Initialization of the intra-process subscription in distributions without
+            // the intra-process buffer events. Its callback was already added.
+            return self.link_intra_process_subscription(
+                event.subscription,
+                subscriber_arc,
+                callback_arc,
+                context_id,
+            );
+        }
 
         subscriber_arc
             .lock()
@@ -481,7 +573,7 @@ impl Processor {
                     target: "rclcpp_subscription_init",
                     "Found different Subscriber with same address. Assuming old Subscriber was deleted: old={old:?}"
                 );
-                old.lock().unwrap().mark_removed();
+                Self::remove_subscriber(&mut self.subscriber_counts, &mut old.lock().unwrap());
             });
 
         Ok(processed_events::ros2::RclcppSubscriptionInit {
@@ -489,6 +581,38 @@ impl Processor {
         })
     }
 
+    /// Links the rclcpp `SubscriptionIntraProcess` and its `callback` to the subscriber
+    /// in distributions without the intra-process buffer events, e.g., Humble.
+    fn link_intra_process_subscription(
+        &mut self,
+        subscription: u64,
+        subscriber_arc: Arc<Mutex<Subscriber>>,
+        callback_arc: Arc<Mutex<Callback>>,
+        context_id: ContextId,
+    ) -> Result<processed_events::ros2::RclcppSubscriptionInit> {
+        let mut subscriber = subscriber_arc.lock().unwrap();
+        subscriber
+            .rclcpp_intra_process_init(subscription)
+            .map_err(|e| error::Causes::AlreadyInitialized(e, subscriber_arc.clone().into()))?;
+        subscriber
+            .set_intra_process_callback(callback_arc.clone())
+            .map_err(|e| eyre!("Intra-process subscription already has a callback: {e}"))?;
+        drop(subscriber);
+
+        callback_arc
+            .lock()
+            .unwrap()
+            .link_subscription(&subscriber_arc)
+            .map_err(|e| eyre!("Callback already linked to its caller: {e}"))?;
+
+        self.subscribers_by_rclcpp_intra_process
+            .insert(subscription.into_id(context_id), subscriber_arc.clone());
+
+        Ok(processed_events::ros2::RclcppSubscriptionInit {
+            subscription: subscriber_arc,
+        })
+    }
+
     pub(super) fn process_rclcpp_subscription_callback_added(
         &mut self,
         event: &raw_events::ros2::RclcppSubscriptionCallbackAdded,
@@ -496,17 +620,60 @@ impl Processor {
         context_id: ContextId,
         context: &Context,
     ) -> Result<processed_events::ros2::RclcppSubscriptionCallbackAdded> {
-        let subscription_arc = self
-            .subscribers_by_rclcpp
-            .get_or_err(event.subscription.into_id(context_id), "rclcpp_handle")
-            .map_err(|e| e.with_ros2_event(event, time, context))?
-            .clone();
+        let subscription_id = event.subscription.into_id(context_id);
+        if !self.traces_intra_process_buffers(context_id)
+            && !self
+                .subscribers_by_rclcpp_intra_process
+                .contains_key(&subscription_id)
+            && !self.subscribers_by_rclcpp.contains_key(&subscription_id)
+        {
+            // The callback of the rclcpp `SubscriptionIntraProcess` is added before the
+            // subscription is initialized. It is linked by the rclcpp_subscription_init event.
+            let callback_arc = self.objects.callbacks.insert(|id| {
+                Callback::new_unlinked_subscription(
+                    id,
+                    event.callback,
+                    context.hostname().to_owned(),
+                )
+            });
+            self.callbacks_by_id
+                .insert(event.callback.into_id(context_id), callback_arc.clone())
+                .and_then(filter_out_removed_callers)
+                .map_or(Ok(()), |old: Arc<Mutex<Callback>>| {
+                    Err(
+                        error::AlreadyExists::with_id(event.callback, &callback_arc, old)
+                            .with_ros2_event(event, time, context),
+                    )
+                })?;
+            self.unlinked_subscription_callbacks
+                .insert(subscription_id, callback_arc.clone());
+
+            return Ok(processed_events::ros2::RclcppSubscriptionCallbackAdded {
+                callback: callback_arc,
+            });
+        }
 
-        let callback_arc = Callback::new_subscription(
-            event.callback,
-            &subscription_arc,
-            context.hostname().to_owned(),
-        );
+        let intra_process_subscription = self
+            .subscribers_by_rclcpp_intra_process
+            .get(&event.subscription.into_id(context_id));
+        let is_intra_process = intra_process_subscription.is_some();
+        let subscription_arc = match intra_process_subscription {
+            Some(subscription_arc) => subscription_arc,
+            None => self
+                .subscribers_by_rclcpp
+                .get_or_err(event.subscription.into_id(context_id), "rclcpp_handle")
+                .map_err(|e| e.with_ros2_event(event, time, context))?,
+        }
+        .clone();
+
+        let callback_arc = self.objects.callbacks.insert(|id| {
+            Callback::new_subscription(
+                id,
+                event.callback,
+                &subscription_arc,
+                context.hostname().to_owned(),
+            )
+        });
 
         self.callbacks_by_id
             .insert(event.callback.into_id(context_id), callback_arc.clone())
@@ -518,13 +685,21 @@ impl Processor {
                 )
             })?;
 
-        subscription_arc
-            .lock()
-            .unwrap()
-            .set_callback(callback_arc.clone())
-            .map_err(|e| {
-                eyre!("Subscription was already initialized by rclcpp_subscription_init event: {e}")
-            })?;
+        let mut subscription = subscription_arc.lock().unwrap();
+        if is_intra_process {
+            subscription
+                .set_intra_process_callback(callback_arc.clone())
+                .map_err(|e| eyre!("Intra-process subscription already has a callback: {e}"))?;
+        } else {
+            subscription
+                .set_callback(callback_arc.clone())
+                .map_err(|e| {
+                    eyre!(
+                        "Subscription was already initialized by rclcpp_subscription_init event: {e}"
+                    )
+                })?;
+        }
+        drop(subscription);
 
         Ok(processed_events::ros2::RclcppSubscriptionCallbackAdded {
             callback: callback_arc,
@@ -545,7 +720,8 @@ impl Processor {
                 "rmw_handle",
             )
             .map_err(|e| e.with_ros2_event(event, time, context))
-            .wrap_err("Taken message missing subscriber.")?;
+            .wrap_err("Taken message missing subscriber.")?
+            .clone();
         let topic = subscriber
             .lock()
             .unwrap()
@@ -554,17 +730,11 @@ impl Processor {
 
         let mut message = SubscriptionMessage::new(event.message);
 
-        if let Some(published_message) =
-            (event.source_timestamp != 0).then_some(()).and_then(|()| {
-                self.published_messages
-                    .get(&(event.source_timestamp, topic))
-                    .or_else(|| {
-                        self.published_messages
-                            .get(&(event.source_timestamp, Known::Unknown))
-                    })
-            })
+        if let Some(published_message) = (event.source_timestamp != 0)
+            .then_some(())
+            .and_then(|()| self.match_published_message(event.source_timestamp, topic, event.taken))
         {
-            message.rmw_take_matched(subscriber.clone(), published_message.clone(), time);
+            message.rmw_take_matched(subscriber.clone(), published_message, time);
         } else {
             if event.source_timestamp == 0 {
                 log::info!(target:"rmw_take", "Missing source timestamp. [{time}] {event:?} {context:?}");
@@ -642,18 +812,16 @@ impl Processor {
         context_id: ContextId,
         context: &Context,
     ) -> processed_events::ros2::RclCppTake {
+        // The message is not needed for matching after rclcpp takes it.
         let message_arc = self
             .received_messages
-            .get(&event.message.into_id(context_id))
-            .and_then(|message_arc| {
-                let mut message = message_arc.lock().unwrap();
-                message.rclcpp_take(time).ok().map(|()| message_arc)
-            });
+            .remove(&event.message.into_id(context_id))
+            .filter(|message_arc| message_arc.lock().unwrap().rclcpp_take(time).is_ok());
 
         let is_new = message_arc.is_none();
 
         let message_arc = if let Some(message_arc) = message_arc {
-            message_arc.clone()
+            message_arc
         } else {
             let mut message = SubscriptionMessage::new(event.message);
             message
@@ -665,9 +833,6 @@ impl Processor {
                 "rclcpp_take: Message was not taken before. Creating new message. [{time}] {event:?} {context:?}"
             );
 
-            self.received_messages
-                .insert(event.message.into_id(context_id), message_arc.clone());
-
             message_arc
         };
 
@@ -687,10 +852,7 @@ impl Processor {
         let service_arc = self
             .services_by_rcl
             .entry(event.service_handle.into_id(context_id))
-            .or_insert_with_key(|key| {
-                let service = Service::new(key.id);
-                Arc::new(Mutex::new(service))
-            });
+            .or_insert_with_key(|key| self.objects.services.insert(|id| Service::new(id, key.id)));
 
         let node_arc = self
             .nodes_by_rcl
@@ -712,15 +874,17 @@ impl Processor {
             service.mark_removed();
             drop(service);
 
-            let mut service = Service::new(event.service_handle.into_id(context_id).id);
-            service
-                .rcl_init(
-                    event.rmw_service_handle,
-                    event.service_name.clone(),
-                    node_arc,
-                )
-                .expect("New Service should not be initialized yet");
-            *service_arc = Arc::new(Mutex::new(service));
+            *service_arc = self.objects.services.insert(|id| {
+                let mut service = Service::new(id, event.service_handle.into_id(context_id).id);
+                service
+                    .rcl_init(
+                        event.rmw_service_handle,
+                        event.service_name.clone(),
+                        node_arc,
+                    )
+                    .expect("New Service should not be initialized yet");
+                service
+            });
         }
 
         node_arc.lock().unwrap().add_service(service_arc.clone());
@@ -742,12 +906,17 @@ impl Processor {
             .entry(event.service_handle.into_id(context_id))
             .or_insert_with_key(|key| {
                 log::warn!("Service not found for callback. Creating new (possibly duplicate) service. Event: [{time}] {event:?} {context:?}");
-                let service = Service::new(key.id);
-                Arc::new(Mutex::new(service))
+                self.objects.services.insert(|id| Service::new(id, key.id))
             });
 
-        let callback_arc =
-            Callback::new_service(event.callback, service_arc, context.hostname().to_owned());
+        let callback_arc = self.objects.callbacks.insert(|id| {
+            Callback::new_service(
+                id,
+                event.callback,
+                service_arc,
+                context.hostname().to_owned(),
+            )
+        });
 
         self.callbacks_by_id
             .insert(event.callback.into_id(context_id), callback_arc.clone())
@@ -782,10 +951,7 @@ impl Processor {
         let client_arc = self
             .clients_by_rcl
             .entry(event.client_handle.into_id(context_id))
-            .or_insert_with_key(|key| {
-                let client = Client::new(key.id);
-                Arc::new(Mutex::new(client))
-            });
+            .or_insert_with_key(|key| self.objects.clients.insert(|id| Client::new(id, key.id)));
 
         let node_arc = self
             .nodes_by_rcl
@@ -808,15 +974,17 @@ impl Processor {
             client.mark_removed();
             drop(client);
 
-            let mut client = Client::new(event.client_handle.into_id(context_id).id);
-            client
-                .rcl_init(
-                    event.rmw_client_handle,
-                    event.service_name.clone(),
-                    node_arc,
-                )
-                .expect("New Client should not be initialized yet");
-            *client_arc = Arc::new(Mutex::new(client));
+            *client_arc = self.objects.clients.insert(|id| {
+                let mut client = Client::new(id, event.client_handle.into_id(context_id).id);
+                client
+                    .rcl_init(
+                        event.rmw_client_handle,
+                        event.service_name.clone(),
+                        node_arc,
+                    )
+                    .expect("New Client should not be initialized yet");
+                client
+            });
         }
 
         node_arc.lock().unwrap().add_client(client_arc.clone());
@@ -836,10 +1004,7 @@ impl Processor {
         let timer_arc = self
             .timers_by_rcl
             .entry(event.timer_handle.into_id(context_id))
-            .or_insert_with_key(|key| {
-                let timer = Timer::new(key.id);
-                Arc::new(Mutex::new(timer))
-            });
+            .or_insert_with_key(|key| self.objects.timers.insert(|id| Timer::new(id, key.id)));
 
         let init_result = timer_arc.lock().unwrap().rcl_init(event.period);
         if let Err(_e) = init_result {
@@ -852,11 +1017,13 @@ impl Processor {
             timer.mark_removed();
             drop(timer);
 
-            let mut timer = Timer::new(event.timer_handle.into_id(context_id).id);
-            timer
-                .rcl_init(event.period)
-                .expect("New Timer should not be initialized yet");
-            *timer_arc = Arc::new(Mutex::new(timer));
+            *timer_arc = self.objects.timers.insert(|id| {
+                let mut timer = Timer::new(id, event.timer_handle.into_id(context_id).id);
+                timer
+                    .rcl_init(event.period)
+                    .expect("New Timer should not be initialized yet");
+                timer
+            });
         }
 
         processed_events::ros2::RclTimerInit {
@@ -876,8 +1043,14 @@ impl Processor {
             .map_err(|e| e.with_ros2_event(event, time, context))?
             .clone();
 
-        let callback_arc =
-            Callback::new_timer(event.callback, &timer_arc, context.hostname().to_owned());
+        let callback_arc = self.objects.callbacks.insert(|id| {
+            Callback::new_timer(
+                id,
+                event.callback,
+                &timer_arc,
+                context.hostname().to_owned(),
+            )
+        });
 
         self.callbacks_by_id
             .insert(event.callback.into_id(context_id), callback_arc.clone())
@@ -967,11 +1140,56 @@ impl Processor {
             .map_err(|e| e.with_ros2_event(event, time, context))
             .wrap_err("Callback not found. Missing rclcpp_*_callback_added event?")?;
 
-        let callback_instance = CallbackInstance::new(callback_arc.clone(), time);
+        if callback_arc.lock().unwrap().get_caller().is_none() {
+            return Err(eyre!(
+                "Callback of an uninitialized subscription started. Missing rclcpp_subscription_init event? [{time}] {event:?} {context:?}"
+            ));
+        }
+
+        // Without the intra-process buffer events, the message executed by the intra-process
+        // callback is not taken before. Only its receive time is known.
+        let intra_process_message = if event.is_intra_process
+            && !self.traces_intra_process_buffers(context_id)
+            && let Some(CallbackCaller::Subscription(subscriber)) =
+                callback_arc.lock().unwrap().get_caller()
+            && let Some(subscriber_arc) = subscriber.get_arc()
+        {
+            Some(Arc::new(Mutex::new(
+                SubscriptionMessage::new_intra_process(subscriber_arc, None, time),
+            )))
+        } else {
+            None
+        };
+
+        let (callback_id, stale_instance) = {
+            let mut callback = callback_arc.lock().unwrap();
+            (callback.get_id(), callback.take_running_instance())
+        };
+        if let Some(stale_instance) = stale_instance {
+            log::warn!(
+                target: "callback_start",
+                "Callback started again before it ended. Missing callback_end event? Dropping the running instance: {}",
+                stale_instance.lock().unwrap()
+            );
+        }
+        let callback_instance =
+            CallbackInstance::new(callback_arc.clone(), intra_process_message, time);
+
+        if let Some(thread) = context
+            .vtid()
+            .and_then(|vtid| self.threads.get(&(context_id.host_id, vtid)))
+        {
+            let thread_on_cpu_time = thread.lock().unwrap().get_on_cpu_time(time);
+            callback_instance
+                .lock()
+                .unwrap()
+                .set_thread_on_cpu_time_at_start(thread_on_cpu_time);
+        }
 
         Ok(processed_events::ros2::CallbackStart {
             callback: callback_instance,
             is_intra_process: event.is_intra_process,
+            callback_id,
         })
     }
 
@@ -987,20 +1205,372 @@ impl Processor {
             .map_err(|e| e.with_ros2_event(event, time, context))
             .wrap_err("Callback not found. Missing rclcpp_*_callback_added event?")?;
 
-        let callback_instance = {
+        let (callback_id, callback_instance) = {
             let mut callback = callback_arc.lock().unwrap();
-            callback
-                .take_running_instance()
-                .expect("No running instance found")
+            let callback_instance = callback.take_running_instance().ok_or_else(|| {
+                eyre!("Callback has no running instance. Missing callback_start event?")
+            })?;
+            (callback.get_id(), callback_instance)
         };
 
         {
             let mut callback_instance = callback_instance.lock().unwrap();
             callback_instance.end(time);
+
+            if let Some(thread) = context
+                .vtid()
+                .and_then(|vtid| self.threads.get(&(context_id.host_id, vtid)))
+            {
+                let thread_on_cpu_time = thread.lock().unwrap().get_on_cpu_time(time);
+                callback_instance.set_thread_on_cpu_time_at_end(thread_on_cpu_time);
+            }
         }
 
         Ok(processed_events::ros2::CallbackEnd {
             callback: callback_instance,
+            callback_id,
+        })
+    }
+
+    pub(super) fn process_rcl_lifecycle_state_machine_init(
+        &mut self,
+        event: &raw_events::ros2::RclLifecycleStateMachineInit,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> Result<processed_events::ros2::RclLifecycleStateMachineInit> {
+        let node_arc = self
+            .nodes_by_rcl
+            .get_or_err(event.node_handle.into_id(context_id), "rcl_handle")
+            .map_err(|e| e.with_ros2_event(event, time, context))
+            .wrap_err("Node not found. Missing rcl_node_init event")?;
+
+        let state_machine_arc = LifecycleStateMachine::new(event.state_machine, node_arc);
+
+        let old_state_machine = node_arc
+            .lock()
+            .unwrap()
+            .replace_lifecycle_state_machine(state_machine_arc.clone());
+        if let Some(old) = old_state_machine {
+            log::warn!(
+                target: "rcl_lifecycle_state_machine_init",
+                "Node already has a lifecycle state machine. Replacing it: old={}",
+                old.lock().unwrap()
+            );
+        }
+
+        self.lifecycle_state_machines.insert(
+            event.state_machine.into_id(context_id),
+            state_machine_arc.clone(),
+        );
+
+        Ok(processed_events::ros2::RclLifecycleStateMachineInit {
+            state_machine: state_machine_arc,
+        })
+    }
+
+    pub(super) fn process_rcl_lifecycle_transition(
+        &mut self,
+        event: &raw_events::ros2::RclLifecycleTransition,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> Result<processed_events::ros2::RclLifecycleTransition> {
+        let state_machine_arc = self
+            .lifecycle_state_machines
+            .get(&event.state_machine.into_id(context_id))
+            .ok_or(error::NotFound::lifecycle_state_machine(event.state_machine))
+            .map_err(|e| e.with_ros2_event(event, time, context))
+            .wrap_err("Lifecycle state machine not found. Missing rcl_lifecycle_state_machine_init event?")?;
+
+        state_machine_arc
+            .lock()
+            .unwrap()
+            .transition(&event.start_label, &event.goal_label);
+
+        Ok(processed_events::ros2::RclLifecycleTransition {
+            state_machine: state_machine_arc.clone(),
+            start_label: event.start_label.clone(),
+            goal_label: event.goal_label.clone(),
+        })
+    }
+
+    fn get_or_create_executor(
+        &mut self,
+        context_id: ContextId,
+        context: &Context,
+    ) -> Arc<Mutex<Executor>> {
+        let vtid = context
+            .vtid()
+            .expect("Executor events without a vtid are not processed");
+        self.executors_by_thread
+            .entry(vtid.into_id(context_id))
+            .or_insert_with(|| Executor::new(context.hostname(), context.vpid(), vtid))
+            .clone()
+    }
+
+    pub(super) fn process_rclcpp_executor_get_next_ready(
+        &mut self,
+        _event: &raw_events::ros2::RclCppExecutorGetNextReady,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> processed_events::ros2::RclcppExecutorGetNextReady {
+        let executor_arc = self.get_or_create_executor(context_id, context);
+        let previous = executor_arc
+            .lock()
+            .unwrap()
+            .enter_state(ExecutorState::GetNextReady, time);
+
+        processed_events::ros2::RclcppExecutorGetNextReady {
+            executor: executor_arc,
+            previous,
+        }
+    }
+
+    pub(super) fn process_rclcpp_executor_wait_for_work(
+        &mut self,
+        event: &raw_events::ros2::RclCppExecutorWaitForWork,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> processed_events::ros2::RclcppExecutorWaitForWork {
+        let executor_arc = self.get_or_create_executor(context_id, context);
+        let previous = executor_arc
+            .lock()
+            .unwrap()
+            .enter_state(ExecutorState::WaitForWork, time);
+
+        processed_events::ros2::RclcppExecutorWaitForWork {
+            executor: executor_arc,
+            timeout: event.timeout,
+            previous,
+        }
+    }
+
+    pub(super) fn process_rclcpp_executor_execute(
+        &mut self,
+        event: &raw_events::ros2::RclCppExecutorExecute,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> processed_events::ros2::RclcppExecutorExecute {
+        let executor_arc = self.get_or_create_executor(context_id, context);
+        let mut executor = executor_arc.lock().unwrap();
+        let previous = executor.enter_state(ExecutorState::Execute, time);
+        let ready_since = executor.get_ready_since();
+        drop(executor);
+
+        processed_events::ros2::RclcppExecutorExecute {
+            executor: executor_arc,
+            handle: event.handle,
+            previous,
+            ready_since,
+        }
+    }
+
+    pub(super) fn process_rclcpp_construct_ring_buffer(
+        &mut self,
+        event: &raw_events::ros2::RclCppConstructRingBuffer,
+        _time: Time,
+        context_id: ContextId,
+        _context: &Context,
+    ) -> processed_events::ros2::RclcppConstructRingBuffer {
+        // The ring buffers are traced since Jazzy.
+        self.infer_distro(context_id, raw_events::ros2::RosDistro::Jazzy);
+        let ring_buffer_arc = RingBuffer::new(event.buffer, event.capacity);
+
+        self.ring_buffers
+            .insert(event.buffer.into_id(context_id), ring_buffer_arc.clone())
+            .inspect(|old| {
+                log::debug!(
+                    target: "rclcpp_construct_ring_buffer",
+                    "Found different RingBuffer with same address. Assuming old RingBuffer was deleted: old={}",
+                    old.lock().unwrap()
+                );
+            });
+
+        processed_events::ros2::RclcppConstructRingBuffer {
+            ring_buffer: ring_buffer_arc,
+        }
+    }
+
+    pub(super) fn process_rclcpp_buffer_to_ipb(
+        &mut self,
+        event: &raw_events::ros2::RclCppBufferToIpb,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> Result<processed_events::ros2::RclcppBufferToIpb> {
+        let ring_buffer_arc = self
+            .ring_buffers
+            .get(&event.buffer.into_id(context_id))
+            .ok_or(error::NotFound::ring_buffer(event.buffer))
+            .map_err(|e| e.with_ros2_event(event, time, context))
+            .wrap_err("Ring buffer not found. Missing rclcpp_construct_ring_buffer event?")?
+            .clone();
+
+        ring_buffer_arc.lock().unwrap().set_ipb(event.ipb);
+        self.ring_buffers_by_ipb
+            .insert(event.ipb.into_id(context_id), ring_buffer_arc.clone());
+
+        Ok(processed_events::ros2::RclcppBufferToIpb {
+            ring_buffer: ring_buffer_arc,
+        })
+    }
+
+    pub(super) fn process_rclcpp_ipb_to_subscription(
+        &mut self,
+        event: &raw_events::ros2::RclCppIpbToSubscription,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> Result<processed_events::ros2::RclcppIpbToSubscription> {
+        let ring_buffer_arc = self
+            .ring_buffers_by_ipb
+            .get(&event.ipb.into_id(context_id))
+            .ok_or(error::NotFound::ring_buffer_by_ipb(event.ipb))
+            .map_err(|e| e.with_ros2_event(event, time, context))
+            .wrap_err("Ring buffer not found. Missing rclcpp_buffer_to_ipb event?")?
+            .clone();
+
+        let subscriber_arc = context
+            .vtid()
+            .and_then(|vtid| self.rcl_subscribers_by_thread.get(&vtid.into_id(context_id)))
+            .ok_or_else(|| {
+                eyre!(
+                    "No subscriber was initialized by this thread. Missing rcl_subscription_init event? [{time}] {event:?} {context:?}"
+                )
+            })?
+            .clone();
+
+        subscriber_arc
+            .lock()
+            .unwrap()
+            .rclcpp_intra_process_init(event.subscription)
+            .map_err(|e| error::Causes::AlreadyInitialized(e, subscriber_arc.clone().into()))?;
+        ring_buffer_arc
+            .lock()
+            .unwrap()
+            .set_subscriber(&subscriber_arc);
+
+        self.subscribers_by_rclcpp_intra_process.insert(
+            event.subscription.into_id(context_id),
+            subscriber_arc.clone(),
+        );
+
+        Ok(processed_events::ros2::RclcppIpbToSubscription {
+            ring_buffer: ring_buffer_arc,
+            subscriber: subscriber_arc,
+        })
+    }
+
+    fn get_ring_buffer(
+        &self,
+        buffer: u64,
+        context_id: ContextId,
+    ) -> Result<&Arc<Mutex<RingBuffer>>, error::NotFound> {
+        self.ring_buffers
+            .get(&buffer.into_id(context_id))
+            .ok_or(error::NotFound::ring_buffer(buffer))
+    }
+
+    pub(super) fn process_rclcpp_ring_buffer_enqueue(
+        &mut self,
+        event: &raw_events::ros2::RclCppRingBufferEnqueue,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> Result<processed_events::ros2::RclcppRingBufferEnqueue> {
+        let ring_buffer_arc = self
+            .get_ring_buffer(event.buffer, context_id)
+            .map_err(|e| e.with_ros2_event(event, time, context))
+            .wrap_err("Ring buffer not found. Missing rclcpp_construct_ring_buffer event?")?
+            .clone();
+
+        let message_arc = context
+            .vtid()
+            .and_then(|vtid| {
+                self.intra_process_messages_by_thread
+                    .get(&vtid.into_id(context_id))
+            })
+            .cloned();
+
+        let mut ring_buffer = ring_buffer_arc.lock().unwrap();
+        let overwritten_message = ring_buffer
+            .enqueue(event.index, event.size, message_arc.clone())
+            .filter(|_| event.overwritten);
+        let size = ring_buffer.get_size();
+        drop(ring_buffer);
+
+        Ok(processed_events::ros2::RclcppRingBufferEnqueue {
+            ring_buffer: ring_buffer_arc,
+            message: message_arc,
+            overwritten: event.overwritten,
+            overwritten_message,
+            size,
+        })
+    }
+
+    pub(super) fn process_rclcpp_ring_buffer_dequeue(
+        &mut self,
+        event: &raw_events::ros2::RclCppRingBufferDequeue,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> Result<processed_events::ros2::RclcppRingBufferDequeue> {
+        let ring_buffer_arc = self
+            .get_ring_buffer(event.buffer, context_id)
+            .map_err(|e| e.with_ros2_event(event, time, context))
+            .wrap_err("Ring buffer not found. Missing rclcpp_construct_ring_buffer event?")?
+            .clone();
+
+        let mut ring_buffer = ring_buffer_arc.lock().unwrap();
+        let published_message = ring_buffer.dequeue(event.index, event.size);
+        let subscriber_arc = ring_buffer.get_subscriber();
+        let size = ring_buffer.get_size();
+        drop(ring_buffer);
+
+        // The dequeued message is executed by the intra-process callback of the subscriber.
+        let message_arc = subscriber_arc.map(|subscriber_arc| {
+            let message_arc = Arc::new(Mutex::new(SubscriptionMessage::new_intra_process(
+                subscriber_arc.clone(),
+                published_message,
+                time,
+            )));
+            subscriber_arc
+                .lock()
+                .unwrap()
+                .replace_taken_message(message_arc.clone());
+
+            message_arc
+        });
+
+        Ok(processed_events::ros2::RclcppRingBufferDequeue {
+            ring_buffer: ring_buffer_arc,
+            message: message_arc,
+            size,
+        })
+    }
+
+    pub(super) fn process_rclcpp_ring_buffer_clear(
+        &mut self,
+        event: &raw_events::ros2::RclCppRingBufferClear,
+        time: Time,
+        context_id: ContextId,
+        context: &Context,
+    ) -> Result<processed_events::ros2::RclcppRingBufferClear> {
+        let ring_buffer_arc = self
+            .get_ring_buffer(event.buffer, context_id)
+            .map_err(|e| e.with_ros2_event(event, time, context))
+            .wrap_err("Ring buffer not found. Missing rclcpp_construct_ring_buffer event?")?
+            .clone();
+
+        let cleared_messages = ring_buffer_arc.lock().unwrap().clear();
+
+        Ok(processed_events::ros2::RclcppRingBufferClear {
+            ring_buffer: ring_buffer_arc,
+            cleared_messages,
         })
     }
 }
@@ -0,0 +1,43 @@
+use bt2_derive::TryFromBtFieldConst;
+use bt2_sys::event::BtEventConst;
+use derive_more::Debug;
+use derive_more::derive::From;
+
+use super::FromBtEvent;
+
+#[derive(Debug, TryFromBtFieldConst, Clone)]
+pub struct SchedSwitch {
+    pub prev_comm: String,
+    pub prev_tid: i32,
+    /// Task state of the previous thread. No sleep state bits are set if it was preempted
+    /// while runnable.
+    pub prev_state: i64,
+    pub next_comm: String,
+    pub next_tid: i32,
+}
+
+#[derive(Debug, TryFromBtFieldConst, Clone)]
+pub struct SchedWakeup {
+    pub comm: String,
+    pub tid: i32,
+    pub target_cpu: i32,
+}
+
+#[derive(Debug, From, Clone)]
+pub enum Event {
+    SchedSwitch(SchedSwitch),
+    SchedWakeup(SchedWakeup),
+}
+
+impl FromBtEvent for Event {
+    fn from_event(event: &BtEventConst) -> Option<Self> {
+        let event_class = event.get_class();
+        let event_name = event_class.get_name().unwrap();
+
+        Some(match event_name {
+            "sched_switch" => SchedSwitch::from_event(event)?.into(),
+            "sched_wakeup" => SchedWakeup::from_event(event)?.into(),
+            _ => return None,
+        })
+    }
+}
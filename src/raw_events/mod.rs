@@ -1,17 +1,28 @@
+use std::ffi::CStr;
 use std::fmt::{Debug, Display};
 
+use bt2_sys::clock_snapshot::BtClockSnapshotConst;
 use bt2_sys::event::BtEventConst;
 use bt2_sys::field::BtFieldConst;
-use bt2_sys::message::BtEventMessageConst;
+use bt2_sys::message::{
+    BtDiscardedEventsMessageConst, BtDiscardedPacketsMessageConst, BtEventMessageConst,
+};
+use bt2_sys::stream::BtStreamConst;
 use bt2_sys::trace::BtEnvironmentEntry;
 use derive_more::derive::From;
 
+use crate::data_quality::{DiscardedItems, DiscardedWindow};
 use crate::events_common::{Context, Time};
 
 pub mod ros2;
 
 pub mod r2r;
 
+pub mod kernel;
+
+#[cfg(test)]
+pub mod testing;
+
 pub trait FromBtEvent: Sized {
     fn from_event(event: &BtEventConst) -> Option<Self>;
 }
@@ -37,30 +48,45 @@ pub fn time_from_message(message: &BtEventMessageConst) -> Time {
     Time::from_nanos(value_from_origin)
 }
 
-pub fn context_from_event(event: &BtEventConst) -> Context {
-    let cpu_id = event
-        .get_packet()
-        .get_context_field()
-        .unwrap_or_else(|| panic!("Missing context field in event {event:?}"))
-        .into_struct()
-        .get_field_by_name_cstr(c"cpu_id")
-        .unwrap_or_else(|| panic!("Missing cpu_id field in event {event:?}"))
-        .into_uint()
-        .get_value()
-        .try_into()
-        .unwrap();
+fn cpu_id_from_event(event: &BtEventConst) -> Option<u32> {
+    Some(
+        event
+            .get_packet()
+            .get_context_field()?
+            .into_struct()
+            .get_field_by_name_cstr(c"cpu_id")?
+            .into_uint()
+            .get_value()
+            .try_into()
+            .unwrap(),
+    )
+}
 
-    let trace = event.get_stream().get_trace();
-    let BtEnvironmentEntry::String(hostname) = trace
-        .get_environment_entry_by_name_cstr(c"hostname")
-        .expect("Trace missing hostname environment entry")
+fn hostname_from_stream(stream: &BtStreamConst) -> Option<String> {
+    let trace = stream.get_trace();
+    let BtEnvironmentEntry::String(hostname) =
+        trace.get_environment_entry_by_name_cstr(c"hostname")?
     else {
         panic!("Unexpected type (int) of hostname environment entry");
     };
-    let hostname = hostname
-        .get()
-        .expect("Hostname should be valid UTF-8.")
-        .to_string();
+    Some(
+        hostname
+            .get()
+            .expect("Hostname should be valid UTF-8.")
+            .to_string(),
+    )
+}
+
+/// Returns the context of a userspace event.
+///
+/// The `hostname` and `vpid` identify the objects of the event, so they must be present.
+/// This is checked for every stream before its events are processed, see
+/// [`crate::preflight::TraceContexts`]. The `cpu_id` and `vtid` are `None` and the
+/// `procname` is empty if missing.
+pub fn context_from_event(event: &BtEventConst) -> Context {
+    let cpu_id = cpu_id_from_event(event);
+    let hostname = hostname_from_stream(&event.get_stream())
+        .expect("Trace missing hostname environment entry");
 
     let common_context = event
         .get_common_context_field()
@@ -75,20 +101,94 @@ pub fn context_from_event(event: &BtEventConst) -> Context {
         .unwrap();
     let vtid = common_context
         .get_field_by_name_cstr(c"vtid")
-        .expect("Missing vtid in event context")
-        .into_int()
-        .get_value()
-        .try_into()
-        .unwrap();
+        .map(|field| field.into_int().get_value().try_into().unwrap());
     let procname = common_context
         .get_field_by_name_cstr(c"procname")
-        .expect("Missing procname  in event context")
-        .into_string()
-        .get_value()
-        .to_string();
+        .map(|field| field.into_string().get_value().to_string())
+        .unwrap_or_default();
     Context::new(cpu_id, vpid, vtid, procname, hostname)
 }
 
+/// Returns the context of a kernel event.
+///
+/// Kernel channels usually do not record the process context, so the `vpid`, `vtid`
+/// and `procname` are filled only if they are present. Otherwise, they are zero, `None`
+/// and empty.
+///
+/// Returns `None` if the `cpu_id` or the `hostname` is missing. The kernel events cannot
+/// be attributed to a CPU of a host without them.
+pub fn context_from_kernel_event(event: &BtEventConst) -> Option<Context> {
+    let cpu_id = cpu_id_from_event(event)?;
+    let hostname = hostname_from_stream(&event.get_stream())?;
+
+    let common_context = event
+        .get_common_context_field()
+        .map(BtFieldConst::into_struct);
+    let get_id = |name: &CStr| {
+        common_context
+            .as_ref()
+            .and_then(|context| context.get_field_by_name_cstr(name))
+            .map(|field| field.into_int().get_value().try_into().unwrap())
+    };
+    let vpid = get_id(c"vpid").unwrap_or_default();
+    let vtid = get_id(c"vtid");
+    let procname = common_context
+        .as_ref()
+        .and_then(|context| context.get_field_by_name_cstr(c"procname"))
+        .map(|field| field.into_string().get_value().to_string())
+        .unwrap_or_default();
+    Some(Context::new(Some(cpu_id), vpid, vtid, procname, hostname))
+}
+
+fn discarded_window(
+    items: DiscardedItems,
+    stream: &BtStreamConst,
+    count: Option<u64>,
+    clock_snapshots: Option<(BtClockSnapshotConst, BtClockSnapshotConst)>,
+) -> DiscardedWindow {
+    let time_range = clock_snapshots.map(|(start, end)| {
+        (
+            Time::from_nanos(start.get_value_from_origin().unwrap()),
+            Time::from_nanos(end.get_value_from_origin().unwrap()),
+        )
+    });
+
+    let stream_name = stream.get_name().map(ToOwned::to_owned);
+    // LTTng names per-CPU stream files `<channel>_<cpu>`.
+    let cpu = stream_name.as_deref().and_then(|name| {
+        let file_name = std::path::Path::new(name).file_name()?.to_str()?;
+        file_name.rsplit_once('_')?.1.parse().ok()
+    });
+
+    DiscardedWindow {
+        items,
+        hostname: hostname_from_stream(stream).unwrap_or_else(|| "unknown".to_owned()),
+        stream: stream_name,
+        cpu,
+        count,
+        start_time: time_range.map(|(start, _)| start),
+        end_time: time_range.map(|(_, end)| end),
+    }
+}
+
+pub fn discarded_events_window(message: &BtDiscardedEventsMessageConst) -> DiscardedWindow {
+    discarded_window(
+        DiscardedItems::Events,
+        &message.get_stream(),
+        message.get_count(),
+        message.get_default_clock_snapshots(),
+    )
+}
+
+pub fn discarded_packets_window(message: &BtDiscardedPacketsMessageConst) -> DiscardedWindow {
+    discarded_window(
+        DiscardedItems::Packets,
+        &message.get_stream(),
+        message.get_count(),
+        message.get_default_clock_snapshots(),
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct FullEvent {
     pub context: Context,
@@ -100,6 +200,7 @@ pub struct FullEvent {
 pub enum Event {
     Ros2(ros2::Event),
     R2r(r2r::Event),
+    Kernel(kernel::Event),
 }
 
 pub fn get_full_event(message: &BtEventMessageConst) -> Option<FullEvent> {
@@ -109,15 +210,25 @@ pub fn get_full_event(message: &BtEventMessageConst) -> Option<FullEvent> {
     let full_name = bt_event_class.get_name().unwrap();
     let provider = full_name.split(':').next().unwrap();
 
-    let event = match provider {
-        "ros2" => Event::Ros2(ros2::Event::from_event(&bt_event)?),
-        "r2r" => Event::R2r(r2r::Event::from_event(&bt_event)?),
+    let (event, context) = match provider {
+        "ros2" => (
+            Event::Ros2(ros2::Event::from_event(&bt_event)?),
+            context_from_event(&bt_event),
+        ),
+        "r2r" => (
+            Event::R2r(r2r::Event::from_event(&bt_event)?),
+            context_from_event(&bt_event),
+        ),
+        // Kernel event names have no provider prefix.
+        _ if !full_name.contains(':') => (
+            Event::Kernel(kernel::Event::from_event(&bt_event)?),
+            context_from_kernel_event(&bt_event)?,
+        ),
         _ => {
             return None;
         }
     };
 
-    let context = context_from_event(&bt_event);
     let time = time_from_message(message);
 
     Some(FullEvent {
@@ -17,6 +17,95 @@ pub struct RclInit {
     pub version: String,
 }
 
+/// Tracepoints of all supported distributions.
+const COMMON_TRACEPOINTS: &[&str] = &[
+    "rcl_init",
+    "rcl_node_init",
+    "rmw_publisher_init",
+    "rcl_publisher_init",
+    "rclcpp_publish",
+    "rcl_publish",
+    "rmw_publish",
+    "rmw_subscription_init",
+    "rcl_subscription_init",
+    "rclcpp_subscription_init",
+    "rclcpp_subscription_callback_added",
+    "rmw_take",
+    "rcl_take",
+    "rclcpp_take",
+    "rcl_service_init",
+    "rclcpp_service_callback_added",
+    "rcl_client_init",
+    "rcl_timer_init",
+    "rclcpp_timer_callback_added",
+    "rclcpp_timer_link_node",
+    "rclcpp_callback_register",
+    "callback_start",
+    "callback_end",
+    "rcl_lifecycle_state_machine_init",
+    "rcl_lifecycle_transition",
+    "rclcpp_executor_get_next_ready",
+    "rclcpp_executor_wait_for_work",
+    "rclcpp_executor_execute",
+];
+
+/// Intra-process communication tracepoints added in Jazzy.
+const INTRA_PROCESS_TRACEPOINTS: &[&str] = &[
+    "rclcpp_intra_publish",
+    "rclcpp_construct_ring_buffer",
+    "rclcpp_buffer_to_ipb",
+    "rclcpp_ipb_to_subscription",
+    "rclcpp_ring_buffer_enqueue",
+    "rclcpp_ring_buffer_dequeue",
+    "rclcpp_ring_buffer_clear",
+];
+
+/// ROS 2 distribution of a traced process.
+///
+/// The payloads and the set of tracepoints differ between the distributions,
+/// e.g., Humble does not trace the intra-process buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, derive_more::Display)]
+pub enum RosDistro {
+    /// Humble or older.
+    #[display("Humble")]
+    Humble,
+    #[display("Iron")]
+    Iron,
+    /// Jazzy or newer.
+    #[display("Jazzy")]
+    Jazzy,
+}
+
+impl RosDistro {
+    /// Detects the distribution from the `tracetools` version in the `rcl_init` event.
+    pub fn from_tracetools_version(version: &str) -> Option<Self> {
+        let major: u32 = version.split('.').next()?.parse().ok()?;
+        Some(match major {
+            0..=4 => Self::Humble,
+            5..=7 => Self::Iron,
+            _ => Self::Jazzy,
+        })
+    }
+
+    /// Returns the names of the `ros2` tracepoints of the distribution without the provider.
+    pub fn tracepoints(self) -> impl Iterator<Item = &'static str> {
+        let intra_process: &[&str] = if self.traces_intra_process_buffers() {
+            INTRA_PROCESS_TRACEPOINTS
+        } else {
+            &[]
+        };
+        COMMON_TRACEPOINTS.iter().chain(intra_process).copied()
+    }
+
+    /// Returns `true` if the distribution traces the intra-process ring buffers.
+    ///
+    /// Older distributions emit a second `rclcpp_subscription_init` for the intra-process
+    /// subscription instead and execute its callback without any take event.
+    pub fn traces_intra_process_buffers(self) -> bool {
+        self >= Self::Jazzy
+    }
+}
+
 #[derive(Debug, TryFromBtFieldConst, Clone)]
 pub struct RclNodeInit {
     #[debug("{node_handle:#x}")]
@@ -433,3 +522,26 @@ impl FromBtEvent for Event {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distro_from_tracetools_version() {
+        assert_eq!(
+            RosDistro::from_tracetools_version("4.1.1"),
+            Some(RosDistro::Humble)
+        );
+        assert_eq!(
+            RosDistro::from_tracetools_version("7.1.0"),
+            Some(RosDistro::Iron)
+        );
+        assert_eq!(
+            RosDistro::from_tracetools_version("8.2.3"),
+            Some(RosDistro::Jazzy)
+        );
+        assert_eq!(RosDistro::from_tracetools_version("unknown"), None);
+        assert!(!RosDistro::Humble.traces_intra_process_buffers());
+    }
+}
@@ -0,0 +1,167 @@
+//! Streams of raw events for the tests of the processing and the analyses.
+
+use crate::events_common::{Context, Time};
+
+use super::{FullEvent, kernel, ros2};
+
+pub const HOSTNAME: &str = "host";
+pub const VPID: u32 = 100;
+
+/// Raw events of a single traced process in the order they were emitted.
+#[derive(Debug, Default)]
+pub struct TraceBuilder {
+    events: Vec<FullEvent>,
+}
+
+impl TraceBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the `event` emitted by the thread `vtid` at `time` nanoseconds.
+    pub fn event(&mut self, time: i64, vtid: u32, event: impl Into<ros2::Event>) -> &mut Self {
+        self.events.push(FullEvent {
+            context: Context::new(
+                Some(0),
+                VPID,
+                Some(vtid),
+                "test".to_owned(),
+                HOSTNAME.to_owned(),
+            ),
+            time: Time::from_nanos(time),
+            event: event.into().into(),
+        });
+        self
+    }
+
+    /// Adds the initialization of the node `name` with the rcl handle `node`.
+    pub fn node(&mut self, time: i64, node: u64, name: &str) -> &mut Self {
+        self.event(
+            time,
+            VPID,
+            ros2::RclNodeInit {
+                node_handle: node,
+                rmw_handle: node + 1,
+                node_name: name.to_owned(),
+                namespace: "/".to_owned(),
+            },
+        )
+    }
+
+    /// Adds the initialization of a timer of the `node` with the `callback`.
+    pub fn timer(&mut self, time: i64, node: u64, timer: u64, callback: u64) -> &mut Self {
+        self.event(
+            time,
+            VPID,
+            ros2::RclTimerInit {
+                timer_handle: timer,
+                period: 1_000,
+            },
+        )
+        .event(
+            time,
+            VPID,
+            ros2::RclcppTimerCallbackAdded {
+                timer_handle: timer,
+                callback,
+            },
+        )
+        .event(
+            time,
+            VPID,
+            ros2::RclcppTimerLinkNode {
+                timer_handle: timer,
+                node_handle: node,
+            },
+        )
+    }
+
+    /// Adds the initialization of a publisher of the `node` to the `topic`.
+    ///
+    /// The rmw handle of the publisher is `publisher + 1`.
+    pub fn publisher(
+        &mut self,
+        time: i64,
+        node: u64,
+        publisher: u64,
+        topic: &str,
+        queue_depth: usize,
+    ) -> &mut Self {
+        self.event(
+            time,
+            VPID,
+            ros2::RclPublisherInit {
+                publisher_handle: publisher,
+                node_handle: node,
+                rmw_publisher_handle: publisher + 1,
+                topic_name: topic.to_owned(),
+                queue_depth,
+            },
+        )
+    }
+
+    /// Adds the initialization of a subscription of the `node` to the `topic`.
+    ///
+    /// The rmw handle of the subscription is `subscription + 1`.
+    pub fn subscription(
+        &mut self,
+        time: i64,
+        node: u64,
+        subscription: u64,
+        topic: &str,
+        queue_depth: usize,
+    ) -> &mut Self {
+        self.event(
+            time,
+            VPID,
+            ros2::RclSubscriptionInit {
+                subscription_handle: subscription,
+                node_handle: node,
+                rmw_subscription_handle: subscription + 1,
+                topic_name: topic.to_owned(),
+                queue_depth,
+            },
+        )
+    }
+
+    /// Adds an execution of the `callback` on the thread `vtid` from `start` to `end`.
+    pub fn execution(&mut self, start: i64, end: i64, vtid: u32, callback: u64) -> &mut Self {
+        self.event(
+            start,
+            vtid,
+            ros2::CallbackStart {
+                callback,
+                is_intra_process: false,
+            },
+        )
+        .event(end, vtid, ros2::CallbackEnd { callback })
+    }
+
+    /// Adds the switch on `cpu` from the thread `prev_tid` in `prev_state` to `next_tid`.
+    pub fn sched_switch(
+        &mut self,
+        time: i64,
+        cpu: u32,
+        prev_tid: i32,
+        prev_state: i64,
+        next_tid: i32,
+    ) -> &mut Self {
+        self.events.push(FullEvent {
+            context: Context::new(Some(cpu), 0, None, String::new(), HOSTNAME.to_owned()),
+            time: Time::from_nanos(time),
+            event: kernel::Event::SchedSwitch(kernel::SchedSwitch {
+                prev_comm: format!("thread{prev_tid}"),
+                prev_tid,
+                prev_state,
+                next_comm: format!("thread{next_tid}"),
+                next_tid,
+            })
+            .into(),
+        });
+        self
+    }
+
+    pub fn build(&mut self) -> Vec<FullEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
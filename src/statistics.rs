@@ -1,9 +1,13 @@
+use std::collections::BTreeMap;
 use std::ops::Deref;
 use std::str::FromStr;
+use std::sync::OnceLock;
 
 use derive_more::derive::{Deref, Display, Into};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+#[derive(Debug, Clone)]
 pub struct Sorted<T> {
     values: Vec<T>,
 }
@@ -127,29 +131,388 @@ impl FromStr for Quantile {
     }
 }
 
-pub trait Mean {
-    type Output;
+/// Relative error of the quantiles computed by a [`QuantileSketch`].
+#[derive(Debug, Display, Into, Deref, Clone, Copy, PartialEq)]
+#[display("{}", _0)]
+pub struct RelativeError(f64);
+
+impl RelativeError {
+    pub fn new(value: f64) -> Result<Self, RelativeErrorConversionError> {
+        if value > 0.0 && value < 1.0 {
+            Ok(Self(value))
+        } else {
+            Err(RelativeErrorConversionError)
+        }
+    }
+
+    #[must_use]
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Relative error must be in the range (0, 1)")]
+pub struct RelativeErrorConversionError;
+
+#[derive(Debug, Clone, Error)]
+pub enum RelativeErrorStringConversionError {
+    #[error("Cannot parse relative error: {0} {1:?}")]
+    ParseFloatError(#[source] std::num::ParseFloatError, String),
+
+    #[error(transparent)]
+    RelativeErrorConversionError(#[from] RelativeErrorConversionError),
+}
+
+impl FromStr for RelativeError {
+    type Err = RelativeErrorStringConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(RelativeError::new(s.parse().map_err(|e| {
+            RelativeErrorStringConversionError::ParseFloatError(e, s.to_string())
+        })?)?)
+    }
+}
 
-    fn mean(&self) -> Option<Self::Output>;
+/// Histogram of values with logarithmic buckets.
+///
+/// The quantiles are within the relative error of the exact nearest rank quantiles,
+/// except the minimum and maximum, which are exact. The number of buckets grows with
+/// the logarithm of the range of the values, not with their count.
+#[derive(Debug, Clone)]
+pub struct QuantileSketch {
+    relative_error: RelativeError,
+    /// Logarithm of the ratio of the bucket bounds.
+    gamma_ln: f64,
+    /// Counts of the positive values by their bucket index.
+    positive: BTreeMap<i32, u64>,
+    /// Counts of the negative values by the bucket index of their absolute value.
+    negative: BTreeMap<i32, u64>,
+    zeros: u64,
+    count: u64,
+    min: i64,
+    max: i64,
+    /// Running mean and sum of squared differences from it by Welford's algorithm.
+    mean: f64,
+    m2: f64,
 }
 
-impl Mean for &[i64] {
-    type Output = i64;
+impl QuantileSketch {
+    #[must_use]
+    pub fn new(relative_error: RelativeError) -> Self {
+        let gamma = (1.0 + relative_error.value()) / (1.0 - relative_error.value());
+        Self {
+            relative_error,
+            gamma_ln: gamma.ln(),
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zeros: 0,
+            count: 0,
+            min: i64::MAX,
+            max: i64::MIN,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    /// Bucket `i` contains the values in the range (gamma^(i-1), gamma^i].
+    fn bucket_index(&self, value: u64) -> i32 {
+        ((value as f64).ln() / self.gamma_ln).ceil() as i32
+    }
+
+    /// Returns the value within the relative error of every value of the bucket.
+    fn bucket_value(&self, index: i32) -> f64 {
+        let gamma = self.gamma_ln.exp();
+        2.0 * (f64::from(index) * self.gamma_ln).exp() / (gamma + 1.0)
+    }
+
+    pub fn push(&mut self, value: i64) {
+        match value.cmp(&0) {
+            std::cmp::Ordering::Greater => {
+                *self
+                    .positive
+                    .entry(self.bucket_index(value.unsigned_abs()))
+                    .or_default() += 1;
+            }
+            std::cmp::Ordering::Less => {
+                *self
+                    .negative
+                    .entry(self.bucket_index(value.unsigned_abs()))
+                    .or_default() += 1;
+            }
+            std::cmp::Ordering::Equal => self.zeros += 1,
+        }
+
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        let delta = value as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value as f64 - self.mean);
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    #[must_use]
+    pub fn relative_error(&self) -> RelativeError {
+        self.relative_error
+    }
+
+    /// Returns the approximate value at the given quantile
+    ///
+    /// Uses the nearest rank method like [`Sorted::quantile`].
+    ///
+    /// # Errors
+    /// If no values were added, the function will return `None`
+    #[must_use]
+    pub fn quantile(&self, quantile: Quantile) -> Option<i64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((quantile.value() * self.count as f64).ceil() as u64).clamp(1, self.count);
+        if rank == 1 {
+            return Some(self.min);
+        }
+        if rank == self.count {
+            return Some(self.max);
+        }
+
+        let buckets = self
+            .negative
+            .iter()
+            .rev()
+            .map(|(&index, &count)| (-self.bucket_value(index), count))
+            .chain(std::iter::once((0.0, self.zeros)))
+            .chain(
+                self.positive
+                    .iter()
+                    .map(|(&index, &count)| (self.bucket_value(index), count)),
+            );
+        let mut seen = 0;
+        for (value, count) in buckets {
+            seen += count;
+            if seen >= rank {
+                return Some((value.round() as i64).clamp(self.min, self.max));
+            }
+        }
+        unreachable!("Rank {rank} should not exceed the count {}", self.count)
+    }
+
+    #[must_use]
+    pub fn median(&self) -> Option<i64> {
+        const MEDIAN_QUANTILE: Quantile = Quantile(0.5);
+        self.quantile(MEDIAN_QUANTILE)
+    }
 
-    fn mean(&self) -> Option<Self::Output> {
-        if self.is_empty() {
+    /// Returns the mean and the sample standard deviation of the values.
+    ///
+    /// The standard deviation is `NaN` for a single value.
+    #[must_use]
+    pub fn mean_and_std_dev(&self) -> Option<(i64, f64)> {
+        if self.count == 0 {
             return None;
         }
+        let std_dev = if self.count == 1 {
+            f64::NAN
+        } else {
+            (self.m2 / (self.count - 1) as f64).sqrt()
+        };
+        Some((self.mean.round() as i64, std_dev))
+    }
+}
 
-        let sum: i128 = self.iter().copied().map(i128::from).sum();
-        let mean = (sum / self.len() as i128)
-            .try_into()
-            .expect("Average of i64 values should fit into i64");
+/// How the samples of the latency and duration series are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StatisticsBackend {
+    /// All samples are kept and the quantiles are exact.
+    #[default]
+    Exact,
+    /// Only a [`QuantileSketch`] of the samples is kept.
+    Sketch(RelativeError),
+}
 
-        Some(mean)
+impl StatisticsBackend {
+    /// Returns empty samples kept by this backend.
+    #[must_use]
+    pub fn samples(self) -> Samples {
+        match self {
+            Self::Exact => Samples::Exact(ExactSamples::default()),
+            Self::Sketch(relative_error) => Samples::Sketch(QuantileSketch::new(relative_error)),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_exact(self) -> bool {
+        matches!(self, Self::Exact)
     }
 }
 
+/// All samples in the order they were added.
+///
+/// The samples are sorted for the quantiles once and kept sorted until the next sample
+/// is added.
+#[derive(Debug, Clone, Default)]
+pub struct ExactSamples {
+    values: Vec<i64>,
+    sorted: OnceLock<Sorted<i64>>,
+}
+
+impl ExactSamples {
+    pub fn push(&mut self, value: i64) {
+        self.values.push(value);
+        self.sorted.take();
+    }
+
+    #[must_use]
+    pub fn sorted(&self) -> &Sorted<i64> {
+        self.sorted
+            .get_or_init(|| Sorted::from_unsorted(&self.values))
+    }
+}
+
+impl From<Vec<i64>> for ExactSamples {
+    fn from(values: Vec<i64>) -> Self {
+        Self {
+            values,
+            sorted: OnceLock::new(),
+        }
+    }
+}
+
+impl Deref for ExactSamples {
+    type Target = [i64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+/// Series of samples in nanoseconds.
+#[derive(Debug, Clone)]
+pub enum Samples {
+    Exact(ExactSamples),
+    Sketch(QuantileSketch),
+}
+
+impl Samples {
+    pub fn push(&mut self, value: i64) {
+        match self {
+            Self::Exact(values) => values.push(value),
+            Self::Sketch(sketch) => sketch.push(value),
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Exact(values) => values.len(),
+            Self::Sketch(sketch) => sketch.len(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the value at each of the `quantiles`.
+    ///
+    /// # Errors
+    /// If there are no samples, the function will return `None`
+    #[must_use]
+    pub fn quantiles(&self, quantiles: &[Quantile]) -> Option<Vec<(Quantile, i64)>> {
+        match self {
+            Self::Exact(values) => {
+                let sorted = values.sorted();
+                quantiles
+                    .iter()
+                    .map(|&q| Some((q, *sorted.quantile(q)?)))
+                    .collect()
+            }
+            Self::Sketch(sketch) => quantiles
+                .iter()
+                .map(|&q| Some((q, sketch.quantile(q)?)))
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn median(&self) -> Option<i64> {
+        match self {
+            Self::Exact(values) => values.sorted().median().copied(),
+            Self::Sketch(sketch) => sketch.median(),
+        }
+    }
+
+    /// Returns the samples for the JSON outputs.
+    ///
+    /// The sketched samples are summarized by the `quantiles`.
+    #[must_use]
+    pub fn export(&self, quantiles: &[Quantile]) -> SamplesExport {
+        match self {
+            Self::Exact(values) => SamplesExport::Raw(values.to_vec()),
+            Self::Sketch(sketch) => SamplesExport::Summary(SamplesSummary {
+                count: sketch.len(),
+                mean: sketch.mean_and_std_dev().map(|(mean, _)| mean),
+                relative_error: sketch.relative_error().value(),
+                quantiles: self
+                    .quantiles(quantiles)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(quantile, value)| QuantileValue {
+                        quantile: quantile.value(),
+                        value,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// Samples in the JSON outputs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SamplesExport<T = i64> {
+    /// All samples in the order they were taken.
+    Raw(Vec<T>),
+    /// Summary of the samples kept by a [`QuantileSketch`].
+    Summary(SamplesSummary),
+}
+
+impl<T> SamplesExport<T> {
+    /// Returns the raw samples, or `None` if only their summary was exported.
+    pub fn into_raw(self) -> Option<Vec<T>> {
+        match self {
+            Self::Raw(values) => Some(values),
+            Self::Summary(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplesSummary {
+    pub count: usize,
+    /// Rounded mean, `None` if there are no samples.
+    pub mean: Option<i64>,
+    /// Relative error of the quantiles.
+    pub relative_error: f64,
+    pub quantiles: Vec<QuantileValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantileValue {
+    pub quantile: f64,
+    pub value: i64,
+}
+
 #[cfg(test)]
 mod test_sorted {
     use super::*;
@@ -204,3 +567,74 @@ mod test_sorted {
         assert_eq!(Some(&5), sorted.quantile(1.0.try_into().unwrap()));
     }
 }
+
+#[cfg(test)]
+mod test_samples {
+    use super::*;
+
+    #[test]
+    fn test_exact_samples_sorted_until_push() {
+        let mut samples = StatisticsBackend::Exact.samples();
+        for value in [3, 1, 2] {
+            samples.push(value);
+        }
+        let median = Quantile::new(0.5).unwrap();
+        let max = Quantile::new(1.0).unwrap();
+
+        assert_eq!(samples.median(), Some(2));
+        assert_eq!(samples.quantiles(&[max]), Some(vec![(max, 3)]));
+
+        samples.push(0);
+        samples.push(5);
+        assert_eq!(
+            samples.quantiles(&[median, max]),
+            Some(vec![(median, 2), (max, 5)])
+        );
+        assert_eq!(samples.export(&[]), SamplesExport::Raw(vec![3, 1, 2, 0, 5]));
+    }
+}
+
+#[cfg(test)]
+mod test_sketch {
+    use super::*;
+
+    #[test]
+    fn test_sketch_quantiles_within_relative_error() {
+        let relative_error = RelativeError::new(0.01).unwrap();
+        let mut sketch = QuantileSketch::new(relative_error);
+        let values: Vec<i64> = (-100_i64..10_000).map(|i| i * i.abs() * 37).collect();
+        for &value in values.iter().rev() {
+            sketch.push(value);
+        }
+        let sorted = Sorted::from_unsorted(&values);
+
+        assert_eq!(sketch.len(), values.len());
+        for q in [0.0, 0.01, 0.1, 0.25, 0.5, 0.9, 0.99, 1.0] {
+            let q = Quantile::new(q).unwrap();
+            let exact = *sorted.quantile(q).unwrap() as f64;
+            let approximate = sketch.quantile(q).unwrap() as f64;
+            assert!(
+                (approximate - exact).abs() <= exact.abs() * relative_error.value(),
+                "{q}: {approximate} != {exact}"
+            );
+        }
+        assert_eq!(sketch.quantile(0.0.try_into().unwrap()), Some(values[0]));
+        assert_eq!(
+            sketch.quantile(1.0.try_into().unwrap()),
+            values.last().copied()
+        );
+
+        let mean = sketch.mean_and_std_dev().unwrap().0;
+        let exact_mean = values.iter().sum::<i64>() / values.len() as i64;
+        assert!((mean - exact_mean).abs() <= 1);
+    }
+
+    #[test]
+    fn test_empty_sketch() {
+        let sketch = QuantileSketch::new(RelativeError::new(0.05).unwrap());
+        assert_eq!(None, sketch.median());
+        assert!(sketch.mean_and_std_dev().is_none());
+        assert!("0".parse::<RelativeError>().is_err());
+        assert!("1.5".parse::<RelativeError>().is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use std::io::Cursor;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use color_eyre::eyre::{Context, OptionExt, bail, ensure, eyre};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::argsv2::chart_args::{
+    ChartOutputFormat, ChartRequest, ChartVariants, ChartedValue, HistogramData,
+};
+use crate::argsv2::viewer_args::ViewerArgs;
+use crate::chart_links::query;
+use crate::charting;
+
+const INDEX_PAGE: &str = include_str!("index.html");
+
+/// Directory inside the data directory into which the charts are rendered.
+const CHARTS_DIRECTORY: &str = "charts";
+
+const DEFAULT_CHART_SIZE: u32 = 800;
+/// Chart sizes accepted from the viewer page. Larger images take too long or too much memory
+/// to render.
+const CHART_SIZES: RangeInclusive<u32> = 100..=4096;
+/// Histogram bin counts accepted from the viewer page.
+const HISTOGRAM_BINS: RangeInclusive<usize> = 1..=1000;
+
+struct Viewer {
+    dotfile: PathBuf,
+    dot_executable: PathBuf,
+    data_directory: PathBuf,
+    charts_directory: PathBuf,
+}
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// Starts the viewer server and serves requests until the process is terminated.
+pub fn run(args: &ViewerArgs) -> color_eyre::Result<()> {
+    if args.viewer.is_some() {
+        log::warn!("The --viewer option is deprecated and ignored, the viewer is built in");
+    }
+    if args.tracer_exec.is_some() {
+        log::warn!("The --tracer-exec option is deprecated and ignored, the viewer is built in");
+    }
+
+    ensure!(
+        args.dotfile.is_file(),
+        "The dotfile {} does not exist",
+        args.dotfile.display()
+    );
+
+    let data_directory = args.data.clone().unwrap_or_else(|| PathBuf::from("."));
+    let charts_directory = data_directory.join(CHARTS_DIRECTORY);
+    std::fs::create_dir_all(&charts_directory).wrap_err_with(|| {
+        format!(
+            "Failed to create charts directory {}",
+            charts_directory.display()
+        )
+    })?;
+
+    let viewer = Viewer {
+        dotfile: args.dotfile.clone(),
+        dot_executable: args.dot.clone(),
+        data_directory,
+        charts_directory,
+    };
+
+    let server = Server::http(&args.address).map_err(|error| {
+        eyre!(
+            "Failed to start the viewer server at {}: {error}",
+            args.address
+        )
+    })?;
+
+    println!("Viewer is running at http://{}/", server.server_addr());
+    println!("Press Ctrl+C to stop it.");
+
+    for request in server.incoming_requests() {
+        log::debug!("{} {}", request.method(), request.url());
+        let response = viewer.handle(&request);
+        if let Err(error) = request.respond(response) {
+            log::warn!("Failed to send a response: {error}");
+        }
+    }
+
+    Ok(())
+}
+
+impl Viewer {
+    fn handle(&self, request: &Request) -> HttpResponse {
+        if request.method() != &Method::Get {
+            return text_response(405, "Only GET requests are supported");
+        }
+
+        let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+
+        let result = match path {
+            "/" | "/index.html" => Ok(content_response(
+                INDEX_PAGE.as_bytes().to_vec(),
+                "text/html; charset=utf-8",
+            )),
+            "/graph.dot" => self.dot_source(),
+            "/graph.svg" => self.render_graph(),
+            "/chart" => self.render_chart(query),
+            _ => return text_response(404, "Not found"),
+        };
+
+        result.unwrap_or_else(|error| {
+            log::error!("Request {} failed: {error:#}", request.url());
+            text_response(500, &format!("{error:#}"))
+        })
+    }
+
+    fn dot_source(&self) -> color_eyre::Result<HttpResponse> {
+        let dot = std::fs::read(&self.dotfile)
+            .wrap_err_with(|| format!("Failed to read {}", self.dotfile.display()))?;
+        Ok(content_response(dot, "text/vnd.graphviz; charset=utf-8"))
+    }
+
+    /// Renders the dotfile with Graphviz.
+    ///
+    /// The file is rendered on every request, so a rerun of the analysis is visible after
+    /// reloading the page.
+    fn render_graph(&self) -> color_eyre::Result<HttpResponse> {
+        let output = Command::new(&self.dot_executable)
+            .arg("-Tsvg")
+            .arg(&self.dotfile)
+            .output()
+            .wrap_err_with(|| {
+                format!(
+                    "Failed to run Graphviz executable {}",
+                    self.dot_executable.display()
+                )
+            })?;
+
+        ensure!(
+            output.status.success(),
+            "Graphviz failed to render {}: {}",
+            self.dotfile.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Ok(content_response(output.stdout, "image/svg+xml"))
+    }
+
+    fn render_chart(&self, query: &str) -> color_eyre::Result<HttpResponse> {
+        let chart = match ChartQuery::parse(query) {
+            Ok(chart) => chart,
+            Err(error) => return Ok(text_response(400, &format!("{error:#}"))),
+        };
+
+        let chart_path = charting::render_chart(
+            &chart.node,
+            chart.interface.as_deref(),
+            Some(&self.data_directory),
+            Some(&self.charts_directory),
+            &chart.request,
+            false,
+        )?;
+
+        let content_type = match chart.request.output_format {
+            ChartOutputFormat::SVG => "image/svg+xml",
+            ChartOutputFormat::PNG => "image/png",
+        };
+        let chart = std::fs::read(&chart_path)
+            .wrap_err_with(|| format!("Failed to read chart {}", chart_path.display()))?;
+
+        Ok(content_response(chart, content_type))
+    }
+}
+
+/// Chart parameters received from the viewer page.
+#[derive(Debug)]
+struct ChartQuery {
+    node: String,
+    interface: Option<String>,
+    request: ChartRequest,
+}
+
+impl ChartQuery {
+    fn parse(query: &str) -> color_eyre::Result<Self> {
+        let mut node = None;
+        let mut interface = None;
+        let mut value = None;
+        let mut plot = "histogram".to_owned();
+        let mut bins = None;
+        let mut size = DEFAULT_CHART_SIZE;
+        let mut output_format = ChartOutputFormat::default();
+
+        for (key, parameter) in query::parse_query(query) {
+            match key.as_str() {
+                "node" => node = Some(parameter),
+                "interface" if !parameter.is_empty() => interface = Some(parameter),
+                // Links can offer multiple values, the first one is charted.
+                "value" if value.is_none() => {
+                    value = Some(
+                        ChartedValue::from_str(&parameter, true)
+                            .map_err(|error| eyre!("Invalid value {parameter:?}: {error}"))?,
+                    );
+                }
+                "plot" => plot = parameter,
+                "bins" if !parameter.is_empty() => {
+                    let count = usize::from_str(&parameter)
+                        .wrap_err_with(|| format!("Invalid bins {parameter:?}"))?;
+                    ensure!(
+                        HISTOGRAM_BINS.contains(&count),
+                        "Bins {count} out of range {HISTOGRAM_BINS:?}"
+                    );
+                    bins = Some(count);
+                }
+                "size" => {
+                    size = u32::from_str(&parameter)
+                        .wrap_err_with(|| format!("Invalid size {parameter:?}"))?;
+                    ensure!(
+                        CHART_SIZES.contains(&size),
+                        "Size {size} out of range {CHART_SIZES:?}"
+                    );
+                }
+                "format" => {
+                    output_format = ChartOutputFormat::from_str(&parameter, true)
+                        .map_err(|error| eyre!("Invalid format {parameter:?}: {error}"))?;
+                }
+                _ => {}
+            }
+        }
+
+        let plot = match plot.as_str() {
+            "histogram" => ChartVariants::Histogram(HistogramData { bins }),
+            "scatter" => ChartVariants::Scatter,
+            _ => bail!("Invalid plot {plot:?}"),
+        };
+
+        Ok(Self {
+            node: node.ok_or_eyre("Missing node parameter")?,
+            interface,
+            request: ChartRequest {
+                value: value.ok_or_eyre("Missing value parameter")?,
+                plot,
+                size,
+                output_format,
+            },
+        })
+    }
+}
+
+fn content_response(content: Vec<u8>, content_type: &str) -> HttpResponse {
+    let header = Header::from_bytes("Content-Type", content_type)
+        .expect("Content type should be a valid header value");
+    Response::from_data(content).with_header(header)
+}
+
+fn text_response(status_code: u16, text: &str) -> HttpResponse {
+    content_response(text.as_bytes().to_vec(), "text/plain; charset=utf-8")
+        .with_status_code(status_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chart_links::chart_link;
+
+    #[test]
+    fn chart_link_roundtrip() {
+        let link = chart_link(
+            "/ns/talker",
+            "Timer(100 ms)",
+            &[
+                ChartedValue::CallbackDuration,
+                ChartedValue::ActivationsDelay,
+            ],
+        );
+        let (path, query) = link.split_once('?').unwrap();
+        assert_eq!(path, "chart");
+
+        let chart = ChartQuery::parse(&format!("{query}&plot=scatter&format=png")).unwrap();
+        assert_eq!(chart.node, "/ns/talker");
+        assert_eq!(chart.interface.as_deref(), Some("Timer(100 ms)"));
+        assert_eq!(chart.request.value, ChartedValue::CallbackDuration);
+        assert_eq!(chart.request.plot, ChartVariants::Scatter);
+        assert_eq!(chart.request.output_format, ChartOutputFormat::PNG);
+    }
+
+    #[test]
+    fn chart_query_requires_node() {
+        assert!(ChartQuery::parse("value=callback-duration").is_err());
+    }
+
+    #[test]
+    fn chart_query_out_of_range_rejected() {
+        let viewer = Viewer {
+            dotfile: PathBuf::new(),
+            dot_executable: PathBuf::new(),
+            data_directory: PathBuf::new(),
+            charts_directory: PathBuf::new(),
+        };
+        let query = "node=/talker&value=callback-duration";
+        for parameter in ["size=0", "size=4294967295", "bins=0", "bins=100000000"] {
+            let response = viewer
+                .render_chart(&format!("{query}&{parameter}"))
+                .unwrap();
+            assert_eq!(response.status_code().0, 400, "{parameter}");
+        }
+        assert!(ChartQuery::parse(&format!("{query}&size=4096&bins=1000")).is_ok());
+    }
+}